@@ -0,0 +1,176 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! The algorithms sessions are negotiated with.
+//!
+//! A session algorithm, named when calling the provider's `OpenSession`,
+//! defines a key exchange and the cipher secrets are transferred with once
+//! it's done. The specification defines `plain`, which transfers secrets
+//! unencrypted, and `dh-ietf1024-sha256-aes128-cbc-pkcs7`, which
+//! [EncryptionType](crate::EncryptionType) selects between. Algorithms
+//! added to the specification later, or implemented by a provider as an
+//! extension, can be plugged in by implementing [SessionAlgorithm] and
+//! registering it on the builder:
+//!
+//! ```no_run
+//! # use secret_service::algorithm::SessionAlgorithm;
+//! # use std::sync::Arc;
+//! # async fn call(my_algorithm: Arc<dyn SessionAlgorithm>) -> Result<(), secret_service::Error> {
+//! use secret_service::SecretService;
+//!
+//! let ss = SecretService::builder()
+//!     .register_algorithm(my_algorithm)
+//!     .algorithm("my-algorithm")
+//!     .connect()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Key exchanges ending with a 128-bit key for AES-CBC, as the
+//! specification's `dh-ietf1024` algorithm, can hand the key back as
+//! [Transport::Aes128Cbc] to reuse the crate's cipher, which also keeps the
+//! key protection of `SecretServiceBuilder::kernel_keyring`.
+
+use crate::session::{wipe, AesKey, DhAlgorithm, PlainAlgorithm};
+use crate::Error;
+
+use zbus::zvariant::{OwnedValue, Value};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// An algorithm sessions can be negotiated with.
+pub trait SessionAlgorithm: Send + Sync {
+    /// The name passed to `OpenSession`.
+    fn name(&self) -> &str;
+
+    /// Starts negotiating a session, before `OpenSession` is called.
+    fn start(&self) -> Result<Box<dyn KeyExchange>, Error>;
+}
+
+/// A key exchange in progress, as started by [SessionAlgorithm::start].
+pub trait KeyExchange: Send {
+    /// The input passed to `OpenSession`, such as our public key.
+    fn input(&self) -> Value<'static>;
+
+    /// Finishes the exchange with the output of `OpenSession`, such as the
+    /// provider's public key.
+    fn finish(self: Box<Self>, output: OwnedValue) -> Result<Transport, Error>;
+}
+
+/// How secrets are transferred in a session, once its key exchange is done.
+pub enum Transport {
+    /// Secrets are transferred unencrypted, which fails with
+    /// [Error::PlainDenied] under the `deny-plain` feature.
+    Plain,
+    /// Secrets are encrypted with AES-128 in CBC mode with PKCS#7 padding,
+    /// with a random IV passed as the parameters of each secret.
+    Aes128Cbc(Aes128Key),
+    /// Secrets are encrypted with a cipher of the algorithm's own.
+    Cipher(Box<dyn SessionCipher>),
+}
+
+/// The cipher of a session, for [Transport::Cipher].
+pub trait SessionCipher: Send + Sync {
+    /// Encrypts a secret, returning the parameters and the value of the
+    /// `Secret` struct sent to the provider.
+    fn encrypt(&self, secret: &[u8]) -> Result<(Vec<u8>, Vec<u8>), Error>;
+
+    /// Decrypts the value of a `Secret` struct received from the provider.
+    fn decrypt(&self, parameters: &[u8], value: &[u8]) -> Result<Vec<u8>, Error>;
+}
+
+/// A key for [Transport::Aes128Cbc], wiped when dropped.
+pub struct Aes128Key(pub(crate) AesKey);
+
+impl Aes128Key {
+    pub fn new(key: [u8; 16]) -> Self {
+        Aes128Key(key.into())
+    }
+}
+
+impl Drop for Aes128Key {
+    fn drop(&mut self) {
+        wipe(&mut self.0);
+    }
+}
+
+impl fmt::Debug for Aes128Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Aes128Key")
+    }
+}
+
+/// The algorithms sessions can be negotiated with, by name.
+#[derive(Clone)]
+pub struct AlgorithmRegistry {
+    algorithms: HashMap<String, Arc<dyn SessionAlgorithm>>,
+}
+
+impl AlgorithmRegistry {
+    /// Creates a registry of the algorithms of the specification.
+    pub fn new() -> Self {
+        let mut registry = AlgorithmRegistry {
+            algorithms: HashMap::new(),
+        };
+        registry.register(Arc::new(PlainAlgorithm));
+        registry.register(Arc::new(DhAlgorithm));
+        registry
+    }
+
+    /// Adds an algorithm, replacing any registered under the same name.
+    pub fn register(&mut self, algorithm: Arc<dyn SessionAlgorithm>) {
+        self.algorithms
+            .insert(algorithm.name().to_owned(), algorithm);
+    }
+
+    /// Returns the algorithm registered as `name`.
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn SessionAlgorithm>> {
+        self.algorithms.get(name)
+    }
+
+    /// Returns the names of the registered algorithms, sorted.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<_> = self.algorithms.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+}
+
+impl Default for AlgorithmRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for AlgorithmRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.names()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ss::{ALGORITHM_DH, ALGORITHM_PLAIN};
+
+    #[test]
+    fn should_register_algorithms() {
+        let mut registry = AlgorithmRegistry::new();
+        assert_eq!(registry.names(), [ALGORITHM_DH, ALGORITHM_PLAIN]);
+        assert_eq!(
+            registry.get(ALGORITHM_PLAIN).unwrap().name(),
+            ALGORITHM_PLAIN
+        );
+        assert!(registry.get("unknown").is_none());
+
+        registry.register(Arc::new(DhAlgorithm));
+        assert_eq!(registry.names().len(), 2);
+    }
+}
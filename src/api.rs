@@ -0,0 +1,135 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Low-level types of the Secret Service D-Bus API.
+//!
+//! The rest of this crate hides these behind [SecretService](crate::SecretService),
+//! [Collection](crate::Collection) and [Item](crate::Item). They are exposed for
+//! integrators building their own flows directly on `zbus`, for example to drive
+//! prompts themselves or to cache object paths, without re-declaring the
+//! D-Bus structures.
+//!
+//! Each type (de)serializes to the signature given in the
+//! [specification](https://specifications.freedesktop.org/secret-service/latest/).
+//!
+//! ```
+//! use secret_service::api::SearchItemsResult;
+//! # async fn call(conn: &zbus::Connection) -> zbus::Result<()> {
+//! let reply = conn
+//!     .call_method(
+//!         Some("org.freedesktop.secrets"),
+//!         "/org/freedesktop/secrets",
+//!         Some("org.freedesktop.Secret.Service"),
+//!         "SearchItems",
+//!         &(std::collections::HashMap::from([("service", "example")]),),
+//!     )
+//!     .await?;
+//! let result: SearchItemsResult = reply.body().deserialize()?;
+//! println!("{} unlocked, {} locked", result.unlocked.len(), result.locked.len());
+//! # Ok(())
+//! # }
+//! ```
+
+pub use crate::proxy::service::{
+    CreateCollectionResult, LockActionResult, OpenSessionResult, SearchItemsResult,
+};
+pub use crate::proxy::SecretStruct;
+
+use crate::ss::{SS_COLLECTION_LABEL, SS_ITEM_ATTRIBUTES, SS_ITEM_LABEL};
+use crate::Error;
+
+use std::collections::HashMap;
+use zbus::zvariant::Value;
+
+/// Properties defined for `CreateCollection`, with their signatures.
+const COLLECTION_PROPERTIES: &[(&str, &str)] = &[(SS_COLLECTION_LABEL, "s")];
+
+/// Properties defined for `CreateItem`, with their signatures.
+const ITEM_PROPERTIES: &[(&str, &str)] = &[(SS_ITEM_LABEL, "s"), (SS_ITEM_ATTRIBUTES, "a{ss}")];
+
+/// Checks the properties of a `CreateCollection` call against the ones the
+/// specification defines, failing with [Error::InvalidProperty] on unknown
+/// names and values of the wrong type.
+///
+/// Providers reject such maps with an unspecific "Invalid properties"
+/// error, if at all. Properties of provider extensions have to be listed
+/// in `extensions` to pass; their values aren't checked.
+pub fn validate_collection_properties(
+    properties: &HashMap<&str, Value<'_>>,
+    extensions: &[&str],
+) -> Result<(), Error> {
+    validate_properties(properties, COLLECTION_PROPERTIES, extensions)
+}
+
+/// Checks the properties of a `CreateItem` call, like
+/// [validate_collection_properties].
+pub fn validate_item_properties(
+    properties: &HashMap<&str, Value<'_>>,
+    extensions: &[&str],
+) -> Result<(), Error> {
+    validate_properties(properties, ITEM_PROPERTIES, extensions)
+}
+
+fn validate_properties(
+    properties: &HashMap<&str, Value<'_>>,
+    known: &[(&str, &str)],
+    extensions: &[&str],
+) -> Result<(), Error> {
+    for (&name, value) in properties {
+        if extensions.contains(&name) {
+            continue;
+        }
+        match known.iter().find(|(known, _)| *known == name) {
+            Some((_, signature)) if value.value_signature() == *signature => {}
+            _ => return Err(Error::InvalidProperty(name.to_owned())),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use zbus::zvariant::Type;
+
+    #[test]
+    fn should_match_spec_signatures() {
+        assert_eq!(OpenSessionResult::signature(), "(vo)");
+        assert_eq!(CreateCollectionResult::signature(), "(oo)");
+        assert_eq!(SearchItemsResult::signature(), "(aoao)");
+        assert_eq!(LockActionResult::signature(), "(aoo)");
+        assert_eq!(SecretStruct::signature(), "(oayays)");
+    }
+
+    #[test]
+    fn should_validate_properties() {
+        let attributes = HashMap::from([("service", "example")]);
+        let mut properties = HashMap::from([
+            ("org.freedesktop.Secret.Item.Label", Value::from("label")),
+            (
+                "org.freedesktop.Secret.Item.Attributes",
+                Value::from(attributes),
+            ),
+        ]);
+        validate_item_properties(&properties, &[]).unwrap();
+        assert!(matches!(
+            validate_collection_properties(&properties, &[]),
+            Err(Error::InvalidProperty(name)) if name.starts_with("org.freedesktop.Secret.Item")
+        ));
+
+        properties.insert("org.freedesktop.Secret.Item.Label", Value::from(1u32));
+        assert!(matches!(
+            validate_item_properties(&properties, &[]),
+            Err(Error::InvalidProperty(name)) if name == "org.freedesktop.Secret.Item.Label"
+        ));
+
+        properties.insert("org.freedesktop.Secret.Item.Label", Value::from("label"));
+        properties.insert("org.gnome.keyring.Item.Type", Value::from(0u32));
+        assert!(validate_item_properties(&properties, &[]).is_err());
+        validate_item_properties(&properties, &["org.gnome.keyring.Item.Type"]).unwrap();
+    }
+}
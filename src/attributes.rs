@@ -0,0 +1,312 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Helpers for encoding richer values into attributes.
+//!
+//! Attributes in the Secret Service API are flat string to string maps. The
+//! helpers in this module define stable encodings for other kinds of values,
+//! so that they can be stored in (and read back from) a single attribute.
+//!
+//! ## Lists
+//! A list of strings (for example tags) is encoded by joining its elements
+//! with `,`. Within an element, `\` is escaped as `\\` and `,` as `\,`.
+//!
+//! ```
+//! use secret_service::attributes::{decode_list, encode_list};
+//!
+//! let encoded = encode_list(["work", "a,b"]);
+//! assert_eq!(encoded, r"work,a\,b");
+//! assert_eq!(decode_list(&encoded), vec!["work", "a,b"]);
+//! ```
+//!
+//! The empty list and a list holding a single empty string both encode to the
+//! empty string, which decodes to the empty list.
+//!
+//! Searches can't look inside an encoded list, so
+//! [`Collection::search_items_by_tag`](crate::Collection::search_items_by_tag)
+//! and [`SecretService::search_items_by_tag`](crate::SecretService::search_items_by_tag)
+//! narrow the search by the remaining attributes and then check the list on
+//! the client.
+//!
+//! ## Typed values
+//! libsecret schemas declare attributes as booleans, integers or strings,
+//! and store booleans as `true` or `false` and integers in decimal.
+//! [AttributeValue] uses the same encodings, so that items stored by C
+//! applications can be found and read back. As the encodings are canonical,
+//! searching for an encoded value finds the items stored with it.
+//!
+//! ```no_run
+//! # async fn call() -> Result<(), secret_service::Error> {
+//! use secret_service::attributes::AttributeValue;
+//! use secret_service::{EncryptionType, SecretService};
+//! use std::collections::HashMap;
+//!
+//! let ss = SecretService::connect(EncryptionType::Dh).await?;
+//! let port = AttributeValue::from(8080).encode();
+//! let secure = AttributeValue::from(true).encode();
+//! let items = ss
+//!     .search_items(HashMap::from([("port", &*port), ("secure", &*secure)]))
+//!     .await?;
+//! for item in items.unlocked {
+//!     let attributes = item.get_attributes().await?;
+//!     let port = attributes.get("port").and_then(|port| AttributeValue::decode_integer(port));
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ## Binary values
+//! Attribute values have to be UTF-8 strings, so binary identifiers such as
+//! hashes or UUID bytes are encoded in lowercase hexadecimal, two digits per
+//! byte, with [encode_bytes]. The encoding is canonical, so searching for
+//! an encoded identifier finds the items stored with it, and
+//! [AttributeValue::Bytes] encodes the same way.
+//!
+//! ```
+//! use secret_service::attributes::{decode_bytes, encode_bytes};
+//!
+//! let encoded = encode_bytes(&[0xde, 0xad, 0xbe, 0xef]);
+//! assert_eq!(encoded, "deadbeef");
+//! assert_eq!(decode_bytes(&encoded), Some(vec![0xde, 0xad, 0xbe, 0xef]));
+//! ```
+
+use std::fmt;
+
+const LIST_SEPARATOR: char = ',';
+const ESCAPE: char = '\\';
+
+/// Encodes a list of strings into a single attribute value.
+pub fn encode_list<I, S>(values: I) -> String
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut encoded = String::new();
+    for (i, value) in values.into_iter().enumerate() {
+        if i > 0 {
+            encoded.push(LIST_SEPARATOR);
+        }
+        for c in value.as_ref().chars() {
+            if c == LIST_SEPARATOR || c == ESCAPE {
+                encoded.push(ESCAPE);
+            }
+            encoded.push(c);
+        }
+    }
+    encoded
+}
+
+/// Decodes an attribute value created by [encode_list].
+///
+/// Decoding is lenient: a trailing lone `\` is kept as is.
+pub fn decode_list(encoded: &str) -> Vec<String> {
+    if encoded.is_empty() {
+        return Vec::new();
+    }
+
+    let mut values = Vec::new();
+    let mut current = String::new();
+    let mut chars = encoded.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            ESCAPE => current.push(chars.next().unwrap_or(ESCAPE)),
+            LIST_SEPARATOR => values.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    values.push(current);
+    values
+}
+
+/// Returns whether the list encoded in `encoded` contains `tag`.
+pub fn list_contains(encoded: &str, tag: &str) -> bool {
+    decode_list(encoded).iter().any(|value| value == tag)
+}
+
+/// Encodes bytes into an attribute value, in lowercase hexadecimal.
+pub fn encode_bytes(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(2 * bytes.len());
+    push_bytes(&mut encoded, bytes);
+    encoded
+}
+
+/// Appends the encoding of `bytes` by [encode_bytes] to `encoded`.
+pub(crate) fn push_bytes(encoded: &mut String, bytes: &[u8]) {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    for byte in bytes {
+        encoded.push(DIGITS[usize::from(byte >> 4)].into());
+        encoded.push(DIGITS[usize::from(byte & 0xf)].into());
+    }
+}
+
+/// Decodes an attribute value created by [encode_bytes], or returns `None`
+/// if `encoded` isn't one.
+///
+/// Uppercase digits are accepted, though searches only find values encoded
+/// the way [encode_bytes] does.
+pub fn decode_bytes(encoded: &str) -> Option<Vec<u8>> {
+    if encoded.len() % 2 != 0 || !encoded.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..encoded.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&encoded[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// A typed attribute value, encoded like libsecret encodes the attribute
+/// types of its schemas.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AttributeValue {
+    /// A boolean, encoded as `true` or `false`.
+    Bool(bool),
+    /// A 32-bit integer, encoded in decimal.
+    Integer(i32),
+    /// A string, stored as is.
+    String(String),
+    /// Bytes, encoded by [encode_bytes]. libsecret schemas see them as a
+    /// string.
+    Bytes(Vec<u8>),
+}
+
+impl AttributeValue {
+    /// Encodes the value into an attribute value.
+    pub fn encode(&self) -> String {
+        self.to_string()
+    }
+
+    /// Decodes a boolean stored by [AttributeValue::Bool], or returns `None`
+    /// if `encoded` isn't one.
+    pub fn decode_bool(encoded: &str) -> Option<bool> {
+        match encoded {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Decodes an integer stored by [AttributeValue::Integer], or returns
+    /// `None` if `encoded` isn't one.
+    pub fn decode_integer(encoded: &str) -> Option<i32> {
+        encoded.parse().ok()
+    }
+
+    /// Decodes bytes stored by [AttributeValue::Bytes], or returns `None` if
+    /// `encoded` isn't any.
+    pub fn decode_bytes(encoded: &str) -> Option<Vec<u8>> {
+        decode_bytes(encoded)
+    }
+}
+
+impl fmt::Display for AttributeValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AttributeValue::Bool(value) => write!(f, "{value}"),
+            AttributeValue::Integer(value) => write!(f, "{value}"),
+            AttributeValue::String(value) => f.write_str(value),
+            AttributeValue::Bytes(value) => f.write_str(&encode_bytes(value)),
+        }
+    }
+}
+
+impl From<bool> for AttributeValue {
+    fn from(value: bool) -> Self {
+        AttributeValue::Bool(value)
+    }
+}
+
+impl From<i32> for AttributeValue {
+    fn from(value: i32) -> Self {
+        AttributeValue::Integer(value)
+    }
+}
+
+impl From<String> for AttributeValue {
+    fn from(value: String) -> Self {
+        AttributeValue::String(value)
+    }
+}
+
+impl From<&str> for AttributeValue {
+    fn from(value: &str) -> Self {
+        AttributeValue::String(value.to_owned())
+    }
+}
+
+impl From<Vec<u8>> for AttributeValue {
+    fn from(value: Vec<u8>) -> Self {
+        AttributeValue::Bytes(value)
+    }
+}
+
+impl From<&[u8]> for AttributeValue {
+    fn from(value: &[u8]) -> Self {
+        AttributeValue::Bytes(value.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_round_trip_lists() {
+        let lists: &[&[&str]] = &[
+            &[],
+            &["one"],
+            &["one", "two"],
+            &["", ""],
+            &["a,b", r"c\d", r"trailing\"],
+            &[",", r"\", r"\,"],
+        ];
+        for list in lists {
+            assert_eq!(&decode_list(&encode_list(*list)), list);
+        }
+    }
+
+    #[test]
+    fn should_check_list_membership() {
+        let encoded = encode_list(["work", "a,b"]);
+        assert!(list_contains(&encoded, "work"));
+        assert!(list_contains(&encoded, "a,b"));
+        assert!(!list_contains(&encoded, "a"));
+        assert!(!list_contains("", ""));
+    }
+
+    #[test]
+    fn should_encode_typed_values_like_libsecret() {
+        assert_eq!(AttributeValue::from(true).encode(), "true");
+        assert_eq!(AttributeValue::from(false).encode(), "false");
+        assert_eq!(AttributeValue::from(-42).encode(), "-42");
+        assert_eq!(AttributeValue::from("text").encode(), "text");
+
+        assert_eq!(AttributeValue::decode_bool("true"), Some(true));
+        assert_eq!(AttributeValue::decode_bool("1"), None);
+        assert_eq!(AttributeValue::decode_integer("-42"), Some(-42));
+        assert_eq!(AttributeValue::decode_integer("4294967296"), None);
+        assert_eq!(AttributeValue::decode_integer("text"), None);
+    }
+
+    #[test]
+    fn should_round_trip_bytes() {
+        let uuid = [
+            0x67, 0xe5, 0x50, 0x44, 0x10, 0xb1, 0x42, 0x6f, 0x92, 0x47, 0xbb, 0x68, 0x0e, 0x5f,
+            0xe0, 0xc8,
+        ];
+        let encoded = AttributeValue::from(&uuid[..]).encode();
+        assert_eq!(encoded, "67e5504410b1426f9247bb680e5fe0c8");
+        assert_eq!(AttributeValue::decode_bytes(&encoded).unwrap(), uuid);
+        assert_eq!(encode_bytes(&[]), "");
+        assert_eq!(decode_bytes(""), Some(Vec::new()));
+        assert_eq!(decode_bytes("00FF"), Some(vec![0, 0xff]));
+
+        for invalid in ["0", "0g", "+f", "é0"] {
+            assert_eq!(decode_bytes(invalid), None);
+        }
+    }
+}
@@ -0,0 +1,199 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+// An object-safe interface over the stores this crate can talk to, for
+// wrappers that pick a store at runtime.
+
+use crate::{Error, SecretService};
+use futures_util::future::BoxFuture;
+use std::collections::HashMap;
+
+/// A store for credentials identified by attributes.
+///
+/// The trait is object safe, so that wrappers can pick a store at runtime and
+/// hold it as a `Box<dyn CredentialBackend>`, without depending on the
+/// concrete types. It's implemented by [SecretService] and, with the
+/// `kernel-keyring` feature, by `kernel::KernelKeyring`; other platform
+/// stores can implement it as well.
+///
+/// ```no_run
+/// # use secret_service::{CredentialBackend, EncryptionType, SecretService};
+/// # use std::collections::HashMap;
+/// # async fn call() {
+/// let backend: Box<dyn CredentialBackend> =
+///     Box::new(SecretService::connect(EncryptionType::Dh).await.unwrap());
+///
+/// let attributes = HashMap::from([("service", "example"), ("user", "alice")]);
+/// backend
+///     .store("Example", attributes.clone(), b"hunter2", "text/plain")
+///     .await
+///     .unwrap();
+/// let secret = backend.retrieve(attributes).await.unwrap();
+/// # }
+/// ```
+pub trait CredentialBackend: Send + Sync {
+    /// Stores a secret, replacing the one stored under exactly the same
+    /// attributes, if any.
+    fn store<'a>(
+        &'a self,
+        label: &'a str,
+        attributes: HashMap<&'a str, &'a str>,
+        secret: &'a [u8],
+        content_type: &'a str,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Returns the secret of an item matching `attributes`, or
+    /// [Error::NoResult] if there is none.
+    fn retrieve<'a>(
+        &'a self,
+        attributes: HashMap<&'a str, &'a str>,
+    ) -> BoxFuture<'a, Result<Vec<u8>, Error>>;
+
+    /// Deletes all items matching `attributes`, returning how many there
+    /// were.
+    fn delete<'a>(
+        &'a self,
+        attributes: HashMap<&'a str, &'a str>,
+    ) -> BoxFuture<'a, Result<usize, Error>>;
+}
+
+/// Stores items in the default collection, unlocking items and the
+/// collection as needed.
+impl CredentialBackend for SecretService<'_> {
+    fn store<'a>(
+        &'a self,
+        label: &'a str,
+        attributes: HashMap<&'a str, &'a str>,
+        secret: &'a [u8],
+        content_type: &'a str,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let collection = self.get_default_collection().await?;
+            if collection.is_locked().await? {
+                collection.unlock().await?;
+            }
+            collection
+                .create_item(label, attributes, secret, true, content_type)
+                .await?;
+            Ok(())
+        })
+    }
+
+    fn retrieve<'a>(
+        &'a self,
+        attributes: HashMap<&'a str, &'a str>,
+    ) -> BoxFuture<'a, Result<Vec<u8>, Error>> {
+        Box::pin(async move {
+            let items = self.search_items(attributes).await?;
+            match (items.unlocked.first(), items.locked.first()) {
+                (Some(item), _) => item.get_secret().await,
+                (None, Some(item)) => item.get_secret_unlocking().await,
+                (None, None) => Err(Error::NoResult),
+            }
+        })
+    }
+
+    fn delete<'a>(
+        &'a self,
+        attributes: HashMap<&'a str, &'a str>,
+    ) -> BoxFuture<'a, Result<usize, Error>> {
+        Box::pin(async move {
+            let items = self.search_items(attributes).await?;
+            let mut deleted = 0;
+            for item in items.unlocked.iter().chain(&items.locked) {
+                item.delete().await?;
+                deleted += 1;
+            }
+            Ok(deleted)
+        })
+    }
+}
+
+#[cfg(feature = "kernel-keyring")]
+impl CredentialBackend for crate::kernel::KernelKeyring {
+    fn store<'a>(
+        &'a self,
+        label: &'a str,
+        attributes: HashMap<&'a str, &'a str>,
+        secret: &'a [u8],
+        content_type: &'a str,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        // keyctl calls don't block, so they are made right away
+        let result = self
+            .create_item(label, attributes, secret, true, content_type)
+            .map(drop);
+        Box::pin(futures_util::future::ready(result))
+    }
+
+    fn retrieve<'a>(
+        &'a self,
+        attributes: HashMap<&'a str, &'a str>,
+    ) -> BoxFuture<'a, Result<Vec<u8>, Error>> {
+        let result = self.search_items(attributes).and_then(|items| {
+            items
+                .first()
+                .ok_or(Error::NoResult)
+                .and_then(|item| item.get_secret())
+        });
+        Box::pin(futures_util::future::ready(result))
+    }
+
+    fn delete<'a>(
+        &'a self,
+        attributes: HashMap<&'a str, &'a str>,
+    ) -> BoxFuture<'a, Result<usize, Error>> {
+        let result = self.search_items(attributes).and_then(|items| {
+            for item in &items {
+                item.delete()?;
+            }
+            Ok(items.len())
+        });
+        Box::pin(futures_util::future::ready(result))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::EncryptionType;
+
+    async fn round_trip(backend: &dyn CredentialBackend) {
+        let attributes = HashMap::from([("test", "backend")]);
+
+        backend
+            .store("Test", attributes.clone(), b"first", "text/plain")
+            .await
+            .unwrap();
+        backend
+            .store("Test", attributes.clone(), b"second", "text/plain")
+            .await
+            .unwrap();
+        assert_eq!(
+            backend.retrieve(attributes.clone()).await.unwrap(),
+            b"second"
+        );
+
+        assert_eq!(backend.delete(attributes.clone()).await.unwrap(), 1);
+        assert!(matches!(
+            backend.retrieve(attributes).await,
+            Err(Error::NoResult)
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_store_credentials_in_secret_service() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        round_trip(&ss).await;
+    }
+
+    #[cfg(feature = "kernel-keyring")]
+    #[tokio::test]
+    async fn should_store_credentials_in_kernel_keyring() {
+        let keyring = crate::kernel::KernelKeyring::session().unwrap();
+        round_trip(&keyring).await;
+    }
+}
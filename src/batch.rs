@@ -0,0 +1,87 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+// Per-object results of operations on several objects at once.
+
+use crate::{Error, ObjectPath};
+
+/// The result of a batch operation for each object it was given, as returned
+/// by [SecretService::unlock_all](crate::SecretService::unlock_all).
+///
+/// Partial failure is common in batches, for instance when some items are
+/// locked or were deleted in the meantime, so a failing object doesn't fail
+/// the others. Errors affecting the whole batch, such as the provider being
+/// unreachable, are still returned as the batch's own error.
+#[derive(Debug)]
+pub struct BatchOutcome<T = ()> {
+    /// The result for each object, in the order the objects were given.
+    pub results: Vec<(ObjectPath, Result<T, Error>)>,
+}
+
+impl<T> BatchOutcome<T> {
+    /// Returns whether the operation succeeded for every object.
+    pub fn is_success(&self) -> bool {
+        self.results.iter().all(|(_, result)| result.is_ok())
+    }
+
+    /// Returns the objects the operation succeeded for.
+    pub fn succeeded(&self) -> impl Iterator<Item = (&ObjectPath, &T)> {
+        self.results
+            .iter()
+            .filter_map(|(path, result)| Some((path, result.as_ref().ok()?)))
+    }
+
+    /// Returns the objects the operation failed for, with their errors.
+    pub fn failed(&self) -> impl Iterator<Item = (&ObjectPath, &Error)> {
+        self.results
+            .iter()
+            .filter_map(|(path, result)| Some((path, result.as_ref().err()?)))
+    }
+
+    /// Returns the successful results, or the first error if the operation
+    /// failed for any object.
+    pub fn into_result(self) -> Result<Vec<(ObjectPath, T)>, Error> {
+        self.results
+            .into_iter()
+            .map(|(path, result)| Ok((path, result?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_split_batch_results() {
+        let path = |n: u32| ObjectPath::try_from(format!("/test/{n}").as_str()).unwrap();
+        let outcome = BatchOutcome {
+            results: vec![
+                (path(1), Ok(1)),
+                (path(2), Err(Error::Locked)),
+                (path(3), Ok(3)),
+            ],
+        };
+
+        assert!(!outcome.is_success());
+        assert_eq!(
+            outcome.succeeded().collect::<Vec<_>>(),
+            [(&path(1), &1), (&path(3), &3)]
+        );
+        assert_eq!(
+            outcome.failed().map(|(path, _)| path).collect::<Vec<_>>(),
+            [&path(2)]
+        );
+        assert!(matches!(outcome.into_result(), Err(Error::Locked)));
+
+        let outcome = BatchOutcome {
+            results: vec![(path(1), Ok(()))],
+        };
+        assert!(outcome.is_success());
+        assert_eq!(outcome.into_result().unwrap(), [(path(1), ())]);
+    }
+}
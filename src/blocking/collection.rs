@@ -5,17 +5,40 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
+use super::import;
 use super::item::Item;
+use super::BlockingStream;
+use crate::builder::Config;
 use crate::error::Error;
-use crate::proxy::collection::CollectionProxyBlocking;
+use crate::event;
+#[cfg(feature = "regex")]
+use crate::grep;
+use crate::journal::{Change, SecretHash};
+use crate::merge;
+use crate::migration::{self, Migrations};
+use crate::proxy::collection::{CollectionProxy, CollectionProxyBlocking};
+use crate::proxy::item::ItemProxyBlocking;
 use crate::proxy::service::ServiceProxyBlocking;
+use crate::query::{self, Query};
+use crate::search;
 use crate::session::Session;
-use crate::ss::{SS_DBUS_NAME, SS_ITEM_ATTRIBUTES, SS_ITEM_LABEL};
-use crate::util::{exec_prompt_blocking, format_secret, lock_or_unlock_blocking, LockAction};
+use crate::snapshot;
+use crate::util::{
+    self, decrypt_secrets, exec_prompt_blocking, format_secret, group_duplicates, item_properties,
+    lock_or_unlock_blocking, rank_label_matches, retain_tagged_blocking, summarize_attributes,
+    LockAction, TEXT_CONTENT_TYPE,
+};
+use crate::{
+    AttributeSummary, BatchOutcome, CollectionSnapshot, ConflictPolicy, Imported, ItemEvent,
+    ItemTimestamp, LabelMatch, NewItem, Progress,
+};
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use zbus::blocking::proxy::Builder as ProxyBuilder;
 use zbus::{
-    zvariant::{Dict, ObjectPath, OwnedObjectPath, Value},
+    zvariant::{ObjectPath, OwnedObjectPath},
     CacheProperties,
 };
 
@@ -24,10 +47,24 @@ use zbus::{
 // whether through a new collection or a collection search
 pub struct Collection<'a> {
     conn: zbus::blocking::Connection,
-    session: &'a Session,
+    pub(crate) session: &'a Session,
+    /// Deprecated copy of [Collection::path], for reading only: assigning to
+    /// it doesn't change the collection the handle refers to.
+    /// [Collection::refresh] updates it along with the path.
+    #[deprecated(
+        since = "4.1.0",
+        note = "use `Collection::path`, which doesn't expose zbus types"
+    )]
     pub collection_path: OwnedObjectPath,
+    pub(crate) path: OwnedObjectPath,
     collection_proxy: CollectionProxyBlocking<'a>,
-    service_proxy: &'a ServiceProxyBlocking<'a>,
+    item_proxy_builder: ProxyBuilder<'static, ItemProxyBlocking<'static>>,
+    pub(crate) service_proxy: &'a ServiceProxyBlocking<'a>,
+    pub(crate) config: &'a Config,
+    /// Set once the object is deleted, so that later calls can say so.
+    deleted: AtomicBool,
+    /// The alias the collection was looked up by, resolved again on refresh.
+    alias: Option<String>,
 }
 
 impl<'a> Collection<'a> {
@@ -35,24 +72,149 @@ impl<'a> Collection<'a> {
         conn: zbus::blocking::Connection,
         session: &'a Session,
         service_proxy: &'a ServiceProxyBlocking,
+        config: &'a Config,
         collection_path: OwnedObjectPath,
     ) -> Result<Self, Error> {
-        let collection_proxy = CollectionProxyBlocking::builder(&conn)
-            .destination(SS_DBUS_NAME)?
-            .path(collection_path.clone())?
-            .cache_properties(CacheProperties::No)
-            .build()?;
+        let collection_proxy = Self::proxy(&conn, config, collection_path.clone())?;
+        let item_proxy_builder = Item::proxy_builder(&conn, config)?;
+        #[allow(deprecated)]
         Ok(Collection {
             conn,
             session,
+            path: collection_path.clone(),
             collection_path,
             collection_proxy,
+            item_proxy_builder,
             service_proxy,
+            config,
+            deleted: AtomicBool::new(false),
+            alias: None,
         })
     }
 
+    fn proxy(
+        conn: &zbus::blocking::Connection,
+        config: &Config,
+        collection_path: OwnedObjectPath,
+    ) -> Result<CollectionProxyBlocking<'a>, Error> {
+        Ok(CollectionProxyBlocking::builder(conn)
+            .destination(config.destination.clone())?
+            .path(collection_path)?
+            .cache_properties(CacheProperties::No)
+            .build()?)
+    }
+
+    /// Records the alias the collection was looked up by, for [Collection::refresh].
+    pub(crate) fn with_alias(mut self, alias: &str) -> Self {
+        self.alias = Some(alias.to_owned());
+        self
+    }
+
+    fn item(&self, item_path: OwnedObjectPath) -> Result<Item<'_>, Error> {
+        Item::new(
+            self.item_proxy_builder.clone(),
+            self.conn.clone(),
+            self.session,
+            self.service_proxy,
+            self.config,
+            item_path,
+        )
+    }
+
+    /// Returns a handle to the same collection that transfers secrets in
+    /// `session`, see [SecretService::open_session](crate::blocking::SecretService::open_session).
+    pub fn with_session<'s>(&'s self, session: &'s Session) -> Collection<'s> {
+        #[allow(deprecated)]
+        Collection {
+            conn: self.conn.clone(),
+            session,
+            collection_path: self.path.clone(),
+            path: self.path.clone(),
+            collection_proxy: self.collection_proxy.clone(),
+            item_proxy_builder: self.item_proxy_builder.clone(),
+            service_proxy: self.service_proxy,
+            config: self.config,
+            deleted: AtomicBool::new(self.deleted.load(Ordering::Relaxed)),
+            alias: self.alias.clone(),
+        }
+    }
+
+    /// The object path of the collection.
+    pub fn path(&self) -> crate::ObjectPath {
+        self.path.clone().into()
+    }
+
+    /// Attaches `operation` and the collection's path to errors of D-Bus calls,
+    /// or reports them as [Error::ObjectDeleted] once the collection is deleted.
+    fn context<E: Into<Error>>(&self, operation: &'static str) -> impl FnOnce(E) -> Error + '_ {
+        move |err| {
+            if self.deleted.load(Ordering::Relaxed) {
+                Error::ObjectDeleted
+            } else {
+                err.into().with_context(operation, &self.path)
+            }
+        }
+    }
+
     pub fn is_locked(&self) -> Result<bool, Error> {
-        Ok(self.collection_proxy.locked()?)
+        self.collection_proxy
+            .locked()
+            .map_err(self.context("is_locked"))
+    }
+
+    /// Returns whether the collection still exists, for handles kept around
+    /// while other applications may delete the collection.
+    ///
+    /// Probes a property of the collection, so that a deleted collection
+    /// shows up as `false` rather than as an error.
+    pub fn exists(&self) -> Result<bool, Error> {
+        if self.deleted.load(Ordering::Relaxed) {
+            return Ok(false);
+        }
+        match self.collection_proxy.locked() {
+            Ok(_) => Ok(true),
+            Err(err) => {
+                let err = Error::from(err);
+                if err.is_unknown_object() {
+                    Ok(false)
+                } else {
+                    Err(err.with_context("exists", &self.path))
+                }
+            }
+        }
+    }
+
+    /// Revalidates the collection, for handles kept around while other
+    /// applications may delete and re-create it.
+    ///
+    /// Collections looked up or created by alias resolve the alias again,
+    /// dropping any cached resolution, so that the handle moves to a
+    /// collection re-created under the alias at a different path. Other
+    /// collections are only checked for existence. Returns
+    /// [Error::NoResult] if the collection is gone.
+    pub fn refresh(&mut self) -> Result<(), Error> {
+        let Some(alias) = &self.alias else {
+            return match self.exists()? {
+                true => Ok(()),
+                false => Err(Error::NoResult),
+            };
+        };
+
+        self.config.alias_cache.clear();
+        let path = self.service_proxy.read_alias(alias)?;
+        if path.as_str() == "/" {
+            return Err(Error::NoResult);
+        }
+        if path != self.path {
+            self.collection_proxy = Self::proxy(&self.conn, self.config, path.clone())?;
+            #[allow(deprecated)]
+            {
+                self.collection_path = path.clone();
+            }
+            self.path = path;
+        }
+        *self.deleted.get_mut() = false;
+        Ok(())
     }
 
     pub fn ensure_unlocked(&self) -> Result<(), Error> {
@@ -63,103 +225,445 @@ impl<'a> Collection<'a> {
         }
     }
 
-    pub fn unlock(&self) -> Result<(), Error> {
-        lock_or_unlock_blocking(
+    /// Unlocks the collection, prompting the user if the provider asks to,
+    /// and returns the paths of the objects the provider reports as unlocked.
+    pub fn unlock(&self) -> Result<Vec<crate::ObjectPath>, Error> {
+        let unlocked = lock_or_unlock_blocking(
             self.conn.clone(),
             self.service_proxy,
-            &self.collection_path,
+            self.config,
+            vec![&*self.path],
             LockAction::Unlock,
         )
+        .map_err(self.context("unlock"))?;
+        Ok(unlocked.into_iter().map(Into::into).collect())
     }
 
-    pub fn lock(&self) -> Result<(), Error> {
-        lock_or_unlock_blocking(
+    /// Locks the collection and returns the paths of the objects the
+    /// provider reports as locked, which leave out any object the provider
+    /// refused to lock.
+    pub fn lock(&self) -> Result<Vec<crate::ObjectPath>, Error> {
+        let locked = lock_or_unlock_blocking(
             self.conn.clone(),
             self.service_proxy,
-            &self.collection_path,
+            self.config,
+            vec![&*self.path],
             LockAction::Lock,
         )
+        .map_err(self.context("lock"))?;
+        Ok(locked.into_iter().map(Into::into).collect())
     }
 
     /// Deletes dbus object, but struct instance still exists (current implementation)
+    ///
+    /// Calls failing on the instance afterwards return [Error::ObjectDeleted].
     pub fn delete(&self) -> Result<(), Error> {
         // ensure_unlocked handles prompt for unlocking if necessary
         self.ensure_unlocked()?;
-        let prompt_path = self.collection_proxy.delete()?;
+        let prompt_path = self
+            .collection_proxy
+            .delete()
+            .map_err(self.context("delete"))?;
 
         // "/" means no prompt necessary
         if prompt_path.as_str() != "/" {
-            exec_prompt_blocking(self.conn.clone(), &prompt_path)?;
+            exec_prompt_blocking(self.conn.clone(), self.config, &prompt_path)?;
         }
+        self.config.alias_cache.clear();
+        self.deleted.store(true, Ordering::Relaxed);
+        self.config.record(self.path(), || Change::Deleted);
 
         Ok(())
     }
 
-    pub fn get_all_items(&self) -> Result<Vec<Item>, Error> {
-        let items = self.collection_proxy.items()?;
+    pub fn get_all_items(&self) -> Result<Vec<Item<'_>>, Error> {
+        let items = self
+            .collection_proxy
+            .items()
+            .map_err(self.context("get_all_items"))?;
 
         // map array of item paths to Item
         let res = items
             .into_iter()
-            .map(|item_path| {
-                Item::new(
-                    self.conn.clone(),
-                    self.session,
-                    self.service_proxy,
-                    item_path.into(),
-                )
-            })
+            .map(|item_path| self.item(item_path.into()))
             .collect::<Result<_, _>>()?;
 
         Ok(res)
     }
 
-    pub fn search_items(&self, attributes: HashMap<&str, &str>) -> Result<Vec<Item>, Error> {
-        let items = self.collection_proxy.search_items(attributes)?;
+    /// Returns all items in the collection in batches of `page_size`.
+    ///
+    /// The list of item paths is read once up front; the items themselves are
+    /// only constructed as each page is pulled from the iterator, which keeps
+    /// memory use and bus traffic bounded when scanning very large collections.
+    pub fn items_paged(
+        &self,
+        page_size: NonZeroUsize,
+    ) -> Result<impl Iterator<Item = Result<Vec<Item<'_>>, Error>> + '_, Error> {
+        let items = self
+            .collection_proxy
+            .items()
+            .map_err(self.context("items_paged"))?;
+        let pages = items
+            .chunks(page_size.get())
+            .map(|page| {
+                page.iter()
+                    .cloned()
+                    .map(OwnedObjectPath::from)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        Ok(pages.into_iter().map(move |page| {
+            page.into_iter()
+                .map(|item_path| self.item(item_path))
+                .collect()
+        }))
+    }
+
+    /// Deletes every item whose `timestamp` is older than `cutoff`
+    /// (in seconds since the Unix epoch).
+    ///
+    /// The outcome lists each item older than `cutoff` with whether it was
+    /// deleted; an item failing to be deleted doesn't stop the others. When
+    /// `dry_run` is set, nothing is deleted and the outcome lists the items
+    /// that would have been purged.
+    pub fn purge_older_than(
+        &self,
+        cutoff: u64,
+        timestamp: ItemTimestamp,
+        dry_run: bool,
+    ) -> Result<BatchOutcome, Error> {
+        self.purge_older_than_with_progress(cutoff, timestamp, dry_run, |_| ())
+    }
+
+    /// Like [Collection::purge_older_than], calling `progress` after each
+    /// item deleted. Nothing is reported on a dry run.
+    pub fn purge_older_than_with_progress(
+        &self,
+        cutoff: u64,
+        timestamp: ItemTimestamp,
+        dry_run: bool,
+        mut progress: impl FnMut(&Progress),
+    ) -> Result<BatchOutcome, Error> {
+        let mut candidates = Vec::new();
+        for item in self.get_all_items()? {
+            let time = match timestamp {
+                ItemTimestamp::Created => item.get_created()?,
+                ItemTimestamp::Modified => item.get_modified()?,
+            };
+            if time < cutoff {
+                candidates.push(item);
+            }
+        }
+
+        let mut state = Progress::new(Some(candidates.len()));
+        let mut results = Vec::with_capacity(candidates.len());
+        for item in &candidates {
+            let deleted = if dry_run {
+                Ok(())
+            } else {
+                let deleted = item.delete();
+                state.advance(None, &deleted, &mut progress);
+                deleted
+            };
+            results.push((item.path(), deleted));
+        }
+
+        Ok(BatchOutcome { results })
+    }
+
+    /// Upgrades the items of an application's schema to the latest version
+    /// of `migrations`, see [crate::Collection::migrate].
+    pub fn migrate(
+        &self,
+        migrations: &Migrations,
+        dry_run: bool,
+        progress: impl FnMut(&Progress),
+    ) -> Result<BatchOutcome<u32>, Error> {
+        migration::migrate_blocking(self, migrations, dry_run, progress)
+    }
+
+    /// Copies every item of `other` into this collection, see
+    /// [crate::Collection::merge_from].
+    pub fn merge_from(
+        &self,
+        other: &Collection<'_>,
+        conflict: impl Into<ConflictPolicy>,
+    ) -> Result<BatchOutcome<Imported>, Error> {
+        merge::merge_from_blocking(self, other, conflict.into())
+    }
+
+    /// Reads the metadata of every item, to compare with a later snapshot,
+    /// see [CollectionSnapshot].
+    pub fn snapshot(&self) -> Result<CollectionSnapshot, Error> {
+        let items = self
+            .collection_proxy
+            .items()
+            .map_err(self.context("snapshot"))?;
+        snapshot::snapshot_blocking(&self.conn, self.config, &self.path, items)
+    }
+
+    /// Subscribes to the items of the collection being created, changed or
+    /// deleted, by this or any other application.
+    ///
+    /// See [crate::Collection::receive_item_events]. The returned iterator
+    /// blocks until the next event arrives, and ends once the
+    /// [SecretService](super::SecretService) the collection came from is
+    /// dropped.
+    pub fn receive_item_events(&self) -> Result<impl Iterator<Item = ItemEvent>, Error> {
+        let collection_proxy = CollectionProxy::from(self.collection_proxy.inner().inner().clone());
+        let events = zbus::block_on(event::item_events(&collection_proxy))
+            .map_err(self.context("receive_item_events"))?;
+        Ok(BlockingStream(Some(Box::pin(
+            self.config.lifetime.bind(events),
+        ))))
+    }
+
+    /// Finds groups of items in the collection that have identical attributes.
+    ///
+    /// Only groups with more than one item are returned. When `compare_secrets`
+    /// is set, the secrets of all candidates are fetched in one batch and the
+    /// groups are split further so that items in a group also share the same
+    /// secret. Items whose secret the provider doesn't return (for example
+    /// because they are locked) are left out in that case.
+    pub fn find_duplicates(&self, compare_secrets: bool) -> Result<Vec<Vec<Item<'_>>>, Error> {
+        let items = self
+            .get_all_items()?
+            .into_iter()
+            .map(|item| {
+                let attributes = BTreeMap::from_iter(item.get_attributes()?);
+                Ok((item, attributes))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let groups = group_duplicates(items);
+
+        if !compare_secrets {
+            return Ok(groups);
+        }
+
+        let candidates = groups.iter().flatten().map(|item| &*item.path).collect();
+        let secrets = self
+            .service_proxy
+            .get_secrets(candidates, self.session.open_blocking()?)?;
+        let mut secrets = decrypt_secrets(self.session, self.config, secrets)?;
+
+        Ok(groups
+            .into_iter()
+            .flat_map(|group| {
+                group_duplicates(group.into_iter().filter_map(|item| {
+                    let secret = secrets.remove(&item.path)?;
+                    Some((item, secret))
+                }))
+            })
+            .collect())
+    }
+
+    /// Counts how many items hold each attribute key and, if `per_value` is
+    /// set, each value of each key.
+    pub fn attribute_summary(&self, per_value: bool) -> Result<AttributeSummary, Error> {
+        let attributes = self
+            .get_all_items()?
+            .iter()
+            .map(|item| item.get_attributes())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(summarize_attributes(attributes, per_value))
+    }
+
+    /// Searches the items of this collection by label, returning matches
+    /// ranked best first.
+    ///
+    /// See [SecretService::search_labels](super::SecretService::search_labels)
+    /// for how labels are matched.
+    pub fn search_labels(&self, query: &str) -> Result<Vec<LabelMatch<Item<'_>>>, Error> {
+        let items = self
+            .get_all_items()?
+            .into_iter()
+            .map(|item| {
+                let label = item.get_label()?;
+                Ok((item, label))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(rank_label_matches(items, query))
+    }
+
+    /// Renames every item matching `attributes` to what `rename` returns for
+    /// its current label, one item after the other.
+    ///
+    /// See [crate::Collection::relabel_matching].
+    pub fn relabel_matching(
+        &self,
+        attributes: HashMap<&str, &str>,
+        mut rename: impl FnMut(&str) -> String,
+    ) -> Result<BatchOutcome, Error> {
+        let items = self.search_items(attributes)?;
+
+        let results = items
+            .iter()
+            .filter_map(|item| {
+                let relabeled = match item.get_label() {
+                    Ok(label) => {
+                        let new_label = rename(&label);
+                        if new_label == label {
+                            return None;
+                        }
+                        item.set_label(&new_label)
+                    }
+                    Err(err) => Err(err),
+                };
+                Some((item.path(), relabeled))
+            })
+            .collect();
+        Ok(BatchOutcome { results })
+    }
+
+    pub fn search_items(&self, attributes: HashMap<&str, &str>) -> Result<Vec<Item<'_>>, Error> {
+        let attributes = self.config.encrypt_attributes(attributes);
+        let items = self
+            .collection_proxy
+            .search_items(attributes.as_map())
+            .map_err(self.context("search_items"))?;
 
         // map array of item paths to Item
         let res = items
             .into_iter()
-            .map(|item_path| {
-                Item::new(
-                    self.conn.clone(),
-                    self.session,
-                    self.service_proxy,
-                    item_path,
-                )
-            })
+            .map(|item_path| self.item(item_path))
             .collect::<Result<_, _>>()?;
 
         Ok(res)
     }
 
+    /// Searches items by attributes, keeping only those whose attributes are
+    /// exactly `attributes`, rather than a superset of them.
+    ///
+    /// See [ItemSearch::exact](crate::ItemSearch::exact).
+    pub fn search_items_exact(
+        &self,
+        attributes: HashMap<&str, &str>,
+    ) -> Result<Vec<Item<'_>>, Error> {
+        let items = self.search_items(attributes.clone())?;
+        search::retain_exact_blocking(items, &attributes)
+    }
+
+    /// Searches the items of this collection by attributes, keeping only
+    /// those whose attribute `key` holds a list containing `tag`.
+    ///
+    /// See [SecretService::search_items_by_tag](crate::SecretService::search_items_by_tag).
+    pub fn search_items_by_tag(
+        &self,
+        mut attributes: HashMap<&str, &str>,
+        key: &str,
+        tag: &str,
+    ) -> Result<Vec<Item<'_>>, Error> {
+        attributes.remove(key);
+        let items = self.search_items(attributes)?;
+        retain_tagged_blocking(items, key, tag)
+    }
+
+    /// Returns the items whose label or an attribute value matches
+    /// `pattern`, see [crate::Collection::grep].
+    #[cfg(feature = "regex")]
+    pub fn grep(&self, pattern: &regex::Regex) -> Result<Vec<Item<'_>>, Error> {
+        grep::grep_blocking(self, pattern, false)
+    }
+
+    /// Like [Collection::grep], also returning the items whose secret, if
+    /// it's UTF-8 text, matches `pattern`.
+    #[cfg(feature = "regex")]
+    pub fn grep_including_secrets(&self, pattern: &regex::Regex) -> Result<Vec<Item<'_>>, Error> {
+        grep::grep_blocking(self, pattern, true)
+    }
+
+    /// Searches the items of this collection with a query string, see
+    /// [crate::query].
+    pub fn search_query(&self, query: &Query) -> Result<Vec<Item<'_>>, Error> {
+        let items = self.search_items(query.required_attributes())?;
+        query::retain_matching_blocking(items, query)
+    }
+
     pub fn get_label(&self) -> Result<String, Error> {
-        Ok(self.collection_proxy.label()?)
+        self.collection_proxy
+            .label()
+            .map_err(self.context("get_label"))
     }
 
     pub fn set_label(&self, new_label: &str) -> Result<(), Error> {
-        Ok(self.collection_proxy.set_label(new_label)?)
+        self.collection_proxy
+            .set_label(new_label)
+            .map_err(self.context("set_label"))?;
+        self.config
+            .record(self.path(), || Change::LabelSet(new_label.to_owned()));
+        Ok(())
     }
 
+    pub fn get_created(&self) -> Result<u64, Error> {
+        self.collection_proxy
+            .created()
+            .map_err(self.context("get_created"))
+    }
+
+    pub fn get_modified(&self) -> Result<u64, Error> {
+        self.collection_proxy
+            .modified()
+            .map_err(self.context("get_modified"))
+    }
+
+    /// Creates an item, doing what `conflict` says if the collection already
+    /// holds one with the same attributes, see [ConflictPolicy].
     pub fn create_item(
         &self,
         label: &str,
         attributes: HashMap<&str, &str>,
         secret: &[u8],
-        replace: bool,
+        conflict: impl Into<ConflictPolicy>,
         content_type: &str,
-    ) -> Result<Item, Error> {
-        let secret_struct = format_secret(self.session, secret, content_type)?;
+    ) -> Result<Item<'_>, Error> {
+        let conflict = conflict.into();
+        self.session.open_blocking()?;
+        let secret_struct = format_secret(self.session, self.config, secret, content_type)?;
+        let searched = attributes.clone();
+        let attributes = self.config.item_attributes(attributes);
 
-        let mut properties: HashMap<&str, Value> = HashMap::new();
-        let attributes: Dict = attributes.into();
-
-        properties.insert(SS_ITEM_LABEL, label.into());
-        properties.insert(SS_ITEM_ATTRIBUTES, attributes.into());
+        if conflict == ConflictPolicy::Fail {
+            let found = self
+                .collection_proxy
+                .search_items(attributes.as_map())
+                .map_err(self.context("create_item"))?;
+            // the provider also returns items holding more attributes
+            let found = found
+                .into_iter()
+                .map(|path| self.item(path))
+                .collect::<Result<_, _>>()?;
+            if !search::retain_exact_blocking(found, &searched)?.is_empty() {
+                return Err(Error::ItemExists);
+            }
+        }
+        let replace = conflict == ConflictPolicy::Replace;
 
-        let created_item = self
+        let mut created_item = self
             .collection_proxy
-            .create_item(properties, secret_struct, replace)?;
+            .create_item(
+                item_properties(label, attributes.as_map(), false),
+                secret_struct,
+                replace,
+            )
+            .map_err(Error::from);
+        if self.config.legacy_properties && created_item.as_ref().is_err_and(Error::is_invalid_args)
+        {
+            let secret_struct = format_secret(self.session, self.config, secret, content_type)?;
+            created_item = self
+                .collection_proxy
+                .create_item(
+                    item_properties(label, attributes.as_map(), true),
+                    secret_struct,
+                    replace,
+                )
+                .map_err(Error::from);
+        }
+        let created_item = created_item.map_err(self.context("create_item"))?;
 
         // This prompt handling is practically identical to create_collection
         let item_path: ObjectPath = {
@@ -171,26 +675,84 @@ impl<'a> Collection<'a> {
                 let prompt_path = created_item.prompt;
 
                 // Exec prompt and parse result
-                let prompt_res = exec_prompt_blocking(self.conn.clone(), &prompt_path)?;
-                prompt_res.try_into()?
+                let prompt_res =
+                    exec_prompt_blocking(self.conn.clone(), self.config, &prompt_path)?;
+                util::prompt_path(self.config, prompt_res)?.into()
             } else {
                 // if not, just return created path
                 created_path.into()
             }
         };
 
-        Item::new(
-            self.conn.clone(),
-            self.session,
-            self.service_proxy,
-            item_path.into(),
+        let item = self.item(item_path.into())?;
+        self.config.record(item.path(), || Change::ItemCreated {
+            collection: self.path(),
+            label: label.to_owned(),
+            secret: SecretHash::new(secret),
+        });
+        Ok(item)
+    }
+
+    /// Creates an item with the default content type set with
+    /// [SecretServiceBuilder::default_content_type](crate::SecretServiceBuilder::default_content_type),
+    /// replacing any item holding the same attributes.
+    pub fn create_item_simple(
+        &self,
+        label: &str,
+        attributes: HashMap<&str, &str>,
+        secret: &[u8],
+    ) -> Result<Item<'_>, Error> {
+        self.create_item(
+            label,
+            attributes,
+            secret,
+            ConflictPolicy::Replace,
+            &self.config.default_content_type,
         )
     }
+
+    /// Creates an item holding a string secret, stored with a `text/plain`
+    /// content type.
+    pub fn create_item_with_string(
+        &self,
+        label: &str,
+        attributes: HashMap<&str, &str>,
+        secret: &str,
+        conflict: impl Into<ConflictPolicy>,
+    ) -> Result<Item<'_>, Error> {
+        self.create_item(
+            label,
+            attributes,
+            secret.as_bytes(),
+            conflict,
+            TEXT_CONTENT_TYPE,
+        )
+    }
+
+    /// Creates many items one after the other, see
+    /// [crate::Collection::import].
+    pub fn import(
+        &self,
+        items: impl IntoIterator<Item = NewItem>,
+        progress: impl FnMut(&Progress),
+    ) -> BatchOutcome<Imported> {
+        import::import(self, items, progress)
+    }
+}
+
+impl PartialEq for Collection<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+    }
 }
 
+impl Eq for Collection<'_> {}
+
 #[cfg(test)]
 mod test {
     use crate::blocking::*;
+    use crate::testing::TestHarness;
+    use crate::{ConflictPolicy, Imported, ItemEvent, ItemTimestamp, NewItem};
 
     #[test]
     fn should_create_collection_struct() {
@@ -199,6 +761,28 @@ mod test {
         // tested under SecretService struct
     }
 
+    #[test_with::no_env(GITHUB_ACTIONS)]
+    #[test]
+    fn should_receive_item_events() {
+        let harness = TestHarness::start().unwrap();
+        let ss = harness.connect_blocking(EncryptionType::Plain).unwrap();
+        let collection = ss.create_collection("TestItemEvents", "").unwrap();
+        let mut events = collection.receive_item_events().unwrap();
+
+        let item = collection
+            .create_item("Test", HashMap::new(), b"test", false, "text/plain")
+            .unwrap();
+        let path = item.path();
+        while events.next().unwrap() != ItemEvent::Created(path.clone()) {}
+
+        item.set_label("TestRelabeled").unwrap();
+        while events.next().unwrap() != ItemEvent::Changed(path.clone()) {}
+
+        item.delete().unwrap();
+        while events.next().unwrap() != ItemEvent::Deleted(path.clone()) {}
+        collection.delete().unwrap();
+    }
+
     #[test]
     fn should_check_if_collection_locked() {
         let ss = SecretService::connect(EncryptionType::Plain).unwrap();
@@ -213,15 +797,19 @@ mod test {
         let collection = ss.get_default_collection().unwrap();
         let locked = collection.is_locked().unwrap();
         if locked {
-            collection.unlock().unwrap();
+            let unlocked = collection.unlock().unwrap();
+            assert!(unlocked.contains(&collection.path()));
             collection.ensure_unlocked().unwrap();
             assert!(!collection.is_locked().unwrap());
-            collection.lock().unwrap();
+            let locked = collection.lock().unwrap();
+            assert!(locked.contains(&collection.path()));
             assert!(collection.is_locked().unwrap());
         } else {
-            collection.lock().unwrap();
+            let locked = collection.lock().unwrap();
+            assert!(locked.contains(&collection.path()));
             assert!(collection.is_locked().unwrap());
-            collection.unlock().unwrap();
+            let unlocked = collection.unlock().unwrap();
+            assert!(unlocked.contains(&collection.path()));
             collection.ensure_unlocked().unwrap();
             assert!(!collection.is_locked().unwrap());
         }
@@ -234,7 +822,7 @@ mod test {
         let collections = ss.get_all_collections().unwrap();
         let count_before = collections.len();
         for collection in collections {
-            let collection_path = &*collection.collection_path;
+            let collection_path = &*collection.path;
             if collection_path.contains("Test") {
                 collection.unlock().unwrap();
                 collection.delete().unwrap();
@@ -248,6 +836,30 @@ mod test {
         )
     }
 
+    #[test_with::no_env(GITHUB_ACTIONS)]
+    #[test]
+    fn should_refresh_recreated_collection() {
+        let harness = TestHarness::start().unwrap();
+        let ss = harness.connect_blocking(EncryptionType::Plain).unwrap();
+        let mut collection = ss
+            .create_collection("TestRefresh", "test_refresh_blocking")
+            .unwrap();
+        let old_path = collection.path();
+        assert!(collection.exists().unwrap());
+
+        let other = ss.get_collection_by_alias("test_refresh_blocking").unwrap();
+        other.delete().unwrap();
+        assert!(!collection.exists().unwrap());
+        assert!(matches!(collection.refresh(), Err(Error::NoResult)));
+
+        ss.create_collection("TestRefreshAgain", "test_refresh_blocking")
+            .unwrap();
+        collection.refresh().unwrap();
+        assert_ne!(collection.path(), old_path);
+        assert!(collection.exists().unwrap());
+        collection.delete().unwrap();
+    }
+
     #[test]
     fn should_get_all_items() {
         let ss = SecretService::connect(EncryptionType::Plain).unwrap();
@@ -255,6 +867,90 @@ mod test {
         collection.get_all_items().unwrap();
     }
 
+    #[test]
+    fn should_get_items_paged() {
+        let ss = SecretService::connect(EncryptionType::Plain).unwrap();
+        let collection = ss.get_default_collection().unwrap();
+        let created: Vec<_> = (0..3)
+            .map(|_| {
+                collection
+                    .create_item("test", HashMap::new(), b"test", false, "text/plain")
+                    .unwrap()
+            })
+            .collect();
+
+        let all_items = collection.get_all_items().unwrap();
+        let pages = collection
+            .items_paged(std::num::NonZeroUsize::new(2).unwrap())
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert!(pages.iter().all(|page| !page.is_empty() && page.len() <= 2));
+        assert_eq!(pages.iter().map(Vec::len).sum::<usize>(), all_items.len());
+
+        for item in created {
+            item.delete().unwrap();
+        }
+    }
+
+    #[test]
+    fn should_find_purge_candidates() {
+        let ss = SecretService::connect(EncryptionType::Plain).unwrap();
+        let collection = ss.get_default_collection().unwrap();
+        let item = collection
+            .create_item("test", HashMap::new(), b"test", false, "text/plain")
+            .unwrap();
+
+        // Nothing is older than the epoch, so this must not delete anything.
+        let purged = collection
+            .purge_older_than(0, ItemTimestamp::Created, false)
+            .unwrap();
+        assert!(purged.results.is_empty());
+
+        let candidates = collection
+            .purge_older_than(u64::MAX, ItemTimestamp::Modified, true)
+            .unwrap();
+        assert!(candidates.succeeded().any(|(path, _)| *path == item.path()));
+
+        // dry run leaves the item in place
+        item.get_label().unwrap();
+        item.delete().unwrap();
+    }
+
+    #[test]
+    fn should_find_duplicates() {
+        let ss = SecretService::connect(EncryptionType::Dh).unwrap();
+        let collection = ss.get_default_collection().unwrap();
+        let attributes = HashMap::from([("test_attributes_in_duplicates", "test")]);
+        let first = collection
+            .create_item("test", attributes.clone(), b"test", false, "text/plain")
+            .unwrap();
+        let second = collection
+            .create_item("test", attributes.clone(), b"test", false, "text/plain")
+            .unwrap();
+        let third = collection
+            .create_item("test", attributes, b"other", false, "text/plain")
+            .unwrap();
+
+        let find_group = |groups: &[Vec<Item<'_>>]| {
+            groups
+                .iter()
+                .find(|group| group.iter().any(|i| i.path == first.path))
+                .map(|group| group.len())
+        };
+
+        let groups = collection.find_duplicates(false).unwrap();
+        assert_eq!(find_group(&groups), Some(3));
+
+        let groups = collection.find_duplicates(true).unwrap();
+        assert_eq!(find_group(&groups), Some(2));
+
+        for item in [first, second, third] {
+            item.delete().unwrap();
+        }
+    }
+
     #[test]
     fn should_search_items() {
         let ss = SecretService::connect(EncryptionType::Plain).unwrap();
@@ -285,10 +981,141 @@ mod test {
             .search_items(HashMap::from([("test_attributes_in_collection", "test")]))
             .unwrap();
 
-        assert_eq!(item.item_path, search_item[0].item_path);
+        assert_eq!(item.path, search_item[0].path);
         item.delete().unwrap();
     }
 
+    #[test]
+    fn should_search_items_by_tag() {
+        let ss = SecretService::connect(EncryptionType::Plain).unwrap();
+        let collection = ss.get_default_collection().unwrap();
+
+        let tags = crate::attributes::encode_list(["work", "a,b"]);
+        let item = collection
+            .create_item(
+                "test",
+                HashMap::from([
+                    ("test_tags_in_collection", tags.as_str()),
+                    ("test_tag_scope", "blocking_collection"),
+                ]),
+                b"test_secret",
+                false,
+                "text/plain",
+            )
+            .unwrap();
+
+        let scope = HashMap::from([("test_tag_scope", "blocking_collection")]);
+        let found = collection
+            .search_items_by_tag(scope.clone(), "test_tags_in_collection", "work")
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(item.path, found[0].path);
+
+        let found = collection
+            .search_items_by_tag(scope, "test_tags_in_collection", "b")
+            .unwrap();
+        assert!(found.is_empty());
+
+        item.delete().unwrap();
+    }
+
+    #[test]
+    fn should_apply_conflict_policy() {
+        let ss = SecretService::connect(EncryptionType::Plain).unwrap();
+        let collection = ss.get_default_collection().unwrap();
+        let attributes = HashMap::from([("test_attribute_conflict", "test_value")]);
+        let item = collection
+            .create_item("Test", attributes.clone(), b"test", false, "text/plain")
+            .unwrap();
+
+        let created = collection.create_item(
+            "Test",
+            attributes.clone(),
+            b"other",
+            ConflictPolicy::Fail,
+            "text/plain",
+        );
+        assert!(matches!(created, Err(Error::ItemExists)));
+        assert_eq!(item.get_secret().unwrap(), b"test");
+
+        let other = collection
+            .create_item(
+                "Test",
+                attributes.clone(),
+                b"other",
+                ConflictPolicy::CreateAnyway,
+                "text/plain",
+            )
+            .unwrap();
+        assert_ne!(item.path(), other.path());
+
+        item.delete().unwrap();
+        other.delete().unwrap();
+        // items holding more attributes don't conflict
+        let mut superset = attributes.clone();
+        superset.insert("test_attribute_conflict_extra", "test_value");
+        let larger = collection
+            .create_item("Test", superset, b"test", false, "text/plain")
+            .unwrap();
+        let created = collection
+            .create_item(
+                "Test",
+                attributes,
+                b"test",
+                ConflictPolicy::Fail,
+                "text/plain",
+            )
+            .unwrap();
+        created.delete().unwrap();
+        larger.delete().unwrap();
+    }
+
+    #[test]
+    fn should_relabel_matching_items() {
+        let ss = SecretService::connect(EncryptionType::Plain).unwrap();
+        let collection = ss.get_default_collection().unwrap();
+        let attributes = HashMap::from([("test_attribute_relabel_blocking", "test_value")]);
+        let item = collection
+            .create_item("OldName", attributes.clone(), b"test", false, "text/plain")
+            .unwrap();
+
+        let outcome = collection
+            .relabel_matching(attributes.clone(), |label| label.replace("Old", "New"))
+            .unwrap();
+        assert!(outcome.is_success());
+        assert_eq!(item.get_label().unwrap(), "NewName");
+        let outcome = collection
+            .relabel_matching(attributes, |label| label.replace("Old", "New"))
+            .unwrap();
+        assert!(outcome.results.is_empty());
+        item.delete().unwrap();
+    }
+
+    #[test]
+    fn should_import_items() {
+        let ss = SecretService::connect(EncryptionType::Plain).unwrap();
+        let collection = ss.get_default_collection().unwrap();
+        let items = vec![
+            NewItem::new("TestImport", "test").attribute("test_attribute_import_blocking", "1"),
+            NewItem::new("TestImport", "test").attribute("test_attribute_import_blocking", "1"),
+        ];
+
+        let mut done = 0;
+        let results = collection.import(items, |progress| done = progress.done);
+        assert_eq!(done, 2);
+        let Ok(Imported::Created(path)) = &results.results[0].1 else {
+            panic!("item not created: {:?}", results.results[0]);
+        };
+        assert!(matches!(results.results[1].1, Ok(Imported::Skipped)));
+
+        let found = collection
+            .search_items(HashMap::from([("test_attribute_import_blocking", "1")]))
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(&found[0].path(), path);
+        found[0].delete().unwrap();
+    }
+
     #[test]
     #[ignore]
     fn should_get_and_set_collection_label() {
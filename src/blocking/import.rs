@@ -0,0 +1,37 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use crate::blocking::Collection;
+use crate::import::{conflict_policy, imported};
+use crate::transaction::borrowed_attributes;
+use crate::{BatchOutcome, ConflictPolicy, Imported, NewItem, Progress};
+
+pub(crate) fn import(
+    collection: &Collection<'_>,
+    items: impl IntoIterator<Item = NewItem>,
+    mut progress: impl FnMut(&Progress),
+) -> BatchOutcome<Imported> {
+    let items = items.into_iter();
+    let mut state = Progress::of(&items);
+    let path = collection.path();
+
+    let results = items
+        .map(|item| {
+            let created = collection.create_item(
+                &item.label,
+                borrowed_attributes(&item.attributes),
+                &item.secret,
+                conflict_policy(&item.attributes, ConflictPolicy::Fail),
+                &item.content_type,
+            );
+            let result = imported(created.map(|created| created.path()));
+            state.advance(Some(&item.label), &result, &mut progress);
+            (path.clone(), result)
+        })
+        .collect();
+    BatchOutcome { results }
+}
@@ -5,48 +5,144 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
+use crate::blocking::Collection;
+use crate::builder::Config;
+use crate::cipher::{envelope_key_id, ClientKey, ENVELOPE_KEY_ATTRIBUTE};
+use crate::compression::strip_encoding;
 use crate::error::Error;
+use crate::history::{SecretVersion, HISTORY_OF_ATTRIBUTE};
+use crate::journal::{attribute_names, Change, SecretHash};
 use crate::proxy::item::ItemProxyBlocking;
 use crate::proxy::service::ServiceProxyBlocking;
-use crate::session::decrypt;
+use crate::proxy::SecretStruct;
 use crate::session::Session;
-use crate::ss::SS_DBUS_NAME;
-use crate::util::{exec_prompt_blocking, format_secret, lock_or_unlock_blocking, LockAction};
+use crate::trash::{trash_collection_blocking, TRASHED_FROM_ATTRIBUTE};
+use crate::util::{
+    collection_of, decrypt_secret, decrypt_session_secret, exec_prompt_blocking, format_secret,
+    format_session_secret, lock_or_unlock_blocking, secret_to_string, sort_by_keys, LockAction,
+    SortKey, SortOrder, TEXT_CONTENT_TYPE,
+};
+use crate::ItemTimestamp;
 
+use std::cmp::Reverse;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use zbus::blocking::proxy::Builder as ProxyBuilder;
 use zbus::{zvariant::OwnedObjectPath, CacheProperties};
 
 pub struct Item<'a> {
     conn: zbus::blocking::Connection,
     session: &'a Session,
+    /// Deprecated copy of [Item::path], for reading only: assigning to it
+    /// doesn't change the item the handle refers to.
+    #[deprecated(
+        since = "4.1.0",
+        note = "use `Item::path`, which doesn't expose zbus types"
+    )]
     pub item_path: OwnedObjectPath,
+    pub(crate) path: OwnedObjectPath,
     item_proxy: ItemProxyBlocking<'a>,
     service_proxy: &'a ServiceProxyBlocking<'a>,
+    config: &'a Config,
+    /// Set once the object is deleted, so that later calls can say so.
+    deleted: AtomicBool,
 }
 
 impl<'a> Item<'a> {
+    /// Returns a builder for item proxies that only lacks the item path.
+    ///
+    /// Cloning it for every item skips setting up and validating the common
+    /// parts of the proxy each time, which otherwise dominates iterating
+    /// large collections.
+    pub(crate) fn proxy_builder(
+        conn: &zbus::blocking::Connection,
+        config: &Config,
+    ) -> Result<ProxyBuilder<'static, ItemProxyBlocking<'static>>, Error> {
+        Ok(ItemProxyBlocking::builder(conn)
+            .destination(config.destination.clone())?
+            .cache_properties(CacheProperties::No))
+    }
+
+    /// Creates an item from a builder returned by [Item::proxy_builder].
     pub(crate) fn new(
+        proxy_builder: ProxyBuilder<'static, ItemProxyBlocking<'static>>,
         conn: zbus::blocking::Connection,
         session: &'a Session,
         service_proxy: &'a ServiceProxyBlocking<'a>,
+        config: &'a Config,
         item_path: OwnedObjectPath,
     ) -> Result<Self, Error> {
-        let item_proxy = ItemProxyBlocking::builder(&conn)
-            .destination(SS_DBUS_NAME)?
-            .path(item_path.clone())?
-            .cache_properties(CacheProperties::No)
-            .build()?;
+        let item_proxy = proxy_builder.path(item_path.clone())?.build()?;
+        #[allow(deprecated)]
         Ok(Item {
             conn,
             session,
+            path: item_path.clone(),
             item_path,
             item_proxy,
             service_proxy,
+            config,
+            deleted: AtomicBool::new(false),
         })
     }
 
+    /// Returns a handle to the same item that transfers secrets in
+    /// `session`, see [SecretService::open_session](crate::blocking::SecretService::open_session).
+    pub fn with_session<'s>(&'s self, session: &'s Session) -> Item<'s> {
+        #[allow(deprecated)]
+        Item {
+            conn: self.conn.clone(),
+            session,
+            item_path: self.path.clone(),
+            path: self.path.clone(),
+            item_proxy: self.item_proxy.clone(),
+            service_proxy: self.service_proxy,
+            config: self.config,
+            deleted: AtomicBool::new(self.deleted.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// The object path of the item.
+    pub fn path(&self) -> crate::ObjectPath {
+        self.path.clone().into()
+    }
+
+    /// Attaches `operation` and the item's path to errors of D-Bus calls,
+    /// or reports them as [Error::ObjectDeleted] once the item is deleted.
+    fn context<E: Into<Error>>(&self, operation: &'static str) -> impl FnOnce(E) -> Error + '_ {
+        move |err| {
+            if self.deleted.load(Ordering::Relaxed) {
+                Error::ObjectDeleted
+            } else {
+                err.into().with_context(operation, &self.path)
+            }
+        }
+    }
+
     pub fn is_locked(&self) -> Result<bool, Error> {
-        Ok(self.item_proxy.locked()?)
+        self.item_proxy.locked().map_err(self.context("is_locked"))
+    }
+
+    /// Returns whether the item still exists, for handles kept around while
+    /// other applications may delete the item.
+    ///
+    /// Probes a property of the item, so that a deleted item shows up as
+    /// `false` rather than as an error.
+    pub fn exists(&self) -> Result<bool, Error> {
+        if self.deleted.load(Ordering::Relaxed) {
+            return Ok(false);
+        }
+        match self.item_proxy.locked() {
+            Ok(_) => Ok(true),
+            Err(err) => {
+                let err = Error::from(err);
+                if err.is_unknown_object() {
+                    Ok(false)
+                } else {
+                    Err(err.with_context("exists", &self.path))
+                }
+            }
+        }
     }
 
     pub fn ensure_unlocked(&self) -> Result<(), Error> {
@@ -57,103 +153,493 @@ impl<'a> Item<'a> {
         }
     }
 
-    pub fn unlock(&self) -> Result<(), Error> {
-        lock_or_unlock_blocking(
+    /// Unlocks the item, prompting the user if the provider asks to, and
+    /// returns the paths of the objects the provider reports as unlocked.
+    ///
+    /// Providers may unlock the item's collection rather than the item, so the
+    /// paths aren't necessarily the item's own.
+    pub fn unlock(&self) -> Result<Vec<crate::ObjectPath>, Error> {
+        let unlocked = lock_or_unlock_blocking(
             self.conn.clone(),
             self.service_proxy,
-            &self.item_path,
+            self.config,
+            vec![&*self.path],
             LockAction::Unlock,
         )
+        .map_err(self.context("unlock"))?;
+        Ok(unlocked.into_iter().map(Into::into).collect())
     }
 
-    pub fn lock(&self) -> Result<(), Error> {
-        lock_or_unlock_blocking(
+    /// Locks the item and returns the paths of the objects the provider
+    /// reports as locked.
+    ///
+    /// Providers may lock the item's collection rather than the item, or
+    /// refuse to lock some objects, which then don't show up in the paths.
+    pub fn lock(&self) -> Result<Vec<crate::ObjectPath>, Error> {
+        let locked = lock_or_unlock_blocking(
             self.conn.clone(),
             self.service_proxy,
-            &self.item_path,
+            self.config,
+            vec![&*self.path],
             LockAction::Lock,
         )
+        .map_err(self.context("lock"))?;
+        Ok(locked.into_iter().map(Into::into).collect())
     }
 
     pub fn get_attributes(&self) -> Result<HashMap<String, String>, Error> {
-        Ok(self.item_proxy.attributes()?)
+        let attributes = self
+            .item_proxy
+            .attributes()
+            .map_err(self.context("get_attributes"))?;
+        self.config.decrypt_attributes(attributes)
     }
 
     pub fn set_attributes(&self, attributes: HashMap<&str, &str>) -> Result<(), Error> {
-        Ok(self.item_proxy.set_attributes(attributes)?)
+        // keeps recording the envelope key the secret is encrypted with
+        let mut attributes: HashMap<&str, &str> = attributes;
+        let stored_key = match self.config.envelope_key {
+            Some(_) if !attributes.contains_key(ENVELOPE_KEY_ATTRIBUTE) => self
+                .item_proxy
+                .attributes()
+                .map_err(self.context("set_attributes"))?
+                .remove(ENVELOPE_KEY_ATTRIBUTE),
+            _ => None,
+        };
+        if let Some(id) = &stored_key {
+            attributes.insert(ENVELOPE_KEY_ATTRIBUTE, id);
+        }
+
+        let attributes = self.config.encrypt_attributes(attributes);
+        self.item_proxy
+            .set_attributes(attributes.as_map())
+            .map_err(self.context("set_attributes"))?;
+        self.config.record(self.path(), || {
+            Change::AttributesSet(attribute_names(&attributes.as_map()))
+        });
+        Ok(())
     }
 
     pub fn get_label(&self) -> Result<String, Error> {
-        Ok(self.item_proxy.label()?)
+        self.item_proxy.label().map_err(self.context("get_label"))
     }
 
     pub fn set_label(&self, new_label: &str) -> Result<(), Error> {
-        Ok(self.item_proxy.set_label(new_label)?)
+        self.item_proxy
+            .set_label(new_label)
+            .map_err(self.context("set_label"))?;
+        self.config
+            .record(self.path(), || Change::LabelSet(new_label.to_owned()));
+        Ok(())
     }
 
     /// Deletes dbus object, but struct instance still exists (current implementation)
+    ///
+    /// Calls failing on the instance afterwards return [Error::ObjectDeleted].
     pub fn delete(&self) -> Result<(), Error> {
         // ensure_unlocked handles prompt for unlocking if necessary
         self.ensure_unlocked()?;
-        let prompt_path = self.item_proxy.delete()?;
+        let prompt_path = self.item_proxy.delete().map_err(self.context("delete"))?;
 
         // "/" means no prompt necessary
         if prompt_path.as_str() != "/" {
-            exec_prompt_blocking(self.conn.clone(), &prompt_path)?;
+            exec_prompt_blocking(self.conn.clone(), self.config, &prompt_path)?;
         }
 
+        self.deleted.store(true, Ordering::Relaxed);
+        self.config.record(self.path(), || Change::Deleted);
+
         Ok(())
     }
 
-    pub fn get_secret(&self) -> Result<Vec<u8>, Error> {
-        let secret_struct = self.item_proxy.get_secret(&self.session.object_path)?;
-        let secret = secret_struct.value;
+    /// Moves the item to the trash collection, returning the item there.
+    ///
+    /// The specification has no trash, so this crate keeps one by
+    /// convention: a collection with the alias `trash`, created with the
+    /// label `Trash` when first needed. The item's label, attributes and
+    /// secret are copied there, with a `trashed_from` attribute recording
+    /// the collection the item was in, before the item is deleted.
+    /// [Item::restore] moves it back, and
+    /// [SecretService::empty_trash](crate::blocking::SecretService::empty_trash)
+    /// deletes the trashed items for good.
+    ///
+    /// The trashed item has a new path, and isn't found by searches of its
+    /// old collection.
+    pub fn trash(&self) -> Result<Item<'a>, Error> {
+        let trash = trash_collection_blocking(&self.conn, self.service_proxy, self.config)?;
+        let mut attributes = self.get_attributes()?;
+        attributes.insert(
+            TRASHED_FROM_ATTRIBUTE.to_owned(),
+            collection_of(self.path.as_str()).to_owned(),
+        );
+        self.move_to(trash, attributes, "trash")
+    }
 
-        if let Some(session_key) = self.session.get_aes_key() {
-            // get "param" (aes_iv) field out of secret struct
-            let aes_iv = secret_struct.parameters;
+    /// Moves a trashed item back to the collection it was trashed from,
+    /// returning the item there, see [Item::trash].
+    ///
+    /// Fails with [Error::NoResult] if the item wasn't trashed.
+    pub fn restore(&self) -> Result<Item<'a>, Error> {
+        let mut attributes = self.get_attributes()?;
+        let collection = attributes
+            .remove(TRASHED_FROM_ATTRIBUTE)
+            .ok_or(Error::NoResult)?;
+        let collection = OwnedObjectPath::try_from(collection)?;
+        self.move_to(collection, attributes, "restore")
+    }
 
-            // decrypt
-            let decrypted_secret = decrypt(&secret, session_key, &aes_iv)?;
+    /// Copies the item into `collection` with `attributes`, then deletes it,
+    /// for [Item::trash] and [Item::restore].
+    fn move_to(
+        &self,
+        collection: OwnedObjectPath,
+        attributes: HashMap<String, String>,
+        operation: &'static str,
+    ) -> Result<Item<'a>, Error> {
+        self.ensure_unlocked()?;
+        let label = self.get_label()?;
+        let (secret, content_type) = self.read_secret(operation)?;
 
-            Ok(decrypted_secret)
-        } else {
-            Ok(secret)
+        let collection = Collection::new(
+            self.conn.clone(),
+            self.session,
+            self.service_proxy,
+            self.config,
+            collection,
+        )?;
+        let attributes = attributes
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        let moved = collection.create_item(&label, attributes, &secret, false, &content_type)?;
+        let moved = Item::new(
+            Item::proxy_builder(&self.conn, self.config)?,
+            self.conn.clone(),
+            self.session,
+            self.service_proxy,
+            self.config,
+            moved.path.clone(),
+        )?;
+        self.delete()?;
+        Ok(moved)
+    }
+
+    pub fn get_secret(&self) -> Result<Vec<u8>, Error> {
+        let secret_struct = self
+            .item_proxy
+            .get_secret(self.session.open_blocking()?)
+            .map_err(self.context("get_secret"))?;
+
+        decrypt_secret(self.session, self.config, secret_struct).map_err(self.context("get_secret"))
+    }
+
+    /// Returns the secret, unlocking the item first if it (or its
+    /// collection) is locked.
+    ///
+    /// Unlocking may show a prompt, which is parented to the window
+    /// configured on the [SecretServiceBuilder](crate::SecretServiceBuilder).
+    pub fn get_secret_unlocking(&self) -> Result<Vec<u8>, Error> {
+        if self.is_locked()? {
+            self.unlock()?;
         }
+
+        self.get_secret()
+    }
+
+    /// Returns the secret as a string.
+    ///
+    /// Fails with [Error::InvalidUtf8] if the secret isn't valid UTF-8, or if
+    /// its content type declares a different charset.
+    pub fn get_secret_string(&self) -> Result<String, Error> {
+        let secret_struct = self
+            .item_proxy
+            .get_secret(self.session.open_blocking()?)
+            .map_err(self.context("get_secret_string"))?;
+        let content_type = strip_encoding(secret_struct.content_type.clone());
+        let secret = decrypt_secret(self.session, self.config, secret_struct)
+            .map_err(self.context("get_secret_string"))?;
+
+        secret_to_string(secret, &content_type)
     }
 
     pub fn get_secret_content_type(&self) -> Result<String, Error> {
-        let secret_struct = self.item_proxy.get_secret(&self.session.object_path)?;
-        let content_type = secret_struct.content_type;
+        let secret_struct = self
+            .item_proxy
+            .get_secret(self.session.open_blocking()?)
+            .map_err(self.context("get_secret_content_type"))?;
+        let content_type = strip_encoding(secret_struct.content_type);
 
         Ok(content_type)
     }
 
     pub fn set_secret(&self, secret: &[u8], content_type: &str) -> Result<(), Error> {
-        let secret_struct = format_secret(self.session, secret, content_type)?;
-        Ok(self.item_proxy.set_secret(secret_struct)?)
+        if self.config.secret_history != 0 {
+            self.archive_secret()?;
+        }
+        self.session.open_blocking()?;
+        let secret_struct = format_secret(self.session, self.config, secret, content_type)?;
+        self.item_proxy
+            .set_secret(secret_struct)
+            .map_err(self.context("set_secret"))?;
+        self.config
+            .record(self.path(), || Change::SecretSet(SecretHash::new(secret)));
+        match &self.config.envelope_key {
+            Some(key) => self.record_envelope_key(key.id(), "set_secret"),
+            None => Ok(()),
+        }
+    }
+
+    /// Sets the secret, unless the item was modified since
+    /// `expected_modified`, as returned by [Item::get_modified] when the
+    /// secret was read.
+    ///
+    /// Fails with [Error::Modified] otherwise, so that writers sharing an
+    /// item don't overwrite each other's changes unknowingly. The check
+    /// and the write are separate calls, so a change made in between still
+    /// goes unnoticed, and `Modified` only has a resolution of seconds.
+    pub fn set_secret_if_unmodified(
+        &self,
+        secret: &[u8],
+        content_type: &str,
+        expected_modified: u64,
+    ) -> Result<(), Error> {
+        let modified = self
+            .item_proxy
+            .modified()
+            .map_err(self.context("set_secret_if_unmodified"))?;
+        if modified != expected_modified {
+            return Err(Error::Modified);
+        }
+        self.set_secret(secret, content_type)
+    }
+
+    /// Returns the previous secrets of the item, oldest first, as kept with
+    /// [SecretServiceBuilder::secret_history](crate::SecretServiceBuilder::secret_history).
+    pub fn secret_history(&self) -> Result<Vec<SecretVersion>, Error> {
+        let archived = self.archived_secrets("secret_history")?;
+        Ok(archived.into_iter().map(|(version, _)| version).collect())
+    }
+
+    /// Sets the secret back to the one archived as `version`, see
+    /// [Item::secret_history]. With the history kept, the current secret is
+    /// archived in turn.
+    ///
+    /// Fails with [Error::NoResult] if there is no such version.
+    pub fn revert_to(&self, version: u32) -> Result<(), Error> {
+        let (_, archived) = self
+            .archived_secrets("revert_to")?
+            .into_iter()
+            .find(|(archived, _)| archived.version == version)
+            .ok_or(Error::NoResult)?;
+        let (secret, content_type) = archived.read_secret("revert_to")?;
+        self.set_secret(&secret, &content_type)
+    }
+
+    /// Returns the previous secrets of the item with the items keeping them,
+    /// oldest first.
+    fn archived_secrets(
+        &self,
+        operation: &'static str,
+    ) -> Result<Vec<(SecretVersion, Item<'a>)>, Error> {
+        let attributes = self
+            .config
+            .encrypt_attributes(HashMap::from([(HISTORY_OF_ATTRIBUTE, self.path.as_str())]));
+        let found = self
+            .service_proxy
+            .search_items(attributes.as_map())
+            .map_err(self.context(operation))?;
+
+        let mut archived = Vec::new();
+        for path in found.unlocked.into_iter().chain(found.locked) {
+            let item = Item::new(
+                Item::proxy_builder(&self.conn, self.config)?,
+                self.conn.clone(),
+                self.session,
+                self.service_proxy,
+                self.config,
+                path,
+            )?;
+            if let Some(version) = SecretVersion::from_attributes(&item.get_attributes()?) {
+                archived.push((version, item));
+            }
+        }
+        archived.sort_unstable_by_key(|(version, _)| version.version);
+        Ok(archived)
+    }
+
+    /// Copies the current secret into the history, deleting the oldest
+    /// versions beyond the configured number.
+    fn archive_secret(&self) -> Result<(), Error> {
+        let archived = self.archived_secrets("set_secret")?;
+        let versions: Vec<_> = archived.iter().map(|(version, _)| *version).collect();
+        let version = SecretVersion::next(&versions);
+
+        let (secret, content_type) = self.read_secret("set_secret")?;
+        let label = self.get_label()?;
+        let collection = self.collection()?;
+        let attributes = version.attributes(self.path.as_str());
+        collection.create_item(
+            &format!("{label} (version {})", version.version),
+            attributes
+                .iter()
+                .map(|(&key, value)| (key, value.as_str()))
+                .collect(),
+            &secret,
+            false,
+            &content_type,
+        )?;
+
+        let excess = (archived.len() + 1).saturating_sub(self.config.secret_history);
+        for (_, item) in archived.iter().take(excess) {
+            item.delete()?;
+        }
+        Ok(())
+    }
+
+    /// Returns the collection holding the item.
+    pub(crate) fn collection(&self) -> Result<Collection<'a>, Error> {
+        Collection::new(
+            self.conn.clone(),
+            self.session,
+            self.service_proxy,
+            self.config,
+            OwnedObjectPath::try_from(collection_of(self.path.as_str()))?,
+        )
+    }
+
+    /// Returns the secret with its content type.
+    pub(crate) fn read_secret(&self, operation: &'static str) -> Result<(Vec<u8>, String), Error> {
+        let secret_struct = self
+            .item_proxy
+            .get_secret(self.session.open_blocking()?)
+            .map_err(self.context(operation))?;
+        let content_type = strip_encoding(secret_struct.content_type.clone());
+        let secret = decrypt_secret(self.session, self.config, secret_struct)
+            .map_err(self.context(operation))?;
+        Ok((secret, content_type))
+    }
+
+    /// Records the id of the envelope key the secret is now encrypted with.
+    fn record_envelope_key(&self, id: &str, operation: &'static str) -> Result<(), Error> {
+        let mut attributes = self
+            .item_proxy
+            .attributes()
+            .map_err(self.context(operation))?;
+        if attributes.get(ENVELOPE_KEY_ATTRIBUTE).map(String::as_str) == Some(id) {
+            return Ok(());
+        }
+
+        // the other values are passed back as stored
+        attributes.insert(ENVELOPE_KEY_ATTRIBUTE.to_owned(), id.to_owned());
+        let attributes = attributes
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        self.item_proxy
+            .set_attributes(attributes)
+            .map_err(self.context(operation))
+    }
+
+    /// Re-encrypts the secret, as returned for the session by the provider,
+    /// from the envelope key `old` to `new`.
+    pub(crate) fn rotate_envelope_key(
+        &self,
+        secret_struct: SecretStruct,
+        old: &ClientKey,
+        new: &ClientKey,
+    ) -> Result<(), Error> {
+        let content_type = secret_struct.content_type.clone();
+        let secret = decrypt_session_secret(self.session, self.config, secret_struct)
+            .map_err(self.context("rotate_key"))?;
+
+        // an interrupted rotation may have updated the secret, but not the
+        // attribute
+        if envelope_key_id(&secret) != Some(new.id().as_bytes()) {
+            let secret = old.open(secret).map_err(self.context("rotate_key"))?;
+            let secret_struct =
+                format_session_secret(self.session, &new.seal(&secret), &content_type)?;
+            self.item_proxy
+                .set_secret(secret_struct)
+                .map_err(self.context("rotate_key"))?;
+        }
+        self.record_envelope_key(new.id(), "rotate_key")
+    }
+
+    /// Sets the secret, stored with the default content type set with
+    /// [SecretServiceBuilder::default_content_type](crate::SecretServiceBuilder::default_content_type).
+    pub fn set_secret_default(&self, secret: &[u8]) -> Result<(), Error> {
+        self.set_secret(secret, &self.config.default_content_type)
+    }
+
+    /// Sets the secret to a string, stored with a `text/plain` content type.
+    pub fn set_secret_string(&self, secret: &str) -> Result<(), Error> {
+        self.set_secret(secret.as_bytes(), TEXT_CONTENT_TYPE)
     }
 
     pub fn get_created(&self) -> Result<u64, Error> {
-        Ok(self.item_proxy.created()?)
+        self.item_proxy
+            .created()
+            .map_err(self.context("get_created"))
     }
 
     pub fn get_modified(&self) -> Result<u64, Error> {
-        Ok(self.item_proxy.modified()?)
+        self.item_proxy
+            .modified()
+            .map_err(self.context("get_modified"))
+    }
+
+    pub(crate) fn sort_key(&self, order: SortOrder) -> Result<SortKey, Error> {
+        Ok(match order {
+            SortOrder::Newest(ItemTimestamp::Created) => {
+                SortKey::Newest(Reverse(self.get_created()?))
+            }
+            SortOrder::Newest(ItemTimestamp::Modified) => {
+                SortKey::Newest(Reverse(self.get_modified()?))
+            }
+            SortOrder::Label => SortKey::label(self.get_label()?),
+        })
     }
 }
 
+fn sort_items(items: &mut Vec<Item<'_>>, order: SortOrder) -> Result<(), Error> {
+    let keys = items
+        .iter()
+        .map(|item| item.sort_key(order))
+        .collect::<Result<_, _>>()?;
+    sort_by_keys(items, keys);
+    Ok(())
+}
+
+/// Sorts items by when they were last modified, most recent first.
+///
+/// Items with the same timestamp keep their order.
+pub fn sort_by_modified(items: &mut Vec<Item<'_>>) -> Result<(), Error> {
+    sort_items(items, SortOrder::Newest(ItemTimestamp::Modified))
+}
+
+/// Sorts items by when they were created, most recent first.
+///
+/// Items with the same timestamp keep their order.
+pub fn sort_by_created(items: &mut Vec<Item<'_>>) -> Result<(), Error> {
+    sort_items(items, SortOrder::Newest(ItemTimestamp::Created))
+}
+
+/// Sorts items alphabetically by label, ignoring case.
+pub fn sort_by_label(items: &mut Vec<Item<'_>>) -> Result<(), Error> {
+    sort_items(items, SortOrder::Label)
+}
+
 impl<'a> Eq for Item<'a> {}
 impl<'a> PartialEq for Item<'a> {
     fn eq(&self, other: &Item) -> bool {
-        self.item_path == other.item_path
-            && self.get_attributes().unwrap() == other.get_attributes().unwrap()
+        self.path == other.path && self.get_attributes().unwrap() == other.get_attributes().unwrap()
     }
 }
 
 #[cfg(test)]
 mod test {
     use crate::blocking::*;
+    use crate::testing::TestHarness;
 
     fn create_test_default_item<'a>(collection: &'a Collection<'_>) -> Item<'a> {
         collection
@@ -174,6 +660,103 @@ mod test {
         }
     }
 
+    #[test]
+    fn should_check_if_item_exists() {
+        let ss = SecretService::connect(EncryptionType::Plain).unwrap();
+        let collection = ss.get_default_collection().unwrap();
+        let attributes = HashMap::from([("test_attributes_exists_blocking", "test")]);
+        let item = collection
+            .create_item("Test", attributes.clone(), b"test", true, "text/plain")
+            .unwrap();
+        assert!(item.exists().unwrap());
+
+        let other = collection.search_items(attributes).unwrap();
+        other[0].delete().unwrap();
+        assert!(!item.exists().unwrap());
+        assert!(!other[0].exists().unwrap());
+    }
+
+    #[test_with::no_env(GITHUB_ACTIONS)]
+    #[test]
+    fn should_trash_and_restore() {
+        let harness = TestHarness::start().unwrap();
+        let ss = harness.connect_blocking(EncryptionType::Plain).unwrap();
+        let collection = ss.get_default_collection().unwrap();
+        let attributes = HashMap::from([("test_attribute_trash_blocking", "test_value")]);
+        let item = collection
+            .create_item("Test", attributes.clone(), b"test", false, "text/plain")
+            .unwrap();
+
+        let trashed = item.trash().unwrap();
+        assert!(!item.exists().unwrap());
+        assert_eq!(
+            trashed.get_attributes().unwrap()["trashed_from"],
+            collection.path().as_str()
+        );
+
+        let restored = trashed.restore().unwrap();
+        let found = collection.search_items(attributes).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].path(), restored.path());
+        assert_eq!(restored.get_secret().unwrap(), b"test");
+        restored.delete().unwrap();
+    }
+
+    #[test]
+    fn should_keep_secret_history() {
+        let ss = SecretService::builder()
+            .encryption(EncryptionType::Plain)
+            .secret_history(1)
+            .connect_blocking()
+            .unwrap();
+        let collection = ss.get_default_collection().unwrap();
+        let item = create_test_default_item(&collection);
+
+        item.set_secret(b"a", "text/plain").unwrap();
+        item.set_secret(b"b", "text/plain").unwrap();
+        let history = item.secret_history().unwrap();
+        assert_eq!(history.len(), 1);
+        item.revert_to(history[0].version).unwrap();
+        assert_eq!(item.get_secret().unwrap(), b"a");
+
+        let path = item.path();
+        let archived = ss
+            .search_items(HashMap::from([("history_of", path.as_str())]))
+            .unwrap();
+        for archived in archived.unlocked {
+            archived.delete().unwrap();
+        }
+        item.delete().unwrap();
+    }
+
+    #[test]
+    fn should_set_secret_if_unmodified() {
+        let ss = SecretService::connect(EncryptionType::Plain).unwrap();
+        let collection = ss.get_default_collection().unwrap();
+        let item = create_test_default_item(&collection);
+
+        let modified = item.get_modified().unwrap();
+        let stale = item.set_secret_if_unmodified(b"other", "text/plain", modified + 1);
+        assert!(matches!(stale, Err(Error::Modified)));
+        assert_eq!(item.get_secret().unwrap(), b"test");
+
+        item.set_secret_if_unmodified(b"other", "text/plain", modified)
+            .unwrap();
+        assert_eq!(item.get_secret().unwrap(), b"other");
+        item.delete().unwrap();
+    }
+
+    #[test]
+    fn should_report_use_after_delete() {
+        let ss = SecretService::connect(EncryptionType::Plain).unwrap();
+        let collection = ss.get_default_collection().unwrap();
+        let item = create_test_default_item(&collection);
+        item.delete().unwrap();
+
+        assert!(matches!(item.get_label(), Err(Error::ObjectDeleted)));
+        assert!(matches!(item.delete(), Err(Error::ObjectDeleted)));
+    }
+
     #[test]
     fn should_check_if_item_locked() {
         let ss = SecretService::connect(EncryptionType::Plain).unwrap();
@@ -308,6 +891,48 @@ mod test {
         assert_eq!(secret, b"test");
     }
 
+    #[test]
+    fn should_reject_invalid_content_type() {
+        let ss = SecretService::connect(EncryptionType::Plain).unwrap();
+        let collection = ss.get_default_collection().unwrap();
+        let item = create_test_default_item(&collection);
+
+        assert!(matches!(
+            item.set_secret(b"new", "text plain"),
+            Err(Error::InvalidContentType)
+        ));
+        assert_eq!(item.get_secret().unwrap(), b"test");
+        item.delete().unwrap();
+
+        assert!(matches!(
+            collection.create_item("Test", HashMap::new(), b"test", false, "text/"),
+            Err(Error::InvalidContentType)
+        ));
+    }
+
+    #[test]
+    fn should_get_secret_unlocking() {
+        let ss = SecretService::connect(EncryptionType::Plain).unwrap();
+        let collection = ss.get_default_collection().unwrap();
+        let item = create_test_default_item(&collection);
+
+        assert_eq!(item.get_secret_unlocking().unwrap(), b"test");
+        item.delete().unwrap();
+    }
+
+    #[test]
+    #[ignore] // should unignore this test this manually, otherwise will constantly prompt during tests.
+    fn should_get_secret_unlocking_locked_item() {
+        let ss = SecretService::connect(EncryptionType::Plain).unwrap();
+        let collection = ss.get_default_collection().unwrap();
+        let item = create_test_default_item(&collection);
+
+        item.lock().unwrap();
+        assert_eq!(item.get_secret_unlocking().unwrap(), b"test");
+        assert!(!item.is_locked().unwrap());
+        item.delete().unwrap();
+    }
+
     #[test]
     fn should_get_secret_content_type() {
         let ss = SecretService::connect(EncryptionType::Plain).unwrap();
@@ -319,6 +944,25 @@ mod test {
         assert_eq!(content_type, "text/plain".to_owned());
     }
 
+    #[test]
+    fn should_create_and_get_secret_string() {
+        let ss = SecretService::connect(EncryptionType::Dh).unwrap();
+        let collection = ss.get_default_collection().unwrap();
+        let item = collection
+            .create_item_with_string("Test", HashMap::new(), "tëst", false)
+            .unwrap();
+
+        assert_eq!(item.get_secret_string().unwrap(), "tëst");
+        assert_eq!(item.get_secret_content_type().unwrap(), "text/plain");
+
+        item.set_secret_string("new_tëst").unwrap();
+        assert_eq!(item.get_secret_string().unwrap(), "new_tëst");
+
+        item.set_secret(&[0xff, 0xfe], "text/plain").unwrap();
+        assert!(matches!(item.get_secret_string(), Err(Error::InvalidUtf8)));
+        item.delete().unwrap();
+    }
+
     #[test]
     fn should_set_secret() {
         let ss = SecretService::connect(EncryptionType::Plain).unwrap();
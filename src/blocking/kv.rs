@@ -0,0 +1,87 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use crate::blocking::Collection;
+use crate::kv::{keys_of, KV_KEY_ATTRIBUTE};
+use crate::util::TEXT_CONTENT_TYPE;
+use crate::{ConflictPolicy, Error};
+
+use std::collections::HashMap;
+
+/// A map from strings to secret strings, stored in a collection, see
+/// [crate::KvStore].
+pub struct KvStore<'a> {
+    collection: Collection<'a>,
+    attribute: String,
+}
+
+impl<'a> KvStore<'a> {
+    pub fn new(collection: Collection<'a>) -> Self {
+        Self::with_attribute(collection, KV_KEY_ATTRIBUTE)
+    }
+
+    /// Creates a store keeping its keys in `attribute`.
+    pub fn with_attribute(collection: Collection<'a>, attribute: &str) -> Self {
+        KvStore {
+            collection,
+            attribute: attribute.to_owned(),
+        }
+    }
+
+    /// Returns the value of `key`, if any.
+    pub fn get(&self, key: &str) -> Result<Option<String>, Error> {
+        let items = self.collection.search_items(self.attributes(key))?;
+        items
+            .first()
+            .map(|item| item.get_secret_string())
+            .transpose()
+    }
+
+    /// Sets the value of `key`, replacing any previous one.
+    pub fn insert(&self, key: &str, value: &str) -> Result<(), Error> {
+        self.collection
+            .create_item(
+                key,
+                self.attributes(key),
+                value.as_bytes(),
+                ConflictPolicy::Replace,
+                TEXT_CONTENT_TYPE,
+            )
+            .map(drop)
+    }
+
+    /// Removes `key`, returning whether it was there.
+    pub fn remove(&self, key: &str) -> Result<bool, Error> {
+        let items = self.collection.search_items(self.attributes(key))?;
+        for item in &items {
+            item.delete()?;
+        }
+        Ok(!items.is_empty())
+    }
+
+    /// Returns the keys of the store, sorted.
+    ///
+    /// See [crate::KvStore::keys].
+    pub fn keys(&self) -> Result<Vec<String>, Error> {
+        let attributes = self
+            .collection
+            .get_all_items()?
+            .iter()
+            .map(|item| item.get_attributes())
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(keys_of(&self.attribute, attributes))
+    }
+
+    /// Returns the collection the store is kept in.
+    pub fn into_inner(self) -> Collection<'a> {
+        self.collection
+    }
+
+    fn attributes<'k>(&'k self, key: &'k str) -> HashMap<&'k str, &'k str> {
+        HashMap::from([(self.attribute.as_str(), key)])
+    }
+}
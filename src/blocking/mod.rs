@@ -17,18 +17,44 @@
 //! [zbus's blocking documentation]: https://docs.rs/zbus/latest/zbus/blocking/index.html
 //! [async `SecretService`]: crate::SecretService
 
+use crate::builder::Config;
+use crate::event;
+use crate::maintenance::{FoundObject, Matcher};
+use crate::proxy::service::{ServiceProxy, ServiceProxyBlocking};
+use crate::query::{self, Query};
+use crate::reconnect;
 use crate::session::Session;
-use crate::ss::SS_COLLECTION_LABEL;
+use crate::trash::TRASH_ALIAS;
 use crate::util;
-use crate::{proxy::service::ServiceProxyBlocking, util::exec_prompt_blocking};
-use crate::{EncryptionType, Error, SearchItemsResult};
-use std::collections::HashMap;
-use zbus::zvariant::{ObjectPath, Value};
+use crate::{
+    BatchOutcome, ClientKey, CollectionEvent, ConnectOptions, EncryptionType, Error, LabelMatch,
+    ObjectPath, PrefetchedItem, Progress, PromptCanceller, SearchItemsResult, SecretServiceBuilder,
+    SecretServiceEvent, ServerInfo, SessionInfo, TreeDump, WindowIdentifier,
+};
+use futures_util::{Stream, StreamExt};
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroUsize;
+use zbus::zvariant::OwnedObjectPath;
 
 mod collection;
 pub use collection::Collection;
+mod import;
 mod item;
-pub use item::Item;
+pub use item::{sort_by_created, sort_by_label, sort_by_modified, Item};
+mod kv;
+pub use kv::KvStore;
+mod object;
+pub use object::{Object, ObjectRef};
+mod rotation;
+mod search;
+pub use search::ItemSearch;
+#[cfg(feature = "tokio")]
+mod spawn;
+#[cfg(feature = "tokio")]
+pub use spawn::SpawnBlocking;
+mod transaction;
+pub use transaction::Transaction;
+pub mod typestate;
 
 /// Secret Service Struct.
 ///
@@ -38,28 +64,176 @@ pub use item::Item;
 /// and negotiate a new cryptographic session
 /// ([EncryptionType::Plain] or [EncryptionType::Dh])
 pub struct SecretService<'a> {
-    conn: zbus::blocking::Connection,
-    session: Session,
-    service_proxy: ServiceProxyBlocking<'a>,
+    pub(crate) conn: zbus::blocking::Connection,
+    pub(crate) session: Session,
+    pub(crate) service_proxy: ServiceProxyBlocking<'a>,
+    pub(crate) config: Config,
 }
 
 impl<'a> SecretService<'a> {
     /// Create a new `SecretService` instance
     pub fn connect(encryption: EncryptionType) -> Result<Self, Error> {
-        let conn = zbus::blocking::Connection::session().map_err(util::handle_conn_error)?;
-        let service_proxy = ServiceProxyBlocking::new(&conn).map_err(util::handle_conn_error)?;
+        Self::builder().encryption(encryption).connect_blocking()
+    }
 
-        let session = Session::new_blocking(&service_proxy, encryption)?;
+    /// Creates a new `SecretService` instance talking to the provider over
+    /// an existing connection, see [SecretServiceBuilder::connection].
+    pub fn connect_with(
+        conn: zbus::blocking::Connection,
+        encryption: EncryptionType,
+    ) -> Result<Self, Error> {
+        Self::builder()
+            .encryption(encryption)
+            .connection(conn.into_inner())
+            .connect_blocking()
+    }
 
-        Ok(SecretService {
-            conn,
-            session,
-            service_proxy,
-        })
+    /// Creates a new `SecretService` instance talking to the provider found
+    /// with `options`, see [ConnectOptions].
+    pub fn connect_with_options(
+        options: ConnectOptions,
+        encryption: EncryptionType,
+    ) -> Result<Self, Error> {
+        Self::builder()
+            .encryption(encryption)
+            .options(options)
+            .connect_blocking()
+    }
+
+    /// Returns a [SecretServiceBuilder] for configuring the connection
+    /// before creating a `SecretService` instance.
+    ///
+    /// Finish it with [SecretServiceBuilder::connect_blocking].
+    pub fn builder() -> SecretServiceBuilder {
+        SecretServiceBuilder::new()
+    }
+
+    /// Returns the executor running the connection's internal tasks.
+    ///
+    /// Only needs to be ticked, from another thread, if the connection was
+    /// made with [SecretServiceBuilder::internal_executor] turned off.
+    pub fn executor(&self) -> &zbus::Executor<'static> {
+        self.conn.inner().executor()
+    }
+
+    /// Connects again and negotiates a new session, the way the builder
+    /// connected, see [crate::SecretService::reconnect].
+    pub fn reconnect(&mut self) -> Result<(), Error> {
+        let (conn, service_proxy, session) =
+            zbus::block_on(reconnect::reconnect(&self.config, self.conn.inner()))?;
+        self.conn = conn.into();
+        self.service_proxy = service_proxy.into_inner().into();
+        self.session = session;
+        Ok(())
+    }
+
+    /// Runs `op`, and if it fails because the connection was lost, connects
+    /// again with [SecretService::reconnect] and runs it once more.
+    ///
+    /// Only for operations that can safely run twice, see
+    /// [crate::SecretService::with_reconnect].
+    pub fn with_reconnect<T>(
+        &mut self,
+        mut op: impl FnMut(&SecretService<'a>) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        match op(self) {
+            Err(err) if err.is_connection_lost() => {
+                self.reconnect()?;
+                op(self)
+            }
+            result => result,
+        }
+    }
+
+    /// Describes the session negotiated with the provider, for diagnostics.
+    ///
+    /// With [SecretServiceBuilder::lazy_session], this describes the session
+    /// to be negotiated until a secret is transferred, with the path `/`.
+    pub fn session(&self) -> SessionInfo {
+        self.session.info()
+    }
+
+    /// Parents the prompts shown from now on to `window`, or to no window.
+    ///
+    /// See [SecretService::set_window](crate::SecretService::set_window).
+    pub fn set_window(&self, window: Option<WindowIdentifier>) {
+        self.config.set_window(window);
+    }
+
+    /// Returns a handle cancelling the prompts this `SecretService` is
+    /// waiting for, from another thread.
+    ///
+    /// See [SecretService::prompt_canceller](crate::SecretService::prompt_canceller).
+    pub fn prompt_canceller(&self) -> PromptCanceller {
+        self.config.prompts.clone()
+    }
+
+    /// Describes the provider, for bug reports and working around the
+    /// quirks of specific providers.
+    ///
+    /// See [SecretService::server_info](crate::SecretService::server_info).
+    pub fn server_info(&self) -> Result<ServerInfo, Error> {
+        crate::server_info::server_info_blocking(self)
+    }
+
+    /// Opens another session with the provider, for operations that should
+    /// use a different kind of session than the one negotiated when
+    /// connecting.
+    ///
+    /// Collections and items use it through their `with_session` methods,
+    /// for example a plain session for bulk reads of non-sensitive secrets
+    /// next to an encrypted one for the rest. The key protection settings
+    /// of the builder don't apply to it.
+    pub fn open_session(&self, encryption: EncryptionType) -> Result<Session, Error> {
+        Session::new_blocking(&self.service_proxy, encryption)
+    }
+
+    /// Describes every collection and item of the provider, without secrets
+    /// or attribute values.
+    ///
+    /// See [SecretService::dump_tree](crate::SecretService::dump_tree).
+    pub fn dump_tree(&self) -> Result<TreeDump, Error> {
+        crate::dump::dump_tree_blocking(self)
+    }
+
+    /// Deletes the collections and items `matcher` selects.
+    ///
+    /// See [SecretService::cleanup](crate::SecretService::cleanup).
+    pub fn cleanup(
+        &self,
+        matcher: &Matcher,
+        dry_run: bool,
+        confirm: impl FnMut(&ObjectPath, &FoundObject) -> bool,
+    ) -> Result<BatchOutcome<FoundObject>, Error> {
+        crate::maintenance::cleanup_blocking(self, matcher, dry_run, confirm)
+    }
+
+    /// Re-encrypts the secrets of all items encrypted with the envelope key
+    /// `old` with `new`, `batch_size` items at a time.
+    ///
+    /// See [SecretService::rotate_key](crate::SecretService::rotate_key).
+    pub fn rotate_key(
+        &self,
+        old: &ClientKey,
+        new: &ClientKey,
+        batch_size: NonZeroUsize,
+    ) -> Result<BatchOutcome, Error> {
+        rotation::rotate_key(self, old, new, batch_size, |_| ())
+    }
+
+    /// Like [SecretService::rotate_key], calling `progress` after each item.
+    pub fn rotate_key_with_progress(
+        &self,
+        old: &ClientKey,
+        new: &ClientKey,
+        batch_size: NonZeroUsize,
+        progress: impl FnMut(&Progress),
+    ) -> Result<BatchOutcome, Error> {
+        rotation::rotate_key(self, old, new, batch_size, progress)
     }
 
     /// Get all collections
-    pub fn get_all_collections(&self) -> Result<Vec<Collection>, Error> {
+    pub fn get_all_collections(&self) -> Result<Vec<Collection<'_>>, Error> {
         let collections = self.service_proxy.collections()?;
         collections
             .into_iter()
@@ -68,43 +242,102 @@ impl<'a> SecretService<'a> {
                     self.conn.clone(),
                     &self.session,
                     &self.service_proxy,
+                    &self.config,
                     object_path.into(),
                 )
             })
             .collect()
     }
 
+    /// Get all collections lazily, setting each one up only when the
+    /// iterator reaches it.
+    pub fn collections_iter(
+        &self,
+    ) -> Result<impl Iterator<Item = Result<Collection<'_>, Error>> + '_, Error> {
+        let collections = self.service_proxy.collections()?;
+
+        Ok(collections.into_iter().map(|object_path| {
+            Collection::new(
+                self.conn.clone(),
+                &self.session,
+                &self.service_proxy,
+                &self.config,
+                object_path.into(),
+            )
+        }))
+    }
+
     /// Get collection by alias.
     ///
     /// Most common would be the `default` alias, but there
     /// is also a specific method for getting the collection
     /// by default alias.
-    pub fn get_collection_by_alias(&self, alias: &str) -> Result<Collection, Error> {
-        let object_path = self.service_proxy.read_alias(alias)?;
+    ///
+    /// Resolved aliases are cached until the service signals that a
+    /// collection was created, deleted or changed.
+    pub fn get_collection_by_alias(&self, alias: &str) -> Result<Collection<'_>, Error> {
+        let object_path = self.resolve_alias(alias)?;
+        Ok(Collection::new(
+            self.conn.clone(),
+            &self.session,
+            &self.service_proxy,
+            &self.config,
+            object_path,
+        )?
+        .with_alias(alias))
+    }
 
-        if object_path.as_str() == "/" {
-            Err(Error::NoResult)
-        } else {
-            Ok(Collection::new(
-                self.conn.clone(),
-                &self.session,
-                &self.service_proxy,
-                object_path,
-            )?)
-        }
+    /// Get the object path of the collection behind an alias, cached like
+    /// in [SecretService::get_collection_by_alias].
+    fn resolve_alias(&self, alias: &str) -> Result<OwnedObjectPath, Error> {
+        util::resolve_alias_blocking(&self.conn, &self.service_proxy, &self.config, alias)
+    }
+
+    /// Returns whether a collection exists under `alias`, without looking
+    /// it up, for startup probes.
+    pub fn collection_exists(&self, alias: &str) -> Result<bool, Error> {
+        let object_path = self.service_proxy.read_alias(alias)?;
+        Ok(object_path.as_str() != "/")
     }
 
     /// Get default collection.
     /// (The collection whos alias is `default`)
-    pub fn get_default_collection(&self) -> Result<Collection, Error> {
+    pub fn get_default_collection(&self) -> Result<Collection<'_>, Error> {
         self.get_collection_by_alias("default")
     }
 
+    /// Get the object path of the default collection, without looking the
+    /// collection up, for comparing or persisting it.
+    ///
+    /// Like [SecretService::get_default_collection], returns
+    /// [Error::NoResult] if there is no default collection.
+    pub fn default_collection_path(&self) -> Result<crate::ObjectPath, Error> {
+        Ok(self.resolve_alias("default")?.into())
+    }
+
+    /// Makes `collection` available under `alias`, replacing the collection
+    /// the alias pointed to, if any.
+    pub fn set_alias(&self, alias: &str, collection: &Collection<'_>) -> Result<(), Error> {
+        let result = self
+            .service_proxy
+            .set_alias(alias, collection.path.as_ref());
+        // the alias may have moved even if the call failed midway
+        self.config.alias_cache.clear();
+        Ok(result?)
+    }
+
+    /// Makes `collection` the default collection.
+    ///
+    /// See [SecretService::make_default_collection](crate::SecretService::make_default_collection).
+    pub fn make_default_collection(&self, collection: &Collection<'_>) -> Result<(), Error> {
+        self.set_alias("default", collection)
+    }
+
     /// Get any collection.
     /// First tries `default` collection, then `session`
     /// collection, then the first collection when it
     /// gets all collections.
-    pub fn get_any_collection(&self) -> Result<Collection, Error> {
+    pub fn get_any_collection(&self) -> Result<Collection<'_>, Error> {
         // default first, then session, then first
 
         self.get_default_collection()
@@ -120,53 +353,75 @@ impl<'a> SecretService<'a> {
     }
 
     /// Creates a new collection with a label and an alias.
-    pub fn create_collection(&self, label: &str, alias: &str) -> Result<Collection, Error> {
-        let mut properties: HashMap<&str, Value> = HashMap::new();
-        properties.insert(SS_COLLECTION_LABEL, label.into());
-
-        let created_collection = self.service_proxy.create_collection(properties, alias)?;
-
-        // This prompt handling is practically identical to create_collection
-        let collection_path: ObjectPath = {
-            // Get path of created object
-            let created_path = created_collection.collection;
-
-            // Check if that path is "/", if so should execute a prompt
-            if created_path.as_str() == "/" {
-                let prompt_path = created_collection.prompt;
-
-                // Exec prompt and parse result
-                let prompt_res = util::exec_prompt_blocking(self.conn.clone(), &prompt_path)?;
-                prompt_res.try_into()?
-            } else {
-                // if not, just return created path
-                created_path.into()
-            }
-        };
+    pub fn create_collection(&self, label: &str, alias: &str) -> Result<Collection<'_>, Error> {
+        let collection_path = util::create_collection_blocking(
+            self.conn.clone(),
+            &self.service_proxy,
+            &self.config,
+            label,
+            alias,
+        )?;
 
-        Collection::new(
+        let collection = Collection::new(
             self.conn.clone(),
             &self.session,
             &self.service_proxy,
-            collection_path.into(),
-        )
+            &self.config,
+            collection_path,
+        )?;
+        Ok(match alias {
+            "" => collection,
+            alias => collection.with_alias(alias),
+        })
+    }
+
+    /// Deletes the items in the trash collection for good, see
+    /// [Item::trash].
+    ///
+    /// Does nothing if there is no trash collection yet.
+    pub fn empty_trash(&self) -> Result<BatchOutcome, Error> {
+        let trash = match self.get_collection_by_alias(TRASH_ALIAS) {
+            Ok(trash) => trash,
+            Err(Error::NoResult) => {
+                return Ok(BatchOutcome {
+                    results: Vec::new(),
+                })
+            }
+            Err(err) => return Err(err),
+        };
+
+        let mut results = Vec::new();
+        for item in trash.get_all_items()? {
+            results.push((item.path(), item.delete()));
+        }
+        Ok(BatchOutcome { results })
+    }
+
+    /// Starts a search of items by attributes, which can be limited and
+    /// ordered before it runs, see [ItemSearch].
+    pub fn search<'s>(&'s self, attributes: HashMap<&'s str, &'s str>) -> ItemSearch<'s> {
+        ItemSearch::new(self, attributes)
     }
 
     /// Searches all items by attributes
     pub fn search_items(
         &self,
         attributes: HashMap<&str, &str>,
-    ) -> Result<SearchItemsResult<Item>, Error> {
-        let items = self.service_proxy.search_items(attributes)?;
+    ) -> Result<SearchItemsResult<Item<'_>>, Error> {
+        let attributes = self.config.encrypt_attributes(attributes);
+        let items = self.service_proxy.search_items(attributes.as_map())?;
+        let proxy_builder = Item::proxy_builder(&self.conn, &self.config)?;
 
         let object_paths_to_items = |items: Vec<_>| {
             items
                 .into_iter()
                 .map(|item_path| {
                     Item::new(
+                        proxy_builder.clone(),
                         self.conn.clone(),
                         &self.session,
                         &self.service_proxy,
+                        &self.config,
                         item_path,
                     )
                 })
@@ -179,22 +434,284 @@ impl<'a> SecretService<'a> {
         })
     }
 
-    /// Unlock all items in a batch
-    pub fn unlock_all(&self, items: &[&Item<'_>]) -> Result<(), Error> {
-        let objects = items.iter().map(|i| &*i.item_path).collect();
+    /// Searches all items by attributes, also fetching the label and
+    /// attributes of every result.
+    pub fn search_items_prefetched(
+        &self,
+        attributes: HashMap<&str, &str>,
+    ) -> Result<SearchItemsResult<PrefetchedItem<Item<'_>>>, Error> {
+        let items = self.search_items(attributes)?;
+
+        Ok(SearchItemsResult {
+            unlocked: util::prefetch_items_blocking(items.unlocked)?,
+            locked: util::prefetch_items_blocking(items.locked)?,
+        })
+    }
+
+    /// Searches all items by attributes, grouping the results by the
+    /// collection holding them.
+    ///
+    /// See [SecretService::search_items_grouped](crate::SecretService::search_items_grouped).
+    pub fn search_items_grouped(
+        &self,
+        attributes: HashMap<&str, &str>,
+    ) -> Result<Vec<(Collection<'_>, Vec<Item<'_>>)>, Error> {
+        let items = self.search_items(attributes)?;
+        let groups =
+            util::group_by_collection(items.unlocked.into_iter().chain(items.locked), |item| {
+                item.path.as_str()
+            })?;
+
+        groups
+            .into_iter()
+            .map(|(path, items)| {
+                let collection = Collection::new(
+                    self.conn.clone(),
+                    &self.session,
+                    &self.service_proxy,
+                    &self.config,
+                    path,
+                )?;
+                Ok((collection, items))
+            })
+            .collect()
+    }
+
+    /// Searches all items by attributes, keeping only those whose attribute
+    /// `key` holds a list containing `tag`.
+    ///
+    /// See [SecretService::search_items_by_tag](crate::SecretService::search_items_by_tag).
+    pub fn search_items_by_tag(
+        &self,
+        mut attributes: HashMap<&str, &str>,
+        key: &str,
+        tag: &str,
+    ) -> Result<SearchItemsResult<Item<'_>>, Error> {
+        attributes.remove(key);
+        let items = self.search_items(attributes)?;
+
+        Ok(SearchItemsResult {
+            unlocked: util::retain_tagged_blocking(items.unlocked, key, tag)?,
+            locked: util::retain_tagged_blocking(items.locked, key, tag)?,
+        })
+    }
+
+    /// Searches all items with a query string, see [crate::query].
+    pub fn search_query(&self, query: &Query) -> Result<SearchItemsResult<Item<'_>>, Error> {
+        let items = self.search_items(query.required_attributes())?;
+
+        Ok(SearchItemsResult {
+            unlocked: query::retain_matching_blocking(items.unlocked, query)?,
+            locked: query::retain_matching_blocking(items.locked, query)?,
+        })
+    }
+
+    /// Searches all items by label, returning matches ranked best first.
+    ///
+    /// Matching is case-insensitive: exact matches rank above prefix matches,
+    /// which rank above substring matches, followed by fuzzy matches where the
+    /// characters of `query` appear in order. The spec can't search labels, so
+    /// they are fetched and compared on the client.
+    pub fn search_labels(&self, query: &str) -> Result<Vec<LabelMatch<Item<'_>>>, Error> {
+        // an empty attribute search returns every item
+        let items = self.search_items(HashMap::new())?;
+        let items = items
+            .unlocked
+            .into_iter()
+            .chain(items.locked)
+            .map(|item| {
+                let label = item.get_label()?;
+                Ok((item, label))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(util::rank_label_matches(items, query))
+    }
+
+    /// Looks up the secrets of environment variables, returning them as a
+    /// map that can be passed to `std::process::Command::envs`.
+    ///
+    /// See [SecretService::secrets_as_env](crate::SecretService::secrets_as_env).
+    pub fn secrets_as_env<'v>(
+        &self,
+        vars: impl IntoIterator<Item = (&'v str, HashMap<&'v str, &'v str>)>,
+    ) -> Result<HashMap<String, String>, Error> {
+        let mut env = HashMap::new();
+        for (var, attributes) in vars {
+            let items = self.search_items(attributes)?;
+            let secret = match (items.unlocked.first(), items.locked.first()) {
+                (Some(item), _) => item.get_secret_string()?,
+                (None, Some(item)) => {
+                    item.unlock()?;
+                    item.get_secret_string()?
+                }
+                (None, None) => return Err(Error::NoResult),
+            };
+            env.insert(var.to_owned(), secret);
+        }
+        Ok(env)
+    }
+
+    /// Subscribes to every change reported by the secret service.
+    ///
+    /// See [SecretService::watch](crate::SecretService::watch). The returned
+    /// iterator blocks until the next event arrives, and ends once this
+    /// `SecretService` is dropped.
+    pub fn watch(&self) -> Result<impl Iterator<Item = SecretServiceEvent>, Error> {
+        let messages = zbus::block_on(zbus::MessageStream::for_match_rule(
+            event::match_rule(&self.config)?,
+            self.conn.inner(),
+            None,
+        ))?;
+        let messages = BlockingStream(Some(Box::pin(self.config.lifetime.bind(messages))));
+
+        Ok(messages.filter_map(|msg| msg.ok().as_ref().and_then(event::parse_event)))
+    }
+
+    /// Subscribes to the collections being created, changed or deleted, by
+    /// this or any other application.
+    ///
+    /// See [SecretService::receive_collection_events](crate::SecretService::receive_collection_events).
+    /// The returned iterator blocks until the next event arrives, and ends
+    /// once this `SecretService` is dropped.
+    pub fn receive_collection_events(
+        &self,
+    ) -> Result<impl Iterator<Item = CollectionEvent>, Error> {
+        let service_proxy = ServiceProxy::from(self.service_proxy.inner().inner().clone());
+        let events = zbus::block_on(event::collection_events(&service_proxy))?;
+        Ok(BlockingStream(Some(Box::pin(
+            self.config.lifetime.bind(events),
+        ))))
+    }
+
+    /// Unlock all items in a batch, prompting the user once if the provider
+    /// asks to.
+    ///
+    /// See [SecretService::unlock_all](crate::SecretService::unlock_all).
+    pub fn unlock_all(&self, items: &[&Item<'_>]) -> Result<BatchOutcome, Error> {
+        let objects = items.iter().map(|i| &*i.path).collect();
         let lock_action_res = self.service_proxy.unlock(objects)?;
 
-        if lock_action_res.object_paths.is_empty() {
-            exec_prompt_blocking(self.conn.clone(), &lock_action_res.prompt)?;
+        let mut unlocked: HashSet<_> = lock_action_res.object_paths.into_iter().collect();
+        let mut dismissed = false;
+        if lock_action_res.prompt.as_str() != "/" {
+            match util::exec_prompt_blocking(
+                self.conn.clone(),
+                &self.config,
+                &lock_action_res.prompt,
+            ) {
+                Ok(result) => unlocked.extend(util::prompt_paths(&self.config, result)?),
+                Err(Error::Prompt) => dismissed = true,
+                Err(err) => return Err(err),
+            }
         }
 
-        Ok(())
+        let results = items
+            .iter()
+            .map(|item| {
+                let result = if unlocked.contains(&item.path) {
+                    Ok(())
+                } else if dismissed {
+                    Err(Error::Prompt)
+                } else {
+                    item.ensure_unlocked()
+                };
+                (item.path(), result)
+            })
+            .collect();
+        Ok(BatchOutcome { results })
+    }
+
+    /// Locks collections and items in a batch, prompting the user once if
+    /// the provider asks to.
+    ///
+    /// See [SecretService::lock_all](crate::SecretService::lock_all).
+    pub fn lock_all(&self, objects: &[ObjectRef<'_>]) -> Result<BatchOutcome, Error> {
+        let paths = objects.iter().map(|o| &**o.object_path()).collect();
+        let lock_action_res = self.service_proxy.lock(paths)?;
+
+        let mut locked: HashSet<_> = lock_action_res.object_paths.into_iter().collect();
+        let mut dismissed = false;
+        if lock_action_res.prompt.as_str() != "/" {
+            match util::exec_prompt_blocking(
+                self.conn.clone(),
+                &self.config,
+                &lock_action_res.prompt,
+            ) {
+                Ok(result) => locked.extend(util::prompt_paths(&self.config, result)?),
+                Err(Error::Prompt) => dismissed = true,
+                Err(err) => return Err(err),
+            }
+        }
+
+        let results = objects
+            .iter()
+            .map(|object| {
+                let result = if locked.contains(object.object_path()) {
+                    Ok(())
+                } else if dismissed {
+                    Err(Error::Prompt)
+                } else {
+                    match object.is_locked() {
+                        Ok(true) => Ok(()),
+                        Ok(false) => Err(Error::NotLocked),
+                        Err(err) => Err(err),
+                    }
+                };
+                (object.path(), result)
+            })
+            .collect();
+        Ok(BatchOutcome { results })
+    }
+
+    /// Returns the secrets of many items with a single call to the
+    /// provider.
+    ///
+    /// See [SecretService::get_secrets](crate::SecretService::get_secrets).
+    pub fn get_secrets(&self, items: &[&Item<'_>]) -> Result<BatchOutcome<Vec<u8>>, Error> {
+        if items.is_empty() {
+            return Ok(BatchOutcome {
+                results: Vec::new(),
+            });
+        }
+        let paths = items.iter().map(|i| &*i.path).collect();
+        let secrets = self
+            .service_proxy
+            .get_secrets(paths, self.session.open_blocking()?)?;
+        Ok(util::secrets_outcome(
+            &self.session,
+            &self.config,
+            items.iter().map(|i| i.path.clone()),
+            secrets,
+        ))
+    }
+}
+
+/// Iterates over a stream, blocking for each item.
+pub(crate) struct BlockingStream<S: Stream + Unpin>(pub(crate) Option<S>);
+
+impl<S: Stream + Unpin> Iterator for BlockingStream<S> {
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<S::Item> {
+        zbus::block_on(self.0.as_mut()?.next())
+    }
+}
+
+impl<S: Stream + Unpin> Drop for BlockingStream<S> {
+    fn drop(&mut self) {
+        // dropping a message stream removes its match rule, which needs the
+        // runtime zbus blocks on
+        if let Some(stream) = self.0.take() {
+            zbus::block_on(async move { drop(stream) });
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::SearchOrder;
     use std::convert::TryFrom;
     use zbus::zvariant::ObjectPath;
 
@@ -203,6 +720,67 @@ mod test {
         SecretService::connect(EncryptionType::Plain).unwrap();
     }
 
+    #[test]
+    fn should_create_secret_service_with_builder() {
+        let ss = SecretService::builder()
+            .encryption(EncryptionType::Plain)
+            .window_id("x11:0x2a00003")
+            .connect_blocking()
+            .unwrap();
+        ss.get_default_collection().unwrap();
+    }
+
+    #[test]
+    fn should_connect_with_existing_connection() {
+        let conn = zbus::blocking::Connection::session().unwrap();
+        let ss = SecretService::connect_with(conn.clone(), EncryptionType::Plain).unwrap();
+        assert_eq!(ss.conn.unique_name(), conn.unique_name());
+        ss.get_default_collection().unwrap();
+    }
+
+    #[test]
+    fn should_work_with_external_executor() {
+        let ss = SecretService::builder()
+            .encryption(EncryptionType::Plain)
+            .internal_executor(false)
+            .connect_blocking()
+            .unwrap();
+        let executor = ss.executor().clone();
+        std::thread::spawn(move || {
+            zbus::block_on(async {
+                loop {
+                    executor.tick().await;
+                }
+            })
+        });
+        let collections = ss.get_all_collections().unwrap();
+        assert!(!collections.is_empty());
+    }
+
+    #[test]
+    fn should_connect_to_configured_destination() {
+        // address the provider by its unique name instead of the well-known one
+        let conn = zbus::blocking::Connection::session().unwrap();
+        let owner = zbus::blocking::fdo::DBusProxy::new(&conn)
+            .unwrap()
+            .get_name_owner(crate::ss::SS_DBUS_NAME.try_into().unwrap())
+            .unwrap();
+        let ss = SecretService::builder()
+            .encryption(EncryptionType::Plain)
+            .destination(owner.as_str())
+            .path(crate::ss::SS_DBUS_PATH)
+            .connect_blocking()
+            .unwrap();
+        let collection = ss.get_default_collection().unwrap();
+        collection.get_all_items().unwrap();
+
+        let missing = SecretService::builder()
+            .encryption(EncryptionType::Plain)
+            .destination("org.example.NoSuchSecrets")
+            .connect_blocking();
+        assert!(missing.is_err());
+    }
+
     #[test]
     fn should_get_all_collections() {
         // Assumes that there will always be a default
@@ -212,12 +790,190 @@ mod test {
         assert!(!collections.is_empty(), "no collections found");
     }
 
+    #[test]
+    fn should_dump_tree_without_secrets() {
+        let ss = SecretService::connect(EncryptionType::Plain).unwrap();
+        let collection = ss.get_default_collection().unwrap();
+        let item = collection
+            .create_item(
+                "Dumped",
+                HashMap::from([("test_dump_tree_blocking", "hidden value")]),
+                b"hidden secret",
+                false,
+                "text/plain",
+            )
+            .unwrap();
+
+        let tree = ss.dump_tree().unwrap();
+        let dumped = tree
+            .collections
+            .iter()
+            .flat_map(|collection| &collection.items)
+            .find(|dumped| dumped.path == item.path().as_str())
+            .unwrap();
+        assert_eq!(dumped.attribute_keys, ["test_dump_tree_blocking"]);
+        assert!(!format!("{tree:?}").contains("hidden"));
+
+        item.delete().unwrap();
+    }
+
+    #[test]
+    fn should_look_up_secrets_as_env() {
+        let ss = SecretService::connect(EncryptionType::Plain).unwrap();
+        let collection = ss.get_default_collection().unwrap();
+        let attributes = HashMap::from([("test_secrets_as_env_blocking", "token")]);
+        let item = collection
+            .create_item("Env", attributes.clone(), b"hunter2", true, "text/plain")
+            .unwrap();
+
+        let env = ss.secrets_as_env([("TEST_TOKEN", attributes)]).unwrap();
+        assert_eq!(env["TEST_TOKEN"], "hunter2");
+        item.delete().unwrap();
+    }
+
+    #[test]
+    fn should_rotate_envelope_key() {
+        let old = ClientKey::new("test_rotate_blocking_old", b"old blocking key").unwrap();
+        let new = ClientKey::new("test_rotate_blocking_new", b"new blocking key").unwrap();
+        let ss = SecretService::builder()
+            .encryption(EncryptionType::Plain)
+            .envelope_key(ClientKey::new("test_rotate_blocking_old", b"old blocking key").unwrap())
+            .connect_blocking()
+            .unwrap();
+        let collection = ss.get_default_collection().unwrap();
+        let item = collection
+            .create_item(
+                "Rotated",
+                HashMap::from([("test_rotate_blocking", "rotated")]),
+                b"rotated",
+                true,
+                "text/plain",
+            )
+            .unwrap();
+
+        let outcome = ss
+            .rotate_key(&old, &new, NonZeroUsize::new(1).unwrap())
+            .unwrap();
+        assert_eq!(outcome.into_result().unwrap(), [(item.path(), ())]);
+        assert!(matches!(
+            item.get_secret().unwrap_err().without_context(),
+            Error::Crypto(_)
+        ));
+
+        let rotated = SecretService::builder()
+            .encryption(EncryptionType::Plain)
+            .envelope_key(new)
+            .connect_blocking()
+            .unwrap();
+        let found = rotated
+            .search_items(HashMap::from([("test_rotate_blocking", "rotated")]))
+            .unwrap();
+        assert_eq!(found.unlocked[0].get_secret().unwrap(), b"rotated");
+        item.delete().unwrap();
+    }
+
+    #[test]
+    fn should_iterate_collections() {
+        let ss = SecretService::connect(EncryptionType::Plain).unwrap();
+        let iterated: Vec<_> = ss
+            .collections_iter()
+            .unwrap()
+            .map(|collection| collection.unwrap().path)
+            .collect();
+        let collections: Vec<_> = ss
+            .get_all_collections()
+            .unwrap()
+            .into_iter()
+            .map(|collection| collection.path)
+            .collect();
+        assert_eq!(iterated, collections);
+    }
+
     #[test]
     fn should_get_collection_by_alias() {
         let ss = SecretService::connect(EncryptionType::Plain).unwrap();
         ss.get_collection_by_alias("session").unwrap();
     }
 
+    #[test]
+    fn should_use_additional_sessions() {
+        let ss = SecretService::connect(EncryptionType::Dh).unwrap();
+        let plain = ss.open_session(EncryptionType::Plain).unwrap();
+        assert_eq!(plain.info().encryption, EncryptionType::Plain);
+        assert_ne!(plain.info().path, ss.session().path);
+
+        let collection = ss.get_default_collection().unwrap();
+        let attributes = HashMap::from([("test_attributes_sessions", "test")]);
+        let plain_collection = collection.with_session(&plain);
+        plain_collection
+            .create_item("Test", attributes.clone(), b"test", true, "text/plain")
+            .unwrap();
+        let items = collection.search_items(attributes).unwrap();
+        assert_eq!(items[0].get_secret().unwrap(), b"test");
+        assert_eq!(items[0].with_session(&plain).get_secret().unwrap(), b"test");
+        items[0].delete().unwrap();
+    }
+
+    #[test]
+    fn should_describe_session() {
+        let ss = SecretService::connect(EncryptionType::Dh).unwrap();
+        let session = ss.session();
+        assert_eq!(session.encryption, EncryptionType::Dh);
+        assert_eq!(session.algorithm(), "dh-ietf1024-sha256-aes128-cbc-pkcs7");
+        assert!(session.created <= std::time::SystemTime::now());
+    }
+
+    #[test]
+    fn should_negotiate_lazy_session() {
+        let ss = SecretService::builder()
+            .lazy_session(true)
+            .connect_blocking()
+            .unwrap();
+        let collection = ss.get_default_collection().unwrap();
+        collection.get_label().unwrap();
+        assert_eq!(ss.session().path.as_str(), "/");
+
+        let attributes = HashMap::from([("test_attributes_lazy_blocking", "test")]);
+        let item = collection
+            .create_item("Test", attributes, b"test", true, "text/plain")
+            .unwrap();
+        assert_ne!(ss.session().path.as_str(), "/");
+        assert_eq!(item.get_secret().unwrap(), b"test");
+        item.delete().unwrap();
+    }
+
+    #[test]
+    fn should_reconnect_the_same_way() {
+        let mut ss = SecretService::builder()
+            .lazy_session(true)
+            .connect_blocking()
+            .unwrap();
+        ss.reconnect().unwrap();
+        assert_eq!(ss.session().path.as_str(), "/");
+        assert_eq!(ss.session().encryption, EncryptionType::Dh);
+
+        let label = ss
+            .with_reconnect(|ss| ss.get_default_collection()?.get_label())
+            .unwrap();
+        assert!(!label.is_empty());
+    }
+
+    #[test]
+    fn should_get_default_collection_path() {
+        let ss = SecretService::connect(EncryptionType::Plain).unwrap();
+        let collection = ss.get_default_collection().unwrap();
+        assert_eq!(ss.default_collection_path().unwrap(), collection.path());
+    }
+
+    #[test]
+    fn should_check_if_collection_exists() {
+        let ss = SecretService::connect(EncryptionType::Plain).unwrap();
+        assert!(ss.collection_exists("session").unwrap());
+        assert!(!ss
+            .collection_exists("definitely_defintely_does_not_exist")
+            .unwrap());
+    }
+
     #[test]
     fn should_return_error_if_collection_doesnt_exist() {
         let ss = SecretService::connect(EncryptionType::Plain).unwrap();
@@ -246,12 +1002,114 @@ mod test {
         let ss = SecretService::connect(EncryptionType::Plain).unwrap();
         let test_collection = ss.create_collection("Test", "").unwrap();
         assert_eq!(
-            ObjectPath::from(test_collection.collection_path.clone()),
+            ObjectPath::from(test_collection.path.clone()),
             ObjectPath::try_from("/org/freedesktop/secrets/collection/Test").unwrap()
         );
         test_collection.delete().unwrap();
     }
 
+    #[test]
+    fn should_search_labels() {
+        let ss = SecretService::connect(EncryptionType::Plain).unwrap();
+        let collection = ss.get_default_collection().unwrap();
+        let item = collection
+            .create_item(
+                "Test label in ss search",
+                HashMap::new(),
+                b"test",
+                false,
+                "text/plain",
+            )
+            .unwrap();
+
+        let matches = ss.search_labels("label in SS search").unwrap();
+        assert!(matches
+            .iter()
+            .any(|m| m.item.path == item.path && m.label == "Test label in ss search"));
+
+        let matches = ss.search_labels("tstlblinsssrch").unwrap();
+        assert!(matches.iter().any(|m| m.item.path == item.path));
+
+        let matches = ss.search_labels("no such label anywhere").unwrap();
+        assert!(matches.iter().all(|m| m.item.path != item.path));
+        item.delete().unwrap();
+    }
+
+    #[test]
+    fn should_sort_search_results() {
+        let ss = SecretService::connect(EncryptionType::Plain).unwrap();
+        let collection = ss.get_default_collection().unwrap();
+        for label in ["sort b", "Sort a", "sort c"] {
+            collection
+                .create_item(
+                    label,
+                    HashMap::from([("test_sort_in_ss_blocking", "sort")]),
+                    b"test",
+                    false,
+                    "text/plain",
+                )
+                .unwrap();
+        }
+
+        let mut items = ss
+            .search_items(HashMap::from([("test_sort_in_ss_blocking", "sort")]))
+            .unwrap();
+        items.sort_by_label().unwrap();
+        let labels: Vec<_> = items
+            .unlocked
+            .iter()
+            .map(|item| item.get_label().unwrap())
+            .collect();
+        assert_eq!(labels, ["Sort a", "sort b", "sort c"]);
+
+        sort_by_created(&mut items.unlocked).unwrap();
+        for pair in items.unlocked.windows(2) {
+            assert!(pair[0].get_created().unwrap() >= pair[1].get_created().unwrap());
+        }
+        for item in items.unlocked {
+            item.delete().unwrap();
+        }
+    }
+
+    #[test]
+    fn should_limit_and_order_search() {
+        let ss = SecretService::connect(EncryptionType::Plain).unwrap();
+        let collection = ss.get_default_collection().unwrap();
+        for label in ["limit b", "Limit a", "limit c"] {
+            collection
+                .create_item(
+                    label,
+                    HashMap::from([("test_search_limit_blocking", "limit")]),
+                    b"test",
+                    false,
+                    "text/plain",
+                )
+                .unwrap();
+        }
+        let attributes = HashMap::from([("test_search_limit_blocking", "limit")]);
+
+        let found = ss
+            .search(attributes.clone())
+            .order_by(SearchOrder::Label)
+            .limit(2)
+            .execute()
+            .unwrap();
+        let labels: Vec<_> = found
+            .unlocked
+            .iter()
+            .chain(&found.locked)
+            .map(|item| item.get_label().unwrap())
+            .collect();
+        assert_eq!(labels, ["Limit a", "limit b"]);
+
+        let found = ss.search(attributes.clone()).limit(1).execute().unwrap();
+        assert_eq!(found.unlocked.len() + found.locked.len(), 1);
+
+        for item in ss.search(attributes).execute().unwrap().unlocked {
+            item.delete().unwrap();
+        }
+    }
+
     #[test]
     fn should_search_items() {
         let ss = SecretService::connect(EncryptionType::Dh).unwrap();
@@ -281,8 +1139,72 @@ mod test {
             .search_items(HashMap::from([("test_attribute_in_ss", "test_value")]))
             .unwrap();
 
-        assert_eq!(item.item_path, search_item.unlocked[0].item_path);
+        assert_eq!(item.path, search_item.unlocked[0].path);
         assert_eq!(search_item.locked.len(), 0);
         item.delete().unwrap();
     }
+
+    #[test]
+    fn should_search_items_prefetched() {
+        let ss = SecretService::connect(EncryptionType::Plain).unwrap();
+        let collection = ss.get_default_collection().unwrap();
+
+        let item = collection
+            .create_item(
+                "test prefetched in blocking SS",
+                HashMap::from([("test_attribute_prefetched_in_blocking_ss", "test_value")]),
+                b"test_secret",
+                false,
+                "text/plain",
+            )
+            .unwrap();
+
+        let search_item = ss
+            .search_items_prefetched(HashMap::from([(
+                "test_attribute_prefetched_in_blocking_ss",
+                "test_value",
+            )]))
+            .unwrap();
+
+        assert_eq!(search_item.unlocked.len(), 1);
+        let found = &search_item.unlocked[0];
+        assert_eq!(item.path, found.item.path);
+        assert_eq!(found.label, "test prefetched in blocking SS");
+        assert_eq!(
+            found
+                .attributes
+                .get("test_attribute_prefetched_in_blocking_ss"),
+            Some(&"test_value".to_owned())
+        );
+        item.delete().unwrap();
+    }
+
+    #[test]
+    fn should_watch_item_events() {
+        let ss = SecretService::connect(EncryptionType::Plain).unwrap();
+        let collection = ss.get_default_collection().unwrap();
+        let mut events = ss.watch().unwrap();
+
+        let item = collection
+            .create_item(
+                "test watch in blocking SS",
+                HashMap::from([("test_attribute_watch_in_blocking_ss", "test_value")]),
+                b"test_secret",
+                false,
+                "text/plain",
+            )
+            .unwrap();
+        let created = SecretServiceEvent::ItemCreated {
+            collection: collection.path(),
+            item: item.path(),
+        };
+        assert!(events.any(|event| event == created));
+
+        let deleted = SecretServiceEvent::ItemDeleted {
+            collection: collection.path(),
+            item: item.path(),
+        };
+        item.delete().unwrap();
+        assert!(events.any(|event| event == deleted));
+    }
 }
@@ -0,0 +1,201 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+// The surface shared by blocking collections and items, see crate::Object.
+
+use crate::blocking::{Collection, Item};
+use crate::{Error, ObjectPath};
+
+use zbus::zvariant::OwnedObjectPath;
+
+/// The calls shared by [Collection] and [Item], so that utilities such as
+/// locking a mix of both can be written once.
+///
+/// The methods are the inherent ones of the same name.
+pub trait Object {
+    /// The object path of the collection or item.
+    fn path(&self) -> ObjectPath;
+
+    fn get_label(&self) -> Result<String, Error>;
+
+    fn set_label(&self, new_label: &str) -> Result<(), Error>;
+
+    fn is_locked(&self) -> Result<bool, Error>;
+
+    /// Locks the object, returning the paths the provider reports as locked.
+    fn lock(&self) -> Result<Vec<ObjectPath>, Error>;
+
+    /// Unlocks the object, returning the paths the provider reports as
+    /// unlocked.
+    fn unlock(&self) -> Result<Vec<ObjectPath>, Error>;
+
+    fn get_created(&self) -> Result<u64, Error>;
+
+    fn get_modified(&self) -> Result<u64, Error>;
+
+    fn delete(&self) -> Result<(), Error>;
+}
+
+impl Object for Collection<'_> {
+    fn path(&self) -> ObjectPath {
+        Collection::path(self)
+    }
+
+    fn get_label(&self) -> Result<String, Error> {
+        Collection::get_label(self)
+    }
+
+    fn set_label(&self, new_label: &str) -> Result<(), Error> {
+        Collection::set_label(self, new_label)
+    }
+
+    fn is_locked(&self) -> Result<bool, Error> {
+        Collection::is_locked(self)
+    }
+
+    fn lock(&self) -> Result<Vec<ObjectPath>, Error> {
+        Collection::lock(self)
+    }
+
+    fn unlock(&self) -> Result<Vec<ObjectPath>, Error> {
+        Collection::unlock(self)
+    }
+
+    fn get_created(&self) -> Result<u64, Error> {
+        Collection::get_created(self)
+    }
+
+    fn get_modified(&self) -> Result<u64, Error> {
+        Collection::get_modified(self)
+    }
+
+    fn delete(&self) -> Result<(), Error> {
+        Collection::delete(self)
+    }
+}
+
+impl Object for Item<'_> {
+    fn path(&self) -> ObjectPath {
+        Item::path(self)
+    }
+
+    fn get_label(&self) -> Result<String, Error> {
+        Item::get_label(self)
+    }
+
+    fn set_label(&self, new_label: &str) -> Result<(), Error> {
+        Item::set_label(self, new_label)
+    }
+
+    fn is_locked(&self) -> Result<bool, Error> {
+        Item::is_locked(self)
+    }
+
+    fn lock(&self) -> Result<Vec<ObjectPath>, Error> {
+        Item::lock(self)
+    }
+
+    fn unlock(&self) -> Result<Vec<ObjectPath>, Error> {
+        Item::unlock(self)
+    }
+
+    fn get_created(&self) -> Result<u64, Error> {
+        Item::get_created(self)
+    }
+
+    fn get_modified(&self) -> Result<u64, Error> {
+        Item::get_modified(self)
+    }
+
+    fn delete(&self) -> Result<(), Error> {
+        Item::delete(self)
+    }
+}
+
+/// A collection or an item, for calls taking a mix of both such as
+/// [SecretService::lock_all](crate::blocking::SecretService::lock_all).
+///
+/// Converts from a reference to either:
+///
+/// ```no_run
+/// # fn call(ss: &secret_service::blocking::SecretService<'_>, collection: &secret_service::blocking::Collection<'_>, item: &secret_service::blocking::Item<'_>) -> Result<(), secret_service::Error> {
+/// ss.lock_all(&[collection.into(), item.into()])?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy)]
+pub enum ObjectRef<'r> {
+    Collection(&'r Collection<'r>),
+    Item(&'r Item<'r>),
+}
+
+impl ObjectRef<'_> {
+    /// The object path of the collection or item.
+    pub fn path(&self) -> ObjectPath {
+        match self {
+            ObjectRef::Collection(collection) => collection.path(),
+            ObjectRef::Item(item) => item.path(),
+        }
+    }
+
+    pub(crate) fn object_path(&self) -> &OwnedObjectPath {
+        match self {
+            ObjectRef::Collection(collection) => &collection.path,
+            ObjectRef::Item(item) => &item.path,
+        }
+    }
+
+    pub(crate) fn is_locked(&self) -> Result<bool, Error> {
+        match self {
+            ObjectRef::Collection(collection) => collection.is_locked(),
+            ObjectRef::Item(item) => item.is_locked(),
+        }
+    }
+}
+
+impl<'r> From<&'r Collection<'r>> for ObjectRef<'r> {
+    fn from(collection: &'r Collection<'r>) -> Self {
+        ObjectRef::Collection(collection)
+    }
+}
+
+impl<'r> From<&'r Item<'r>> for ObjectRef<'r> {
+    fn from(item: &'r Item<'r>) -> Self {
+        ObjectRef::Item(item)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::blocking::SecretService;
+    use crate::EncryptionType;
+    use std::collections::HashMap;
+
+    fn relabel(object: &impl Object, label: &str) -> String {
+        object.set_label(label).unwrap();
+        object.get_label().unwrap()
+    }
+
+    #[test]
+    fn should_share_calls_between_collections_and_items() {
+        let ss = SecretService::connect(EncryptionType::Plain).unwrap();
+        let collection = ss.get_default_collection().unwrap();
+        let item = collection
+            .create_item("Test", HashMap::new(), b"test", false, "text/plain")
+            .unwrap();
+
+        let label = collection.get_label().unwrap();
+        assert_eq!(relabel(&item, "TestObject"), "TestObject");
+        assert_eq!(relabel(&collection, "TestObject"), "TestObject");
+        collection.set_label(&label).unwrap();
+
+        assert!(Object::get_created(&item).unwrap() > 0);
+        Object::delete(&item).unwrap();
+        assert!(!item.exists().unwrap());
+    }
+}
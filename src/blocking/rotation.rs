@@ -0,0 +1,60 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use crate::blocking::{Item, SecretService};
+use crate::cipher::ENVELOPE_KEY_ATTRIBUTE;
+use crate::{BatchOutcome, ClientKey, Error, ObjectPath, Progress};
+
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+
+pub(crate) fn rotate_key(
+    ss: &SecretService<'_>,
+    old: &ClientKey,
+    new: &ClientKey,
+    batch_size: NonZeroUsize,
+    mut progress: impl FnMut(&Progress),
+) -> Result<BatchOutcome, Error> {
+    let attributes = ss
+        .config
+        .encrypt_attributes(HashMap::from([(ENVELOPE_KEY_ATTRIBUTE, old.id())]));
+    let found = ss.service_proxy.search_items(attributes.as_map())?;
+
+    let proxy_builder = Item::proxy_builder(&ss.conn, &ss.config)?;
+    let mut state = Progress::new(Some(found.unlocked.len() + found.locked.len()));
+    let mut results: Vec<(ObjectPath, Result<(), Error>)> = Vec::new();
+    for batch in found.unlocked.chunks(batch_size.get()) {
+        let mut secrets = ss.service_proxy.get_secrets(
+            batch.iter().map(|path| &**path).collect(),
+            ss.session.open_blocking()?,
+        )?;
+        results.extend(batch.iter().map(|path| {
+            let rotated = (|| {
+                // not returned for items locked or deleted since the search
+                let secret = secrets.remove(path).ok_or(Error::Locked)?;
+                let item = Item::new(
+                    proxy_builder.clone(),
+                    ss.conn.clone(),
+                    &ss.session,
+                    &ss.service_proxy,
+                    &ss.config,
+                    path.clone(),
+                )?;
+                item.rotate_envelope_key(secret, old, new)
+            })();
+            state.advance(None, &rotated, &mut progress);
+            (path.clone().into(), rotated)
+        }));
+    }
+
+    for path in found.locked {
+        let result = Err(Error::Locked);
+        state.advance(None, &result, &mut progress);
+        results.push((path.into(), result));
+    }
+    Ok(BatchOutcome { results })
+}
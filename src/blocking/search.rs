@@ -0,0 +1,112 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use crate::blocking::{Item, SecretService};
+use crate::search::{is_exact_match, split_items, truncate_paths};
+use crate::util::sort_by_keys;
+use crate::{Error, SearchItemsResult, SearchOrder};
+
+use std::collections::HashMap;
+
+/// A search of items by attributes, created with [SecretService::search].
+///
+/// See [crate::ItemSearch]. Ordered searches fetch the properties of the
+/// items one after the other.
+#[must_use = "a search does nothing until executed"]
+pub struct ItemSearch<'a> {
+    service: &'a SecretService<'a>,
+    attributes: HashMap<&'a str, &'a str>,
+    limit: Option<usize>,
+    order: Option<SearchOrder>,
+    exact: bool,
+}
+
+impl<'a> ItemSearch<'a> {
+    pub(crate) fn new(
+        service: &'a SecretService<'a>,
+        attributes: HashMap<&'a str, &'a str>,
+    ) -> Self {
+        ItemSearch {
+            service,
+            attributes,
+            limit: None,
+            order: None,
+            exact: false,
+        }
+    }
+
+    /// Returns at most `limit` items in total.
+    ///
+    /// Without [ItemSearch::order_by], unlocked items are kept first.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Orders the results, across locked and unlocked items, before the
+    /// [limit](ItemSearch::limit) applies.
+    pub fn order_by(mut self, order: SearchOrder) -> Self {
+        self.order = Some(order);
+        self
+    }
+
+    /// Only returns items whose attributes are exactly those searched for,
+    /// see [crate::ItemSearch::exact].
+    pub fn exact(mut self) -> Self {
+        self.exact = true;
+        self
+    }
+
+    /// Runs the search.
+    pub fn execute(self) -> Result<SearchItemsResult<Item<'a>>, Error> {
+        let service = self.service;
+        let attributes = service.config.encrypt_attributes(self.attributes.clone());
+        let found = service.service_proxy.search_items(attributes.as_map())?;
+        let (mut unlocked, mut locked) = (found.unlocked, found.locked);
+        if self.order.is_none() && !self.exact {
+            truncate_paths(&mut unlocked, &mut locked, self.limit);
+        }
+
+        let proxy_builder = Item::proxy_builder(&service.conn, &service.config)?;
+        let tagged = unlocked
+            .into_iter()
+            .map(|path| (false, path))
+            .chain(locked.into_iter().map(|path| (true, path)));
+        let mut items = tagged
+            .map(|(locked, path)| {
+                let item = Item::new(
+                    proxy_builder.clone(),
+                    service.conn.clone(),
+                    &service.session,
+                    &service.service_proxy,
+                    &service.config,
+                    path,
+                )?;
+                Ok((locked, item))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        if self.exact {
+            let mut exact = Vec::new();
+            for (locked, item) in items {
+                if is_exact_match(&self.attributes, &item.get_attributes()?) {
+                    exact.push((locked, item));
+                }
+            }
+            items = exact;
+        }
+
+        if let Some(order) = self.order {
+            let keys = items
+                .iter()
+                .map(|(_, item)| item.sort_key(order.into()))
+                .collect::<Result<_, _>>()?;
+            sort_by_keys(&mut items, keys);
+        }
+        Ok(split_items(items, self.limit))
+    }
+}
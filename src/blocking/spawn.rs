@@ -0,0 +1,127 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Running the blocking API from async code on tokio.
+
+use super::SecretService;
+use crate::{EncryptionType, Error, SecretServiceBuilder};
+use std::sync::Arc;
+
+/// Runs calls into a shared blocking [SecretService] on tokio's blocking
+/// thread pool.
+///
+/// Useful for mostly async applications that can't hold the futures of the
+/// async API across awaits, for example because they must be `Send`. The
+/// adapter is cheap to clone; all clones share the same connection and session.
+///
+/// Only available with the `tokio` feature.
+///
+/// ```no_run
+/// # use secret_service::blocking::SpawnBlocking;
+/// # use secret_service::EncryptionType;
+/// # async fn call() -> Result<(), secret_service::Error> {
+/// let ss = SpawnBlocking::connect(EncryptionType::Dh).await?;
+/// let labels = ss
+///     .run(|ss| {
+///         let collection = ss.get_default_collection()?;
+///         let items = collection.get_all_items()?;
+///         items.iter().map(|item| item.get_label()).collect::<Result<Vec<_>, _>>()
+///     })
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct SpawnBlocking {
+    service: Arc<Shared>,
+}
+
+struct Shared(Option<SecretService<'static>>);
+
+impl Shared {
+    fn get(&self) -> &SecretService<'static> {
+        self.0.as_ref().expect("service is only taken when dropped")
+    }
+}
+
+impl Drop for Shared {
+    fn drop(&mut self) {
+        // dropping blocking proxies blocks on the runtime zbus uses internally,
+        // which panics on a thread that is driving async tasks
+        if let Some(service) = self.0.take() {
+            if tokio::runtime::Handle::try_current().is_ok() {
+                std::thread::spawn(move || drop(service));
+            }
+        }
+    }
+}
+
+impl SpawnBlocking {
+    /// Wraps an existing blocking `SecretService`.
+    pub fn new(service: SecretService<'static>) -> Self {
+        SpawnBlocking {
+            service: Arc::new(Shared(Some(service))),
+        }
+    }
+
+    /// Connects to the secret service on the blocking thread pool.
+    pub async fn connect(encryption: EncryptionType) -> Result<Self, Error> {
+        Self::connect_with(SecretService::builder().encryption(encryption)).await
+    }
+
+    /// Connects to the secret service with the settings of `builder` on the
+    /// blocking thread pool.
+    pub async fn connect_with(builder: SecretServiceBuilder) -> Result<Self, Error> {
+        spawn(move || builder.connect_blocking().map(Self::new)).await
+    }
+
+    /// Runs `f` with the shared `SecretService` on the blocking thread pool.
+    ///
+    /// Handles such as `Collection` and `Item` borrow the `SecretService`, so
+    /// `f` has to turn them into owned values before returning.
+    ///
+    /// A panic in `f` is resumed in the calling task.
+    pub async fn run<F, T>(&self, f: F) -> Result<T, Error>
+    where
+        F: FnOnce(&SecretService<'static>) -> Result<T, Error> + Send + 'static,
+        T: Send + 'static,
+    {
+        let service = Arc::clone(&self.service);
+        spawn(move || f(service.get())).await
+    }
+}
+
+async fn spawn<F, T>(f: F) -> Result<T, Error>
+where
+    F: FnOnce() -> Result<T, Error> + Send + 'static,
+    T: Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(res) => res,
+        Err(err) => std::panic::resume_unwind(err.into_panic()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn should_run_on_blocking_pool() {
+        let ss = SpawnBlocking::connect(EncryptionType::Plain).await.unwrap();
+        let count = ss
+            .clone()
+            .run(|ss| Ok(ss.get_default_collection()?.get_all_items()?.len()))
+            .await
+            .unwrap();
+        let other = ss
+            .run(|ss| Ok(ss.get_default_collection()?.get_all_items()?.len()))
+            .await
+            .unwrap();
+        assert_eq!(count, other);
+    }
+}
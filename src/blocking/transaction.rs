@@ -0,0 +1,249 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+// Blocking counterpart of crate::Transaction.
+
+use crate::blocking::{Collection, Item};
+use crate::transaction::{borrowed_attributes, owned_attributes};
+use crate::{Error, TransactionReport};
+
+use std::collections::HashMap;
+
+/// Changes to several items, applied in order by [Transaction::execute].
+///
+/// See [crate::Transaction], including how failures are undone.
+#[derive(Default)]
+pub struct Transaction<'t> {
+    operations: Vec<Operation<'t>>,
+}
+
+enum Operation<'t> {
+    Create {
+        collection: &'t Collection<'t>,
+        label: String,
+        attributes: HashMap<String, String>,
+        secret: Vec<u8>,
+        content_type: String,
+    },
+    SetSecret {
+        item: &'t Item<'t>,
+        secret: Vec<u8>,
+        content_type: String,
+    },
+    Delete {
+        item: &'t Item<'t>,
+    },
+}
+
+/// How to undo an operation that was applied.
+enum Undo<'t> {
+    Delete(Item<'t>),
+    SetSecret {
+        item: &'t Item<'t>,
+        secret: Vec<u8>,
+        content_type: String,
+    },
+    Create {
+        collection: Box<Collection<'t>>,
+        label: String,
+        attributes: HashMap<String, String>,
+        secret: Vec<u8>,
+        content_type: String,
+    },
+}
+
+impl<'t> Transaction<'t> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues creating an item, like [Collection::create_item] without
+    /// replacing.
+    pub fn create_item(
+        &mut self,
+        collection: &'t Collection<'t>,
+        label: &str,
+        attributes: HashMap<&str, &str>,
+        secret: &[u8],
+        content_type: &str,
+    ) -> &mut Self {
+        self.operations.push(Operation::Create {
+            collection,
+            label: label.to_owned(),
+            attributes: owned_attributes(attributes),
+            secret: secret.to_vec(),
+            content_type: content_type.to_owned(),
+        });
+        self
+    }
+
+    /// Queues setting the secret of an item, like [Item::set_secret].
+    pub fn set_secret(
+        &mut self,
+        item: &'t Item<'t>,
+        secret: &[u8],
+        content_type: &str,
+    ) -> &mut Self {
+        self.operations.push(Operation::SetSecret {
+            item,
+            secret: secret.to_vec(),
+            content_type: content_type.to_owned(),
+        });
+        self
+    }
+
+    /// Queues deleting an item, like [Item::delete].
+    pub fn delete(&mut self, item: &'t Item<'t>) -> &mut Self {
+        self.operations.push(Operation::Delete { item });
+        self
+    }
+
+    /// Applies the operations in order, undoing the applied ones if one
+    /// fails.
+    pub fn execute(self) -> TransactionReport {
+        let mut applied = Vec::new();
+        let mut failed = None;
+        for (index, operation) in self.operations.into_iter().enumerate() {
+            match apply(operation) {
+                Ok(undo) => applied.push((index, undo)),
+                Err(err) => {
+                    failed = Some((index, err));
+                    break;
+                }
+            }
+        }
+
+        let mut created = Vec::new();
+        let mut rolled_back = Vec::new();
+        if failed.is_none() {
+            for (_, undo) in &applied {
+                if let Undo::Delete(item) = undo {
+                    created.push(item.path());
+                }
+            }
+        } else {
+            for (index, undo) in applied.into_iter().rev() {
+                rolled_back.push((index, undo_operation(undo)));
+            }
+        }
+
+        TransactionReport {
+            created,
+            failed,
+            rolled_back,
+        }
+    }
+}
+
+fn apply(operation: Operation<'_>) -> Result<Undo<'_>, Error> {
+    Ok(match operation {
+        Operation::Create {
+            collection,
+            label,
+            attributes,
+            secret,
+            content_type,
+        } => {
+            let item = collection.create_item(
+                &label,
+                borrowed_attributes(&attributes),
+                &secret,
+                false,
+                &content_type,
+            )?;
+            Undo::Delete(item)
+        }
+        Operation::SetSecret {
+            item,
+            secret,
+            content_type,
+        } => {
+            let (previous, previous_content_type) = item.read_secret("set_secret")?;
+            item.set_secret(&secret, &content_type)?;
+            Undo::SetSecret {
+                item,
+                secret: previous,
+                content_type: previous_content_type,
+            }
+        }
+        Operation::Delete { item } => {
+            let (secret, content_type) = item.read_secret("delete")?;
+            let undo = Undo::Create {
+                collection: Box::new(item.collection()?),
+                label: item.get_label()?,
+                attributes: item.get_attributes()?,
+                secret,
+                content_type,
+            };
+            item.delete()?;
+            undo
+        }
+    })
+}
+
+fn undo_operation(undo: Undo<'_>) -> Result<(), Error> {
+    match undo {
+        Undo::Delete(item) => item.delete(),
+        Undo::SetSecret {
+            item,
+            secret,
+            content_type,
+        } => item.set_secret(&secret, &content_type),
+        Undo::Create {
+            collection,
+            label,
+            attributes,
+            secret,
+            content_type,
+        } => collection
+            .create_item(
+                &label,
+                borrowed_attributes(&attributes),
+                &secret,
+                false,
+                &content_type,
+            )
+            .map(drop),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::blocking::SecretService;
+    use crate::EncryptionType;
+
+    #[test]
+    fn should_execute_transaction() {
+        let ss = SecretService::connect(EncryptionType::Plain).unwrap();
+        let collection = ss.get_default_collection().unwrap();
+        let attributes = HashMap::from([("test_attribute_transaction_blocking", "test_value")]);
+        let item = collection
+            .create_item("Test", HashMap::new(), b"test", false, "text/plain")
+            .unwrap();
+
+        let mut transaction = Transaction::new();
+        transaction
+            .create_item(
+                &collection,
+                "TestCreated",
+                attributes.clone(),
+                b"created",
+                "text/plain",
+            )
+            .delete(&item);
+        let report = transaction.execute();
+
+        assert!(report.is_committed());
+        assert!(report.rolled_back.is_empty());
+        assert!(!item.exists().unwrap());
+        let found = collection.search_items(attributes).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(report.created, [found[0].path()]);
+        found[0].delete().unwrap();
+    }
+}
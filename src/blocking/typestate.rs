@@ -0,0 +1,100 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Blocking items that track their lock state in their type, see
+//! [crate::typestate].
+
+use crate::blocking;
+use crate::{Error, ObjectPath};
+
+pub use crate::typestate::{Locked, Unlocked};
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// An item whose lock state `S` is tracked in its type.
+pub struct Item<'a, S> {
+    item: blocking::Item<'a>,
+    state: PhantomData<S>,
+}
+
+/// An item in either state, as returned by [Item::check].
+pub enum State<'a> {
+    Locked(Item<'a, Locked>),
+    Unlocked(Item<'a, Unlocked>),
+}
+
+impl<'a> Item<'a, Locked> {
+    /// Checks whether `item` is locked, returning it in the matching state.
+    pub fn check(item: blocking::Item<'a>) -> Result<State<'a>, Error> {
+        Ok(if item.is_locked()? {
+            State::Locked(Item::new(item))
+        } else {
+            State::Unlocked(Item::new(item))
+        })
+    }
+
+    /// Unlocks the item, prompting the user if the provider asks to.
+    ///
+    /// Fails with [Error::Locked] if the item is still locked afterwards,
+    /// as providers may unlock something else than asked for.
+    pub fn unlock(self) -> Result<Item<'a, Unlocked>, Error> {
+        self.item.unlock()?;
+        self.item.ensure_unlocked()?;
+        Ok(Item::new(self.item))
+    }
+}
+
+impl<'a> Item<'a, Unlocked> {
+    pub fn get_secret(&self) -> Result<Vec<u8>, Error> {
+        self.item.get_secret()
+    }
+
+    pub fn get_secret_string(&self) -> Result<String, Error> {
+        self.item.get_secret_string()
+    }
+
+    pub fn set_secret(&self, secret: &[u8], content_type: &str) -> Result<(), Error> {
+        self.item.set_secret(secret, content_type)
+    }
+
+    /// Locks the item.
+    ///
+    /// Providers may lock the item's collection rather than the item, or
+    /// refuse to, so the item is checked again afterwards.
+    pub fn lock(self) -> Result<State<'a>, Error> {
+        self.item.lock()?;
+        Item::check(self.item)
+    }
+}
+
+impl<'a, S> Item<'a, S> {
+    fn new(item: blocking::Item<'a>) -> Self {
+        Item {
+            item,
+            state: PhantomData,
+        }
+    }
+
+    /// The object path of the item.
+    pub fn path(&self) -> ObjectPath {
+        self.item.path()
+    }
+
+    pub fn get_label(&self) -> Result<String, Error> {
+        self.item.get_label()
+    }
+
+    pub fn get_attributes(&self) -> Result<HashMap<String, String>, Error> {
+        self.item.get_attributes()
+    }
+
+    /// Returns the item without its state, for the calls not offered here.
+    pub fn into_inner(self) -> blocking::Item<'a> {
+        self.item
+    }
+}
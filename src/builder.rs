@@ -0,0 +1,823 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Central configuration for connecting to the secret service.
+
+use crate::algorithm::{AlgorithmRegistry, SessionAlgorithm};
+use crate::cache::AliasCache;
+use crate::cipher::{AttributeCipher, ClientKey, EncryptedAttributes, ENVELOPE_KEY_ATTRIBUTE};
+use crate::compression::{Compression, Compressor};
+use crate::journal::{Change, Journal, JournalSink};
+use crate::lifetime::Lifetime;
+use crate::prompt::CustomPrompter;
+use crate::proxy::service::{ServiceProxy, ServiceProxyBlocking};
+use crate::reconnect::Reconnect;
+use crate::ss::{SS_DBUS_NAME, SS_DBUS_PATH};
+use crate::warm::WarmConnection;
+use crate::{
+    blocking, util, Collection, EncryptionType, Error, ObjectPath, PromptCanceller, Prompter,
+    SecretService, WindowIdentifier,
+};
+use async_lock::Semaphore;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, RwLock};
+
+/// Settings and state shared by a [SecretService] and every `Collection` and
+/// `Item` created from it.
+#[derive(Debug)]
+pub(crate) struct Config {
+    /// Window identifier handed to the provider when showing a prompt,
+    /// which can change once connected.
+    pub(crate) window_id: RwLock<String>,
+    /// Bus name the provider is registered under.
+    pub(crate) destination: String,
+    /// Object path of the provider's `Service` object, under which all
+    /// collections, items and prompts live.
+    pub(crate) path: String,
+    /// Resolved collection aliases.
+    pub(crate) alias_cache: AliasCache,
+    /// Limits the number of calls made concurrently, if set.
+    pub(crate) call_limit: Option<Semaphore>,
+    /// Ends the signal streams handed out once the service is dropped.
+    pub(crate) lifetime: Lifetime,
+    /// Encrypts the values of some attributes on the client, if set.
+    pub(crate) attribute_cipher: Option<AttributeCipher>,
+    /// Encrypts secrets on the client, if set.
+    pub(crate) envelope_key: Option<ClientKey>,
+    /// Retries creating collections and items with the legacy property
+    /// names when the provider rejects the standard ones.
+    pub(crate) legacy_properties: bool,
+    /// How many previous secrets of each item are kept, if any.
+    pub(crate) secret_history: usize,
+    /// Records the changes made, if set.
+    pub(crate) journal: Option<Journal>,
+    /// Compresses large secrets, if set.
+    pub(crate) compression: Option<Compression>,
+    /// Checks the provider's responses against the specification.
+    pub(crate) strict: bool,
+    /// Content type of secrets stored without one.
+    pub(crate) default_content_type: String,
+    /// Dismisses the prompts waited for on request.
+    pub(crate) prompts: PromptCanceller,
+    /// Handles prompts in place of showing them, if set.
+    pub(crate) prompter: Option<CustomPrompter>,
+    /// How the connection was made, set once connected.
+    pub(crate) reconnect: Option<Reconnect>,
+    /// Address of the bus the provider is on, if not the session bus.
+    #[cfg(all(unix, any(test, feature = "test-harness")))]
+    pub(crate) bus_address: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            window_id: RwLock::default(),
+            destination: SS_DBUS_NAME.to_owned(),
+            path: SS_DBUS_PATH.to_owned(),
+            alias_cache: AliasCache::default(),
+            call_limit: None,
+            lifetime: Lifetime::default(),
+            attribute_cipher: None,
+            envelope_key: None,
+            legacy_properties: false,
+            secret_history: 0,
+            journal: None,
+            compression: None,
+            strict: false,
+            default_content_type: DEFAULT_CONTENT_TYPE.to_owned(),
+            prompts: PromptCanceller::default(),
+            prompter: None,
+            reconnect: None,
+            #[cfg(all(unix, any(test, feature = "test-harness")))]
+            bus_address: None,
+        }
+    }
+}
+
+impl Config {
+    /// Returns the window identifier prompts are parented to.
+    pub(crate) fn window_id(&self) -> String {
+        self.window_id.read().unwrap().clone()
+    }
+
+    /// Parents the prompts shown from now on to `window`, or to no window.
+    pub(crate) fn set_window(&self, window: Option<WindowIdentifier>) {
+        *self.window_id.write().unwrap() = window.map(String::from).unwrap_or_default();
+    }
+
+    /// Encrypts the attributes of an item, or of a search for items, as
+    /// configured with [SecretServiceBuilder::encrypt_attributes].
+    pub(crate) fn encrypt_attributes<'a>(
+        &self,
+        attributes: HashMap<&'a str, &'a str>,
+    ) -> EncryptedAttributes<'a> {
+        match &self.attribute_cipher {
+            Some(cipher) => cipher.encrypt_attributes(attributes),
+            None => EncryptedAttributes::plain(attributes),
+        }
+    }
+
+    /// Encrypts the attributes of an item being stored, recording the
+    /// envelope key its secret is encrypted with.
+    pub(crate) fn item_attributes<'a>(
+        &'a self,
+        mut attributes: HashMap<&'a str, &'a str>,
+    ) -> EncryptedAttributes<'a> {
+        if let Some(key) = &self.envelope_key {
+            attributes.insert(ENVELOPE_KEY_ATTRIBUTE, key.id());
+        }
+        self.encrypt_attributes(attributes)
+    }
+
+    /// Decrypts the attributes of an item read from the provider.
+    pub(crate) fn decrypt_attributes(
+        &self,
+        attributes: HashMap<String, String>,
+    ) -> Result<HashMap<String, String>, Error> {
+        match &self.attribute_cipher {
+            Some(cipher) => cipher.decrypt_attributes(attributes),
+            None => Ok(attributes),
+        }
+    }
+
+    /// Encrypts a secret being stored, as configured with
+    /// [SecretServiceBuilder::envelope_key].
+    pub(crate) fn seal_secret<'a>(&self, secret: &'a [u8]) -> Cow<'a, [u8]> {
+        match &self.envelope_key {
+            Some(key) => Cow::Owned(key.seal(secret)),
+            None => Cow::Borrowed(secret),
+        }
+    }
+
+    /// Records a change accepted by the provider in the journal, if one is
+    /// set.
+    pub(crate) fn record(&self, path: ObjectPath, change: impl FnOnce() -> Change) {
+        if let Some(journal) = &self.journal {
+            journal.record(path, change());
+        }
+    }
+
+    /// Compresses a secret being stored, as configured with
+    /// [SecretServiceBuilder::compression], returning it with its content
+    /// type.
+    pub(crate) fn compress_secret<'a>(
+        &self,
+        secret: &'a [u8],
+        content_type: &'a str,
+    ) -> (Cow<'a, [u8]>, Cow<'a, str>) {
+        match &self.compression {
+            Some(compression) => compression.compress(secret, content_type),
+            None => (Cow::Borrowed(secret), Cow::Borrowed(content_type)),
+        }
+    }
+
+    /// Decrypts a secret read from the provider.
+    pub(crate) fn open_secret(&self, secret: Vec<u8>) -> Result<Vec<u8>, Error> {
+        match &self.envelope_key {
+            Some(key) => key.open(secret),
+            None => Ok(secret),
+        }
+    }
+}
+
+/// Content type of secrets stored without one, unless set with
+/// [SecretServiceBuilder::default_content_type].
+const DEFAULT_CONTENT_TYPE: &str = "text/plain; charset=utf8";
+
+/// Environment variable read by [SecretServiceBuilder::encryption_from_env].
+const ENCRYPTION_ENV: &str = "SECRET_SERVICE_ENCRYPTION";
+
+/// Parses the value of [ENCRYPTION_ENV], returning `None` to keep the
+/// encryption the application asked for.
+fn encryption_override(value: Option<&str>) -> Result<Option<EncryptionType>, Error> {
+    let Some(value) = value else {
+        return Ok(None);
+    };
+    match value.trim().to_ascii_lowercase().as_str() {
+        "plain" => Ok(Some(EncryptionType::Plain)),
+        "dh" => Ok(Some(EncryptionType::Dh)),
+        "auto" | "" => Ok(None),
+        _ => Err(Error::InvalidEncryptionOverride(value.to_owned())),
+    }
+}
+
+/// Where to find the provider, for providers other than the one registered
+/// under the standard name, such as a per-user KeePassXC instance registered
+/// under another name, or a test double.
+///
+/// Passed to [SecretService::connect_with_options], or to
+/// [SecretServiceBuilder::options] along with other settings. The default
+/// options address the standard provider.
+///
+/// ```no_run
+/// # use secret_service::{ConnectOptions, EncryptionType, SecretService};
+/// # async fn call() -> Result<(), secret_service::Error> {
+/// let options = ConnectOptions {
+///     destination: "org.example.Secrets".to_owned(),
+///     ..ConnectOptions::default()
+/// };
+/// let ss = SecretService::connect_with_options(options, EncryptionType::Dh).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConnectOptions {
+    /// The bus name the provider is registered under, by default
+    /// `org.freedesktop.secrets`.
+    pub destination: String,
+    /// The object path of the provider's `Service` object, under which its
+    /// collections, items and prompts live, by default
+    /// `/org/freedesktop/secrets`.
+    pub path: String,
+}
+
+impl Default for ConnectOptions {
+    fn default() -> Self {
+        ConnectOptions {
+            destination: SS_DBUS_NAME.to_owned(),
+            path: SS_DBUS_PATH.to_owned(),
+        }
+    }
+}
+
+/// Builder for configuring a connection to the secret service.
+///
+/// Created with [SecretService::builder] or [blocking::SecretService::builder].
+/// Finish with [SecretServiceBuilder::connect] for the async API, or with
+/// [SecretServiceBuilder::connect_blocking] for the [blocking] API.
+///
+/// ```
+/// # use secret_service::SecretService;
+/// # use secret_service::EncryptionType;
+/// # async fn call() {
+/// let ss = SecretService::builder()
+///     .encryption(EncryptionType::Dh)
+///     .window_id("x11:0x2a00003")
+///     .connect()
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+#[derive(Debug)]
+#[must_use]
+pub struct SecretServiceBuilder {
+    /// Name of the algorithm the session is negotiated with.
+    algorithm: String,
+    algorithms: AlgorithmRegistry,
+    internal_executor: bool,
+    #[cfg(feature = "kernel-keyring")]
+    kernel_keyring: bool,
+    lazy_session: bool,
+    plain_fallback: bool,
+    /// Connection to use instead of the session bus.
+    pub(crate) conn: Option<zbus::Connection>,
+    pub(crate) config: Config,
+    /// The first invalid setting, returned when connecting.
+    error: Option<Error>,
+}
+
+impl Default for SecretServiceBuilder {
+    fn default() -> Self {
+        SecretServiceBuilder {
+            algorithm: EncryptionType::Dh.algorithm().name().to_owned(),
+            algorithms: AlgorithmRegistry::new(),
+            internal_executor: true,
+            #[cfg(feature = "kernel-keyring")]
+            kernel_keyring: false,
+            lazy_session: false,
+            plain_fallback: false,
+            conn: None,
+            config: Config::default(),
+            error: None,
+        }
+    }
+}
+
+impl SecretServiceBuilder {
+    /// Creates a builder with the default settings: an encrypted
+    /// ([EncryptionType::Dh]) session and no parent window for prompts.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the kind of session negotiated with the provider.
+    pub fn encryption(mut self, encryption: EncryptionType) -> Self {
+        self.algorithm = encryption.algorithm().name().to_owned();
+        self
+    }
+
+    /// Makes `algorithm` available to [SecretServiceBuilder::algorithm],
+    /// replacing any registered under the same name, see the
+    /// [algorithm](crate::algorithm) module.
+    pub fn register_algorithm(mut self, algorithm: Arc<dyn SessionAlgorithm>) -> Self {
+        self.algorithms.register(algorithm);
+        self
+    }
+
+    /// Negotiates the session with the algorithm registered as `name`,
+    /// instead of the one of an [EncryptionType].
+    ///
+    /// Connecting fails with [Error::UnknownAlgorithm] if no algorithm is
+    /// registered under the name.
+    pub fn algorithm(mut self, name: impl Into<String>) -> Self {
+        self.algorithm = name.into();
+        self
+    }
+
+    /// Lets the `SECRET_SERVICE_ENCRYPTION` environment variable override the
+    /// kind of session, so that users can work around providers with broken
+    /// Diffie-Hellman implementations without a rebuild.
+    ///
+    /// The variable is read when this is called. `plain` and `dh` select
+    /// [EncryptionType::Plain] and [EncryptionType::Dh], while `auto`, or an
+    /// unset or empty variable, keep the kind set with
+    /// [SecretServiceBuilder::encryption]. Connecting fails with
+    /// [Error::InvalidEncryptionOverride] for any other value. Plain
+    /// sessions are still refused under the `deny-plain` feature.
+    pub fn encryption_from_env(mut self) -> Self {
+        let value = std::env::var(ENCRYPTION_ENV).ok();
+        match encryption_override(value.as_deref()) {
+            Ok(Some(encryption)) => self = self.encryption(encryption),
+            Ok(None) => {}
+            Err(err) => self.fail(err),
+        }
+        self
+    }
+
+    /// Sets the platform-specific window identifier passed to prompts,
+    /// so that they can be shown as children of the application window.
+    pub fn window_id(mut self, window_id: impl Into<String>) -> Self {
+        self.config.window_id = RwLock::new(window_id.into());
+        self
+    }
+
+    /// Parents prompts to a window of the application, see
+    /// [WindowIdentifier].
+    pub fn window(self, window: WindowIdentifier) -> Self {
+        self.window_id(window)
+    }
+
+    /// Parents prompts to a GTK window, see the [gtk](crate::gtk) module.
+    ///
+    /// The handle has to be kept alive for as long as prompts may be shown.
+    #[cfg(feature = "gtk4")]
+    pub fn gtk_window(self, handle: &crate::gtk::WindowHandle) -> Self {
+        self.window(handle.identifier().clone())
+    }
+
+    /// Talks to the provider over `conn`, instead of opening a connection to
+    /// the session bus.
+    ///
+    /// For applications that already hold a connection, such as GTK
+    /// applications using zbus elsewhere. The connection may be to another
+    /// bus, and is kept by [SecretService::reconnect]. As given connections
+    /// are async, [SecretServiceBuilder::connect_blocking] drives the
+    /// negotiation with the async API.
+    pub fn connection(mut self, conn: zbus::Connection) -> Self {
+        self.conn = Some(conn);
+        self
+    }
+
+    /// Sets the bus name of the provider to talk to, instead of the standard
+    /// `org.freedesktop.secrets`.
+    ///
+    /// Useful for test doubles or bridging daemons that register under a
+    /// different well-known name.
+    pub fn destination(mut self, destination: impl Into<String>) -> Self {
+        self.config.destination = destination.into();
+        self
+    }
+
+    /// Sets the object path of the provider's `Service` object, instead of
+    /// the standard `/org/freedesktop/secrets`.
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.config.path = path.into();
+        self
+    }
+
+    /// Sets where to find the provider, like [SecretServiceBuilder::destination]
+    /// and [SecretServiceBuilder::path] do, see [ConnectOptions].
+    pub fn options(self, options: ConnectOptions) -> Self {
+        self.destination(options.destination).path(options.path)
+    }
+
+    /// Limits how many calls to the provider the async API makes
+    /// concurrently.
+    ///
+    /// Operations spanning many items, such as [SecretService::search_labels],
+    /// otherwise issue one call per item at once, which some providers handle
+    /// poorly. By default there is no limit.
+    pub fn max_concurrent_calls(mut self, limit: NonZeroUsize) -> Self {
+        self.config.call_limit = Some(Semaphore::new(limit.get()));
+        self
+    }
+
+    /// Encrypts the values of `attributes` with `key` on the client, before
+    /// they are handed to the provider.
+    ///
+    /// Providers store attributes in the clear, readable by any local
+    /// process, so this keeps sensitive lookup keys such as account ids or
+    /// email addresses out of the keyring's metadata. The encryption is
+    /// deterministic: values in searches are encrypted the same way, so
+    /// searching by them keeps working, and values read back with
+    /// `Item::get_attributes` are decrypted. Attribute names stay visible,
+    /// as does whether two items share a value.
+    ///
+    /// `key` should be random bytes kept by the application. Connecting
+    /// fails with [Error::Crypto] if it's shorter than 16 bytes. Items
+    /// stored with another key aren't found by searches, and their
+    /// values fail to decrypt with [Error::Crypto]. Values stored before
+    /// encryption was turned on are returned as they are, but can't be
+    /// searched for anymore. [CollectionMirror](crate::CollectionMirror)
+    /// holds the values as stored.
+    pub fn encrypt_attributes<I, S>(mut self, key: &[u8], attributes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let attributes = attributes.into_iter().map(Into::into).collect();
+        match AttributeCipher::new(key, attributes) {
+            Ok(cipher) => self.config.attribute_cipher = Some(cipher),
+            Err(err) => self.fail(err),
+        }
+        self
+    }
+
+    /// Encrypts secrets with `key` on the client, before they are handed to
+    /// the provider.
+    ///
+    /// Once a collection is unlocked, any local application can read its
+    /// secrets from the provider. Secrets encrypted with a key held by the
+    /// application stay unreadable to them, as well as to the provider
+    /// itself. Secrets stored with `Collection::create_item` and
+    /// `Item::set_secret` are encrypted, and decrypted again when read, also
+    /// in batches such as `Collection::find_duplicates`. The id of the key
+    /// is recorded in the item's `envelope_key_id` attribute.
+    ///
+    /// Secrets encrypted with another key fail to decrypt with
+    /// [Error::Crypto]. Secrets stored before encryption was turned on are
+    /// returned as they are.
+    pub fn envelope_key(mut self, key: ClientKey) -> Self {
+        self.config.envelope_key = Some(key);
+        self
+    }
+
+    /// Sets whether creating collections and items is retried with the
+    /// legacy property names `Label` and `Attributes`, when the provider
+    /// rejects the namespaced ones of the specification with `InvalidArgs`.
+    ///
+    /// Some providers predating the specification only accept the legacy
+    /// names. Off by default, as the retry costs a second call on every
+    /// failure.
+    pub fn legacy_property_names(mut self, enabled: bool) -> Self {
+        self.config.legacy_properties = enabled;
+        self
+    }
+
+    /// Keeps up to `versions` previous secrets of each item, so that
+    /// overwritten secrets can be recovered with `Item::revert_to`.
+    ///
+    /// Before `Item::set_secret` replaces a secret, the secret is copied into
+    /// an item of its own in the same collection, labelled after the item
+    /// and linked to it by attributes holding the item's path and the
+    /// version, see `Item::secret_history`. Once there are more than
+    /// `versions`, the oldest are deleted. Archived secrets are ordinary
+    /// items, so other applications see them too. They are linked by path,
+    /// so they stay behind when the item is deleted or trashed. Off by
+    /// default, and with a `versions` of 0.
+    pub fn secret_history(mut self, versions: usize) -> Self {
+        self.config.secret_history = versions;
+        self
+    }
+
+    /// Records the changes made through the connection in `sink`, see the
+    /// [journal](crate::journal) module.
+    pub fn journal(mut self, sink: Arc<dyn JournalSink>) -> Self {
+        self.config.journal = Some(Journal(sink));
+        self
+    }
+
+    /// Hands the prompts of the connection to `prompter`, instead of
+    /// showing them and waiting for the user, see [Prompter].
+    pub fn prompter(mut self, prompter: Arc<dyn Prompter>) -> Self {
+        self.config.prompter = Some(CustomPrompter(prompter));
+        self
+    }
+
+    /// Compresses secrets of at least `threshold` bytes with `compressor`
+    /// before encrypting and storing them, see the
+    /// [compression](crate::compression) module.
+    ///
+    /// Secrets compression doesn't make smaller are stored as they are.
+    /// Compressed secrets are decompressed when read, which fails with
+    /// [Error::Compression] on connections without a compressor for their
+    /// encoding.
+    pub fn compression(mut self, compressor: Arc<dyn Compressor>, threshold: usize) -> Self {
+        self.config.compression = Some(Compression {
+            compressor,
+            threshold,
+        });
+        self
+    }
+
+    /// Sets the content type of secrets stored with
+    /// `Collection::create_item_simple` and `Item::set_secret_default`,
+    /// instead of `text/plain; charset=utf8`.
+    ///
+    /// Applications sharing a collection should agree on it, so that they
+    /// read each other's secrets the same way. Storing fails with
+    /// [Error::InvalidContentType] if it isn't a valid MIME type.
+    pub fn default_content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.config.default_content_type = content_type.into();
+        self
+    }
+
+    /// Sets whether the provider's responses are checked against the
+    /// specification, failing with [Error::InvalidResponse] when they
+    /// aren't as expected.
+    ///
+    /// Secrets must belong to the session they were requested for, carry an
+    /// IV of the length the session's algorithm uses and be padded to whole
+    /// blocks if encrypted, and have a valid, non-empty content type.
+    /// Prompts must complete with a result of the type the prompting method
+    /// defines. Without it, secrets are decrypted as returned, which can
+    /// yield garbage or panic on a malformed IV, and prompt results are
+    /// converted as far as they can be. Off by default.
+    pub fn strict(mut self, enabled: bool) -> Self {
+        self.config.strict = enabled;
+        self
+    }
+
+    /// Sets whether zbus drives the connection from its own executor thread,
+    /// which is the default.
+    ///
+    /// Hosts with their own reactor, such as single-threaded GUIs, can turn
+    /// it off and instead tick the executor returned by
+    /// [SecretService::executor] (or [blocking::SecretService::executor])
+    /// themselves. Calls stall for as long as the executor isn't ticked.
+    ///
+    /// With zbus' `tokio` integration the connection is always driven by the
+    /// tokio runtime, and this setting has no effect.
+    pub fn internal_executor(mut self, enabled: bool) -> Self {
+        self.internal_executor = enabled;
+        self
+    }
+
+    /// Keeps the AES key of an encrypted session in the Linux kernel's
+    /// process keyring, loading it only while encrypting or decrypting a
+    /// secret.
+    ///
+    /// This way a memory dump of the process doesn't trivially reveal the
+    /// key protecting secrets in transit. Has no effect on
+    /// [EncryptionType::Plain] sessions.
+    #[cfg(feature = "kernel-keyring")]
+    pub fn kernel_keyring(mut self, enabled: bool) -> Self {
+        self.kernel_keyring = enabled;
+        self
+    }
+
+    /// Sets whether the session is negotiated on the first operation
+    /// transferring a secret, instead of when connecting.
+    ///
+    /// Tools that only read metadata, such as listing collections or the
+    /// labels of items, then never call `OpenSession` nor generate the
+    /// Diffie-Hellman key pair, which makes them start faster. The first
+    /// secret read or stored pays for the negotiation instead, and fails
+    /// with its errors. Plain sessions are still refused under the
+    /// `deny-plain` feature when connecting. Off by default.
+    pub fn lazy_session(mut self, enabled: bool) -> Self {
+        self.lazy_session = enabled;
+        self
+    }
+
+    /// Sets whether a plain session is negotiated if the provider rejects
+    /// the algorithm with `NotSupported`, as some minimal providers do with
+    /// [EncryptionType::Dh].
+    ///
+    /// [SecretService::session] tells which kind of session was negotiated.
+    /// Secrets are then sent over the bus unencrypted, so this is only for
+    /// applications that would rather work than fail on such providers. The
+    /// fallback never happens under the `deny-plain` feature. Off by
+    /// default.
+    pub fn plain_fallback(mut self, enabled: bool) -> Self {
+        self.plain_fallback = enabled;
+        self
+    }
+
+    /// Records `err` to be returned when connecting, unless a setting was
+    /// invalid before.
+    fn fail(&mut self, err: Error) {
+        self.error.get_or_insert(err);
+    }
+
+    /// Returns the algorithm to negotiate the session with.
+    fn session_algorithm(&self) -> Result<Arc<dyn SessionAlgorithm>, Error> {
+        self.algorithms
+            .get(&self.algorithm)
+            .cloned()
+            .ok_or_else(|| Error::UnknownAlgorithm(self.algorithm.clone()))
+    }
+
+    /// Records how to connect, for negotiating the session now and again
+    /// on [SecretService::reconnect], or returns the first invalid setting.
+    fn reconnect(&mut self) -> Result<Reconnect, Error> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        Ok(Reconnect {
+            algorithm: self.session_algorithm()?,
+            lazy_session: self.lazy_session,
+            plain_fallback: self.plain_fallback,
+            #[cfg(feature = "kernel-keyring")]
+            kernel_keyring: self.kernel_keyring,
+            internal_executor: self.internal_executor,
+            given_connection: self.conn.is_some(),
+        })
+    }
+
+    /// Connects to the secret service and negotiates a session.
+    pub async fn connect<'a>(mut self) -> Result<SecretService<'a>, Error> {
+        let reconnect = self.reconnect()?;
+        let conn = self.open_connection().await?;
+
+        // without the internal executor, nothing else ticks it until the
+        // connection is handed over
+        let (service_proxy, session) = util::drive_executor(conn.executor(), async {
+            let service_proxy = self.service_proxy(&conn).await?;
+            let session = reconnect.session(&service_proxy).await?;
+            Ok::<_, Error>((service_proxy, session))
+        })
+        .await?;
+        self.config.reconnect = Some(reconnect);
+
+        Ok(SecretService {
+            conn,
+            session,
+            service_proxy,
+            config: self.config,
+        })
+    }
+
+    /// Connects to the secret service, negotiates a session and opens the
+    /// default collection, reading whether it's locked.
+    ///
+    /// The session is negotiated while the `default` alias is resolved and
+    /// the collection's lock state read, which saves the round trips of
+    /// doing so one after the other on the start-up path of most
+    /// applications. Fails with [Error::NoResult] if there is no default
+    /// collection.
+    ///
+    /// ```no_run
+    /// # async fn call() -> Result<(), secret_service::Error> {
+    /// use secret_service::SecretService;
+    ///
+    /// let warm = SecretService::builder().connect_and_open_default().await?;
+    /// if warm.locked() {
+    ///     warm.default_collection().unlock().await?;
+    /// }
+    /// let items = warm.default_collection().get_all_items().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn connect_and_open_default<'a>(mut self) -> Result<WarmConnection<'a>, Error> {
+        let reconnect = self.reconnect()?;
+        let conn = self.open_connection().await?;
+
+        let (service_proxy, session, (path, collection_proxy, locked)) =
+            util::drive_executor(conn.executor(), async {
+                let service_proxy = self.service_proxy(&conn).await?;
+                let default = async {
+                    let path =
+                        util::resolve_alias(&conn, &service_proxy, &self.config, "default").await?;
+                    let collection_proxy =
+                        Collection::proxy(&conn, &self.config, path.clone()).await?;
+                    let locked = collection_proxy.locked().await?;
+                    Ok::<_, Error>((path, collection_proxy, locked))
+                };
+                let (session, default) =
+                    futures_util::try_join!(reconnect.session(&service_proxy), default)?;
+                Ok::<_, Error>((service_proxy, session, default))
+            })
+            .await?;
+        self.config.reconnect = Some(reconnect);
+
+        Ok(WarmConnection {
+            service: SecretService {
+                conn,
+                session,
+                service_proxy,
+                config: self.config,
+            },
+            path,
+            collection_proxy,
+            locked,
+        })
+    }
+
+    /// Returns the connection given to the builder, or connects to the
+    /// session bus.
+    async fn open_connection(&mut self) -> Result<zbus::Connection, Error> {
+        match self.conn.take() {
+            Some(conn) => Ok(conn),
+            None => util::session_bus(self.internal_executor).await,
+        }
+    }
+
+    async fn service_proxy<'a>(&self, conn: &zbus::Connection) -> Result<ServiceProxy<'a>, Error> {
+        ServiceProxy::builder(conn)
+            .destination(self.config.destination.clone())?
+            .path(self.config.path.clone())?
+            .build()
+            .await
+            .map_err(util::handle_conn_error)
+    }
+
+    /// Connects to the secret service and negotiates a session,
+    /// blocking the current thread.
+    pub fn connect_blocking<'a>(mut self) -> Result<blocking::SecretService<'a>, Error> {
+        if !self.internal_executor || self.conn.is_some() {
+            // the executor has to be ticked during the negotiation, which
+            // only the async API does, and a given connection is async
+            let service = zbus::block_on(self.connect())?;
+            return Ok(blocking::SecretService {
+                conn: service.conn.into(),
+                session: service.session,
+                service_proxy: service.service_proxy.into_inner().into(),
+                config: service.config,
+            });
+        }
+
+        let conn = zbus::blocking::Connection::session().map_err(util::handle_conn_error)?;
+        let service_proxy = ServiceProxyBlocking::builder(&conn)
+            .destination(self.config.destination.clone())?
+            .path(self.config.path.clone())?
+            .build()
+            .map_err(util::handle_conn_error)?;
+
+        let reconnect = self.reconnect()?;
+        let session = reconnect.session_blocking(&service_proxy)?;
+        self.config.reconnect = Some(reconnect);
+
+        Ok(blocking::SecretService {
+            conn,
+            session,
+            service_proxy,
+            config: self.config,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_parse_encryption_override() {
+        assert_eq!(
+            encryption_override(Some("plain")).unwrap(),
+            Some(EncryptionType::Plain)
+        );
+        assert_eq!(
+            encryption_override(Some("DH")).unwrap(),
+            Some(EncryptionType::Dh)
+        );
+        assert_eq!(encryption_override(Some("auto")).unwrap(), None);
+        assert_eq!(encryption_override(None).unwrap(), None);
+        assert!(matches!(
+            encryption_override(Some("rot13")),
+            Err(Error::InvalidEncryptionOverride(value)) if value == "rot13"
+        ));
+    }
+
+    #[test]
+    fn should_apply_connect_options() {
+        let builder = SecretServiceBuilder::new();
+        let defaults = ConnectOptions::default();
+        assert_eq!(builder.config.destination, defaults.destination);
+        assert_eq!(builder.config.path, defaults.path);
+
+        let builder = builder.options(ConnectOptions {
+            destination: "org.example.Secrets".to_owned(),
+            path: "/org/example/secrets".to_owned(),
+        });
+        assert_eq!(builder.config.destination, "org.example.Secrets");
+        assert_eq!(builder.config.path, "/org/example/secrets");
+    }
+
+    #[test]
+    fn should_reject_short_attribute_keys() {
+        let result = zbus::block_on(
+            SecretServiceBuilder::new()
+                .encrypt_attributes(b"short", ["test_account"])
+                .connect(),
+        );
+        assert!(matches!(result, Err(Error::Crypto(_))));
+    }
+}
@@ -0,0 +1,144 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+// Caching of alias resolutions, so that repeatedly getting the default
+// collection doesn't cost a ReadAlias round trip each time.
+
+use crate::builder::Config;
+use crate::ss::SS_SERVICE_INTERFACE;
+use crate::Error;
+use futures_util::StreamExt;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use zbus::zvariant::OwnedObjectPath;
+use zbus::{message, MatchRule, MessageStream, Task};
+
+/// Caches alias to collection path resolutions.
+///
+/// Once [AliasCache::watch] has subscribed to the signals of the `Service`
+/// object, the cache is cleared whenever a collection is created, deleted or
+/// changed, since any of these may move an alias.
+#[derive(Debug, Default)]
+pub(crate) struct AliasCache {
+    state: Arc<Mutex<State>>,
+    watcher: Mutex<Option<Task<()>>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    paths: HashMap<String, OwnedObjectPath>,
+    /// Bumped on every invalidation, so that a resolution that raced with a
+    /// signal isn't cached.
+    generation: u64,
+}
+
+impl AliasCache {
+    pub(crate) fn get(&self, alias: &str) -> Option<OwnedObjectPath> {
+        self.state.lock().unwrap().paths.get(alias).cloned()
+    }
+
+    /// Returns the generation to pass to [AliasCache::insert] for a
+    /// resolution started now.
+    pub(crate) fn generation(&self) -> u64 {
+        self.state.lock().unwrap().generation
+    }
+
+    /// Caches `path` for `alias`, unless the cache was cleared since
+    /// `generation` was read.
+    ///
+    /// Only call this after [AliasCache::watch] succeeded, as nothing would
+    /// invalidate the entry otherwise.
+    pub(crate) fn insert(&self, alias: &str, path: OwnedObjectPath, generation: u64) {
+        let mut state = self.state.lock().unwrap();
+        if state.generation == generation {
+            state.paths.insert(alias.to_owned(), path);
+        }
+    }
+
+    /// Forgets all resolutions, for operations that may move an alias.
+    pub(crate) fn clear(&self) {
+        self.state.lock().unwrap().clear();
+    }
+
+    /// Forgets all resolutions and unsubscribes, for when the connection
+    /// the signals come in on is replaced.
+    pub(crate) fn reset(&self) {
+        self.watcher.lock().unwrap().take();
+        self.clear();
+    }
+
+    /// Subscribes to the signals invalidating the cache, unless already
+    /// subscribed.
+    pub(crate) async fn watch(
+        &self,
+        conn: &zbus::Connection,
+        config: &Config,
+    ) -> Result<(), Error> {
+        if self.watcher.lock().unwrap().is_some() {
+            return Ok(());
+        }
+
+        let rule = MatchRule::builder()
+            .msg_type(message::Type::Signal)
+            .sender(config.destination.clone())?
+            .path(config.path.clone())?
+            .interface(SS_SERVICE_INTERFACE)?
+            .build();
+        let mut signals = MessageStream::for_match_rule(rule, conn, None).await?;
+
+        // the stream has to be drained continuously, or it would eventually
+        // stall the connection, so it's handed to a task
+        let state = Arc::clone(&self.state);
+        let task = conn.executor().spawn(
+            async move {
+                while signals.next().await.is_some() {
+                    state.lock().unwrap().clear();
+                }
+            },
+            "secret-service alias cache",
+        );
+
+        self.watcher.lock().unwrap().get_or_insert(task);
+        Ok(())
+    }
+
+    /// Blocking variant of [AliasCache::watch].
+    pub(crate) fn watch_blocking(
+        &self,
+        conn: &zbus::blocking::Connection,
+        config: &Config,
+    ) -> Result<(), Error> {
+        zbus::block_on(self.watch(conn.inner(), config))
+    }
+}
+
+impl State {
+    fn clear(&mut self) {
+        self.paths.clear();
+        self.generation += 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_not_cache_stale_resolutions() {
+        let cache = AliasCache::default();
+        let path = OwnedObjectPath::try_from("/org/freedesktop/secrets/collection/login").unwrap();
+
+        let generation = cache.generation();
+        cache.insert("default", path.clone(), generation);
+        assert_eq!(cache.get("default"), Some(path.clone()));
+
+        let generation = cache.generation();
+        cache.state.lock().unwrap().clear();
+        cache.insert("default", path, generation);
+        assert_eq!(cache.get("default"), None);
+    }
+}
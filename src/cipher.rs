@@ -0,0 +1,417 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+// Client-side encryption with keys held by the application, so that data
+// stored by the provider can't be read from its metadata alone.
+
+use crate::attributes::{decode_bytes, push_bytes};
+use crate::session::{decrypt, encrypt, hkdf, wipe, AesKey};
+use crate::Error;
+
+use rand::{rngs::OsRng, Rng};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Marks attribute values encrypted by an [AttributeCipher].
+const ENCRYPTED_PREFIX: &str = "ss-enc1:";
+
+const IV_LEN: usize = 16;
+
+/// Attribute recording the id of the [ClientKey] an item's secret is
+/// encrypted with.
+pub(crate) const ENVELOPE_KEY_ATTRIBUTE: &str = "envelope_key_id";
+
+/// Starts secrets encrypted with a [ClientKey].
+const ENVELOPE_MAGIC: &[u8] = b"ss-env1";
+
+const TAG_LEN: usize = 32;
+
+const MIN_KEY_LEN: usize = 16;
+
+/// A key held by the application to encrypt secrets with before they are
+/// handed to the provider, see
+/// [SecretServiceBuilder::envelope_key](crate::SecretServiceBuilder::envelope_key).
+///
+/// The id names the key in the items it encrypts, so that they can be told
+/// apart from items encrypted with another key.
+///
+/// ## Protecting the key
+/// Where the key material comes from is up to the application. Storing it
+/// next to the keyring it protects adds little, so deployments where the
+/// login keyring is weakly protected, such as kiosks with auto-login, are
+/// better off sealing it to the device, for instance with a TPM through the
+/// `tss-esapi` crate, and unsealing it at startup to pass it to
+/// [ClientKey::new]. The crate doesn't integrate a TPM stack itself: it
+/// would pull in the native TSS libraries for every user, while the sealing
+/// policy (PCRs, authorization) is specific to each deployment anyway.
+///
+/// The `ClientKey` keeps keys derived from the key material rather than the
+/// material itself, but neither they nor the copies made while deriving them
+/// are wiped from memory. Applications should wipe the buffer they pass to
+/// [ClientKey::new] themselves once it returns.
+pub struct ClientKey {
+    id: String,
+    enc_key: AesKey,
+    mac_key: AesKey,
+}
+
+impl ClientKey {
+    /// Creates a key named `id` from `key`, which should be random bytes.
+    ///
+    /// Fails with [Error::Crypto] if `key` is shorter than 16 bytes, or if
+    /// `id` is longer than 255 bytes.
+    pub fn new(id: impl Into<String>, key: &[u8]) -> Result<Self, Error> {
+        let id = id.into();
+        if key.len() < MIN_KEY_LEN {
+            return Err(Error::Crypto("client key must be at least 16 bytes"));
+        }
+        if id.len() > 255 {
+            return Err(Error::Crypto("client key id must fit in 255 bytes"));
+        }
+
+        let mut enc_key = AesKey::default();
+        hkdf(
+            key.to_vec(),
+            Some(b"secret-service envelope encryption"),
+            &mut enc_key,
+        );
+        let mut mac_key = AesKey::default();
+        hkdf(
+            key.to_vec(),
+            Some(b"secret-service envelope mac"),
+            &mut mac_key,
+        );
+        Ok(ClientKey {
+            id,
+            enc_key,
+            mac_key,
+        })
+    }
+
+    /// Returns the id of the key.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn tag(&self, data: &[u8]) -> [u8; TAG_LEN] {
+        let mut tag = [0; TAG_LEN];
+        hkdf(data.to_vec(), Some(&self.mac_key), &mut tag);
+        tag
+    }
+
+    /// Encrypts `secret`, recording the id of the key along with it.
+    pub(crate) fn seal(&self, secret: &[u8]) -> Vec<u8> {
+        let mut iv = [0; IV_LEN];
+        OsRng.fill(&mut iv);
+
+        let mut sealed = ENVELOPE_MAGIC.to_vec();
+        sealed.push(self.id.len() as u8);
+        sealed.extend_from_slice(self.id.as_bytes());
+        sealed.extend_from_slice(&iv);
+        sealed.extend(encrypt(secret, &self.enc_key, &iv));
+        let tag = self.tag(&sealed);
+        sealed.extend_from_slice(&tag);
+        sealed
+    }
+
+    /// Decrypts a secret encrypted with [ClientKey::seal]. Secrets that
+    /// aren't encrypted are returned as they are.
+    pub(crate) fn open(&self, secret: Vec<u8>) -> Result<Vec<u8>, Error> {
+        let Some(id) = envelope_key_id(&secret) else {
+            return Ok(secret);
+        };
+        if id != self.id.as_bytes() {
+            return Err(Error::Crypto("secret is encrypted with another client key"));
+        }
+
+        let header_len = ENVELOPE_MAGIC.len() + 1 + id.len();
+        let Some(tag_start) = secret.len().checked_sub(TAG_LEN) else {
+            return Err(Error::Crypto("malformed secret envelope"));
+        };
+        if tag_start < header_len + IV_LEN {
+            return Err(Error::Crypto("malformed secret envelope"));
+        }
+        let (sealed, tag) = secret.split_at(tag_start);
+        if !equal_in_constant_time(&self.tag(sealed), tag) {
+            return Err(Error::Crypto("secret envelope failed authentication"));
+        }
+
+        let (iv, data) = sealed[header_len..].split_at(IV_LEN);
+        decrypt(data, &self.enc_key, iv)
+    }
+}
+
+impl fmt::Debug for ClientKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // leaves out the keys
+        f.debug_struct("ClientKey")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for ClientKey {
+    fn drop(&mut self) {
+        wipe(&mut self.enc_key);
+        wipe(&mut self.mac_key);
+    }
+}
+
+/// Returns the id of the key `secret` is encrypted with, if it's encrypted
+/// with a [ClientKey].
+pub(crate) fn envelope_key_id(secret: &[u8]) -> Option<&[u8]> {
+    let rest = secret.strip_prefix(ENVELOPE_MAGIC)?;
+    let (&len, rest) = rest.split_first()?;
+    rest.get(..usize::from(len))
+}
+
+fn equal_in_constant_time(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// Deterministically encrypts the values of some attributes.
+///
+/// Providers match attribute values exactly, so a value has to encrypt to
+/// the same ciphertext every time. As in SIV mode, the IV is derived from
+/// the attribute and its value with a keyed PRF (HKDF-SHA256, whose extract
+/// step is an HMAC), and checked again on decryption, which authenticates
+/// the value.
+pub(crate) struct AttributeCipher {
+    enc_key: AesKey,
+    iv_key: AesKey,
+    attributes: HashSet<String>,
+}
+
+impl AttributeCipher {
+    pub(crate) fn new(key: &[u8], attributes: HashSet<String>) -> Result<Self, Error> {
+        if key.len() < MIN_KEY_LEN {
+            return Err(Error::Crypto("attribute key must be at least 16 bytes"));
+        }
+
+        let mut enc_key = AesKey::default();
+        hkdf(
+            key.to_vec(),
+            Some(b"secret-service attribute encryption"),
+            &mut enc_key,
+        );
+        let mut iv_key = AesKey::default();
+        hkdf(
+            key.to_vec(),
+            Some(b"secret-service attribute iv"),
+            &mut iv_key,
+        );
+        Ok(AttributeCipher {
+            enc_key,
+            iv_key,
+            attributes,
+        })
+    }
+
+    fn iv(&self, attribute: &str, value: &str) -> [u8; IV_LEN] {
+        // the attribute is part of the input, so that equal values of
+        // different attributes can't be told apart
+        let mut input = Vec::with_capacity(attribute.len() + 1 + value.len());
+        input.extend_from_slice(attribute.as_bytes());
+        input.push(0);
+        input.extend_from_slice(value.as_bytes());
+
+        let mut iv = [0; IV_LEN];
+        hkdf(input, Some(&self.iv_key), &mut iv);
+        iv
+    }
+
+    fn encrypt_value(&self, attribute: &str, value: &str) -> String {
+        let iv = self.iv(attribute, value);
+        let mut encrypted = ENCRYPTED_PREFIX.to_owned();
+        push_bytes(&mut encrypted, &iv);
+        push_bytes(
+            &mut encrypted,
+            &encrypt(value.as_bytes(), &self.enc_key, &iv),
+        );
+        encrypted
+    }
+
+    fn decrypt_value(&self, attribute: &str, value: String) -> Result<String, Error> {
+        // stored before encryption was turned on
+        let Some(encoded) = value.strip_prefix(ENCRYPTED_PREFIX) else {
+            return Ok(value);
+        };
+        let bytes = decode_bytes(encoded)
+            .filter(|bytes| bytes.len() > IV_LEN)
+            .ok_or(Error::Crypto("malformed encrypted attribute"))?;
+
+        let (iv, data) = bytes.split_at(IV_LEN);
+        let decrypted = decrypt(data, &self.enc_key, iv)
+            .ok()
+            .and_then(|decrypted| String::from_utf8(decrypted).ok())
+            .filter(|decrypted| self.iv(attribute, decrypted) == iv);
+        decrypted.ok_or(Error::Crypto("attribute decryption failed"))
+    }
+
+    /// Encrypts the values of the configured attributes in `attributes`.
+    pub(crate) fn encrypt_attributes<'a>(
+        &self,
+        attributes: HashMap<&'a str, &'a str>,
+    ) -> EncryptedAttributes<'a> {
+        let attributes = attributes
+            .into_iter()
+            .map(|(key, value)| match self.attributes.contains(key) {
+                true => (key, Cow::Owned(self.encrypt_value(key, value))),
+                false => (key, Cow::Borrowed(value)),
+            })
+            .collect();
+        EncryptedAttributes(attributes)
+    }
+
+    /// Decrypts the values of the configured attributes in `attributes`.
+    pub(crate) fn decrypt_attributes(
+        &self,
+        attributes: HashMap<String, String>,
+    ) -> Result<HashMap<String, String>, Error> {
+        attributes
+            .into_iter()
+            .map(|(key, value)| match self.attributes.contains(&key) {
+                true => {
+                    let value = self.decrypt_value(&key, value)?;
+                    Ok((key, value))
+                }
+                false => Ok((key, value)),
+            })
+            .collect()
+    }
+}
+
+impl fmt::Debug for AttributeCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // leaves out the keys
+        f.debug_struct("AttributeCipher")
+            .field("attributes", &self.attributes)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for AttributeCipher {
+    fn drop(&mut self) {
+        wipe(&mut self.enc_key);
+        wipe(&mut self.iv_key);
+    }
+}
+
+/// Attributes with some of their values encrypted, ready to be sent to the
+/// provider.
+pub(crate) struct EncryptedAttributes<'a>(HashMap<&'a str, Cow<'a, str>>);
+
+impl<'a> EncryptedAttributes<'a> {
+    /// Leaves the attributes as they are.
+    pub(crate) fn plain(attributes: HashMap<&'a str, &'a str>) -> Self {
+        EncryptedAttributes(
+            attributes
+                .into_iter()
+                .map(|(key, value)| (key, Cow::Borrowed(value)))
+                .collect(),
+        )
+    }
+
+    pub(crate) fn as_map(&self) -> HashMap<&str, &str> {
+        self.0
+            .iter()
+            .map(|(key, value)| (*key, value.as_ref()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn cipher(key: &[u8]) -> AttributeCipher {
+        AttributeCipher::new(key, HashSet::from(["account".to_owned()])).unwrap()
+    }
+
+    #[test]
+    fn should_encrypt_attributes_deterministically() {
+        let cipher = cipher(b"0123456789abcdef");
+        let attributes = HashMap::from([("account", "alice"), ("service", "example")]);
+
+        let encrypted = cipher.encrypt_attributes(attributes.clone());
+        let encrypted = encrypted.as_map();
+        assert_eq!(encrypted["service"], "example");
+        assert!(encrypted["account"].starts_with(ENCRYPTED_PREFIX));
+        assert!(!encrypted["account"].contains("alice"));
+        assert_eq!(
+            cipher.encrypt_attributes(attributes.clone()).as_map(),
+            encrypted
+        );
+
+        let stored = encrypted
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        let decrypted = cipher.decrypt_attributes(stored).unwrap();
+        assert_eq!(decrypted["account"], "alice");
+        assert_eq!(decrypted["service"], "example");
+
+        // another key neither matches nor decrypts
+        let other = self::cipher(b"fedcba9876543210");
+        let other_encrypted = other.encrypt_attributes(attributes);
+        assert_ne!(other_encrypted.as_map()["account"], encrypted["account"]);
+        let stored = HashMap::from([("account".to_owned(), encrypted["account"].to_owned())]);
+        assert!(matches!(
+            other.decrypt_attributes(stored),
+            Err(Error::Crypto(_))
+        ));
+    }
+
+    #[test]
+    fn should_seal_secrets() {
+        let key = ClientKey::new("first", b"0123456789abcdef").unwrap();
+        let sealed = key.seal(b"hunter2");
+        assert_eq!(envelope_key_id(&sealed), Some(&b"first"[..]));
+        assert!(!sealed.windows(7).any(|window| window == b"hunter2"));
+        assert_ne!(key.seal(b"hunter2"), sealed);
+        assert_eq!(key.open(sealed.clone()).unwrap(), b"hunter2");
+        assert_eq!(key.open(b"hunter2".to_vec()).unwrap(), b"hunter2");
+        assert_eq!(key.open(key.seal(b"")).unwrap(), b"");
+
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(matches!(key.open(tampered), Err(Error::Crypto(_))));
+        let truncated = sealed[..sealed.len() - TAG_LEN].to_vec();
+        assert!(matches!(key.open(truncated), Err(Error::Crypto(_))));
+
+        let other = ClientKey::new("second", b"0123456789abcdef").unwrap();
+        assert!(matches!(other.open(sealed.clone()), Err(Error::Crypto(_))));
+        let renamed = ClientKey::new("first", b"fedcba9876543210").unwrap();
+        assert!(matches!(renamed.open(sealed), Err(Error::Crypto(_))));
+    }
+
+    #[test]
+    fn should_reject_short_client_keys() {
+        assert!(matches!(
+            ClientKey::new("short", b"0123456789abcde"),
+            Err(Error::Crypto(_))
+        ));
+        assert!(matches!(
+            ClientKey::new("x".repeat(256), b"0123456789abcdef"),
+            Err(Error::Crypto(_))
+        ));
+    }
+
+    #[test]
+    fn should_keep_values_stored_in_the_clear() {
+        let cipher = cipher(b"0123456789abcdef");
+        let stored = HashMap::from([("account".to_owned(), "alice".to_owned())]);
+        assert_eq!(cipher.decrypt_attributes(stored.clone()).unwrap(), stored);
+
+        let tampered = HashMap::from([("account".to_owned(), format!("{ENCRYPTED_PREFIX}00ff"))]);
+        assert!(matches!(
+            cipher.decrypt_attributes(tampered),
+            Err(Error::Crypto(_))
+        ));
+    }
+}
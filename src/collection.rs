@@ -5,29 +5,96 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
+use crate::builder::Config;
+use crate::event;
+#[cfg(feature = "regex")]
+use crate::grep;
+use crate::import;
+use crate::journal::{Change, SecretHash};
+use crate::merge;
+use crate::migration::{self, Migrations};
+use crate::mirror::CollectionMirror;
 use crate::proxy::collection::CollectionProxy;
 use crate::proxy::service::ServiceProxy;
+use crate::query::{self, Query};
+use crate::search;
 use crate::session::Session;
-use crate::ss::{SS_DBUS_NAME, SS_ITEM_ATTRIBUTES, SS_ITEM_LABEL};
-use crate::util::{exec_prompt, format_secret, lock_or_unlock, LockAction};
+use crate::snapshot;
+use crate::util::{
+    self, decrypt_secrets, exec_prompt, format_secret, group_duplicates, item_properties,
+    limit_concurrency, lock_or_unlock, rank_label_matches, retain_tagged, summarize_attributes,
+    LockAction, TEXT_CONTENT_TYPE,
+};
 use crate::Error;
-use crate::Item;
+use crate::{
+    AttributeSummary, BatchOutcome, CollectionSnapshot, Imported, Item, ItemEvent, ItemTimestamp,
+    LabelMatch, NewItem, Progress,
+};
 
-use std::collections::HashMap;
+use futures_util::{FutureExt, Stream, StreamExt};
+use std::collections::{BTreeMap, HashMap};
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicBool, Ordering};
 use zbus::{
-    zvariant::{Dict, ObjectPath, OwnedObjectPath, Value},
+    zvariant::{ObjectPath, OwnedObjectPath},
     CacheProperties,
 };
 
+/// How many items [Collection::relabel_matching] handles at a time.
+const RELABEL_BATCH_SIZE: usize = 32;
+
+/// What creating an item does if the collection already holds one with the
+/// same attributes.
+///
+/// `bool` converts into it as the `replace` flag of the specification, so
+/// that `true` replaces and `false` creates anyway.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ConflictPolicy {
+    /// The existing item is replaced.
+    Replace,
+    /// Fails with [Error::ItemExists], leaving the existing item as is.
+    ///
+    /// The specification has no such flag, so the collection is searched
+    /// before creating the item. An item created by another application in
+    /// between is not detected.
+    Fail,
+    /// Another item is created next to the existing one.
+    CreateAnyway,
+}
+
+impl From<bool> for ConflictPolicy {
+    fn from(replace: bool) -> Self {
+        if replace {
+            ConflictPolicy::Replace
+        } else {
+            ConflictPolicy::CreateAnyway
+        }
+    }
+}
+
 // Collection struct.
 // Should always be created from the SecretService entry point,
 // whether through a new collection or a collection search
 pub struct Collection<'a> {
     conn: zbus::Connection,
-    session: &'a Session,
+    pub(crate) session: &'a Session,
+    /// Deprecated copy of [Collection::path], for reading only: assigning to
+    /// it doesn't change the collection the handle refers to.
+    /// [Collection::refresh] updates it along with the path.
+    #[deprecated(
+        since = "4.1.0",
+        note = "use `Collection::path`, which doesn't expose zbus types"
+    )]
     pub collection_path: OwnedObjectPath,
+    pub(crate) path: OwnedObjectPath,
     collection_proxy: CollectionProxy<'a>,
-    service_proxy: &'a ServiceProxy<'a>,
+    pub(crate) service_proxy: &'a ServiceProxy<'a>,
+    pub(crate) config: &'a Config,
+    /// Set once the object is deleted, so that later calls can say so.
+    deleted: AtomicBool,
+    /// The alias the collection was looked up by, resolved again on refresh.
+    alias: Option<String>,
 }
 
 impl<'a> Collection<'a> {
@@ -35,26 +102,156 @@ impl<'a> Collection<'a> {
         conn: zbus::Connection,
         session: &'a Session,
         service_proxy: &'a ServiceProxy<'_>,
+        config: &'a Config,
         collection_path: OwnedObjectPath,
     ) -> Result<Collection<'a>, Error> {
-        let collection_proxy = CollectionProxy::builder(&conn)
-            .destination(SS_DBUS_NAME)?
-            .path(collection_path.clone())?
-            .cache_properties(CacheProperties::No)
-            .build()
-            .await?;
+        let collection_proxy = Self::proxy(&conn, config, collection_path.clone()).await?;
+        Ok(Self::with_proxy(
+            conn,
+            session,
+            service_proxy,
+            config,
+            collection_path,
+            collection_proxy,
+        ))
+    }
 
-        Ok(Collection {
+    /// Creates a collection with a proxy built beforehand.
+    pub(crate) fn with_proxy(
+        conn: zbus::Connection,
+        session: &'a Session,
+        service_proxy: &'a ServiceProxy<'_>,
+        config: &'a Config,
+        collection_path: OwnedObjectPath,
+        collection_proxy: CollectionProxy<'a>,
+    ) -> Collection<'a> {
+        #[allow(deprecated)]
+        Collection {
             conn,
             session,
+            path: collection_path.clone(),
             collection_path,
             collection_proxy,
             service_proxy,
-        })
+            config,
+            deleted: AtomicBool::new(false),
+            alias: None,
+        }
+    }
+
+    pub(crate) async fn proxy(
+        conn: &zbus::Connection,
+        config: &Config,
+        collection_path: OwnedObjectPath,
+    ) -> Result<CollectionProxy<'a>, Error> {
+        Ok(CollectionProxy::builder(conn)
+            .destination(config.destination.clone())?
+            .path(collection_path)?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await?)
+    }
+
+    /// Records the alias the collection was looked up by, for [Collection::refresh].
+    pub(crate) fn with_alias(mut self, alias: &str) -> Self {
+        self.alias = Some(alias.to_owned());
+        self
+    }
+
+    /// Returns a handle to the same collection that transfers secrets in
+    /// `session`, see [SecretService::open_session](crate::SecretService::open_session).
+    pub fn with_session<'s>(&'s self, session: &'s Session) -> Collection<'s> {
+        #[allow(deprecated)]
+        Collection {
+            conn: self.conn.clone(),
+            session,
+            collection_path: self.path.clone(),
+            path: self.path.clone(),
+            collection_proxy: self.collection_proxy.clone(),
+            service_proxy: self.service_proxy,
+            config: self.config,
+            deleted: AtomicBool::new(self.deleted.load(Ordering::Relaxed)),
+            alias: self.alias.clone(),
+        }
+    }
+
+    /// The object path of the collection.
+    pub fn path(&self) -> crate::ObjectPath {
+        self.path.clone().into()
+    }
+
+    /// Attaches `operation` and the collection's path to errors of D-Bus calls,
+    /// or reports them as [Error::ObjectDeleted] once the collection is deleted.
+    fn context<E: Into<Error>>(&self, operation: &'static str) -> impl FnOnce(E) -> Error + '_ {
+        move |err| {
+            if self.deleted.load(Ordering::Relaxed) {
+                Error::ObjectDeleted
+            } else {
+                err.into().with_context(operation, &self.path)
+            }
+        }
     }
 
     pub async fn is_locked(&self) -> Result<bool, Error> {
-        Ok(self.collection_proxy.locked().await?)
+        self.collection_proxy
+            .locked()
+            .await
+            .map_err(self.context("is_locked"))
+    }
+
+    /// Returns whether the collection still exists, for handles kept around
+    /// while other applications may delete the collection.
+    ///
+    /// Probes a property of the collection, so that a deleted collection
+    /// shows up as `false` rather than as an error.
+    pub async fn exists(&self) -> Result<bool, Error> {
+        if self.deleted.load(Ordering::Relaxed) {
+            return Ok(false);
+        }
+        match self.collection_proxy.locked().await {
+            Ok(_) => Ok(true),
+            Err(err) => {
+                let err = Error::from(err);
+                if err.is_unknown_object() {
+                    Ok(false)
+                } else {
+                    Err(err.with_context("exists", &self.path))
+                }
+            }
+        }
+    }
+
+    /// Revalidates the collection, for handles kept around while other
+    /// applications may delete and re-create it.
+    ///
+    /// Collections looked up or created by alias resolve the alias again,
+    /// dropping any cached resolution, so that the handle moves to a
+    /// collection re-created under the alias at a different path. Other
+    /// collections are only checked for existence. Returns
+    /// [Error::NoResult] if the collection is gone.
+    pub async fn refresh(&mut self) -> Result<(), Error> {
+        let Some(alias) = &self.alias else {
+            return match self.exists().await? {
+                true => Ok(()),
+                false => Err(Error::NoResult),
+            };
+        };
+
+        self.config.alias_cache.clear();
+        let path = self.service_proxy.read_alias(alias).await?;
+        if path.as_str() == "/" {
+            return Err(Error::NoResult);
+        }
+        if path != self.path {
+            self.collection_proxy = Self::proxy(&self.conn, self.config, path.clone()).await?;
+            #[allow(deprecated)]
+            {
+                self.collection_path = path.clone();
+            }
+            self.path = path;
+        }
+        *self.deleted.get_mut() = false;
+        Ok(())
     }
 
     pub async fn ensure_unlocked(&self) -> Result<(), Error> {
@@ -65,42 +262,66 @@ impl<'a> Collection<'a> {
         }
     }
 
-    pub async fn unlock(&self) -> Result<(), Error> {
-        lock_or_unlock(
+    /// Unlocks the collection, prompting the user if the provider asks to,
+    /// and returns the paths of the objects the provider reports as unlocked.
+    pub async fn unlock(&self) -> Result<Vec<crate::ObjectPath>, Error> {
+        let unlocked = lock_or_unlock(
             self.conn.clone(),
             self.service_proxy,
-            &self.collection_path,
+            self.config,
+            vec![&*self.path],
             LockAction::Unlock,
         )
         .await
+        .map_err(self.context("unlock"))?;
+        Ok(unlocked.into_iter().map(Into::into).collect())
     }
 
-    pub async fn lock(&self) -> Result<(), Error> {
-        lock_or_unlock(
+    /// Locks the collection and returns the paths of the objects the
+    /// provider reports as locked, which leave out any object the provider
+    /// refused to lock.
+    pub async fn lock(&self) -> Result<Vec<crate::ObjectPath>, Error> {
+        let locked = lock_or_unlock(
             self.conn.clone(),
             self.service_proxy,
-            &self.collection_path,
+            self.config,
+            vec![&*self.path],
             LockAction::Lock,
         )
         .await
+        .map_err(self.context("lock"))?;
+        Ok(locked.into_iter().map(Into::into).collect())
     }
 
     /// Deletes dbus object, but struct instance still exists (current implementation)
+    ///
+    /// Calls failing on the instance afterwards return [Error::ObjectDeleted].
     pub async fn delete(&self) -> Result<(), Error> {
         // ensure_unlocked handles prompt for unlocking if necessary
         self.ensure_unlocked().await?;
-        let prompt_path = self.collection_proxy.delete().await?;
+        let prompt_path = self
+            .collection_proxy
+            .delete()
+            .await
+            .map_err(self.context("delete"))?;
 
         // "/" means no prompt necessary
         if prompt_path.as_str() != "/" {
-            exec_prompt(self.conn.clone(), &prompt_path).await?;
+            exec_prompt(self.conn.clone(), self.config, &prompt_path).await?;
         }
+        self.config.alias_cache.clear();
+        self.deleted.store(true, Ordering::Relaxed);
+        self.config.record(self.path(), || Change::Deleted);
 
         Ok(())
     }
 
     pub async fn get_all_items(&self) -> Result<Vec<Item<'_>>, Error> {
-        let items = self.collection_proxy.items().await?;
+        let items = self
+            .collection_proxy
+            .items()
+            .await
+            .map_err(self.context("get_all_items"))?;
 
         // map array of item paths to Item
         futures_util::future::join_all(items.into_iter().map(|item_path| {
@@ -108,6 +329,7 @@ impl<'a> Collection<'a> {
                 self.conn.clone(),
                 self.session,
                 self.service_proxy,
+                self.config,
                 item_path.into(),
             )
         }))
@@ -116,11 +338,351 @@ impl<'a> Collection<'a> {
         .collect::<Result<_, _>>()
     }
 
+    /// Returns all items in the collection in batches of `page_size`.
+    ///
+    /// The list of item paths is read once up front; the items themselves are
+    /// only constructed as each page is polled, which keeps memory use and bus
+    /// traffic bounded when scanning very large collections.
+    pub async fn items_paged(
+        &self,
+        page_size: NonZeroUsize,
+    ) -> Result<impl Stream<Item = Result<Vec<Item<'_>>, Error>> + '_, Error> {
+        let items = self
+            .collection_proxy
+            .items()
+            .await
+            .map_err(self.context("items_paged"))?;
+        let pages = items
+            .chunks(page_size.get())
+            .map(|page| {
+                page.iter()
+                    .cloned()
+                    .map(OwnedObjectPath::from)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        Ok(futures_util::stream::iter(pages).then(move |page| {
+            futures_util::future::join_all(page.into_iter().map(|item_path| {
+                Item::new(
+                    self.conn.clone(),
+                    self.session,
+                    self.service_proxy,
+                    self.config,
+                    item_path,
+                )
+            }))
+            .map(|items| items.into_iter().collect::<Result<_, _>>())
+        }))
+    }
+
+    /// Deletes every item whose `timestamp` is older than `cutoff`
+    /// (in seconds since the Unix epoch).
+    ///
+    /// The outcome lists each item older than `cutoff` with whether it was
+    /// deleted; an item failing to be deleted doesn't stop the others. When
+    /// `dry_run` is set, nothing is deleted and the outcome lists the items
+    /// that would have been purged.
+    pub async fn purge_older_than(
+        &self,
+        cutoff: u64,
+        timestamp: ItemTimestamp,
+        dry_run: bool,
+    ) -> Result<BatchOutcome, Error> {
+        self.purge_older_than_with_progress(cutoff, timestamp, dry_run, |_| ())
+            .await
+    }
+
+    /// Like [Collection::purge_older_than], calling `progress` after each
+    /// item deleted. Nothing is reported on a dry run.
+    pub async fn purge_older_than_with_progress(
+        &self,
+        cutoff: u64,
+        timestamp: ItemTimestamp,
+        dry_run: bool,
+        mut progress: impl FnMut(&Progress),
+    ) -> Result<BatchOutcome, Error> {
+        let items = self.get_all_items().await?;
+
+        let timestamps = futures_util::future::join_all(items.iter().map(|item| {
+            let call = match timestamp {
+                ItemTimestamp::Created => item.get_created().left_future(),
+                ItemTimestamp::Modified => item.get_modified().right_future(),
+            };
+            limit_concurrency(self.config, call)
+        }))
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+
+        let candidates: Vec<_> = items
+            .into_iter()
+            .zip(timestamps)
+            .filter(|(_, time)| *time < cutoff)
+            .map(|(item, _)| item)
+            .collect();
+
+        let mut state = Progress::new(Some(candidates.len()));
+        let mut results = Vec::with_capacity(candidates.len());
+        for item in &candidates {
+            let deleted = if dry_run {
+                Ok(())
+            } else {
+                let deleted = item.delete().await;
+                state.advance(None, &deleted, &mut progress);
+                deleted
+            };
+            results.push((item.path(), deleted));
+        }
+
+        Ok(BatchOutcome { results })
+    }
+
+    /// Upgrades the items of an application's schema to the latest version
+    /// of `migrations`, see [crate::migration].
+    ///
+    /// The outcome lists the items migrated, with the version each was
+    /// upgraded from, and those that failed. `progress` is called after each
+    /// item holding the schema, whether or not it had to be migrated. When
+    /// `dry_run` is set, nothing is changed and the outcome lists the items
+    /// that would have been migrated.
+    pub async fn migrate(
+        &self,
+        migrations: &Migrations,
+        dry_run: bool,
+        progress: impl FnMut(&Progress),
+    ) -> Result<BatchOutcome<u32>, Error> {
+        migration::migrate(self, migrations, dry_run, progress).await
+    }
+
+    /// Copies every item of `other` into this collection, doing what
+    /// `conflict` says for items whose attributes this collection already
+    /// holds, such as to consolidate old keyrings into one.
+    ///
+    /// The outcome tells, for each item of `other`, whether it was copied or
+    /// skipped. Secrets are fetched in batches, so only a batch of them is
+    /// held in memory at once. Locked items of `other` fail with
+    /// [Error::Locked], and items without attributes are always copied, as
+    /// with [Collection::import]. `other` is left as it was.
+    pub async fn merge_from(
+        &self,
+        other: &Collection<'_>,
+        conflict: impl Into<ConflictPolicy>,
+    ) -> Result<BatchOutcome<Imported>, Error> {
+        merge::merge_from(self, other, conflict.into()).await
+    }
+
+    /// Finds groups of items in the collection that have identical attributes.
+    ///
+    /// Only groups with more than one item are returned. When `compare_secrets`
+    /// is set, the secrets of all candidates are fetched in one batch and the
+    /// groups are split further so that items in a group also share the same
+    /// secret. Items whose secret the provider doesn't return (for example
+    /// because they are locked) are left out in that case.
+    pub async fn find_duplicates(
+        &self,
+        compare_secrets: bool,
+    ) -> Result<Vec<Vec<Item<'_>>>, Error> {
+        let items = self.get_all_items().await?;
+        let attributes = futures_util::future::join_all(
+            items
+                .iter()
+                .map(|item| limit_concurrency(self.config, item.get_attributes())),
+        )
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+
+        let groups = group_duplicates(
+            items
+                .into_iter()
+                .zip(attributes.into_iter().map(BTreeMap::from_iter)),
+        );
+
+        if !compare_secrets {
+            return Ok(groups);
+        }
+
+        let candidates = groups.iter().flatten().map(|item| &*item.path).collect();
+        let secrets = self
+            .service_proxy
+            .get_secrets(candidates, self.session.open().await?)
+            .await?;
+        let mut secrets = decrypt_secrets(self.session, self.config, secrets)?;
+
+        Ok(groups
+            .into_iter()
+            .flat_map(|group| {
+                group_duplicates(group.into_iter().filter_map(|item| {
+                    let secret = secrets.remove(&item.path)?;
+                    Some((item, secret))
+                }))
+            })
+            .collect())
+    }
+
+    /// Counts how many items hold each attribute key and, if `per_value` is
+    /// set, each value of each key.
+    ///
+    /// The attributes of the items are fetched concurrently, up to the limit
+    /// set with [SecretServiceBuilder::max_concurrent_calls](crate::SecretServiceBuilder::max_concurrent_calls).
+    pub async fn attribute_summary(&self, per_value: bool) -> Result<AttributeSummary, Error> {
+        let items = self.get_all_items().await?;
+        let attributes = futures_util::future::try_join_all(
+            items
+                .iter()
+                .map(|item| limit_concurrency(self.config, item.get_attributes())),
+        )
+        .await?;
+
+        Ok(summarize_attributes(attributes, per_value))
+    }
+
+    /// Searches the items of this collection by label, returning matches
+    /// ranked best first.
+    ///
+    /// See [SecretService::search_labels](crate::SecretService::search_labels)
+    /// for how labels are matched.
+    pub async fn search_labels(&self, query: &str) -> Result<Vec<LabelMatch<Item<'_>>>, Error> {
+        let items = self.get_all_items().await?;
+        let labels = futures_util::future::join_all(
+            items
+                .iter()
+                .map(|item| limit_concurrency(self.config, item.get_label())),
+        )
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(rank_label_matches(items.into_iter().zip(labels), query))
+    }
+
+    /// Renames every item matching `attributes` to what `rename` returns for
+    /// its current label, such as when rebranding an application.
+    ///
+    /// Items are handled in batches, their labels fetched and set
+    /// concurrently. The outcome lists the items renamed, and those
+    /// whose label couldn't be read or set. Items for which `rename` returns
+    /// the current label are left alone and aren't listed.
+    pub async fn relabel_matching(
+        &self,
+        attributes: HashMap<&str, &str>,
+        mut rename: impl FnMut(&str) -> String,
+    ) -> Result<BatchOutcome, Error> {
+        let items = self.search_items(attributes).await?;
+
+        let mut results = Vec::new();
+        for batch in items.chunks(RELABEL_BATCH_SIZE) {
+            let labels = futures_util::future::join_all(
+                batch
+                    .iter()
+                    .map(|item| limit_concurrency(self.config, item.get_label())),
+            )
+            .await;
+            let renamed: Vec<_> = batch
+                .iter()
+                .zip(labels)
+                .filter_map(|(item, label)| match label {
+                    Ok(label) => {
+                        let new_label = rename(&label);
+                        (new_label != label).then_some((item, Ok(new_label)))
+                    }
+                    Err(err) => Some((item, Err(err))),
+                })
+                .collect();
+            let relabeled =
+                futures_util::future::join_all(renamed.into_iter().map(|(item, new_label)| {
+                    limit_concurrency(self.config, async move {
+                        let relabeled = match new_label {
+                            Ok(new_label) => item.set_label(&new_label).await,
+                            Err(err) => Err(err),
+                        };
+                        (item.path(), relabeled)
+                    })
+                }))
+                .await;
+            results.extend(relabeled);
+        }
+        Ok(BatchOutcome { results })
+    }
+
+    /// Reads the metadata of every item, to compare with a later snapshot,
+    /// see [CollectionSnapshot].
+    pub async fn snapshot(&self) -> Result<CollectionSnapshot, Error> {
+        let items = self
+            .collection_proxy
+            .items()
+            .await
+            .map_err(self.context("snapshot"))?;
+        snapshot::snapshot(&self.conn, self.config, &self.path, items).await
+    }
+
+    /// Returns a local copy of the label, attributes and lock state of
+    /// every item, kept up to date from the service's signals.
+    ///
+    /// Reading the mirror never calls the provider, so UIs can render and
+    /// filter the items as often as they like, for example on every
+    /// keystroke.
+    pub async fn mirror(&self) -> Result<CollectionMirror, Error> {
+        CollectionMirror::new(&self.conn, self.config, &self.collection_proxy, self.path()).await
+    }
+
+    /// Subscribes to the items of the collection being created, changed or
+    /// deleted, by this or any other application, for keeping a local view
+    /// of the collection in sync without polling
+    /// [Collection::get_all_items].
+    ///
+    /// The stream ends once the [SecretService](crate::SecretService) the
+    /// collection came from is dropped.
+    pub async fn receive_item_events(&self) -> Result<impl Stream<Item = ItemEvent>, Error> {
+        let events = event::item_events(&self.collection_proxy)
+            .await
+            .map_err(self.context("receive_item_events"))?;
+        Ok(self.config.lifetime.bind(events))
+    }
+
+    /// Searches items by attributes, answering from the index of `mirror`
+    /// (see [CollectionMirror::search]) instead of calling the provider.
+    ///
+    /// If the index has no match, or `mirror` is of another collection, the
+    /// provider is searched instead, so that items the mirror hasn't caught up
+    /// with yet are still found.
+    pub async fn search_items_indexed(
+        &self,
+        mirror: &CollectionMirror,
+        attributes: HashMap<&str, &str>,
+    ) -> Result<Vec<Item<'_>>, Error> {
+        let paths = if mirror.collection_path().as_str() == self.path.as_str() {
+            mirror.search(&self.config.encrypt_attributes(attributes.clone()).as_map())
+        } else {
+            Vec::new()
+        };
+        if paths.is_empty() {
+            return self.search_items(attributes).await;
+        }
+
+        futures_util::future::try_join_all(paths.into_iter().map(|item_path| {
+            Item::new(
+                self.conn.clone(),
+                self.session,
+                self.service_proxy,
+                self.config,
+                item_path.into(),
+            )
+        }))
+        .await
+    }
+
     pub async fn search_items(
         &self,
         attributes: HashMap<&str, &str>,
     ) -> Result<Vec<Item<'_>>, Error> {
-        let items = self.collection_proxy.search_items(attributes).await?;
+        let attributes = self.config.encrypt_attributes(attributes);
+        let items = self
+            .collection_proxy
+            .search_items(attributes.as_map())
+            .await
+            .map_err(self.context("search_items"))?;
 
         // map array of item paths to Item
         futures_util::future::join_all(items.into_iter().map(|item_path| {
@@ -128,6 +690,7 @@ impl<'a> Collection<'a> {
                 self.conn.clone(),
                 self.session,
                 self.service_proxy,
+                self.config,
                 item_path,
             )
         }))
@@ -136,34 +699,161 @@ impl<'a> Collection<'a> {
         .collect::<Result<_, _>>()
     }
 
+    /// Searches items by attributes, keeping only those whose attributes are
+    /// exactly `attributes`, rather than a superset of them.
+    ///
+    /// See [ItemSearch::exact](crate::ItemSearch::exact).
+    pub async fn search_items_exact(
+        &self,
+        attributes: HashMap<&str, &str>,
+    ) -> Result<Vec<Item<'_>>, Error> {
+        let items = self.search_items(attributes.clone()).await?;
+        search::retain_exact(self.config, items, &attributes).await
+    }
+
+    /// Searches the items of this collection by attributes, keeping only
+    /// those whose attribute `key` holds a list containing `tag`.
+    ///
+    /// See [SecretService::search_items_by_tag](crate::SecretService::search_items_by_tag).
+    pub async fn search_items_by_tag(
+        &self,
+        mut attributes: HashMap<&str, &str>,
+        key: &str,
+        tag: &str,
+    ) -> Result<Vec<Item<'_>>, Error> {
+        attributes.remove(key);
+        let items = self.search_items(attributes).await?;
+        retain_tagged(self.config, items, key, tag).await
+    }
+
+    /// Returns the items whose label or an attribute value matches
+    /// `pattern`, such as for interactive search tools.
+    ///
+    /// The labels and attributes of the items are fetched concurrently and
+    /// matched on the client. Secrets aren't matched, see
+    /// [Collection::grep_including_secrets].
+    #[cfg(feature = "regex")]
+    pub async fn grep(&self, pattern: &regex::Regex) -> Result<Vec<Item<'_>>, Error> {
+        grep::grep(self, pattern, false).await
+    }
+
+    /// Like [Collection::grep], also returning the items whose secret, if
+    /// it's UTF-8 text, matches `pattern`.
+    ///
+    /// The secrets of the items not matching otherwise are fetched in one
+    /// batch, so they are held in memory at once. Secrets of locked items
+    /// aren't returned by the provider and don't match.
+    #[cfg(feature = "regex")]
+    pub async fn grep_including_secrets(
+        &self,
+        pattern: &regex::Regex,
+    ) -> Result<Vec<Item<'_>>, Error> {
+        grep::grep(self, pattern, true).await
+    }
+
+    /// Searches the items of this collection with a query string, see
+    /// [crate::query].
+    pub async fn search_query(&self, query: &Query) -> Result<Vec<Item<'_>>, Error> {
+        let items = self.search_items(query.required_attributes()).await?;
+        query::retain_matching(self.config, items, query).await
+    }
+
     pub async fn get_label(&self) -> Result<String, Error> {
-        Ok(self.collection_proxy.label().await?)
+        self.collection_proxy
+            .label()
+            .await
+            .map_err(self.context("get_label"))
     }
 
     pub async fn set_label(&self, new_label: &str) -> Result<(), Error> {
-        Ok(self.collection_proxy.set_label(new_label).await?)
+        self.collection_proxy
+            .set_label(new_label)
+            .await
+            .map_err(self.context("set_label"))?;
+        self.config
+            .record(self.path(), || Change::LabelSet(new_label.to_owned()));
+        Ok(())
+    }
+
+    pub async fn get_created(&self) -> Result<u64, Error> {
+        self.collection_proxy
+            .created()
+            .await
+            .map_err(self.context("get_created"))
     }
 
+    pub async fn get_modified(&self) -> Result<u64, Error> {
+        self.collection_proxy
+            .modified()
+            .await
+            .map_err(self.context("get_modified"))
+    }
+
+    /// Creates an item, doing what `conflict` says if the collection already
+    /// holds one with the same attributes, see [ConflictPolicy].
     pub async fn create_item(
         &self,
         label: &str,
         attributes: HashMap<&str, &str>,
         secret: &[u8],
-        replace: bool,
+        conflict: impl Into<ConflictPolicy>,
         content_type: &str,
     ) -> Result<Item<'_>, Error> {
-        let secret_struct = format_secret(self.session, secret, content_type)?;
+        let conflict = conflict.into();
+        self.session.open().await?;
+        let secret_struct = format_secret(self.session, self.config, secret, content_type)?;
+        let searched = attributes.clone();
+        let attributes = self.config.item_attributes(attributes);
 
-        let mut properties: HashMap<&str, Value> = HashMap::new();
-        let attributes: Dict = attributes.into();
-
-        properties.insert(SS_ITEM_LABEL, label.into());
-        properties.insert(SS_ITEM_ATTRIBUTES, attributes.into());
+        if conflict == ConflictPolicy::Fail {
+            let found = self
+                .collection_proxy
+                .search_items(attributes.as_map())
+                .await
+                .map_err(self.context("create_item"))?;
+            // the provider also returns items holding more attributes
+            let found = futures_util::future::try_join_all(found.into_iter().map(|path| {
+                Item::new(
+                    self.conn.clone(),
+                    self.session,
+                    self.service_proxy,
+                    self.config,
+                    path,
+                )
+            }))
+            .await?;
+            if !search::retain_exact(self.config, found, &searched)
+                .await?
+                .is_empty()
+            {
+                return Err(Error::ItemExists);
+            }
+        }
+        let replace = conflict == ConflictPolicy::Replace;
 
-        let created_item = self
+        let mut created_item = self
             .collection_proxy
-            .create_item(properties, secret_struct, replace)
-            .await?;
+            .create_item(
+                item_properties(label, attributes.as_map(), false),
+                secret_struct,
+                replace,
+            )
+            .await
+            .map_err(Error::from);
+        if self.config.legacy_properties && created_item.as_ref().is_err_and(Error::is_invalid_args)
+        {
+            let secret_struct = format_secret(self.session, self.config, secret, content_type)?;
+            created_item = self
+                .collection_proxy
+                .create_item(
+                    item_properties(label, attributes.as_map(), true),
+                    secret_struct,
+                    replace,
+                )
+                .await
+                .map_err(Error::from);
+        }
+        let created_item = created_item.map_err(self.context("create_item"))?;
 
         // This prompt handling is practically identical to create_collection
         let item_path: ObjectPath = {
@@ -175,26 +865,102 @@ impl<'a> Collection<'a> {
                 let prompt_path = created_item.prompt;
 
                 // Exec prompt and parse result
-                let prompt_res = exec_prompt(self.conn.clone(), &prompt_path).await?;
-                prompt_res.try_into()?
+                let prompt_res = exec_prompt(self.conn.clone(), self.config, &prompt_path).await?;
+                util::prompt_path(self.config, prompt_res)?.into()
             } else {
                 // if not, just return created path
                 created_path.into()
             }
         };
 
-        Item::new(
+        let item = Item::new(
             self.conn.clone(),
             self.session,
             self.service_proxy,
+            self.config,
             item_path.into(),
         )
+        .await?;
+        self.config.record(item.path(), || Change::ItemCreated {
+            collection: self.path(),
+            label: label.to_owned(),
+            secret: SecretHash::new(secret),
+        });
+        Ok(item)
+    }
+
+    /// Creates an item with the default content type set with
+    /// [SecretServiceBuilder::default_content_type](crate::SecretServiceBuilder::default_content_type),
+    /// replacing any item holding the same attributes.
+    pub async fn create_item_simple(
+        &self,
+        label: &str,
+        attributes: HashMap<&str, &str>,
+        secret: &[u8],
+    ) -> Result<Item<'_>, Error> {
+        self.create_item(
+            label,
+            attributes,
+            secret,
+            ConflictPolicy::Replace,
+            &self.config.default_content_type,
+        )
+        .await
+    }
+
+    /// Creates an item holding a string secret, stored with a `text/plain`
+    /// content type.
+    pub async fn create_item_with_string(
+        &self,
+        label: &str,
+        attributes: HashMap<&str, &str>,
+        secret: &str,
+        conflict: impl Into<ConflictPolicy>,
+    ) -> Result<Item<'_>, Error> {
+        self.create_item(
+            label,
+            attributes,
+            secret.as_bytes(),
+            conflict,
+            TEXT_CONTENT_TYPE,
+        )
         .await
     }
+
+    /// Creates many items, such as when migrating from another password
+    /// manager, returning what was done with each in the order given.
+    ///
+    /// The items don't have paths before they're created, so each result of
+    /// the outcome is given with the path of this collection; the path of a
+    /// created item is in [Imported::Created].
+    ///
+    /// Items are created in batches of bounded concurrency, with `progress`
+    /// called as each one is done. A failure doesn't stop the import.
+    ///
+    /// Items with attributes are skipped if the collection already holds one
+    /// with the same attributes, so an interrupted import can be resumed by
+    /// running it again. Items without attributes can't be told apart and
+    /// are always created.
+    pub async fn import(
+        &self,
+        items: impl IntoIterator<Item = NewItem>,
+        progress: impl FnMut(&Progress),
+    ) -> BatchOutcome<Imported> {
+        import::import(self, items, progress).await
+    }
 }
 
+impl PartialEq for Collection<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+    }
+}
+
+impl Eq for Collection<'_> {}
+
 #[cfg(test)]
 mod test {
+    use crate::testing::TestHarness;
     use crate::*;
 
     #[tokio::test]
@@ -218,15 +984,19 @@ mod test {
         let collection = ss.get_default_collection().await.unwrap();
         let locked = collection.is_locked().await.unwrap();
         if locked {
-            collection.unlock().await.unwrap();
+            let unlocked = collection.unlock().await.unwrap();
+            assert!(unlocked.contains(&collection.path()));
             collection.ensure_unlocked().await.unwrap();
             assert!(!collection.is_locked().await.unwrap());
-            collection.lock().await.unwrap();
+            let locked = collection.lock().await.unwrap();
+            assert!(locked.contains(&collection.path()));
             assert!(collection.is_locked().await.unwrap());
         } else {
-            collection.lock().await.unwrap();
+            let locked = collection.lock().await.unwrap();
+            assert!(locked.contains(&collection.path()));
             assert!(collection.is_locked().await.unwrap());
-            collection.unlock().await.unwrap();
+            let unlocked = collection.unlock().await.unwrap();
+            assert!(unlocked.contains(&collection.path()));
             collection.ensure_unlocked().await.unwrap();
             assert!(!collection.is_locked().await.unwrap());
         }
@@ -239,7 +1009,7 @@ mod test {
         let collections = ss.get_all_collections().await.unwrap();
         let count_before = collections.len();
         for collection in collections {
-            let collection_path = &*collection.collection_path;
+            let collection_path = &*collection.path;
             if collection_path.contains("Test") {
                 collection.unlock().await.unwrap();
                 collection.delete().await.unwrap();
@@ -253,6 +1023,32 @@ mod test {
         );
     }
 
+    #[test_with::no_env(GITHUB_ACTIONS)]
+    #[tokio::test]
+    async fn should_refresh_recreated_collection() {
+        let harness = TestHarness::start().unwrap();
+        let ss = harness.connect(EncryptionType::Plain).await.unwrap();
+        let mut collection = ss
+            .create_collection("TestRefresh", "test_refresh")
+            .await
+            .unwrap();
+        let old_path = collection.path();
+        assert!(collection.exists().await.unwrap());
+
+        let other = ss.get_collection_by_alias("test_refresh").await.unwrap();
+        other.delete().await.unwrap();
+        assert!(!collection.exists().await.unwrap());
+        assert!(matches!(collection.refresh().await, Err(Error::NoResult)));
+
+        ss.create_collection("TestRefreshAgain", "test_refresh")
+            .await
+            .unwrap();
+        collection.refresh().await.unwrap();
+        assert_ne!(collection.path(), old_path);
+        assert!(collection.exists().await.unwrap());
+        collection.delete().await.unwrap();
+    }
+
     #[tokio::test]
     async fn should_get_all_items() {
         let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
@@ -260,6 +1056,134 @@ mod test {
         collection.get_all_items().await.unwrap();
     }
 
+    #[tokio::test]
+    async fn should_get_items_paged() {
+        use futures_util::TryStreamExt;
+
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let mut created = Vec::new();
+        for _ in 0..3 {
+            let item = collection
+                .create_item("test", HashMap::new(), b"test", false, "text/plain")
+                .await
+                .unwrap();
+            created.push(item);
+        }
+
+        let all_items = collection.get_all_items().await.unwrap();
+        let pages: Vec<_> = collection
+            .items_paged(std::num::NonZeroUsize::new(2).unwrap())
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert!(pages.iter().all(|page| !page.is_empty() && page.len() <= 2));
+        assert_eq!(pages.iter().map(Vec::len).sum::<usize>(), all_items.len());
+
+        for item in created {
+            item.delete().await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn should_find_purge_candidates() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let item = collection
+            .create_item("test", HashMap::new(), b"test", false, "text/plain")
+            .await
+            .unwrap();
+
+        // Nothing is older than the epoch, so this must not delete anything.
+        let purged = collection
+            .purge_older_than(0, ItemTimestamp::Created, false)
+            .await
+            .unwrap();
+        assert!(purged.results.is_empty());
+
+        let candidates = collection
+            .purge_older_than(u64::MAX, ItemTimestamp::Modified, true)
+            .await
+            .unwrap();
+        assert!(candidates.succeeded().any(|(path, _)| *path == item.path()));
+
+        // dry run leaves the item in place
+        item.get_label().await.unwrap();
+        item.delete().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_find_duplicates() {
+        let ss = SecretService::connect(EncryptionType::Dh).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let attributes = HashMap::from([("test_attributes_in_duplicates", "test")]);
+        let first = collection
+            .create_item("test", attributes.clone(), b"test", false, "text/plain")
+            .await
+            .unwrap();
+        let second = collection
+            .create_item("test", attributes.clone(), b"test", false, "text/plain")
+            .await
+            .unwrap();
+        let third = collection
+            .create_item("test", attributes, b"other", false, "text/plain")
+            .await
+            .unwrap();
+
+        let find_group = |groups: &[Vec<Item<'_>>]| {
+            groups
+                .iter()
+                .find(|group| group.iter().any(|i| i.path == first.path))
+                .map(|group| group.len())
+        };
+
+        let groups = collection.find_duplicates(false).await.unwrap();
+        assert_eq!(find_group(&groups), Some(3));
+
+        let groups = collection.find_duplicates(true).await.unwrap();
+        assert_eq!(find_group(&groups), Some(2));
+
+        for item in [first, second, third] {
+            item.delete().await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn should_summarize_attributes() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let mut items = Vec::new();
+        for value in ["a", "a", "b"] {
+            let item = collection
+                .create_item(
+                    "test",
+                    HashMap::from([("test_attribute_summary", value)]),
+                    b"test",
+                    false,
+                    "text/plain",
+                )
+                .await
+                .unwrap();
+            items.push(item);
+        }
+
+        let summary = collection.attribute_summary(false).await.unwrap();
+        assert_eq!(summary.keys["test_attribute_summary"], 3);
+        assert!(summary.items >= 3);
+        assert!(summary.values.is_empty());
+
+        let summary = collection.attribute_summary(true).await.unwrap();
+        let values = &summary.values["test_attribute_summary"];
+        assert_eq!((values["a"], values["b"]), (2, 1));
+
+        for item in items {
+            item.delete().await.unwrap();
+        }
+    }
+
     #[tokio::test]
     async fn should_search_items() {
         let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
@@ -293,8 +1217,261 @@ mod test {
             .await
             .unwrap();
 
-        assert_eq!(item.item_path, search_item[0].item_path);
+        assert_eq!(item.path, search_item[0].path);
+        item.delete().await.unwrap();
+    }
+
+    #[test_with::no_env(GITHUB_ACTIONS)]
+    #[tokio::test]
+    async fn should_receive_item_events() {
+        let harness = TestHarness::start().unwrap();
+        let ss = harness.connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.create_collection("TestItemEvents", "").await.unwrap();
+        let mut events = Box::pin(collection.receive_item_events().await.unwrap());
+
+        let item = collection
+            .create_item("Test", HashMap::new(), b"test", false, "text/plain")
+            .await
+            .unwrap();
+        let path = item.path();
+        while events.next().await.unwrap() != ItemEvent::Created(path.clone()) {}
+
+        item.set_label("TestRelabeled").await.unwrap();
+        while events.next().await.unwrap() != ItemEvent::Changed(path.clone()) {}
+
         item.delete().await.unwrap();
+        while events.next().await.unwrap() != ItemEvent::Deleted(path.clone()) {}
+        collection.delete().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_mirror_item_metadata() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let existing = collection
+            .create_item(
+                "Mirrored",
+                HashMap::from([("test_mirror", "existing")]),
+                b"test",
+                false,
+                "text/plain",
+            )
+            .await
+            .unwrap();
+
+        let mirror = collection.mirror().await.unwrap();
+        let metadata = mirror.get(&existing.path()).unwrap();
+        assert_eq!(metadata.label, "Mirrored");
+        assert!(!metadata.locked);
+
+        // changes only reach the mirror through signals
+        async fn wait_until(mirror: &CollectionMirror, mut done: impl FnMut() -> bool) {
+            let updated = async {
+                loop {
+                    let changed = mirror.changed();
+                    if done() {
+                        break;
+                    }
+                    changed.await;
+                }
+            };
+            tokio::time::timeout(std::time::Duration::from_secs(5), updated)
+                .await
+                .expect("mirror was not updated");
+        }
+
+        let created = collection
+            .create_item(
+                "Created",
+                HashMap::from([("test_mirror", "created")]),
+                b"test",
+                false,
+                "text/plain",
+            )
+            .await
+            .unwrap();
+        wait_until(&mirror, || mirror.get(&created.path()).is_some()).await;
+        assert_eq!(
+            mirror.filter(|metadata| {
+                metadata.attributes.get("test_mirror").map(String::as_str) == Some("created")
+            }),
+            vec![created.path()]
+        );
+
+        existing.set_label("Relabeled").await.unwrap();
+        wait_until(&mirror, || {
+            mirror.get(&existing.path()).unwrap().label == "Relabeled"
+        })
+        .await;
+
+        created.delete().await.unwrap();
+        wait_until(&mirror, || mirror.get(&created.path()).is_none()).await;
+        existing.delete().await.unwrap();
+        wait_until(&mirror, || mirror.get(&existing.path()).is_none()).await;
+    }
+
+    #[tokio::test]
+    async fn should_search_items_indexed() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let indexed = collection
+            .create_item(
+                "Indexed",
+                HashMap::from([("test_index", "indexed")]),
+                b"test",
+                false,
+                "text/plain",
+            )
+            .await
+            .unwrap();
+        let mirror = collection.mirror().await.unwrap();
+
+        let found = collection
+            .search_items_indexed(&mirror, HashMap::from([("test_index", "indexed")]))
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].path, indexed.path);
+
+        // not indexed yet, so found by the provider
+        let created = collection
+            .create_item(
+                "Created",
+                HashMap::from([("test_index", "created")]),
+                b"test",
+                false,
+                "text/plain",
+            )
+            .await
+            .unwrap();
+        let found = collection
+            .search_items_indexed(&mirror, HashMap::from([("test_index", "created")]))
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].path, created.path);
+
+        indexed.delete().await.unwrap();
+        created.delete().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_search_items_by_tag() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+
+        let tags = crate::attributes::encode_list(["work", "a,b"]);
+        let item = collection
+            .create_item(
+                "test",
+                HashMap::from([
+                    ("test_tags_in_collection", tags.as_str()),
+                    ("test_tag_scope", "collection"),
+                ]),
+                b"test_secret",
+                false,
+                "text/plain",
+            )
+            .await
+            .unwrap();
+
+        let scope = HashMap::from([("test_tag_scope", "collection")]);
+        let found = collection
+            .search_items_by_tag(scope.clone(), "test_tags_in_collection", "a,b")
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(item.path, found[0].path);
+
+        let found = collection
+            .search_items_by_tag(scope, "test_tags_in_collection", "a")
+            .await
+            .unwrap();
+        assert!(found.is_empty());
+
+        item.delete().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_relabel_matching_items() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let attributes = HashMap::from([("test_attribute_relabel", "test_value")]);
+        let mut items = Vec::new();
+        for label in ["OldName", "OldName2", "Other"] {
+            let item = collection
+                .create_item(label, attributes.clone(), b"test", false, "text/plain")
+                .await;
+            items.push(item.unwrap());
+        }
+
+        let outcome = collection
+            .relabel_matching(attributes, |label| label.replace("Old", "New"))
+            .await
+            .unwrap();
+        assert!(outcome.is_success());
+        assert_eq!(outcome.results.len(), 2);
+        for (item, label) in items.iter().zip(["NewName", "NewName2", "Other"]) {
+            assert_eq!(item.get_label().await.unwrap(), label);
+            item.delete().await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn should_apply_conflict_policy() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let attributes = HashMap::from([("test_attribute_conflict", "test_value")]);
+        let item = collection
+            .create_item("Test", attributes.clone(), b"test", false, "text/plain")
+            .await
+            .unwrap();
+
+        let created = collection
+            .create_item(
+                "Test",
+                attributes.clone(),
+                b"other",
+                ConflictPolicy::Fail,
+                "text/plain",
+            )
+            .await;
+        assert!(matches!(created, Err(Error::ItemExists)));
+        assert_eq!(item.get_secret().await.unwrap(), b"test");
+
+        let other = collection
+            .create_item(
+                "Test",
+                attributes.clone(),
+                b"other",
+                ConflictPolicy::CreateAnyway,
+                "text/plain",
+            )
+            .await
+            .unwrap();
+        assert_ne!(item.path(), other.path());
+
+        item.delete().await.unwrap();
+        other.delete().await.unwrap();
+        // items holding more attributes don't conflict
+        let mut superset = attributes.clone();
+        superset.insert("test_attribute_conflict_extra", "test_value");
+        let larger = collection
+            .create_item("Test", superset, b"test", false, "text/plain")
+            .await
+            .unwrap();
+        let created = collection
+            .create_item(
+                "Test",
+                attributes,
+                b"test",
+                ConflictPolicy::Fail,
+                "text/plain",
+            )
+            .await
+            .unwrap();
+        created.delete().await.unwrap();
+        larger.delete().await.unwrap();
     }
 
     #[tokio::test]
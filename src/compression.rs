@@ -0,0 +1,252 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Compressing large secrets before they're stored.
+//!
+//! With a [Compressor] set through
+//! [SecretServiceBuilder::compression](crate::SecretServiceBuilder::compression),
+//! secrets of at least a given size are compressed before being encrypted
+//! and sent to the provider, which keeps bundles of certificates and similar
+//! blobs from bloating the keyring. The encoding is recorded in the
+//! [COMPRESSION_PARAMETER] of the content type, such as
+//! `application/x-pem-file; x-compression=zstd`, and secrets carrying it are
+//! decompressed again when read. The parameter is removed from the content
+//! types returned, so applications see the one they stored.
+//!
+//! The crate doesn't bundle any compression format. Applications wrap the
+//! one they use, such as zstd:
+//!
+//! ```ignore
+//! use secret_service::compression::Compressor;
+//!
+//! struct Zstd;
+//!
+//! impl Compressor for Zstd {
+//!     fn encoding(&self) -> &str {
+//!         "zstd"
+//!     }
+//!
+//!     fn compress(&self, data: &[u8]) -> Vec<u8> {
+//!         zstd::bulk::compress(data, 0).expect("compressing to memory")
+//!     }
+//!
+//!     fn decompress(&self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+//!         zstd::stream::decode_all(data)
+//!     }
+//! }
+//! ```
+//!
+//! Other applications reading the items see compressed secrets, so those
+//! sharing a collection should agree on the compression.
+
+use crate::Error;
+
+use std::borrow::Cow;
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+
+/// Content type parameter recording the encoding a secret is compressed
+/// with.
+pub const COMPRESSION_PARAMETER: &str = "x-compression";
+
+/// A compression format.
+pub trait Compressor: Send + Sync {
+    /// Name of the format, recorded in the content type. Must be a MIME
+    /// token, such as `zstd` or `gzip`.
+    fn encoding(&self) -> &str;
+
+    fn compress(&self, data: &[u8]) -> Vec<u8>;
+
+    fn decompress(&self, data: &[u8]) -> io::Result<Vec<u8>>;
+}
+
+/// The compression of a connection, as configured on the builder.
+pub(crate) struct Compression {
+    pub(crate) compressor: Arc<dyn Compressor>,
+    /// Size from which secrets are compressed, in bytes.
+    pub(crate) threshold: usize,
+}
+
+impl Compression {
+    /// Compresses `secret` if it's large enough and compression makes it
+    /// smaller, returning it with the content type recording the encoding.
+    pub(crate) fn compress<'a>(
+        &self,
+        secret: &'a [u8],
+        content_type: &'a str,
+    ) -> (Cow<'a, [u8]>, Cow<'a, str>) {
+        if secret.len() >= self.threshold {
+            let compressed = self.compressor.compress(secret);
+            if compressed.len() < secret.len() {
+                let content_type = format!(
+                    "{content_type}; {COMPRESSION_PARAMETER}={}",
+                    self.compressor.encoding()
+                );
+                return (Cow::Owned(compressed), Cow::Owned(content_type));
+            }
+        }
+        (Cow::Borrowed(secret), Cow::Borrowed(content_type))
+    }
+}
+
+impl fmt::Debug for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Compression")
+            .field("encoding", &self.compressor.encoding())
+            .field("threshold", &self.threshold)
+            .finish()
+    }
+}
+
+/// Returns the encoding recorded in `content_type`, if any.
+fn encoding(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case(COMPRESSION_PARAMETER)
+            .then(|| value.trim())
+    })
+}
+
+/// Decompresses a secret stored with `content_type`, if it was compressed.
+pub(crate) fn decompress(
+    compression: Option<&Compression>,
+    secret: Vec<u8>,
+    content_type: &str,
+) -> Result<Vec<u8>, Error> {
+    let Some(encoding) = encoding(content_type) else {
+        return Ok(secret);
+    };
+    match compression {
+        Some(compression) if compression.compressor.encoding() == encoding => compression
+            .compressor
+            .decompress(&secret)
+            .map_err(Error::Compression),
+        _ => Err(Error::Compression(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("no compressor for {encoding}"),
+        ))),
+    }
+}
+
+/// Removes the encoding from a content type read from the provider.
+pub(crate) fn strip_encoding(content_type: String) -> String {
+    if encoding(&content_type).is_none() {
+        return content_type;
+    }
+    let mut parts = content_type.split(';');
+    let mut stripped = parts.next().unwrap_or_default().to_owned();
+    for param in parts {
+        let is_encoding = param
+            .split_once('=')
+            .is_some_and(|(key, _)| key.trim().eq_ignore_ascii_case(COMPRESSION_PARAMETER));
+        if !is_encoding {
+            stripped.push(';');
+            stripped.push_str(param);
+        }
+    }
+    stripped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{EncryptionType, SecretService};
+    use std::collections::HashMap;
+
+    /// Run-length encoding, as pairs of a count and a byte.
+    struct RunLength;
+
+    impl Compressor for RunLength {
+        fn encoding(&self) -> &str {
+            "x-rle"
+        }
+
+        fn compress(&self, data: &[u8]) -> Vec<u8> {
+            let mut compressed: Vec<u8> = Vec::new();
+            for &byte in data {
+                match compressed.as_mut_slice() {
+                    [.., count, last] if *last == byte && *count < u8::MAX => *count += 1,
+                    _ => compressed.extend([1, byte]),
+                }
+            }
+            compressed
+        }
+
+        fn decompress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+            if data.len() % 2 != 0 {
+                return Err(io::ErrorKind::InvalidData.into());
+            }
+            Ok(data
+                .chunks(2)
+                .flat_map(|run| std::iter::repeat(run[1]).take(run[0].into()))
+                .collect())
+        }
+    }
+
+    fn compression() -> Compression {
+        Compression {
+            compressor: Arc::new(RunLength),
+            threshold: 8,
+        }
+    }
+
+    #[test]
+    fn should_compress_large_secrets() {
+        let compression = compression();
+        let (secret, content_type) = compression.compress(b"aaaa", "text/plain");
+        assert_eq!((&*secret, &*content_type), (&b"aaaa"[..], "text/plain"));
+        let (secret, content_type) = compression.compress(b"abcdefgh", "text/plain");
+        assert_eq!((&*secret, &*content_type), (&b"abcdefgh"[..], "text/plain"));
+
+        let (secret, content_type) = compression.compress(&[0; 64], "text/plain; charset=utf-8");
+        assert_eq!(&*secret, [64, 0]);
+        assert_eq!(
+            content_type,
+            "text/plain; charset=utf-8; x-compression=x-rle"
+        );
+        assert_eq!(
+            decompress(Some(&compression), secret.into_owned(), &content_type).unwrap(),
+            [0; 64]
+        );
+        assert!(decompress(None, vec![64, 0], &content_type).is_err());
+        assert_eq!(
+            strip_encoding(content_type.into_owned()),
+            "text/plain; charset=utf-8"
+        );
+        assert_eq!(strip_encoding("text/plain".to_owned()), "text/plain");
+    }
+
+    #[tokio::test]
+    async fn should_store_compressed_secrets() {
+        let ss = SecretService::builder()
+            .encryption(EncryptionType::Plain)
+            .compression(Arc::new(RunLength), 8)
+            .connect()
+            .await
+            .unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let attributes = HashMap::from([("test_attribute_compression", "test_value")]);
+        let secret = [b'x'; 1024];
+        let item = collection
+            .create_item("Test", attributes.clone(), &secret, false, "text/plain")
+            .await
+            .unwrap();
+
+        assert_eq!(item.get_secret().await.unwrap(), secret);
+        assert_eq!(item.get_secret_content_type().await.unwrap(), "text/plain");
+        let plain = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let stored = plain.search_items(attributes).await.unwrap().unlocked;
+        assert!(matches!(
+            stored[0].get_secret().await,
+            Err(Error::Compression(_))
+        ));
+
+        item.delete().await.unwrap();
+    }
+}
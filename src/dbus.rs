@@ -0,0 +1,36 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! The `zbus` and `zvariant` types appearing in this crate's API.
+//!
+//! Object paths, connections and errors from `zbus` show up in public
+//! signatures, so code naming them has to use the same `zbus` version as this
+//! crate, or face mismatches between same-named types. Importing them from
+//! here instead keeps the versions in step. Where the API offers a
+//! crate-owned alternative, such as [ObjectPath](crate::ObjectPath), prefer
+//! that, as it doesn't change with the `zbus` version:
+//!
+//! ```no_run
+//! use secret_service::dbus::OwnedObjectPath;
+//! use secret_service::{EncryptionType, SecretService};
+//! # async fn call() {
+//!
+//! let ss = SecretService::connect(EncryptionType::Dh).await.unwrap();
+//! let collection = ss.get_default_collection().await.unwrap();
+//! let path: OwnedObjectPath = collection.path().into();
+//! # }
+//! ```
+//!
+//! The crates themselves are re-exported as well, for anything not listed.
+
+pub use zbus;
+pub use zbus::zvariant;
+
+pub use zbus::fdo::Error as FdoError;
+pub use zbus::zvariant::Error as ZvariantError;
+pub use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Type, Value};
+pub use zbus::{Connection, Error as ZbusError, Executor};
@@ -0,0 +1,140 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+// A description of the provider's collections and items without their
+// secrets, for bug reports.
+
+use crate::util::limit_concurrency;
+use crate::{blocking, Collection, Error, Item, SecretService};
+
+/// The collections and items of a provider, as returned by
+/// [SecretService::dump_tree] and [blocking::SecretService::dump_tree].
+///
+/// The dump holds no secrets and no attribute values, only attribute keys, so
+/// it can be attached to bug reports to show the shape of a keyring. Labels
+/// are included as they are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TreeDump {
+    pub collections: Vec<CollectionDump>,
+}
+
+/// A collection in a [TreeDump].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CollectionDump {
+    pub path: String,
+    pub label: String,
+    pub locked: bool,
+    /// Seconds since the Unix epoch.
+    pub created: u64,
+    /// Seconds since the Unix epoch.
+    pub modified: u64,
+    pub items: Vec<ItemDump>,
+}
+
+/// An item in a [TreeDump].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ItemDump {
+    pub path: String,
+    pub label: String,
+    /// The keys of the item's attributes, sorted; values are left out.
+    pub attribute_keys: Vec<String>,
+    pub locked: bool,
+    /// Seconds since the Unix epoch.
+    pub created: u64,
+    /// Seconds since the Unix epoch.
+    pub modified: u64,
+}
+
+pub(crate) async fn dump_tree(ss: &SecretService<'_>) -> Result<TreeDump, Error> {
+    let collections = ss.get_all_collections().await?;
+    let collections =
+        futures_util::future::try_join_all(collections.iter().map(dump_collection)).await?;
+    Ok(TreeDump { collections })
+}
+
+async fn dump_collection(collection: &Collection<'_>) -> Result<CollectionDump, Error> {
+    let items = collection.get_all_items().await?;
+    let items = futures_util::future::try_join_all(
+        items
+            .iter()
+            .map(|item| limit_concurrency(collection.config, dump_item(item))),
+    );
+    let (label, locked, created, modified, items) = futures_util::try_join!(
+        collection.get_label(),
+        collection.is_locked(),
+        collection.get_created(),
+        collection.get_modified(),
+        items,
+    )?;
+    Ok(CollectionDump {
+        path: collection.path().to_string(),
+        label,
+        locked,
+        created,
+        modified,
+        items,
+    })
+}
+
+async fn dump_item(item: &Item<'_>) -> Result<ItemDump, Error> {
+    let (label, attributes, locked, created, modified) = futures_util::try_join!(
+        item.get_label(),
+        item.get_attributes(),
+        item.is_locked(),
+        item.get_created(),
+        item.get_modified(),
+    )?;
+    Ok(ItemDump {
+        path: item.path().to_string(),
+        label,
+        attribute_keys: attribute_keys(attributes.into_keys()),
+        locked,
+        created,
+        modified,
+    })
+}
+
+pub(crate) fn dump_tree_blocking(ss: &blocking::SecretService<'_>) -> Result<TreeDump, Error> {
+    let collections = ss
+        .get_all_collections()?
+        .iter()
+        .map(|collection| {
+            let items = collection
+                .get_all_items()?
+                .iter()
+                .map(|item| {
+                    Ok(ItemDump {
+                        path: item.path().to_string(),
+                        label: item.get_label()?,
+                        attribute_keys: attribute_keys(item.get_attributes()?.into_keys()),
+                        locked: item.is_locked()?,
+                        created: item.get_created()?,
+                        modified: item.get_modified()?,
+                    })
+                })
+                .collect::<Result<_, Error>>()?;
+            Ok(CollectionDump {
+                path: collection.path().to_string(),
+                label: collection.get_label()?,
+                locked: collection.is_locked()?,
+                created: collection.get_created()?,
+                modified: collection.get_modified()?,
+                items,
+            })
+        })
+        .collect::<Result<_, Error>>()?;
+    Ok(TreeDump { collections })
+}
+
+fn attribute_keys(keys: impl Iterator<Item = String>) -> Vec<String> {
+    let mut keys: Vec<_> = keys.collect();
+    keys.sort();
+    keys
+}
@@ -5,10 +5,18 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
+use crate::ObjectPath;
+
 use std::{error, fmt};
-use zbus::zvariant;
+use zbus::{zvariant, DBusError};
+use zvariant::OwnedObjectPath;
 
 /// An error that could occur interacting with the secret service dbus interface.
+///
+/// The [Error::Zbus], [Error::ZbusFdo] and [Error::Zvariant] variants carry
+/// `zbus` types, which change with every `zbus` major version. To tell
+/// provider errors apart, prefer [Error::dbus_error_name], which is meant to
+/// outlive them.
 #[derive(Debug)]
 #[non_exhaustive]
 pub enum Error {
@@ -25,11 +33,66 @@ pub enum Error {
     Locked,
     /// No object was found in the object for the request.
     NoResult,
+    /// A collection or item was used after it was deleted through the same
+    /// instance.
+    ObjectDeleted,
+    /// An item was created with [ConflictPolicy::Fail](crate::ConflictPolicy::Fail),
+    /// but the collection already holds one with the same attributes.
+    ItemExists,
+    /// A secret was set with `Item::set_secret_if_unmodified`, but the item
+    /// was modified since the time expected.
+    Modified,
+    /// An object was to be locked, but the provider left it unlocked.
+    NotLocked,
     /// An authorization prompt was dismissed, but is required to continue.
     Prompt,
     /// A secret service provider, or a session to connect to one, was found
     /// on the system.
     Unavailable,
+    /// A secret requested as text was not valid UTF-8, or its content type
+    /// declared a different charset.
+    InvalidUtf8,
+    /// A content type passed for a secret isn't a valid MIME type, as
+    /// defined by RFC 2045.
+    InvalidContentType,
+    /// A property passed to `CreateCollection` or `CreateItem` isn't defined
+    /// by the specification, or its value has the wrong type, see
+    /// [api::validate_item_properties](crate::api::validate_item_properties).
+    InvalidProperty(String),
+    /// A plain session was requested, but the `deny-plain` feature rules
+    /// them out.
+    PlainDenied,
+    /// A session algorithm was selected by a name no algorithm is
+    /// registered under, see the [algorithm](crate::algorithm) module.
+    UnknownAlgorithm(String),
+    /// Storing or loading a key in the kernel keyring failed.
+    KernelKeyring(std::io::Error),
+    /// Compressing or decompressing a secret failed, or it was compressed
+    /// with an encoding no compressor is configured for, see the
+    /// [compression](crate::compression) module.
+    Compression(std::io::Error),
+    /// The provider replied with something the specification rules out,
+    /// caught with
+    /// [SecretServiceBuilder::strict](crate::SecretServiceBuilder::strict).
+    InvalidResponse(String),
+    /// An item's [SCHEMA_VERSION_ATTRIBUTE](crate::migration::SCHEMA_VERSION_ATTRIBUTE)
+    /// holds the value, which isn't a version number, see the
+    /// [migration](crate::migration) module.
+    InvalidSchemaVersion(String),
+    /// The `SECRET_SERVICE_ENCRYPTION` environment variable holds the value,
+    /// which names no kind of session, see
+    /// [SecretServiceBuilder::encryption_from_env](crate::SecretServiceBuilder::encryption_from_env).
+    InvalidEncryptionOverride(String),
+    /// A call failed while operating on a collection or item, see
+    /// [Error::context].
+    Context {
+        /// Name of the method that failed, such as `get_secret`.
+        operation: &'static str,
+        /// Path of the collection or item operated on.
+        path: ObjectPath,
+        /// The error the call failed with.
+        source: Box<Error>,
+    },
 }
 
 impl fmt::Display for Error {
@@ -41,8 +104,33 @@ impl fmt::Display for Error {
             Error::Zvariant(err) => write!(f, "zbus serde error: {err}"),
             Error::Locked => f.write_str("SS Error: object locked"),
             Error::NoResult => f.write_str("SS error: result not returned from SS API"),
+            Error::ObjectDeleted => f.write_str("SS error: object was deleted"),
+            Error::ItemExists => f.write_str("SS error: an item with these attributes exists"),
+            Error::Modified => f.write_str("SS error: item was modified in the meantime"),
+            Error::NotLocked => f.write_str("SS error: object was left unlocked"),
             Error::Prompt => f.write_str("SS error: prompt dismissed"),
             Error::Unavailable => f.write_str("no secret service provider or dbus session found"),
+            Error::InvalidUtf8 => f.write_str("SS error: secret is not valid UTF-8 text"),
+            Error::InvalidContentType => f.write_str("SS error: invalid content type"),
+            Error::InvalidProperty(name) => write!(f, "SS error: invalid property {name}"),
+            Error::PlainDenied => {
+                f.write_str("plain sessions are denied by the deny-plain feature")
+            }
+            Error::UnknownAlgorithm(name) => write!(f, "unknown session algorithm {name}"),
+            Error::KernelKeyring(err) => write!(f, "kernel keyring error: {err}"),
+            Error::Compression(err) => write!(f, "compression error: {err}"),
+            Error::InvalidResponse(reason) => write!(f, "SS error: invalid response: {reason}"),
+            Error::InvalidSchemaVersion(version) => {
+                write!(f, "invalid schema version {version:?}")
+            }
+            Error::InvalidEncryptionOverride(value) => {
+                write!(f, "invalid SECRET_SERVICE_ENCRYPTION value {value:?}")
+            }
+            Error::Context {
+                operation,
+                path,
+                source,
+            } => write!(f, "{operation} on {path} failed: {source}"),
         }
     }
 }
@@ -53,11 +141,123 @@ impl error::Error for Error {
             Error::Zbus(ref err) => Some(err),
             Error::ZbusFdo(ref err) => Some(err),
             Error::Zvariant(ref err) => Some(err),
+            Error::KernelKeyring(ref err) => Some(err),
+            Error::Compression(ref err) => Some(err),
+            Error::Context { ref source, .. } => Some(source),
             _ => None,
         }
     }
 }
 
+impl Error {
+    /// Returns the name of the D-Bus error the call failed with, such as
+    /// `org.freedesktop.Secret.Error.NoSuchObject`, if it failed with one.
+    pub fn dbus_error_name(&self) -> Option<String> {
+        match self.without_context() {
+            Error::Zbus(err) | Error::ZbusFdo(zbus::fdo::Error::ZBus(err)) => zbus_error_name(err),
+            Error::ZbusFdo(err) => Some(err.name().to_string()),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the method that failed and the path of the
+    /// collection or item it operated on, if known.
+    ///
+    /// Context is attached to the errors of calls made by `Collection` and
+    /// `Item` methods, so that a failure in an operation spanning many items
+    /// names the item that caused it. Errors with a meaning of their own,
+    /// such as [Error::Locked], are returned as is.
+    pub fn context(&self) -> Option<(&'static str, &ObjectPath)> {
+        match self {
+            Error::Context {
+                operation, path, ..
+            } => Some((operation, path)),
+            _ => None,
+        }
+    }
+
+    /// Returns the error without its context, for matching on its kind.
+    pub fn without_context(&self) -> &Error {
+        match self {
+            Error::Context { source, .. } => source.without_context(),
+            err => err,
+        }
+    }
+
+    /// Returns whether a call failed because the object it was made on
+    /// doesn't exist (anymore).
+    ///
+    /// Providers disagree on the error: zbus-based ones and KWallet reply
+    /// `UnknownObject`, while gnome-keyring replies `UnknownMethod` for the
+    /// interfaces of objects it doesn't know.
+    pub(crate) fn is_unknown_object(&self) -> bool {
+        matches!(
+            self.dbus_error_name().as_deref(),
+            Some(
+                "org.freedesktop.DBus.Error.UnknownObject"
+                    | "org.freedesktop.DBus.Error.UnknownInterface"
+                    | "org.freedesktop.DBus.Error.UnknownMethod"
+                    | "org.freedesktop.Secret.Error.NoSuchObject"
+            )
+        )
+    }
+
+    /// Returns whether a call failed because the connection to the bus, or
+    /// the provider's session, is gone, such as after either restarted.
+    ///
+    /// Calls keep failing until [SecretService::reconnect](crate::SecretService::reconnect)
+    /// connected again.
+    pub fn is_connection_lost(&self) -> bool {
+        match self.without_context() {
+            Error::Zbus(zbus::Error::InputOutput(_))
+            | Error::ZbusFdo(zbus::fdo::Error::ZBus(zbus::Error::InputOutput(_))) => true,
+            err => matches!(
+                err.dbus_error_name().as_deref(),
+                Some(
+                    "org.freedesktop.DBus.Error.Disconnected"
+                        | "org.freedesktop.DBus.Error.NoReply"
+                        | "org.freedesktop.DBus.Error.ServiceUnknown"
+                        | "org.freedesktop.DBus.Error.NameHasNoOwner"
+                        | "org.freedesktop.Secret.Error.NoSession"
+                )
+            ),
+        }
+    }
+
+    /// Returns whether a call failed because the provider rejected its
+    /// arguments.
+    pub(crate) fn is_invalid_args(&self) -> bool {
+        self.dbus_error_name().as_deref() == Some("org.freedesktop.DBus.Error.InvalidArgs")
+    }
+
+    /// Attaches the failing operation and object to errors of D-Bus calls.
+    pub(crate) fn with_context(self, operation: &'static str, path: &OwnedObjectPath) -> Error {
+        match self {
+            Error::Zbus(_)
+            | Error::ZbusFdo(_)
+            | Error::Zvariant(_)
+            | Error::Crypto(_)
+            | Error::InvalidResponse(_) => Error::Context {
+                operation,
+                path: path.clone().into(),
+                source: Box::new(self),
+            },
+            err => err,
+        }
+    }
+}
+
+fn zbus_error_name(err: &zbus::Error) -> Option<String> {
+    match err {
+        zbus::Error::MethodError(name, _, _) => Some(name.to_string()),
+        zbus::Error::FDO(err) => match &**err {
+            zbus::fdo::Error::ZBus(err) => zbus_error_name(err),
+            err => Some(err.name().to_string()),
+        },
+        _ => None,
+    }
+}
+
 impl From<zbus::Error> for Error {
     fn from(err: zbus::Error) -> Error {
         Error::Zbus(err)
@@ -75,3 +275,72 @@ impl From<zvariant::Error> for Error {
         Error::Zvariant(err)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_name_dbus_errors() {
+        let failed = || zbus::fdo::Error::Failed("failed".into());
+        assert_eq!(
+            Error::from(failed()).dbus_error_name().as_deref(),
+            Some("org.freedesktop.DBus.Error.Failed")
+        );
+        assert_eq!(
+            Error::from(zbus::Error::from(failed()))
+                .dbus_error_name()
+                .as_deref(),
+            Some("org.freedesktop.DBus.Error.Failed")
+        );
+        assert_eq!(Error::Locked.dbus_error_name(), None);
+    }
+
+    #[test]
+    fn should_recognize_unknown_objects() {
+        let unknown = Error::from(zbus::fdo::Error::UnknownObject("gone".into()));
+        assert!(unknown.is_unknown_object());
+        // as returned by property getters
+        let nested = zbus::fdo::Error::ZBus(zbus::fdo::Error::UnknownObject("gone".into()).into());
+        assert!(Error::from(nested).is_unknown_object());
+        assert!(!Error::from(zbus::fdo::Error::Failed("failed".into())).is_unknown_object());
+        assert!(!Error::Locked.is_unknown_object());
+    }
+
+    #[test]
+    fn should_recognize_lost_connections() {
+        let broken = std::io::Error::from(std::io::ErrorKind::BrokenPipe);
+        assert!(Error::from(zbus::Error::InputOutput(broken.into())).is_connection_lost());
+        let unknown = Error::from(zbus::fdo::Error::ServiceUnknown("gone".into()));
+        assert!(unknown.is_connection_lost());
+        assert!(!Error::from(zbus::fdo::Error::Failed("failed".into())).is_connection_lost());
+        assert!(!Error::Locked.is_connection_lost());
+    }
+
+    #[test]
+    fn should_attach_context() {
+        let path =
+            OwnedObjectPath::try_from("/org/freedesktop/secrets/collection/login/1").unwrap();
+        let err = Error::from(zbus::fdo::Error::Failed("failed".into()))
+            .with_context("get_label", &path)
+            .with_context("search_labels", &path);
+
+        let (operation, context_path) = err.context().unwrap();
+        assert_eq!(operation, "get_label");
+        assert_eq!(context_path.as_str(), path.as_str());
+        assert!(matches!(err.without_context(), Error::ZbusFdo(_)));
+        assert_eq!(
+            err.dbus_error_name().as_deref(),
+            Some("org.freedesktop.DBus.Error.Failed")
+        );
+        assert!(err
+            .to_string()
+            .starts_with("get_label on /org/freedesktop/secrets/collection/login/1 failed"));
+
+        // errors with their own meaning are left alone
+        assert!(matches!(
+            Error::Locked.with_context("get_label", &path),
+            Error::Locked
+        ));
+    }
+}
@@ -0,0 +1,205 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Events emitted by the secret service, see [SecretService::watch](crate::SecretService::watch).
+
+use crate::builder::Config;
+use crate::proxy::collection::CollectionProxy;
+use crate::proxy::service::ServiceProxy;
+use crate::ss::{SS_COLLECTION_INTERFACE, SS_ITEM_INTERFACE, SS_SERVICE_INTERFACE};
+use crate::{Error, ObjectPath};
+use futures_util::{future, stream, Stream, StreamExt};
+use std::collections::HashMap;
+use zbus::zvariant::{self, OwnedObjectPath, OwnedValue};
+use zbus::{message, MatchRule, Message};
+
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+
+/// A change reported by the secret service.
+///
+/// Yielded by [SecretService::watch](crate::SecretService::watch) and
+/// [blocking::SecretService::watch](crate::blocking::SecretService::watch).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SecretServiceEvent {
+    /// A collection was created.
+    CollectionCreated(ObjectPath),
+    /// A collection was deleted.
+    CollectionDeleted(ObjectPath),
+    /// A property of a collection changed.
+    CollectionChanged(ObjectPath),
+    /// An item was created in `collection`.
+    ItemCreated {
+        collection: ObjectPath,
+        item: ObjectPath,
+    },
+    /// An item was deleted from `collection`.
+    ItemDeleted {
+        collection: ObjectPath,
+        item: ObjectPath,
+    },
+    /// A property or the secret of an item in `collection` changed.
+    ItemChanged {
+        collection: ObjectPath,
+        item: ObjectPath,
+    },
+    /// A collection or item was locked or unlocked.
+    LockStateChanged { path: ObjectPath, locked: bool },
+}
+
+/// A collection created, changed or deleted, by this or any other
+/// application.
+///
+/// Yielded by
+/// [SecretService::receive_collection_events](crate::SecretService::receive_collection_events)
+/// and its blocking counterpart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CollectionEvent {
+    Created(ObjectPath),
+    /// A property of the collection changed, such as its label.
+    Changed(ObjectPath),
+    Deleted(ObjectPath),
+}
+
+/// An item created, changed or deleted in a collection, by this or any
+/// other application.
+///
+/// Yielded by
+/// [Collection::receive_item_events](crate::Collection::receive_item_events)
+/// and its blocking counterpart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ItemEvent {
+    Created(ObjectPath),
+    /// A property or the secret of the item changed.
+    Changed(ObjectPath),
+    Deleted(ObjectPath),
+}
+
+fn path(object: &zvariant::ObjectPath<'_>) -> ObjectPath {
+    OwnedObjectPath::from(object.to_owned()).into()
+}
+
+/// Merges the collection signals of the `Service` object into a single
+/// stream, skipping signals that can't be parsed.
+pub(crate) async fn collection_events(
+    service_proxy: &ServiceProxy<'_>,
+) -> Result<impl Stream<Item = CollectionEvent> + Send + 'static, Error> {
+    let created = service_proxy
+        .receive_collection_created()
+        .await?
+        .filter_map(|signal| {
+            let args = signal.args().ok();
+            future::ready(args.map(|args| CollectionEvent::Created(path(args.collection()))))
+        });
+    let changed = service_proxy
+        .receive_collection_changed()
+        .await?
+        .filter_map(|signal| {
+            let args = signal.args().ok();
+            future::ready(args.map(|args| CollectionEvent::Changed(path(args.collection()))))
+        });
+    let deleted = service_proxy
+        .receive_collection_deleted()
+        .await?
+        .filter_map(|signal| {
+            let args = signal.args().ok();
+            future::ready(args.map(|args| CollectionEvent::Deleted(path(args.collection()))))
+        });
+    Ok(stream::select(created, stream::select(changed, deleted)))
+}
+
+/// Merges the item signals of a `Collection` object into a single stream,
+/// skipping signals that can't be parsed.
+pub(crate) async fn item_events(
+    collection_proxy: &CollectionProxy<'_>,
+) -> Result<impl Stream<Item = ItemEvent> + Send + 'static, Error> {
+    let created = collection_proxy
+        .receive_item_created()
+        .await?
+        .filter_map(|signal| {
+            let args = signal.args().ok();
+            future::ready(args.map(|args| ItemEvent::Created(path(args.item()))))
+        });
+    let changed = collection_proxy
+        .receive_item_changed()
+        .await?
+        .filter_map(|signal| {
+            let args = signal.args().ok();
+            future::ready(args.map(|args| ItemEvent::Changed(path(args.item()))))
+        });
+    let deleted = collection_proxy
+        .receive_item_deleted()
+        .await?
+        .filter_map(|signal| {
+            let args = signal.args().ok();
+            future::ready(args.map(|args| ItemEvent::Deleted(path(args.item()))))
+        });
+    Ok(stream::select(created, stream::select(changed, deleted)))
+}
+
+/// Matches every signal sent from below the secret service's base path,
+/// so that a single subscription also covers collections created later.
+pub(crate) fn match_rule(config: &Config) -> Result<MatchRule<'static>, Error> {
+    Ok(MatchRule::builder()
+        .msg_type(message::Type::Signal)
+        .sender(config.destination.clone())?
+        .path_namespace(config.path.clone())?
+        .build())
+}
+
+/// Converts a signal into an event, skipping signals that aren't events
+/// (such as prompt completions) or that can't be parsed.
+pub(crate) fn parse_event(msg: &Message) -> Option<SecretServiceEvent> {
+    let header = msg.header();
+    let path = ObjectPath::from(OwnedObjectPath::from(header.path()?.to_owned()));
+    let body = msg.body();
+
+    let event = match (header.interface()?.as_str(), header.member()?.as_str()) {
+        (SS_SERVICE_INTERFACE, "CollectionCreated") => {
+            SecretServiceEvent::CollectionCreated(deserialize_path(&body)?)
+        }
+        (SS_SERVICE_INTERFACE, "CollectionDeleted") => {
+            SecretServiceEvent::CollectionDeleted(deserialize_path(&body)?)
+        }
+        (SS_SERVICE_INTERFACE, "CollectionChanged") => {
+            SecretServiceEvent::CollectionChanged(deserialize_path(&body)?)
+        }
+        (SS_COLLECTION_INTERFACE, "ItemCreated") => SecretServiceEvent::ItemCreated {
+            collection: path,
+            item: deserialize_path(&body)?,
+        },
+        (SS_COLLECTION_INTERFACE, "ItemDeleted") => SecretServiceEvent::ItemDeleted {
+            collection: path,
+            item: deserialize_path(&body)?,
+        },
+        (SS_COLLECTION_INTERFACE, "ItemChanged") => SecretServiceEvent::ItemChanged {
+            collection: path,
+            item: deserialize_path(&body)?,
+        },
+        (PROPERTIES_INTERFACE, "PropertiesChanged") => {
+            let (interface, changed, _invalidated): (
+                String,
+                HashMap<String, OwnedValue>,
+                Vec<String>,
+            ) = body.deserialize().ok()?;
+            if interface != SS_COLLECTION_INTERFACE && interface != SS_ITEM_INTERFACE {
+                return None;
+            }
+            let locked = bool::try_from(changed.get("Locked")?).ok()?;
+            SecretServiceEvent::LockStateChanged { path, locked }
+        }
+        _ => return None,
+    };
+
+    Some(event)
+}
+
+fn deserialize_path(body: &message::Body) -> Option<ObjectPath> {
+    Some(body.deserialize::<OwnedObjectPath>().ok()?.into())
+}
@@ -0,0 +1,126 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+// Matching the labels, attribute values and optionally secrets of items
+// against a regular expression on the client.
+
+use crate::util::{decrypt_secrets, limit_concurrency};
+use crate::{blocking, Collection, Error, Item};
+
+use regex::Regex;
+use std::collections::HashMap;
+
+/// Returns whether `pattern` matches the label or an attribute value.
+fn matches(pattern: &Regex, label: &str, attributes: &HashMap<String, String>) -> bool {
+    pattern.is_match(label) || attributes.values().any(|value| pattern.is_match(value))
+}
+
+/// Returns whether `pattern` matches the secret, if it's text.
+fn matches_secret(pattern: &Regex, secret: Option<Vec<u8>>) -> bool {
+    secret
+        .and_then(|secret| String::from_utf8(secret).ok())
+        .is_some_and(|secret| pattern.is_match(&secret))
+}
+
+pub(crate) async fn grep<'a>(
+    collection: &'a Collection<'_>,
+    pattern: &Regex,
+    secrets: bool,
+) -> Result<Vec<Item<'a>>, Error> {
+    let items = collection.get_all_items().await?;
+    let fields = futures_util::future::try_join_all(items.iter().map(|item| {
+        limit_concurrency(collection.config, async {
+            futures_util::try_join!(item.get_label(), item.get_attributes())
+        })
+    }))
+    .await?;
+
+    let (mut matching, rest): (Vec<_>, Vec<_>) = items
+        .into_iter()
+        .zip(fields)
+        .partition(|(_, (label, attributes))| matches(pattern, label, attributes));
+    if secrets && !rest.is_empty() {
+        let paths = rest.iter().map(|(item, _)| &*item.path).collect();
+        let secrets = collection
+            .service_proxy
+            .get_secrets(paths, collection.session.open().await?)
+            .await?;
+        let mut secrets = decrypt_secrets(collection.session, collection.config, secrets)?;
+        matching.extend(
+            rest.into_iter()
+                .filter(|(item, _)| matches_secret(pattern, secrets.remove(&item.path))),
+        );
+    }
+    Ok(matching.into_iter().map(|(item, _)| item).collect())
+}
+
+pub(crate) fn grep_blocking<'a>(
+    collection: &'a blocking::Collection<'_>,
+    pattern: &Regex,
+    secrets: bool,
+) -> Result<Vec<blocking::Item<'a>>, Error> {
+    let mut matching = Vec::new();
+    let mut rest = Vec::new();
+    for item in collection.get_all_items()? {
+        if matches(pattern, &item.get_label()?, &item.get_attributes()?) {
+            matching.push(item);
+        } else {
+            rest.push(item);
+        }
+    }
+    if secrets && !rest.is_empty() {
+        let paths = rest.iter().map(|item| &*item.path).collect();
+        let secrets = collection
+            .service_proxy
+            .get_secrets(paths, collection.session.open_blocking()?)?;
+        let mut secrets = decrypt_secrets(collection.session, collection.config, secrets)?;
+        matching.extend(
+            rest.into_iter()
+                .filter(|item| matches_secret(pattern, secrets.remove(&item.path))),
+        );
+    }
+    Ok(matching)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{EncryptionType, SecretService};
+
+    #[tokio::test]
+    async fn should_grep_items() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let attributes = HashMap::from([("test_attribute_grep", "grep-value-42")]);
+        let by_attribute = collection
+            .create_item("Test", attributes, b"test", false, "text/plain")
+            .await
+            .unwrap();
+        let by_secret = collection
+            .create_item(
+                "Test",
+                HashMap::new(),
+                b"grep-secret-42",
+                false,
+                "text/plain",
+            )
+            .await
+            .unwrap();
+
+        let pattern = Regex::new(r"^grep-\w+-42$").unwrap();
+        let paths = |items: Vec<Item<'_>>| items.iter().map(Item::path).collect::<Vec<_>>();
+        let found = paths(collection.grep(&pattern).await.unwrap());
+        assert!(found.contains(&by_attribute.path()));
+        assert!(!found.contains(&by_secret.path()));
+        let found = paths(collection.grep_including_secrets(&pattern).await.unwrap());
+        assert!(found.contains(&by_attribute.path()));
+        assert!(found.contains(&by_secret.path()));
+
+        by_attribute.delete().await.unwrap();
+        by_secret.delete().await.unwrap();
+    }
+}
@@ -0,0 +1,121 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Window identifiers for prompts, derived from GTK 4 windows.
+//!
+//! Prompts are shown as children of the window whose identifier is passed to
+//! [SecretServiceBuilder::window_id](crate::SecretServiceBuilder::window_id).
+//! On X11 that is the window's XID, while on Wayland the window has to be
+//! exported first, and stay exported for as long as prompts may be shown.
+//! [WindowHandle] does either, depending on the backend the window runs on.
+//!
+//! ```no_run
+//! # async fn call(window: &gtk4::ApplicationWindow) {
+//! use secret_service::gtk::WindowHandle;
+//! use secret_service::SecretService;
+//!
+//! // keep the handle around for as long as prompts may be shown
+//! let handle = WindowHandle::new(window).await;
+//! let mut builder = SecretService::builder();
+//! if let Some(handle) = &handle {
+//!     builder = builder.gtk_window(handle);
+//! }
+//! let ss = builder.connect().await.unwrap();
+//! # }
+//! ```
+
+use crate::WindowIdentifier;
+
+use gdk4_wayland::WaylandToplevel;
+use gdk4_x11::X11Surface;
+use gtk4::prelude::*;
+
+use async_lock::OnceCell;
+use std::sync::Arc;
+
+/// The identifier of a GTK window, for parenting prompts to it.
+///
+/// On Wayland, the window stays exported until the handle is dropped.
+#[derive(Debug)]
+pub struct WindowHandle {
+    identifier: WindowIdentifier,
+    /// The toplevel to unexport on drop, on Wayland.
+    exported: Option<WaylandToplevel>,
+}
+
+impl WindowHandle {
+    /// Returns the handle of `window`, which must be realized, or `None` if
+    /// it isn't or runs on a backend without window identifiers.
+    ///
+    /// On Wayland, the export completes on the GLib main loop, which has to
+    /// run while the returned future is awaited, as it does in futures
+    /// spawned with `glib::spawn_future_local`.
+    pub async fn new(window: &impl IsA<gtk4::Native>) -> Option<Self> {
+        let surface = window.as_ref().surface()?;
+
+        if let Some(surface) = surface.downcast_ref::<X11Surface>() {
+            // XIDs are a c_ulong, which is narrower on 32-bit targets
+            #[allow(clippy::useless_conversion)]
+            let xid = u64::from(surface.xid());
+            return Some(WindowHandle {
+                identifier: WindowIdentifier::X11(xid),
+                exported: None,
+            });
+        }
+
+        if let Ok(toplevel) = surface.downcast::<WaylandToplevel>() {
+            let handle = export_wayland(&toplevel).await?;
+            return Some(WindowHandle {
+                identifier: WindowIdentifier::Wayland(handle),
+                exported: Some(toplevel),
+            });
+        }
+
+        None
+    }
+
+    /// Returns the identifier, as expected by
+    /// [SecretServiceBuilder::window](crate::SecretServiceBuilder::window).
+    pub fn identifier(&self) -> &WindowIdentifier {
+        &self.identifier
+    }
+}
+
+impl Drop for WindowHandle {
+    fn drop(&mut self) {
+        if let Some(toplevel) = &self.exported {
+            toplevel.unexport_handle();
+        }
+    }
+}
+
+/// Where the Wayland export callback hands the handle over.
+type ExportedCell = Arc<OnceCell<Option<String>>>;
+
+/// Owned by the export callback, to end the wait with `None` when GTK drops
+/// the callback without calling it, so that nobody waits forever.
+struct Exported(ExportedCell);
+
+impl Drop for Exported {
+    fn drop(&mut self) {
+        let _ = self.0.set_blocking(None);
+    }
+}
+
+/// Exports a Wayland toplevel, returning its handle.
+async fn export_wayland(toplevel: &WaylandToplevel) -> Option<String> {
+    let cell = ExportedCell::default();
+    let exported = Exported(Arc::clone(&cell));
+    let started = toplevel.export_handle(move |_, handle| {
+        let _ = exported.0.set_blocking(handle.ok().map(str::to_owned));
+    });
+    if !started {
+        return None;
+    }
+
+    cell.wait().await.clone()
+}
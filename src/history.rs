@@ -0,0 +1,84 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+// Previous secrets of items, kept as items of their own linked by attributes,
+// see SecretServiceBuilder::secret_history.
+
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Attribute of archived secrets, holding the path of the item they belong
+/// to.
+pub(crate) const HISTORY_OF_ATTRIBUTE: &str = "history_of";
+
+/// Attribute of archived secrets, holding their version.
+pub(crate) const HISTORY_VERSION_ATTRIBUTE: &str = "history_version";
+
+/// Attribute of archived secrets, holding when they were replaced, in
+/// seconds since the Unix epoch.
+pub(crate) const HISTORY_ARCHIVED_ATTRIBUTE: &str = "history_archived";
+
+/// A previous secret of an item, as returned by
+/// [Item::secret_history](crate::Item::secret_history).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SecretVersion {
+    /// Numbers the versions of an item from 1, oldest first.
+    pub version: u32,
+    /// When the secret was replaced, in seconds since the Unix epoch.
+    pub archived: u64,
+}
+
+impl SecretVersion {
+    /// Reads the version from the attributes of an archived secret.
+    pub(crate) fn from_attributes(attributes: &HashMap<String, String>) -> Option<Self> {
+        Some(SecretVersion {
+            version: attributes.get(HISTORY_VERSION_ATTRIBUTE)?.parse().ok()?,
+            archived: attributes.get(HISTORY_ARCHIVED_ATTRIBUTE)?.parse().ok()?,
+        })
+    }
+
+    /// Returns the attributes recording the version, for the secret of the
+    /// item at `path`.
+    pub(crate) fn attributes(&self, path: &str) -> HashMap<&'static str, String> {
+        HashMap::from([
+            (HISTORY_OF_ATTRIBUTE, path.to_owned()),
+            (HISTORY_VERSION_ATTRIBUTE, self.version.to_string()),
+            (HISTORY_ARCHIVED_ATTRIBUTE, self.archived.to_string()),
+        ])
+    }
+
+    /// Returns the version following the `archived` ones, replaced now.
+    pub(crate) fn next(archived: &[SecretVersion]) -> Self {
+        let version = archived.iter().map(|v| v.version).max().unwrap_or(0) + 1;
+        let archived = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        SecretVersion { version, archived }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_number_versions() {
+        let first = SecretVersion::next(&[]);
+        assert_eq!(first.version, 1);
+        let second = SecretVersion::next(&[first]);
+        assert_eq!(second.version, 2);
+
+        let attributes = second
+            .attributes("/test/1")
+            .into_iter()
+            .map(|(key, value)| (key.to_owned(), value))
+            .collect();
+        assert_eq!(SecretVersion::from_attributes(&attributes), Some(second));
+        assert_eq!(attributes[HISTORY_OF_ATTRIBUTE], "/test/1");
+    }
+}
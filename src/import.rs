@@ -0,0 +1,181 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+// Creating many items at once, such as when migrating a password database.
+
+use crate::transaction::borrowed_attributes;
+use crate::util::{limit_concurrency, TEXT_CONTENT_TYPE};
+use crate::{BatchOutcome, Collection, ConflictPolicy, Error, ObjectPath, Progress};
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// How many items are created concurrently, before reporting progress.
+const IMPORT_BATCH_SIZE: usize = 16;
+
+/// An item to create with [Collection::import].
+#[derive(Clone)]
+pub struct NewItem {
+    pub label: String,
+    pub attributes: HashMap<String, String>,
+    pub secret: Vec<u8>,
+    pub content_type: String,
+}
+
+impl NewItem {
+    /// Creates an item without attributes, holding a `text/plain` secret.
+    pub fn new(label: impl Into<String>, secret: impl Into<Vec<u8>>) -> Self {
+        NewItem {
+            label: label.into(),
+            attributes: HashMap::new(),
+            secret: secret.into(),
+            content_type: TEXT_CONTENT_TYPE.to_owned(),
+        }
+    }
+
+    pub fn attribute(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.attributes.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = content_type.into();
+        self
+    }
+}
+
+/// Leaves the secret out.
+impl fmt::Debug for NewItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NewItem")
+            .field("label", &self.label)
+            .field("attributes", &self.attributes)
+            .field("content_type", &self.content_type)
+            .finish_non_exhaustive()
+    }
+}
+
+/// What [Collection::import] did with an item.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Imported {
+    /// The item was created at the path.
+    Created(ObjectPath),
+    /// An item with the same attributes existed already, likely from an
+    /// earlier, interrupted import.
+    Skipped,
+}
+
+pub(crate) async fn import(
+    collection: &Collection<'_>,
+    items: impl IntoIterator<Item = NewItem>,
+    mut progress: impl FnMut(&Progress),
+) -> BatchOutcome<Imported> {
+    let mut items = items.into_iter();
+    let mut state = Progress::of(&items);
+    let path = collection.path();
+
+    let mut results = Vec::new();
+    loop {
+        let batch: Vec<_> = items.by_ref().take(IMPORT_BATCH_SIZE).collect();
+        if batch.is_empty() {
+            return BatchOutcome { results };
+        }
+        let imported = futures_util::future::join_all(
+            batch
+                .iter()
+                .map(|item| limit_concurrency(collection.config, import_item(collection, item))),
+        )
+        .await;
+        for (item, result) in batch.iter().zip(imported) {
+            state.advance(Some(&item.label), &result, &mut progress);
+            results.push((path.clone(), result));
+        }
+    }
+}
+
+async fn import_item(collection: &Collection<'_>, item: &NewItem) -> Result<Imported, Error> {
+    let created = collection
+        .create_item(
+            &item.label,
+            borrowed_attributes(&item.attributes),
+            &item.secret,
+            // skips items already imported
+            conflict_policy(&item.attributes, ConflictPolicy::Fail),
+            &item.content_type,
+        )
+        .await;
+    imported(created.map(|created| created.path()))
+}
+
+/// Applies `conflict` to items with attributes, as items without can't be
+/// told apart, and searching for no attributes would find every item.
+pub(crate) fn conflict_policy(
+    attributes: &HashMap<String, String>,
+    conflict: ConflictPolicy,
+) -> ConflictPolicy {
+    if attributes.is_empty() {
+        ConflictPolicy::CreateAnyway
+    } else {
+        conflict
+    }
+}
+
+pub(crate) fn imported(created: Result<ObjectPath, Error>) -> Result<Imported, Error> {
+    match created {
+        Ok(path) => Ok(Imported::Created(path)),
+        Err(Error::ItemExists) => Ok(Imported::Skipped),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{EncryptionType, SecretService};
+
+    #[tokio::test]
+    async fn should_import_items() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let items: Vec<_> = (0..20)
+            .map(|n| {
+                NewItem::new(format!("TestImport{n}"), "test")
+                    .attribute("test_attribute_import", n.to_string())
+            })
+            .collect();
+
+        let mut reported = Vec::new();
+        let results = collection
+            .import(items[..5].to_vec(), |progress| {
+                reported.push(progress.clone())
+            })
+            .await;
+        assert!(results.is_success());
+        assert_eq!(reported.len(), 5);
+        assert_eq!(reported[4].remaining(), Some(0));
+        assert_eq!(reported[4].label.as_deref(), Some("TestImport4"));
+
+        // resumes where the first import stopped
+        let results = collection.import(items, |_| ()).await;
+        let created = results
+            .succeeded()
+            .filter(|(_, imported)| matches!(imported, Imported::Created(_)))
+            .count();
+        assert_eq!(created, 15);
+        assert!(matches!(results.results[0].1, Ok(Imported::Skipped)));
+
+        for n in 0..20 {
+            let value = n.to_string();
+            let found = collection
+                .search_items(HashMap::from([("test_attribute_import", value.as_str())]))
+                .await
+                .unwrap();
+            assert_eq!(found.len(), 1);
+            found[0].delete().await.unwrap();
+        }
+    }
+}
@@ -5,23 +5,45 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
+use crate::builder::Config;
+use crate::cipher::{envelope_key_id, ClientKey, ENVELOPE_KEY_ATTRIBUTE};
+use crate::compression::strip_encoding;
 use crate::error::Error;
+use crate::history::{SecretVersion, HISTORY_OF_ATTRIBUTE};
+use crate::journal::{attribute_names, Change, SecretHash};
 use crate::proxy::item::ItemProxy;
 use crate::proxy::service::ServiceProxy;
-use crate::session::decrypt;
+use crate::proxy::SecretStruct;
 use crate::session::Session;
-use crate::ss::SS_DBUS_NAME;
-use crate::util::{exec_prompt, format_secret, lock_or_unlock, LockAction};
+use crate::trash::{trash_collection, TRASHED_FROM_ATTRIBUTE};
+use crate::util::{
+    collection_of, decrypt_secret, decrypt_session_secret, exec_prompt, format_secret,
+    format_session_secret, limit_concurrency, lock_or_unlock, secret_to_string, sort_by_keys,
+    LockAction, SortKey, SortOrder, TEXT_CONTENT_TYPE,
+};
+use crate::{Collection, ItemTimestamp};
 
+use std::cmp::Reverse;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use zbus::{zvariant::OwnedObjectPath, CacheProperties};
 
 pub struct Item<'a> {
     conn: zbus::Connection,
     session: &'a Session,
+    /// Deprecated copy of [Item::path], for reading only: assigning to it
+    /// doesn't change the item the handle refers to.
+    #[deprecated(
+        since = "4.1.0",
+        note = "use `Item::path`, which doesn't expose zbus types"
+    )]
     pub item_path: OwnedObjectPath,
+    pub(crate) path: OwnedObjectPath,
     item_proxy: ItemProxy<'a>,
     service_proxy: &'a ServiceProxy<'a>,
+    config: &'a Config,
+    /// Set once the object is deleted, so that later calls can say so.
+    deleted: AtomicBool,
 }
 
 impl<'a> Item<'a> {
@@ -29,26 +51,89 @@ impl<'a> Item<'a> {
         conn: zbus::Connection,
         session: &'a Session,
         service_proxy: &'a ServiceProxy<'a>,
+        config: &'a Config,
         item_path: OwnedObjectPath,
     ) -> Result<Item<'a>, Error> {
         let item_proxy = ItemProxy::builder(&conn)
-            .destination(SS_DBUS_NAME)?
+            .destination(config.destination.clone())?
             .path(item_path.clone())?
             .cache_properties(CacheProperties::No)
             .build()
             .await?;
 
+        #[allow(deprecated)]
         Ok(Item {
             conn,
             session,
+            path: item_path.clone(),
             item_path,
             item_proxy,
             service_proxy,
+            config,
+            deleted: AtomicBool::new(false),
         })
     }
 
+    /// Returns a handle to the same item that transfers secrets in
+    /// `session`, see [SecretService::open_session](crate::SecretService::open_session).
+    pub fn with_session<'s>(&'s self, session: &'s Session) -> Item<'s> {
+        #[allow(deprecated)]
+        Item {
+            conn: self.conn.clone(),
+            session,
+            item_path: self.path.clone(),
+            path: self.path.clone(),
+            item_proxy: self.item_proxy.clone(),
+            service_proxy: self.service_proxy,
+            config: self.config,
+            deleted: AtomicBool::new(self.deleted.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// The object path of the item.
+    pub fn path(&self) -> crate::ObjectPath {
+        self.path.clone().into()
+    }
+
+    /// Attaches `operation` and the item's path to errors of D-Bus calls,
+    /// or reports them as [Error::ObjectDeleted] once the item is deleted.
+    fn context<E: Into<Error>>(&self, operation: &'static str) -> impl FnOnce(E) -> Error + '_ {
+        move |err| {
+            if self.deleted.load(Ordering::Relaxed) {
+                Error::ObjectDeleted
+            } else {
+                err.into().with_context(operation, &self.path)
+            }
+        }
+    }
+
     pub async fn is_locked(&self) -> Result<bool, Error> {
-        Ok(self.item_proxy.locked().await?)
+        self.item_proxy
+            .locked()
+            .await
+            .map_err(self.context("is_locked"))
+    }
+
+    /// Returns whether the item still exists, for handles kept around while
+    /// other applications may delete the item.
+    ///
+    /// Probes a property of the item, so that a deleted item shows up as
+    /// `false` rather than as an error.
+    pub async fn exists(&self) -> Result<bool, Error> {
+        if self.deleted.load(Ordering::Relaxed) {
+            return Ok(false);
+        }
+        match self.item_proxy.locked().await {
+            Ok(_) => Ok(true),
+            Err(err) => {
+                let err = Error::from(err);
+                if err.is_unknown_object() {
+                    Ok(false)
+                } else {
+                    Err(err.with_context("exists", &self.path))
+                }
+            }
+        }
     }
 
     pub async fn ensure_unlocked(&self) -> Result<(), Error> {
@@ -59,97 +144,475 @@ impl<'a> Item<'a> {
         }
     }
 
-    pub async fn unlock(&self) -> Result<(), Error> {
-        lock_or_unlock(
+    /// Unlocks the item, prompting the user if the provider asks to, and
+    /// returns the paths of the objects the provider reports as unlocked.
+    ///
+    /// Providers may unlock the item's collection rather than the item, so the
+    /// paths aren't necessarily the item's own.
+    pub async fn unlock(&self) -> Result<Vec<crate::ObjectPath>, Error> {
+        let unlocked = lock_or_unlock(
             self.conn.clone(),
             self.service_proxy,
-            &self.item_path,
+            self.config,
+            vec![&*self.path],
             LockAction::Unlock,
         )
         .await
+        .map_err(self.context("unlock"))?;
+        Ok(unlocked.into_iter().map(Into::into).collect())
     }
 
-    pub async fn lock(&self) -> Result<(), Error> {
-        lock_or_unlock(
+    /// Locks the item and returns the paths of the objects the provider
+    /// reports as locked.
+    ///
+    /// Providers may lock the item's collection rather than the item, or
+    /// refuse to lock some objects, which then don't show up in the paths.
+    pub async fn lock(&self) -> Result<Vec<crate::ObjectPath>, Error> {
+        let locked = lock_or_unlock(
             self.conn.clone(),
             self.service_proxy,
-            &self.item_path,
+            self.config,
+            vec![&*self.path],
             LockAction::Lock,
         )
         .await
+        .map_err(self.context("lock"))?;
+        Ok(locked.into_iter().map(Into::into).collect())
     }
 
     pub async fn get_attributes(&self) -> Result<HashMap<String, String>, Error> {
-        Ok(self.item_proxy.attributes().await?)
+        let attributes = self
+            .item_proxy
+            .attributes()
+            .await
+            .map_err(self.context("get_attributes"))?;
+        self.config.decrypt_attributes(attributes)
     }
 
     pub async fn set_attributes(&self, attributes: HashMap<&str, &str>) -> Result<(), Error> {
-        Ok(self.item_proxy.set_attributes(attributes).await?)
+        // keeps recording the envelope key the secret is encrypted with
+        let mut attributes: HashMap<&str, &str> = attributes;
+        let stored_key = match self.config.envelope_key {
+            Some(_) if !attributes.contains_key(ENVELOPE_KEY_ATTRIBUTE) => self
+                .item_proxy
+                .attributes()
+                .await
+                .map_err(self.context("set_attributes"))?
+                .remove(ENVELOPE_KEY_ATTRIBUTE),
+            _ => None,
+        };
+        if let Some(id) = &stored_key {
+            attributes.insert(ENVELOPE_KEY_ATTRIBUTE, id);
+        }
+
+        let attributes = self.config.encrypt_attributes(attributes);
+        self.item_proxy
+            .set_attributes(attributes.as_map())
+            .await
+            .map_err(self.context("set_attributes"))?;
+        self.config.record(self.path(), || {
+            Change::AttributesSet(attribute_names(&attributes.as_map()))
+        });
+        Ok(())
     }
 
     pub async fn get_label(&self) -> Result<String, Error> {
-        Ok(self.item_proxy.label().await?)
+        self.item_proxy
+            .label()
+            .await
+            .map_err(self.context("get_label"))
     }
 
     pub async fn set_label(&self, new_label: &str) -> Result<(), Error> {
-        Ok(self.item_proxy.set_label(new_label).await?)
+        self.item_proxy
+            .set_label(new_label)
+            .await
+            .map_err(self.context("set_label"))?;
+        self.config
+            .record(self.path(), || Change::LabelSet(new_label.to_owned()));
+        Ok(())
     }
 
     /// Deletes dbus object, but struct instance still exists (current implementation)
+    ///
+    /// Calls failing on the instance afterwards return [Error::ObjectDeleted].
     pub async fn delete(&self) -> Result<(), Error> {
         // ensure_unlocked handles prompt for unlocking if necessary
         self.ensure_unlocked().await?;
-        let prompt_path = self.item_proxy.delete().await?;
+        let prompt_path = self
+            .item_proxy
+            .delete()
+            .await
+            .map_err(self.context("delete"))?;
 
         // "/" means no prompt necessary
         if prompt_path.as_str() != "/" {
-            exec_prompt(self.conn.clone(), &prompt_path).await?;
+            exec_prompt(self.conn.clone(), self.config, &prompt_path).await?;
         }
 
+        self.deleted.store(true, Ordering::Relaxed);
+        self.config.record(self.path(), || Change::Deleted);
+
         Ok(())
     }
 
+    /// Moves the item to the trash collection, returning the item there.
+    ///
+    /// The specification has no trash, so this crate keeps one by
+    /// convention: a collection with the alias `trash`, created with the
+    /// label `Trash` when first needed. The item's label, attributes and
+    /// secret are copied there, with a `trashed_from` attribute recording
+    /// the collection the item was in, before the item is deleted.
+    /// [Item::restore] moves it back, and
+    /// [SecretService::empty_trash](crate::SecretService::empty_trash)
+    /// deletes the trashed items for good.
+    ///
+    /// The trashed item has a new path, and isn't found by searches of its
+    /// old collection.
+    pub async fn trash(&self) -> Result<Item<'a>, Error> {
+        let trash = trash_collection(&self.conn, self.service_proxy, self.config).await?;
+        let mut attributes = self.get_attributes().await?;
+        attributes.insert(
+            TRASHED_FROM_ATTRIBUTE.to_owned(),
+            collection_of(self.path.as_str()).to_owned(),
+        );
+        self.move_to(trash, attributes, "trash").await
+    }
+
+    /// Moves a trashed item back to the collection it was trashed from,
+    /// returning the item there, see [Item::trash].
+    ///
+    /// Fails with [Error::NoResult] if the item wasn't trashed.
+    pub async fn restore(&self) -> Result<Item<'a>, Error> {
+        let mut attributes = self.get_attributes().await?;
+        let collection = attributes
+            .remove(TRASHED_FROM_ATTRIBUTE)
+            .ok_or(Error::NoResult)?;
+        let collection = OwnedObjectPath::try_from(collection)?;
+        self.move_to(collection, attributes, "restore").await
+    }
+
+    /// Copies the item into `collection` with `attributes`, then deletes it,
+    /// for [Item::trash] and [Item::restore].
+    async fn move_to(
+        &self,
+        collection: OwnedObjectPath,
+        attributes: HashMap<String, String>,
+        operation: &'static str,
+    ) -> Result<Item<'a>, Error> {
+        self.ensure_unlocked().await?;
+        let label = self.get_label().await?;
+        let (secret, content_type) = self.read_secret(operation).await?;
+
+        let collection = Collection::new(
+            self.conn.clone(),
+            self.session,
+            self.service_proxy,
+            self.config,
+            collection,
+        )
+        .await?;
+        let attributes = attributes
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        let moved = collection
+            .create_item(&label, attributes, &secret, false, &content_type)
+            .await?;
+        let moved = Item::new(
+            self.conn.clone(),
+            self.session,
+            self.service_proxy,
+            self.config,
+            moved.path.clone(),
+        )
+        .await?;
+        self.delete().await?;
+        Ok(moved)
+    }
+
     pub async fn get_secret(&self) -> Result<Vec<u8>, Error> {
         let secret_struct = self
             .item_proxy
-            .get_secret(&self.session.object_path)
-            .await?;
-        let secret = secret_struct.value;
-
-        if let Some(session_key) = self.session.get_aes_key() {
-            // get "param" (aes_iv) field out of secret struct
-            let aes_iv = secret_struct.parameters;
+            .get_secret(self.session.open().await?)
+            .await
+            .map_err(self.context("get_secret"))?;
 
-            // decrypt
-            let decrypted_secret = decrypt(&secret, session_key, &aes_iv)?;
+        decrypt_secret(self.session, self.config, secret_struct).map_err(self.context("get_secret"))
+    }
 
-            Ok(decrypted_secret)
-        } else {
-            Ok(secret)
+    /// Returns the secret, unlocking the item first if it (or its
+    /// collection) is locked.
+    ///
+    /// Unlocking may show a prompt, which is parented to the window
+    /// configured on the [SecretServiceBuilder](crate::SecretServiceBuilder).
+    pub async fn get_secret_unlocking(&self) -> Result<Vec<u8>, Error> {
+        if self.is_locked().await? {
+            self.unlock().await?;
         }
+
+        self.get_secret().await
+    }
+
+    /// Returns the secret as a string.
+    ///
+    /// Fails with [Error::InvalidUtf8] if the secret isn't valid UTF-8, or if
+    /// its content type declares a different charset.
+    pub async fn get_secret_string(&self) -> Result<String, Error> {
+        let secret_struct = self
+            .item_proxy
+            .get_secret(self.session.open().await?)
+            .await
+            .map_err(self.context("get_secret_string"))?;
+        let content_type = strip_encoding(secret_struct.content_type.clone());
+        let secret = decrypt_secret(self.session, self.config, secret_struct)
+            .map_err(self.context("get_secret_string"))?;
+
+        secret_to_string(secret, &content_type)
     }
 
     pub async fn get_secret_content_type(&self) -> Result<String, Error> {
         let secret_struct = self
             .item_proxy
-            .get_secret(&self.session.object_path)
-            .await?;
-        let content_type = secret_struct.content_type;
+            .get_secret(self.session.open().await?)
+            .await
+            .map_err(self.context("get_secret_content_type"))?;
+        let content_type = strip_encoding(secret_struct.content_type);
 
         Ok(content_type)
     }
 
     pub async fn set_secret(&self, secret: &[u8], content_type: &str) -> Result<(), Error> {
-        let secret_struct = format_secret(self.session, secret, content_type)?;
-        Ok(self.item_proxy.set_secret(secret_struct).await?)
+        if self.config.secret_history != 0 {
+            self.archive_secret().await?;
+        }
+        self.session.open().await?;
+        let secret_struct = format_secret(self.session, self.config, secret, content_type)?;
+        self.item_proxy
+            .set_secret(secret_struct)
+            .await
+            .map_err(self.context("set_secret"))?;
+        self.config
+            .record(self.path(), || Change::SecretSet(SecretHash::new(secret)));
+        match &self.config.envelope_key {
+            Some(key) => self.record_envelope_key(key.id(), "set_secret").await,
+            None => Ok(()),
+        }
+    }
+
+    /// Sets the secret, unless the item was modified since
+    /// `expected_modified`, as returned by [Item::get_modified] when the
+    /// secret was read.
+    ///
+    /// Fails with [Error::Modified] otherwise, so that writers sharing an
+    /// item don't overwrite each other's changes unknowingly. The check
+    /// and the write are separate calls, so a change made in between still
+    /// goes unnoticed, and `Modified` only has a resolution of seconds.
+    pub async fn set_secret_if_unmodified(
+        &self,
+        secret: &[u8],
+        content_type: &str,
+        expected_modified: u64,
+    ) -> Result<(), Error> {
+        let modified = self
+            .item_proxy
+            .modified()
+            .await
+            .map_err(self.context("set_secret_if_unmodified"))?;
+        if modified != expected_modified {
+            return Err(Error::Modified);
+        }
+        self.set_secret(secret, content_type).await
+    }
+
+    /// Returns the previous secrets of the item, oldest first, as kept with
+    /// [SecretServiceBuilder::secret_history](crate::SecretServiceBuilder::secret_history).
+    pub async fn secret_history(&self) -> Result<Vec<SecretVersion>, Error> {
+        let archived = self.archived_secrets("secret_history").await?;
+        Ok(archived.into_iter().map(|(version, _)| version).collect())
+    }
+
+    /// Sets the secret back to the one archived as `version`, see
+    /// [Item::secret_history]. With the history kept, the current secret is
+    /// archived in turn.
+    ///
+    /// Fails with [Error::NoResult] if there is no such version.
+    pub async fn revert_to(&self, version: u32) -> Result<(), Error> {
+        let (_, archived) = self
+            .archived_secrets("revert_to")
+            .await?
+            .into_iter()
+            .find(|(archived, _)| archived.version == version)
+            .ok_or(Error::NoResult)?;
+        let (secret, content_type) = archived.read_secret("revert_to").await?;
+        self.set_secret(&secret, &content_type).await
+    }
+
+    /// Returns the previous secrets of the item with the items keeping them,
+    /// oldest first.
+    async fn archived_secrets(
+        &self,
+        operation: &'static str,
+    ) -> Result<Vec<(SecretVersion, Item<'a>)>, Error> {
+        let attributes = self
+            .config
+            .encrypt_attributes(HashMap::from([(HISTORY_OF_ATTRIBUTE, self.path.as_str())]));
+        let found = self
+            .service_proxy
+            .search_items(attributes.as_map())
+            .await
+            .map_err(self.context(operation))?;
+
+        let mut archived = Vec::new();
+        for path in found.unlocked.into_iter().chain(found.locked) {
+            let item = Item::new(
+                self.conn.clone(),
+                self.session,
+                self.service_proxy,
+                self.config,
+                path,
+            )
+            .await?;
+            if let Some(version) = SecretVersion::from_attributes(&item.get_attributes().await?) {
+                archived.push((version, item));
+            }
+        }
+        archived.sort_unstable_by_key(|(version, _)| version.version);
+        Ok(archived)
+    }
+
+    /// Copies the current secret into the history, deleting the oldest
+    /// versions beyond the configured number.
+    async fn archive_secret(&self) -> Result<(), Error> {
+        let archived = self.archived_secrets("set_secret").await?;
+        let versions: Vec<_> = archived.iter().map(|(version, _)| *version).collect();
+        let version = SecretVersion::next(&versions);
+
+        let (secret, content_type) = self.read_secret("set_secret").await?;
+        let label = self.get_label().await?;
+        let collection = self.collection().await?;
+        let attributes = version.attributes(self.path.as_str());
+        collection
+            .create_item(
+                &format!("{label} (version {})", version.version),
+                attributes
+                    .iter()
+                    .map(|(&key, value)| (key, value.as_str()))
+                    .collect(),
+                &secret,
+                false,
+                &content_type,
+            )
+            .await?;
+
+        let excess = (archived.len() + 1).saturating_sub(self.config.secret_history);
+        for (_, item) in archived.iter().take(excess) {
+            item.delete().await?;
+        }
+        Ok(())
+    }
+
+    /// Returns the collection holding the item.
+    pub(crate) async fn collection(&self) -> Result<Collection<'a>, Error> {
+        Collection::new(
+            self.conn.clone(),
+            self.session,
+            self.service_proxy,
+            self.config,
+            OwnedObjectPath::try_from(collection_of(self.path.as_str()))?,
+        )
+        .await
+    }
+
+    /// Returns the secret with its content type.
+    pub(crate) async fn read_secret(
+        &self,
+        operation: &'static str,
+    ) -> Result<(Vec<u8>, String), Error> {
+        let secret_struct = self
+            .item_proxy
+            .get_secret(self.session.open().await?)
+            .await
+            .map_err(self.context(operation))?;
+        let content_type = strip_encoding(secret_struct.content_type.clone());
+        let secret = decrypt_secret(self.session, self.config, secret_struct)
+            .map_err(self.context(operation))?;
+        Ok((secret, content_type))
+    }
+
+    /// Records the id of the envelope key the secret is now encrypted with.
+    async fn record_envelope_key(&self, id: &str, operation: &'static str) -> Result<(), Error> {
+        let mut attributes = self
+            .item_proxy
+            .attributes()
+            .await
+            .map_err(self.context(operation))?;
+        if attributes.get(ENVELOPE_KEY_ATTRIBUTE).map(String::as_str) == Some(id) {
+            return Ok(());
+        }
+
+        // the other values are passed back as stored
+        attributes.insert(ENVELOPE_KEY_ATTRIBUTE.to_owned(), id.to_owned());
+        let attributes = attributes
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        self.item_proxy
+            .set_attributes(attributes)
+            .await
+            .map_err(self.context(operation))
+    }
+
+    /// Re-encrypts the secret, as returned for the session by the provider,
+    /// from the envelope key `old` to `new`.
+    pub(crate) async fn rotate_envelope_key(
+        &self,
+        secret_struct: SecretStruct,
+        old: &ClientKey,
+        new: &ClientKey,
+    ) -> Result<(), Error> {
+        let content_type = secret_struct.content_type.clone();
+        let secret = decrypt_session_secret(self.session, self.config, secret_struct)
+            .map_err(self.context("rotate_key"))?;
+
+        // an interrupted rotation may have updated the secret, but not the
+        // attribute
+        if envelope_key_id(&secret) != Some(new.id().as_bytes()) {
+            let secret = old.open(secret).map_err(self.context("rotate_key"))?;
+            let secret_struct =
+                format_session_secret(self.session, &new.seal(&secret), &content_type)?;
+            self.item_proxy
+                .set_secret(secret_struct)
+                .await
+                .map_err(self.context("rotate_key"))?;
+        }
+        self.record_envelope_key(new.id(), "rotate_key").await
+    }
+
+    /// Sets the secret, stored with the default content type set with
+    /// [SecretServiceBuilder::default_content_type](crate::SecretServiceBuilder::default_content_type).
+    pub async fn set_secret_default(&self, secret: &[u8]) -> Result<(), Error> {
+        self.set_secret(secret, &self.config.default_content_type)
+            .await
+    }
+
+    /// Sets the secret to a string, stored with a `text/plain` content type.
+    pub async fn set_secret_string(&self, secret: &str) -> Result<(), Error> {
+        self.set_secret(secret.as_bytes(), TEXT_CONTENT_TYPE).await
     }
 
     pub async fn get_created(&self) -> Result<u64, Error> {
-        Ok(self.item_proxy.created().await?)
+        self.item_proxy
+            .created()
+            .await
+            .map_err(self.context("get_created"))
     }
 
     pub async fn get_modified(&self) -> Result<u64, Error> {
-        Ok(self.item_proxy.modified().await?)
+        self.item_proxy
+            .modified()
+            .await
+            .map_err(self.context("get_modified"))
     }
 
     /// Returns if an item is equal to `other`.
@@ -159,12 +622,57 @@ impl<'a> Item<'a> {
         let this_attrs = self.get_attributes().await?;
         let other_attrs = other.get_attributes().await?;
 
-        Ok(self.item_path == other.item_path && this_attrs == other_attrs)
+        Ok(self.path == other.path && this_attrs == other_attrs)
     }
+
+    pub(crate) async fn sort_key(&self, order: SortOrder) -> Result<SortKey, Error> {
+        Ok(match order {
+            SortOrder::Newest(ItemTimestamp::Created) => {
+                SortKey::Newest(Reverse(self.get_created().await?))
+            }
+            SortOrder::Newest(ItemTimestamp::Modified) => {
+                SortKey::Newest(Reverse(self.get_modified().await?))
+            }
+            SortOrder::Label => SortKey::label(self.get_label().await?),
+        })
+    }
+}
+
+async fn sort_items(items: &mut Vec<Item<'_>>, order: SortOrder) -> Result<(), Error> {
+    let keys = futures_util::future::try_join_all(
+        items
+            .iter()
+            .map(|item| limit_concurrency(item.config, item.sort_key(order))),
+    )
+    .await?;
+    sort_by_keys(items, keys);
+    Ok(())
+}
+
+/// Sorts items by when they were last modified, most recent first.
+///
+/// The timestamps are fetched concurrently, up to the limit set with
+/// [SecretServiceBuilder::max_concurrent_calls](crate::SecretServiceBuilder::max_concurrent_calls).
+/// Items with the same timestamp keep their order.
+pub async fn sort_by_modified(items: &mut Vec<Item<'_>>) -> Result<(), Error> {
+    sort_items(items, SortOrder::Newest(ItemTimestamp::Modified)).await
+}
+
+/// Sorts items by when they were created, most recent first, like
+/// [sort_by_modified].
+pub async fn sort_by_created(items: &mut Vec<Item<'_>>) -> Result<(), Error> {
+    sort_items(items, SortOrder::Newest(ItemTimestamp::Created)).await
+}
+
+/// Sorts items alphabetically by label, ignoring case, fetching the labels
+/// like [sort_by_modified] fetches timestamps.
+pub async fn sort_by_label(items: &mut Vec<Item<'_>>) -> Result<(), Error> {
+    sort_items(items, SortOrder::Label).await
 }
 
 #[cfg(test)]
 mod test {
+    use crate::testing::TestHarness;
     use crate::*;
 
     async fn create_test_default_item<'a>(collection: &'a Collection<'_>) -> Item<'a> {
@@ -187,6 +695,164 @@ mod test {
         }
     }
 
+    #[tokio::test]
+    async fn should_check_if_item_exists() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let attributes = HashMap::from([("test_attributes_exists", "test")]);
+        let item = collection
+            .create_item("Test", attributes.clone(), b"test", true, "text/plain")
+            .await
+            .unwrap();
+        assert!(item.exists().await.unwrap());
+
+        let other = collection.search_items(attributes).await.unwrap();
+        other[0].delete().await.unwrap();
+        assert!(!item.exists().await.unwrap());
+        assert!(!other[0].exists().await.unwrap());
+    }
+
+    #[test_with::no_env(GITHUB_ACTIONS)]
+    #[tokio::test]
+    async fn should_trash_and_restore() {
+        let harness = TestHarness::start().unwrap();
+        let ss = harness.connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let attributes = HashMap::from([("test_attribute_trash", "test_value")]);
+        let item = collection
+            .create_item("Test", attributes.clone(), b"test", false, "text/plain")
+            .await
+            .unwrap();
+        assert!(matches!(item.restore().await, Err(Error::NoResult)));
+
+        let trashed = item.trash().await.unwrap();
+        assert!(!item.exists().await.unwrap());
+        assert!(collection
+            .search_items(attributes.clone())
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            trashed.get_attributes().await.unwrap()["trashed_from"],
+            collection.path().as_str()
+        );
+        assert_eq!(trashed.get_secret().await.unwrap(), b"test");
+
+        let restored = trashed.restore().await.unwrap();
+        let found = collection.search_items(attributes).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].path(), restored.path());
+        assert_eq!(restored.get_label().await.unwrap(), "Test");
+        assert!(!restored
+            .get_attributes()
+            .await
+            .unwrap()
+            .contains_key("trashed_from"));
+
+        let trashed = restored.trash().await.unwrap();
+        let outcome = ss.empty_trash().await.unwrap();
+        assert!(outcome.succeeded().any(|(path, _)| *path == trashed.path()));
+        assert!(!trashed.exists().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn should_keep_secret_history() {
+        let ss = SecretService::builder()
+            .encryption(EncryptionType::Plain)
+            .secret_history(2)
+            .connect()
+            .await
+            .unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let item = create_test_default_item(&collection).await;
+        assert!(item.secret_history().await.unwrap().is_empty());
+
+        for secret in [b"a", b"b", b"c"] {
+            item.set_secret(secret, "text/plain").await.unwrap();
+        }
+        let versions: Vec<_> = item
+            .secret_history()
+            .await
+            .unwrap()
+            .iter()
+            .map(|version| version.version)
+            .collect();
+        assert_eq!(versions, [2, 3]);
+
+        item.revert_to(2).await.unwrap();
+        assert_eq!(item.get_secret().await.unwrap(), b"a");
+        let versions: Vec<_> = item
+            .secret_history()
+            .await
+            .unwrap()
+            .iter()
+            .map(|version| version.version)
+            .collect();
+        assert_eq!(versions, [3, 4]);
+        assert!(matches!(item.revert_to(1).await, Err(Error::NoResult)));
+
+        let path = item.path();
+        let archived = ss
+            .search_items(HashMap::from([("history_of", path.as_str())]))
+            .await
+            .unwrap();
+        for archived in archived.unlocked {
+            archived.delete().await.unwrap();
+        }
+        item.delete().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_set_secret_if_unmodified() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let item = create_test_default_item(&collection).await;
+
+        let modified = item.get_modified().await.unwrap();
+        let stale = item
+            .set_secret_if_unmodified(b"other", "text/plain", modified + 1)
+            .await;
+        assert!(matches!(stale, Err(Error::Modified)));
+        assert_eq!(item.get_secret().await.unwrap(), b"test");
+
+        item.set_secret_if_unmodified(b"other", "text/plain", modified)
+            .await
+            .unwrap();
+        assert_eq!(item.get_secret().await.unwrap(), b"other");
+        item.delete().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_report_use_after_delete() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let item = create_test_default_item(&collection).await;
+        item.delete().await.unwrap();
+
+        assert!(matches!(item.get_label().await, Err(Error::ObjectDeleted)));
+        assert!(matches!(item.delete().await, Err(Error::ObjectDeleted)));
+    }
+
+    #[tokio::test]
+    async fn should_attach_context_to_errors() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let attributes = HashMap::from([("test_attributes_context", "test")]);
+        let item = collection
+            .create_item("Test", attributes.clone(), b"test", true, "text/plain")
+            .await
+            .unwrap();
+        // deleted through another instance, which this one can't know about
+        let other = collection.search_items(attributes).await.unwrap();
+        other[0].delete().await.unwrap();
+
+        let err = item.get_label().await.unwrap_err();
+        let (operation, path) = err.context().unwrap();
+        assert_eq!(operation, "get_label");
+        assert_eq!(*path, item.path());
+        assert!(err.dbus_error_name().is_some());
+    }
+
     #[tokio::test]
     async fn should_check_if_item_locked() {
         let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
@@ -323,6 +989,116 @@ mod test {
         assert_eq!(secret, b"test");
     }
 
+    #[cfg(feature = "kernel-keyring")]
+    #[tokio::test]
+    async fn should_get_secret_encrypted_with_kernel_key() {
+        let ss = SecretService::builder()
+            .encryption(EncryptionType::Dh)
+            .kernel_keyring(true)
+            .connect()
+            .await
+            .unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let item = create_test_default_item(&collection).await;
+
+        let secret = item.get_secret().await.unwrap();
+        item.delete().await.unwrap();
+        assert_eq!(secret, b"test");
+    }
+
+    #[tokio::test]
+    async fn should_encrypt_secrets_with_envelope_key() {
+        let ss = SecretService::builder()
+            .encryption(EncryptionType::Dh)
+            .envelope_key(ClientKey::new("test", b"0123456789abcdef").unwrap())
+            .connect()
+            .await
+            .unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let attributes = HashMap::from([("test_envelope", "test")]);
+        let item = collection
+            .create_item("Test", attributes.clone(), b"test", true, "text/plain")
+            .await
+            .unwrap();
+        assert_eq!(item.get_secret().await.unwrap(), b"test");
+        assert_eq!(item.get_secret_string().await.unwrap(), "test");
+        let stored = item.get_attributes().await.unwrap();
+        assert_eq!(stored["envelope_key_id"], "test");
+
+        // the key id survives replacing the attributes
+        item.set_attributes(attributes.clone()).await.unwrap();
+        let stored = item.get_attributes().await.unwrap();
+        assert_eq!(stored["envelope_key_id"], "test");
+
+        // without the key, the secret can't be read
+        let plain = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let found = plain.search_items(attributes.clone()).await.unwrap();
+        let plain_item = &found.unlocked[0];
+        assert_ne!(plain_item.get_secret().await.unwrap(), b"test");
+
+        // secrets stored in the clear are read as they are
+        plain_item.set_secret(b"clear", "text/plain").await.unwrap();
+        assert_eq!(item.get_secret().await.unwrap(), b"clear");
+        item.set_secret(b"sealed", "text/plain").await.unwrap();
+        assert_ne!(plain_item.get_secret().await.unwrap(), b"sealed");
+        assert_eq!(item.get_secret().await.unwrap(), b"sealed");
+
+        let other = SecretService::builder()
+            .encryption(EncryptionType::Plain)
+            .envelope_key(ClientKey::new("other", b"0123456789abcdef").unwrap())
+            .connect()
+            .await
+            .unwrap();
+        let found = other.search_items(attributes).await.unwrap();
+        let err = found.unlocked[0].get_secret().await.unwrap_err();
+        assert!(matches!(err.without_context(), Error::Crypto(_)));
+        item.delete().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_reject_invalid_content_type() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let item = create_test_default_item(&collection).await;
+
+        assert!(matches!(
+            item.set_secret(b"new", "text plain").await,
+            Err(Error::InvalidContentType)
+        ));
+        assert_eq!(item.get_secret().await.unwrap(), b"test");
+        item.delete().await.unwrap();
+
+        assert!(matches!(
+            collection
+                .create_item("Test", HashMap::new(), b"test", false, "text/")
+                .await,
+            Err(Error::InvalidContentType)
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_get_secret_unlocking() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let item = create_test_default_item(&collection).await;
+
+        assert_eq!(item.get_secret_unlocking().await.unwrap(), b"test");
+        item.delete().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore] // should unignore this test this manually, otherwise will constantly prompt during tests.
+    async fn should_get_secret_unlocking_locked_item() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let item = create_test_default_item(&collection).await;
+
+        item.lock().await.unwrap();
+        assert_eq!(item.get_secret_unlocking().await.unwrap(), b"test");
+        assert!(!item.is_locked().await.unwrap());
+        item.delete().await.unwrap();
+    }
+
     #[tokio::test]
     async fn should_get_secret_content_type() {
         let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
@@ -334,6 +1110,64 @@ mod test {
         assert_eq!(content_type, "text/plain".to_owned());
     }
 
+    #[tokio::test]
+    async fn should_create_and_get_secret_string() {
+        let ss = SecretService::connect(EncryptionType::Dh).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let item = collection
+            .create_item_with_string("Test", HashMap::new(), "tëst", false)
+            .await
+            .unwrap();
+
+        assert_eq!(item.get_secret_string().await.unwrap(), "tëst");
+        assert_eq!(item.get_secret_content_type().await.unwrap(), "text/plain");
+
+        item.set_secret_string("new_tëst").await.unwrap();
+        assert_eq!(item.get_secret_string().await.unwrap(), "new_tëst");
+
+        item.set_secret(&[0xff, 0xfe], "text/plain").await.unwrap();
+        assert!(matches!(
+            item.get_secret_string().await,
+            Err(Error::InvalidUtf8)
+        ));
+        item.delete().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_use_default_content_type() {
+        let ss = SecretService::connect(EncryptionType::Dh).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let attributes = HashMap::from([("test_attribute_default_type", "test")]);
+        let item = collection
+            .create_item_simple("Test", attributes.clone(), b"test")
+            .await
+            .unwrap();
+        assert_eq!(
+            item.get_secret_content_type().await.unwrap(),
+            "text/plain; charset=utf8"
+        );
+        assert_eq!(item.get_secret_string().await.unwrap(), "test");
+
+        let custom = SecretService::builder()
+            .default_content_type("application/octet-stream")
+            .connect()
+            .await
+            .unwrap();
+        let collection = custom.get_default_collection().await.unwrap();
+        let item = collection
+            .create_item_simple("Test", attributes.clone(), b"replaced")
+            .await
+            .unwrap();
+        item.set_secret_default(b"new").await.unwrap();
+        assert_eq!(item.get_secret().await.unwrap(), b"new");
+        assert_eq!(
+            item.get_secret_content_type().await.unwrap(),
+            "application/octet-stream"
+        );
+        assert_eq!(collection.search_items(attributes).await.unwrap().len(), 1);
+        item.delete().await.unwrap();
+    }
+
     #[tokio::test]
     async fn should_set_secret() {
         let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
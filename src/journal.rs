@@ -0,0 +1,220 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! A journal of the changes an application makes to the keyring.
+//!
+//! With a sink set through
+//! [SecretServiceBuilder::journal](crate::SecretServiceBuilder::journal),
+//! every change made through the collections and items of a connection is
+//! recorded once the provider has accepted it: creating collections and
+//! items, setting secrets, labels and attributes, and deleting. Changes made
+//! by other applications, or by this one through another connection, aren't.
+//!
+//! Secrets are never recorded. Entries carry a [SecretHash] instead, which
+//! tells whether two secrets are equal, such as for undo tooling checking
+//! that a secret is still the one it set. As the hash isn't keyed, short or
+//! common secrets can be guessed from it, so journals should be kept as
+//! private as the keyring itself.
+//!
+//! ```no_run
+//! # async fn call() -> Result<(), secret_service::Error> {
+//! use secret_service::journal::JournalEntry;
+//! use secret_service::SecretService;
+//! use std::sync::{Arc, Mutex};
+//!
+//! let journal = Arc::new(Mutex::new(Vec::<JournalEntry>::new()));
+//! let ss = SecretService::builder()
+//!     .journal(journal.clone())
+//!     .connect()
+//!     .await?;
+//! // ...
+//! for entry in journal.lock().unwrap().iter() {
+//!     println!("{:?} {} {:?}", entry.time, entry.path, entry.change);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::session::hkdf;
+use crate::ObjectPath;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// Length of a [SecretHash] in bytes.
+const HASH_LEN: usize = 32;
+
+/// Salt of the hashes, so that they don't match hashes of the same secret
+/// taken elsewhere.
+const HASH_SALT: &[u8] = b"secret-service journal";
+
+/// Receives the entries of a journal.
+///
+/// Entries are recorded on the task or thread making the change, right
+/// after the provider returned, so sinks shouldn't block for long.
+pub trait JournalSink: Send + Sync {
+    fn record(&self, entry: JournalEntry);
+}
+
+/// Collects the entries in memory.
+impl JournalSink for Mutex<Vec<JournalEntry>> {
+    fn record(&self, entry: JournalEntry) {
+        self.lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(entry);
+    }
+}
+
+/// A change made to a collection or item.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct JournalEntry {
+    /// When the provider accepted the change.
+    pub time: SystemTime,
+    /// The collection or item changed.
+    pub path: ObjectPath,
+    pub change: Change,
+}
+
+/// What was changed, see [JournalEntry].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Change {
+    /// A collection was created.
+    CollectionCreated { label: String },
+    /// An item was created in `collection`.
+    ItemCreated {
+        collection: ObjectPath,
+        label: String,
+        secret: SecretHash,
+    },
+    /// The secret of an item was set.
+    SecretSet(SecretHash),
+    /// The label of a collection or item was set.
+    LabelSet(String),
+    /// The attributes of an item were set. Their values may be sensitive,
+    /// so only the names are recorded.
+    AttributesSet(Vec<String>),
+    /// A collection or item was deleted.
+    Deleted,
+}
+
+/// A hash of a secret, recorded in place of its value.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SecretHash([u8; HASH_LEN]);
+
+impl SecretHash {
+    /// Hashes `secret`, as stored by the application, before any envelope
+    /// encryption.
+    pub fn new(secret: &[u8]) -> Self {
+        let mut hash = [0; HASH_LEN];
+        hkdf(secret.to_vec(), Some(HASH_SALT), &mut hash);
+        SecretHash(hash)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Display for SecretHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+impl fmt::Debug for SecretHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretHash({self})")
+    }
+}
+
+/// Returns the sorted names of `attributes`, for [Change::AttributesSet].
+pub(crate) fn attribute_names(attributes: &HashMap<&str, &str>) -> Vec<String> {
+    let mut names: Vec<String> = attributes.keys().map(|&name| name.to_owned()).collect();
+    names.sort_unstable();
+    names
+}
+
+/// The sink of a connection, wrapped for [Config](crate::builder::Config)'s
+/// `Debug` implementation.
+pub(crate) struct Journal(pub(crate) Arc<dyn JournalSink>);
+
+impl Journal {
+    pub(crate) fn record(&self, path: ObjectPath, change: Change) {
+        self.0.record(JournalEntry {
+            time: SystemTime::now(),
+            path,
+            change,
+        });
+    }
+}
+
+impl fmt::Debug for Journal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Journal")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{EncryptionType, SecretService};
+
+    #[test]
+    fn should_hash_secrets() {
+        assert_eq!(SecretHash::new(b"test"), SecretHash::new(b"test"));
+        assert_ne!(SecretHash::new(b"test"), SecretHash::new(b"other"));
+        assert_eq!(SecretHash::new(b"test").to_string().len(), 2 * HASH_LEN);
+    }
+
+    #[tokio::test]
+    async fn should_journal_changes() {
+        let journal = Arc::new(Mutex::new(Vec::new()));
+        let ss = SecretService::builder()
+            .encryption(EncryptionType::Plain)
+            .journal(journal.clone())
+            .connect()
+            .await
+            .unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let item = collection
+            .create_item("Test", HashMap::new(), b"test", false, "text/plain")
+            .await
+            .unwrap();
+        item.set_secret(b"other", "text/plain").await.unwrap();
+        item.set_attributes(HashMap::from([("test_attribute_journal", "test_value")]))
+            .await
+            .unwrap();
+        item.delete().await.unwrap();
+
+        let changes: Vec<_> = journal
+            .lock()
+            .unwrap()
+            .drain(..)
+            .inspect(|entry| assert_eq!(entry.path, item.path()))
+            .map(|entry| entry.change)
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                Change::ItemCreated {
+                    collection: collection.path(),
+                    label: "Test".to_owned(),
+                    secret: SecretHash::new(b"test"),
+                },
+                Change::SecretSet(SecretHash::new(b"other")),
+                Change::AttributesSet(vec!["test_attribute_journal".to_owned()]),
+                Change::Deleted,
+            ]
+        );
+    }
+}
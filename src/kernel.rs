@@ -0,0 +1,367 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! A fallback secret store in the Linux kernel keyring.
+//!
+//! Headless hosts often run no Secret Service provider at all. For them, the
+//! `kernel-keyring` feature provides [KernelKeyring], which keeps items in a
+//! kernel keyring (see `keyrings(7)`) and mirrors the item related methods of
+//! [blocking::Collection](crate::blocking::Collection) and
+//! [blocking::Item](crate::blocking::Item), so the same application code can
+//! run against either. [KernelKeyring] also implements
+//! [CredentialBackend](crate::CredentialBackend), as does the async
+//! [SecretService](crate::SecretService), so code written against that trait
+//! can use either as well. The fallback is never used implicitly:
+//!
+//! ```no_run
+//! # use secret_service::blocking::SecretService;
+//! # use secret_service::kernel::KernelKeyring;
+//! # use secret_service::{EncryptionType, Error};
+//! # use std::collections::HashMap;
+//! # fn call() -> Result<(), Error> {
+//! match SecretService::connect(EncryptionType::Dh) {
+//!     Ok(ss) => {
+//!         let collection = ss.get_default_collection()?;
+//!         collection.create_item("label", HashMap::new(), b"secret", true, "text/plain")?;
+//!     }
+//!     Err(Error::Unavailable) => {
+//!         let keyring = KernelKeyring::user()?;
+//!         keyring.create_item("label", HashMap::new(), b"secret", true, "text/plain")?;
+//!     }
+//!     Err(err) => return Err(err),
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Each item is stored as a `user` key holding its label, attributes, content
+//! type and secret. Searches read every item of the keyring, so they are only
+//! suited to a modest number of items. Secrets are protected by the kernel's
+//! key permissions rather than encrypted, and keys hold at most 32 KiB.
+
+use crate::keyring::{self, Serial, KEY_SPEC_SESSION_KEYRING, KEY_SPEC_USER_KEYRING};
+use crate::util;
+use crate::Error;
+
+use rand::{rngs::OsRng, Rng};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ffi::CString;
+use zbus::zvariant::{self, serialized, Type, LE};
+
+/// Prefix of the descriptions of the keys holding items.
+const DESCRIPTION_PREFIX: &str = "secret-service:item:";
+
+/// The contents of an item, serialized into a key's payload.
+#[derive(Debug, Serialize, Deserialize, Type)]
+struct Entry {
+    label: String,
+    attributes: HashMap<String, String>,
+    content_type: String,
+    secret: Vec<u8>,
+}
+
+impl Entry {
+    fn matches(&self, attributes: &HashMap<&str, &str>) -> bool {
+        attributes
+            .iter()
+            .all(|(key, value)| self.attributes.get(*key).map(String::as_str) == Some(*value))
+    }
+}
+
+fn context() -> serialized::Context {
+    serialized::Context::new_dbus(LE, 0)
+}
+
+/// A kernel keyring holding items.
+#[derive(Debug)]
+pub struct KernelKeyring {
+    keyring: Serial,
+}
+
+impl KernelKeyring {
+    /// Opens the user keyring, which is shared by all processes of the user
+    /// and lives as long as any of them runs.
+    pub fn user() -> Result<Self, Error> {
+        Self::open(KEY_SPEC_USER_KEYRING)
+    }
+
+    /// Opens the session keyring, which is inherited by child processes and
+    /// lives as long as the login session.
+    pub fn session() -> Result<Self, Error> {
+        Self::open(KEY_SPEC_SESSION_KEYRING)
+    }
+
+    fn open(keyring: Serial) -> Result<Self, Error> {
+        let keyring = keyring::keyring_id(keyring).map_err(Error::KernelKeyring)?;
+        Ok(KernelKeyring { keyring })
+    }
+
+    /// Get all items in the keyring.
+    pub fn get_all_items(&self) -> Result<Vec<KernelItem<'_>>, Error> {
+        Ok(self.entries()?.into_iter().map(|(item, _)| item).collect())
+    }
+
+    /// Search for items in the keyring by attributes.
+    pub fn search_items(
+        &self,
+        attributes: HashMap<&str, &str>,
+    ) -> Result<Vec<KernelItem<'_>>, Error> {
+        Ok(self
+            .entries()?
+            .into_iter()
+            .filter(|(_, entry)| entry.matches(&attributes))
+            .map(|(item, _)| item)
+            .collect())
+    }
+
+    /// Creates a new item.
+    ///
+    /// With `replace` set, an item with exactly the same attributes is
+    /// overwritten instead.
+    pub fn create_item(
+        &self,
+        label: &str,
+        attributes: HashMap<&str, &str>,
+        secret: &[u8],
+        replace: bool,
+        content_type: &str,
+    ) -> Result<KernelItem<'_>, Error> {
+        util::validate_content_type(content_type)?;
+        let entry = Entry {
+            label: label.to_owned(),
+            attributes: attributes
+                .into_iter()
+                .map(|(key, value)| (key.to_owned(), value.to_owned()))
+                .collect(),
+            content_type: content_type.to_owned(),
+            secret: secret.to_vec(),
+        };
+
+        let existing = if replace {
+            self.entries()?
+                .into_iter()
+                .find(|(_, existing)| existing.attributes == entry.attributes)
+        } else {
+            None
+        };
+
+        let description = match existing {
+            Some((item, _)) => item.description()?,
+            None => {
+                let id: [u8; 16] = OsRng.gen();
+                let id: String = id.iter().map(|byte| format!("{byte:02x}")).collect();
+                format!("{DESCRIPTION_PREFIX}{id}")
+            }
+        };
+        self.write(&description, &entry)
+    }
+
+    /// Returns every item of the keyring, along with its contents.
+    fn entries(&self) -> Result<Vec<(KernelItem<'_>, Entry)>, Error> {
+        let serials = keyring::list_keyring(self.keyring).map_err(Error::KernelKeyring)?;
+
+        let mut entries = Vec::new();
+        for serial in serials {
+            // the keyring may hold unrelated keys, or keys removed since
+            // listing, which are skipped
+            match keyring::describe_key(serial) {
+                Ok(description) if description.starts_with(DESCRIPTION_PREFIX) => {}
+                _ => continue,
+            }
+            let item = KernelItem {
+                keyring: self,
+                serial,
+            };
+            if let Ok(entry) = item.entry() {
+                entries.push((item, entry));
+            }
+        }
+        Ok(entries)
+    }
+
+    fn write(&self, description: &str, entry: &Entry) -> Result<KernelItem<'_>, Error> {
+        let description = CString::new(description).expect("descriptions don't contain NUL");
+        let payload = zvariant::to_bytes(context(), entry)?;
+        let serial =
+            keyring::add_key(&description, &payload, self.keyring).map_err(Error::KernelKeyring)?;
+        Ok(KernelItem {
+            keyring: self,
+            serial,
+        })
+    }
+}
+
+/// An item stored in a [KernelKeyring].
+#[derive(Debug)]
+pub struct KernelItem<'a> {
+    keyring: &'a KernelKeyring,
+    serial: Serial,
+}
+
+impl<'a> KernelItem<'a> {
+    fn description(&self) -> Result<String, Error> {
+        keyring::describe_key(self.serial).map_err(Error::KernelKeyring)
+    }
+
+    fn entry(&self) -> Result<Entry, Error> {
+        let payload = keyring::read_key(self.serial).map_err(Error::KernelKeyring)?;
+        let (entry, _) = serialized::Data::new(payload, context()).deserialize()?;
+        Ok(entry)
+    }
+
+    fn update(&self, update: impl FnOnce(&mut Entry)) -> Result<(), Error> {
+        let mut entry = self.entry()?;
+        update(&mut entry);
+        // adding a key under the same description replaces its payload
+        self.keyring.write(&self.description()?, &entry)?;
+        Ok(())
+    }
+
+    pub fn get_attributes(&self) -> Result<HashMap<String, String>, Error> {
+        Ok(self.entry()?.attributes)
+    }
+
+    pub fn set_attributes(&self, attributes: HashMap<&str, &str>) -> Result<(), Error> {
+        self.update(|entry| {
+            entry.attributes = attributes
+                .into_iter()
+                .map(|(key, value)| (key.to_owned(), value.to_owned()))
+                .collect();
+        })
+    }
+
+    pub fn get_label(&self) -> Result<String, Error> {
+        Ok(self.entry()?.label)
+    }
+
+    pub fn set_label(&self, new_label: &str) -> Result<(), Error> {
+        self.update(|entry| entry.label = new_label.to_owned())
+    }
+
+    /// Deletes the item from the keyring.
+    pub fn delete(&self) -> Result<(), Error> {
+        keyring::remove_key(self.serial, self.keyring.keyring).map_err(Error::KernelKeyring)
+    }
+
+    pub fn get_secret(&self) -> Result<Vec<u8>, Error> {
+        Ok(self.entry()?.secret)
+    }
+
+    pub fn get_secret_content_type(&self) -> Result<String, Error> {
+        Ok(self.entry()?.content_type)
+    }
+
+    /// Returns the secret as a string.
+    ///
+    /// Fails with [Error::InvalidUtf8] if the secret isn't valid UTF-8, or if
+    /// its content type declares a different charset.
+    pub fn get_secret_string(&self) -> Result<String, Error> {
+        let entry = self.entry()?;
+        util::secret_to_string(entry.secret, &entry.content_type)
+    }
+
+    pub fn set_secret(&self, secret: &[u8], content_type: &str) -> Result<(), Error> {
+        util::validate_content_type(content_type)?;
+        self.update(|entry| {
+            entry.secret = secret.to_vec();
+            entry.content_type = content_type.to_owned();
+        })
+    }
+
+    /// Sets the secret to a string, stored with a `text/plain` content type.
+    pub fn set_secret_string(&self, secret: &str) -> Result<(), Error> {
+        self.set_secret(secret.as_bytes(), util::TEXT_CONTENT_TYPE)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // the session keyring of a test process may be shared with other test
+    // runs, so items are told apart by a random attribute
+    fn test_attributes(id: &str) -> HashMap<&str, &str> {
+        HashMap::from([("test", "kernel"), ("id", id)])
+    }
+
+    fn random_id() -> String {
+        format!("{:x}", OsRng.gen::<u64>())
+    }
+
+    #[test]
+    fn should_create_and_search_items() {
+        let keyring = KernelKeyring::session().unwrap();
+        let id = random_id();
+        let item = keyring
+            .create_item("Test", test_attributes(&id), b"test", false, "text/plain")
+            .unwrap();
+
+        let found = keyring.search_items(test_attributes(&id)).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].get_label().unwrap(), "Test");
+        assert_eq!(found[0].get_secret().unwrap(), b"test");
+        assert_eq!(found[0].get_secret_content_type().unwrap(), "text/plain");
+        assert_eq!(
+            found[0].get_attributes().unwrap(),
+            HashMap::from([("test".into(), "kernel".into()), ("id".into(), id.clone())])
+        );
+
+        item.delete().unwrap();
+        assert!(keyring
+            .search_items(test_attributes(&id))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn should_replace_item_with_same_attributes() {
+        let keyring = KernelKeyring::session().unwrap();
+        let id = random_id();
+        let first = keyring
+            .create_item("Test", test_attributes(&id), b"first", true, "text/plain")
+            .unwrap();
+        let second = keyring
+            .create_item("Test", test_attributes(&id), b"second", true, "text/plain")
+            .unwrap();
+
+        assert_eq!(first.serial, second.serial);
+        assert_eq!(first.get_secret().unwrap(), b"second");
+        assert_eq!(keyring.search_items(test_attributes(&id)).unwrap().len(), 1);
+        second.delete().unwrap();
+    }
+
+    #[test]
+    fn should_update_item() {
+        let keyring = KernelKeyring::session().unwrap();
+        let id = random_id();
+        let item = keyring
+            .create_item("Test", test_attributes(&id), b"test", false, "text/plain")
+            .unwrap();
+
+        item.set_label("Relabeled").unwrap();
+        item.set_secret("tëst".as_bytes(), "text/plain; charset=utf8")
+            .unwrap();
+        assert_eq!(item.get_label().unwrap(), "Relabeled");
+        assert_eq!(item.get_secret_string().unwrap(), "tëst");
+
+        item.set_attributes(HashMap::from([("test", "kernel"), ("moved", &id)]))
+            .unwrap();
+        assert!(keyring
+            .search_items(test_attributes(&id))
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            keyring
+                .search_items(HashMap::from([("moved", id.as_str())]))
+                .unwrap()
+                .len(),
+            1
+        );
+        item.delete().unwrap();
+    }
+}
@@ -0,0 +1,205 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+// Minimal bindings to the Linux kernel key retention service (see
+// keyrings(7)), used to keep key material out of the process' memory and as
+// a fallback store for secrets.
+
+use std::ffi::CStr;
+use std::io;
+
+/// Serial number identifying a key or keyring.
+pub(crate) type Serial = libc::c_long;
+
+// from linux/keyctl.h
+pub(crate) const KEY_SPEC_PROCESS_KEYRING: Serial = -2;
+pub(crate) const KEY_SPEC_SESSION_KEYRING: Serial = -3;
+pub(crate) const KEY_SPEC_USER_KEYRING: Serial = -4;
+const KEYCTL_GET_KEYRING_ID: libc::c_long = 0;
+const KEYCTL_DESCRIBE: libc::c_long = 6;
+const KEYCTL_UNLINK: libc::c_long = 9;
+const KEYCTL_READ: libc::c_long = 11;
+const KEYCTL_INVALIDATE: libc::c_long = 21;
+
+const KEY_TYPE_USER: &[u8] = b"user\0";
+
+fn check(ret: libc::c_long) -> io::Result<libc::c_long> {
+    if ret < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(ret)
+    }
+}
+
+/// Resolves a special keyring such as [KEY_SPEC_USER_KEYRING] to its
+/// serial, creating the keyring if needed.
+pub(crate) fn keyring_id(keyring: Serial) -> io::Result<Serial> {
+    // SAFETY: no pointers are passed
+    check(unsafe { libc::syscall(libc::SYS_keyctl, KEYCTL_GET_KEYRING_ID, keyring, 1) })
+}
+
+/// Adds a `user` key holding `payload` to `keyring`.
+///
+/// If the keyring already holds a `user` key with the same description, its
+/// payload is replaced instead, keeping its serial.
+pub(crate) fn add_key(description: &CStr, payload: &[u8], keyring: Serial) -> io::Result<Serial> {
+    // SAFETY: all pointers are valid for the given lengths, and the strings
+    // are NUL terminated
+    check(unsafe {
+        libc::syscall(
+            libc::SYS_add_key,
+            KEY_TYPE_USER.as_ptr(),
+            description.as_ptr(),
+            payload.as_ptr(),
+            payload.len(),
+            keyring,
+        )
+    })
+}
+
+/// Reads the payload of a key into `buf`, returning the payload's length.
+///
+/// If `buf` is too small, it's left untouched.
+pub(crate) fn read_key_into(serial: Serial, buf: &mut [u8]) -> io::Result<usize> {
+    // SAFETY: `buf` is valid for writes of its length
+    let len = check(unsafe {
+        libc::syscall(
+            libc::SYS_keyctl,
+            KEYCTL_READ,
+            serial,
+            buf.as_mut_ptr(),
+            buf.len(),
+        )
+    })?;
+    Ok(len as usize)
+}
+
+/// Reads the whole payload of a key, or the serials linked in a keyring.
+pub(crate) fn read_key(serial: Serial) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    loop {
+        let len = read_key_into(serial, &mut buf)?;
+        if len <= buf.len() {
+            buf.truncate(len);
+            return Ok(buf);
+        }
+        // the payload may have grown in between, so read until it fits
+        buf.resize(len, 0);
+    }
+}
+
+/// Returns the description a key was created with.
+pub(crate) fn describe_key(serial: Serial) -> io::Result<String> {
+    let mut buf = Vec::new();
+    loop {
+        // SAFETY: `buf` is valid for writes of its length
+        let len = check(unsafe {
+            libc::syscall(
+                libc::SYS_keyctl,
+                KEYCTL_DESCRIBE,
+                serial,
+                buf.as_mut_ptr(),
+                buf.len(),
+            )
+        })? as usize;
+        if len <= buf.len() {
+            buf.truncate(len);
+            break;
+        }
+        buf.resize(len, 0);
+    }
+
+    // formatted as "type;uid;gid;perm;description\0"
+    let described = CStr::from_bytes_until_nul(&buf)
+        .map_err(|_| io::Error::from(io::ErrorKind::InvalidData))?
+        .to_string_lossy();
+    Ok(described
+        .splitn(5, ';')
+        .nth(4)
+        .unwrap_or_default()
+        .to_owned())
+}
+
+/// Returns the serials of the keys linked in `keyring`.
+pub(crate) fn list_keyring(keyring: Serial) -> io::Result<Vec<Serial>> {
+    let payload = read_key(keyring)?;
+    Ok(payload
+        .chunks_exact(4)
+        .map(|serial| i32::from_ne_bytes(serial.try_into().unwrap()).into())
+        .collect())
+}
+
+/// Removes a key from the kernel, or at least from `keyring` on kernels too
+/// old to invalidate keys.
+pub(crate) fn remove_key(serial: Serial, keyring: Serial) -> io::Result<()> {
+    // SAFETY: no pointers are passed
+    unsafe {
+        // invalidating needs Linux 3.5, fall back to unlinking
+        if libc::syscall(libc::SYS_keyctl, KEYCTL_INVALIDATE, serial) < 0 {
+            check(libc::syscall(
+                libc::SYS_keyctl,
+                KEYCTL_UNLINK,
+                serial,
+                keyring,
+            ))?;
+        }
+    }
+    Ok(())
+}
+
+/// A `user` key held in the kernel's process keyring.
+///
+/// The key is removed from the kernel when dropped.
+#[derive(Debug)]
+pub(crate) struct KernelKey {
+    serial: Serial,
+}
+
+impl KernelKey {
+    /// Adds a key holding `payload` to the process keyring.
+    pub(crate) fn add(description: &CStr, payload: &[u8]) -> io::Result<Self> {
+        let serial = add_key(description, payload, KEY_SPEC_PROCESS_KEYRING)?;
+        Ok(KernelKey { serial })
+    }
+
+    /// Reads the payload into `buf`, returning the payload's length.
+    ///
+    /// If `buf` is too small, it's left untouched.
+    pub(crate) fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        read_key_into(self.serial, buf)
+    }
+}
+
+impl Drop for KernelKey {
+    fn drop(&mut self) {
+        let _ = remove_key(self.serial, KEY_SPEC_PROCESS_KEYRING);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_store_and_remove_key() {
+        let payload = [7; 16];
+        let description = CStr::from_bytes_with_nul(b"secret-service:test\0").unwrap();
+        let key = KernelKey::add(description, &payload).unwrap();
+
+        let mut buf = [0; 16];
+        assert_eq!(key.read(&mut buf).unwrap(), 16);
+        assert_eq!(buf, payload);
+        assert_eq!(describe_key(key.serial).unwrap(), "secret-service:test");
+
+        let serial = key.serial;
+        let keyring = keyring_id(KEY_SPEC_PROCESS_KEYRING).unwrap();
+        assert!(list_keyring(keyring).unwrap().contains(&serial));
+
+        drop(key);
+        assert!(read_key(serial).is_err());
+    }
+}
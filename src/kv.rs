@@ -0,0 +1,149 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+// A string map stored as items of a collection.
+
+use crate::util::{limit_concurrency, TEXT_CONTENT_TYPE};
+use crate::{Collection, ConflictPolicy, Error};
+
+use std::collections::HashMap;
+
+/// The attribute holding the key of an entry, unless set with
+/// [KvStore::with_attribute].
+pub(crate) const KV_KEY_ATTRIBUTE: &str = "kv_key";
+
+/// A map from strings to secret strings, stored in a collection.
+///
+/// Each entry is an item labeled with its key, holding the key in a single
+/// attribute, `kv_key` by default, and the value as a `text/plain` secret.
+/// Applications sharing a collection can keep their entries apart by using
+/// an attribute of their own.
+///
+/// ```no_run
+/// # async fn call(ss: &secret_service::SecretService<'_>) -> Result<(), secret_service::Error> {
+/// use secret_service::KvStore;
+///
+/// let store = KvStore::with_attribute(ss.get_default_collection().await?, "my-app-key");
+/// store.insert("api-token", "hunter2").await?;
+/// assert_eq!(store.get("api-token").await?.as_deref(), Some("hunter2"));
+/// # Ok(())
+/// # }
+/// ```
+pub struct KvStore<'a> {
+    collection: Collection<'a>,
+    attribute: String,
+}
+
+impl<'a> KvStore<'a> {
+    pub fn new(collection: Collection<'a>) -> Self {
+        Self::with_attribute(collection, KV_KEY_ATTRIBUTE)
+    }
+
+    /// Creates a store keeping its keys in `attribute`.
+    pub fn with_attribute(collection: Collection<'a>, attribute: &str) -> Self {
+        KvStore {
+            collection,
+            attribute: attribute.to_owned(),
+        }
+    }
+
+    /// Returns the value of `key`, if any.
+    pub async fn get(&self, key: &str) -> Result<Option<String>, Error> {
+        let items = self.collection.search_items(self.attributes(key)).await?;
+        match items.first() {
+            Some(item) => item.get_secret_string().await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Sets the value of `key`, replacing any previous one.
+    pub async fn insert(&self, key: &str, value: &str) -> Result<(), Error> {
+        self.collection
+            .create_item(
+                key,
+                self.attributes(key),
+                value.as_bytes(),
+                ConflictPolicy::Replace,
+                TEXT_CONTENT_TYPE,
+            )
+            .await
+            .map(drop)
+    }
+
+    /// Removes `key`, returning whether it was there.
+    pub async fn remove(&self, key: &str) -> Result<bool, Error> {
+        let items = self.collection.search_items(self.attributes(key)).await?;
+        for item in &items {
+            item.delete().await?;
+        }
+        Ok(!items.is_empty())
+    }
+
+    /// Returns the keys of the store, sorted.
+    ///
+    /// The attributes of every item of the collection are fetched, as the
+    /// specification can't search for items holding an attribute whatever
+    /// its value.
+    pub async fn keys(&self) -> Result<Vec<String>, Error> {
+        let items = self.collection.get_all_items().await?;
+        let attributes = futures_util::future::try_join_all(
+            items
+                .iter()
+                .map(|item| limit_concurrency(self.collection.config, item.get_attributes())),
+        )
+        .await?;
+        Ok(keys_of(&self.attribute, attributes))
+    }
+
+    /// Returns the collection the store is kept in.
+    pub fn into_inner(self) -> Collection<'a> {
+        self.collection
+    }
+
+    fn attributes<'k>(&'k self, key: &'k str) -> HashMap<&'k str, &'k str> {
+        HashMap::from([(self.attribute.as_str(), key)])
+    }
+}
+
+/// Returns the sorted keys found in `attribute`.
+pub(crate) fn keys_of(
+    attribute: &str,
+    attributes: impl IntoIterator<Item = HashMap<String, String>>,
+) -> Vec<String> {
+    let mut keys: Vec<_> = attributes
+        .into_iter()
+        .filter_map(|mut attributes| attributes.remove(attribute))
+        .collect();
+    keys.sort_unstable();
+    keys.dedup();
+    keys
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{EncryptionType, SecretService};
+
+    #[tokio::test]
+    async fn should_store_strings() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let store = KvStore::with_attribute(collection, "test_attribute_kv");
+
+        assert_eq!(store.get("first").await.unwrap(), None);
+        store.insert("first", "one").await.unwrap();
+        store.insert("second", "two").await.unwrap();
+        store.insert("first", "uno").await.unwrap();
+        assert_eq!(store.get("first").await.unwrap().as_deref(), Some("uno"));
+        assert_eq!(store.keys().await.unwrap(), ["first", "second"]);
+
+        assert!(store.remove("first").await.unwrap());
+        assert!(!store.remove("first").await.unwrap());
+        assert!(store.remove("second").await.unwrap());
+        assert!(store.keys().await.unwrap().is_empty());
+    }
+}
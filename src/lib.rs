@@ -93,6 +93,9 @@
 //! # }
 //! ```
 //!
+//! Further connection options, such as the window identifier used to parent
+//! prompts, are set through [SecretService::builder].
+//!
 //! Once the SecretService struct is initialized, it can be used to navigate to a collection.
 //! Items can also be directly searched for without getting a collection first.
 //!
@@ -117,39 +120,155 @@
 //! Specifics in SecretService API Draft Proposal:
 //! <https://standards.freedesktop.org/secret-service/>
 //!
+//! The `deny-plain` feature guarantees that a binary never sends secrets
+//! unencrypted, by refusing to open [EncryptionType::Plain] sessions.
+//!
+//! With the `kernel-keyring` feature, the AES key of an encrypted session can
+//! be kept in the Linux kernel keyring instead of the process' memory, see
+//! `SecretServiceBuilder::kernel_keyring`. The same feature provides a
+//! fallback store for hosts without a provider, see the `kernel` module.
+//!
+//! Attributes are stored in the clear by providers. The values of sensitive
+//! ones can be encrypted on the client, see
+//! [SecretServiceBuilder::encrypt_attributes]. Secrets themselves can be
+//! encrypted with a key held by the application, so that other applications
+//! can't read them from an unlocked keyring, see
+//! [SecretServiceBuilder::envelope_key].
+//!
 //! ### Async
 //!
 //! This crate, following `zbus`, is async by default. If you want a synchronous interface
 //! that blocks, see the [blocking] module instead.
+//!
+//! ### Cancellation
+//!
+//! Any future returned by the async API can be dropped to cancel the
+//! operation, for instance when the user presses a "Cancel" button while
+//! waiting for it in a `select`. Waiting stops right away, and a prompt shown
+//! for the operation is dismissed. Calls that already reached the provider
+//! aren't rolled back though, so a cancelled operation working through
+//! several objects may have been partially applied. The blocking API can't
+//! be cancelled.
 //
 // Util currently has interfaces (dbus method namespace) to make it easier to call methods.
 // Util contains function to execute prompts (used in many collection and item methods, like
 // delete)
 
+pub mod algorithm;
+pub mod api;
+pub mod attributes;
+mod backend;
+mod batch;
 pub mod blocking;
+mod builder;
+mod cache;
+mod cipher;
+pub mod compression;
+pub mod dbus;
+mod dump;
 mod error;
+mod event;
+#[cfg(feature = "regex")]
+mod grep;
+#[cfg(feature = "gtk4")]
+pub mod gtk;
+mod history;
+mod import;
+pub mod journal;
+#[cfg(feature = "kernel-keyring")]
+pub mod kernel;
+#[cfg(feature = "kernel-keyring")]
+mod keyring;
+mod kv;
+mod lifetime;
+pub mod maintenance;
+mod merge;
+pub mod migration;
+mod mirror;
+mod object;
+mod path;
+mod progress;
+mod prompt;
 mod proxy;
+pub mod query;
+mod reconnect;
+#[cfg(feature = "replay")]
+pub mod replay;
+mod rotation;
+mod search;
+mod server_info;
 mod session;
+mod snapshot;
 mod ss;
+#[cfg(all(unix, any(test, feature = "test-harness")))]
+pub mod testing;
+mod transaction;
+mod trash;
+pub mod typestate;
 mod util;
+mod warm;
+mod window;
 
 mod collection;
-pub use collection::Collection;
+pub use collection::{Collection, ConflictPolicy};
+
+pub use backend::CredentialBackend;
+
+pub use batch::BatchOutcome;
+
+pub use builder::{ConnectOptions, SecretServiceBuilder};
+
+pub use cipher::ClientKey;
+
+pub use dump::{CollectionDump, ItemDump, TreeDump};
 
 pub use error::Error;
 
+pub use event::{CollectionEvent, ItemEvent, SecretServiceEvent};
+
+pub use history::SecretVersion;
+
+pub use import::{Imported, NewItem};
+
+pub use kv::KvStore;
+
 mod item;
-pub use item::Item;
+pub use item::{sort_by_created, sort_by_label, sort_by_modified, Item};
+
+pub use mirror::{CollectionMirror, ItemMetadata};
 
-pub use session::EncryptionType;
+pub use object::{Object, ObjectRef};
 
+pub use path::ObjectPath;
+
+pub use progress::Progress;
+
+pub use prompt::{Prompt, PromptCanceller, PromptCompletion, Prompter};
+
+pub use snapshot::{CollectionSnapshot, ItemSnapshot, SnapshotDiff};
+
+pub use search::{ItemSearch, SearchOrder};
+
+pub use server_info::ServerInfo;
+
+pub use session::{EncryptionType, Session, SessionInfo};
+
+pub use transaction::{Transaction, TransactionReport};
+
+pub use warm::WarmConnection;
+
+pub use window::WindowIdentifier;
+
+use crate::builder::Config;
 use crate::proxy::service::ServiceProxy;
-use crate::session::Session;
-use crate::ss::SS_COLLECTION_LABEL;
+use crate::query::Query;
+use crate::trash::TRASH_ALIAS;
 use crate::util::exec_prompt;
-use futures_util::TryFutureExt;
-use std::collections::HashMap;
-use zbus::zvariant::{ObjectPath, Value};
+use futures_util::future::BoxFuture;
+use futures_util::{Stream, StreamExt, TryFutureExt};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::num::NonZeroUsize;
+use zbus::zvariant::OwnedObjectPath;
 
 /// Secret Service Struct.
 ///
@@ -162,6 +281,7 @@ pub struct SecretService<'a> {
     conn: zbus::Connection,
     session: Session,
     service_proxy: ServiceProxy<'a>,
+    config: Config,
 }
 
 /// Used to indicate locked and unlocked items in the
@@ -172,24 +292,347 @@ pub struct SearchItemsResult<T> {
     pub locked: Vec<T>,
 }
 
+impl SearchItemsResult<Item<'_>> {
+    /// Sorts both lists with [sort_by_modified].
+    pub async fn sort_by_modified(&mut self) -> Result<(), Error> {
+        futures_util::try_join!(
+            sort_by_modified(&mut self.unlocked),
+            sort_by_modified(&mut self.locked)
+        )?;
+        Ok(())
+    }
+
+    /// Sorts both lists with [sort_by_created].
+    pub async fn sort_by_created(&mut self) -> Result<(), Error> {
+        futures_util::try_join!(
+            sort_by_created(&mut self.unlocked),
+            sort_by_created(&mut self.locked)
+        )?;
+        Ok(())
+    }
+
+    /// Sorts both lists with [sort_by_label].
+    pub async fn sort_by_label(&mut self) -> Result<(), Error> {
+        futures_util::try_join!(
+            sort_by_label(&mut self.unlocked),
+            sort_by_label(&mut self.locked)
+        )?;
+        Ok(())
+    }
+}
+
+impl SearchItemsResult<blocking::Item<'_>> {
+    /// Sorts both lists with [blocking::sort_by_modified].
+    pub fn sort_by_modified(&mut self) -> Result<(), Error> {
+        blocking::sort_by_modified(&mut self.unlocked)?;
+        blocking::sort_by_modified(&mut self.locked)
+    }
+
+    /// Sorts both lists with [blocking::sort_by_created].
+    pub fn sort_by_created(&mut self) -> Result<(), Error> {
+        blocking::sort_by_created(&mut self.unlocked)?;
+        blocking::sort_by_created(&mut self.locked)
+    }
+
+    /// Sorts both lists with [blocking::sort_by_label].
+    pub fn sort_by_label(&mut self) -> Result<(), Error> {
+        blocking::sort_by_label(&mut self.unlocked)?;
+        blocking::sort_by_label(&mut self.locked)
+    }
+}
+
+/// An item returned together with its label and attributes, as returned by
+/// [SecretService::search_items_prefetched] and
+/// [blocking::SecretService::search_items_prefetched].
+#[derive(Debug)]
+pub struct PrefetchedItem<T> {
+    pub item: T,
+    /// The label of the item at the time of the search.
+    pub label: String,
+    /// The attributes of the item at the time of the search.
+    pub attributes: HashMap<String, String>,
+}
+
+/// An item whose label matched a query, as returned by
+/// [SecretService::search_labels] and [Collection::search_labels].
+#[derive(Debug)]
+pub struct LabelMatch<T> {
+    pub item: T,
+    /// The label of the item at the time of the search.
+    pub label: String,
+    /// How well the label matched; higher is better.
+    pub score: u32,
+}
+
+/// How often attributes occur in a collection, as returned by
+/// [Collection::attribute_summary] and
+/// [blocking::Collection::attribute_summary].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AttributeSummary {
+    /// The number of items in the collection.
+    pub items: usize,
+    /// The number of items holding each attribute key.
+    pub keys: BTreeMap<String, usize>,
+    /// The number of items holding each value of each attribute key, only
+    /// filled in when values were asked for.
+    pub values: BTreeMap<String, BTreeMap<String, usize>>,
+}
+
+/// Selects which of an item's timestamps an operation looks at,
+/// for example in [Collection::purge_older_than].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ItemTimestamp {
+    /// The `Created` property of the item.
+    Created,
+    /// The `Modified` property of the item.
+    Modified,
+}
+
 impl<'a> SecretService<'a> {
     /// Create a new `SecretService` instance.
     pub async fn connect(encryption: EncryptionType) -> Result<SecretService<'a>, Error> {
-        let conn = zbus::Connection::session()
+        Self::builder().encryption(encryption).connect().await
+    }
+
+    /// Creates a new `SecretService` instance talking to the provider over
+    /// an existing connection, see [SecretServiceBuilder::connection].
+    pub async fn connect_with(
+        conn: zbus::Connection,
+        encryption: EncryptionType,
+    ) -> Result<SecretService<'a>, Error> {
+        Self::builder()
+            .encryption(encryption)
+            .connection(conn)
+            .connect()
+            .await
+    }
+
+    /// Creates a new `SecretService` instance talking to the provider found
+    /// with `options`, see [ConnectOptions].
+    pub async fn connect_with_options(
+        options: ConnectOptions,
+        encryption: EncryptionType,
+    ) -> Result<SecretService<'a>, Error> {
+        Self::builder()
+            .encryption(encryption)
+            .options(options)
+            .connect()
             .await
-            .map_err(util::handle_conn_error)?;
+    }
 
-        let service_proxy = ServiceProxy::new(&conn)
+    /// Connects and opens the default collection in one go, see
+    /// [SecretServiceBuilder::connect_and_open_default].
+    pub async fn connect_and_open_default(
+        encryption: EncryptionType,
+    ) -> Result<WarmConnection<'a>, Error> {
+        Self::builder()
+            .encryption(encryption)
+            .connect_and_open_default()
             .await
-            .map_err(util::handle_conn_error)?;
+    }
 
-        let session = Session::new(&service_proxy, encryption).await?;
+    /// Returns a [SecretServiceBuilder] for configuring the connection
+    /// before creating a `SecretService` instance.
+    pub fn builder() -> SecretServiceBuilder {
+        SecretServiceBuilder::new()
+    }
 
-        Ok(SecretService {
-            conn,
-            session,
-            service_proxy,
-        })
+    /// Returns the executor running the connection's internal tasks.
+    ///
+    /// Only needs to be ticked by the host app if the connection was made
+    /// with [SecretServiceBuilder::internal_executor] turned off; then calls
+    /// make no progress while it isn't ticked.
+    ///
+    /// ```no_run
+    /// # use secret_service::SecretService;
+    /// # async fn call() {
+    /// let ss = SecretService::builder()
+    ///     .internal_executor(false)
+    ///     .connect()
+    ///     .await
+    ///     .unwrap();
+    /// let executor = ss.executor().clone();
+    /// let ticking = async move {
+    ///     loop {
+    ///         executor.tick().await;
+    ///     }
+    /// };
+    /// // hand `ticking` to the host app's reactor
+    /// # }
+    /// ```
+    pub fn executor(&self) -> &zbus::Executor<'static> {
+        self.conn.executor()
+    }
+
+    /// Connects again and negotiates a new session, the way the builder
+    /// connected.
+    ///
+    /// Once the session bus or the provider restarted, every call fails, as
+    /// the connection or the session it was negotiated on are gone, see
+    /// [Error::is_connection_lost]. A connection handed to the builder is
+    /// kept, and only the session negotiated again. Signal streams received
+    /// before have to be received again, and with
+    /// [SecretServiceBuilder::internal_executor] turned off, the executor of
+    /// the new connection has to be ticked instead.
+    ///
+    /// Reconnecting isn't transparent: it takes `&mut self`, so the
+    /// collections and items handed out by this `SecretService` have to be
+    /// dropped first, and looked up again afterwards.
+    pub async fn reconnect(&mut self) -> Result<(), Error> {
+        let (conn, service_proxy, session) = reconnect::reconnect(&self.config, &self.conn).await?;
+        self.conn = conn;
+        self.service_proxy = service_proxy;
+        self.session = session;
+        Ok(())
+    }
+
+    /// Runs `op`, and if it fails because the connection was lost, connects
+    /// again with [SecretService::reconnect] and runs it once more.
+    ///
+    /// Only use it for operations that can safely run twice, such as
+    /// looking up collections, searching items, or reading labels,
+    /// attributes and secrets. When the connection is lost during a call,
+    /// there is no telling whether the provider carried it out, so running
+    /// `op` again may repeat a change: creating an item with
+    /// [ConflictPolicy::CreateAnyway] may leave a duplicate, and deleting an
+    /// object fails the second time if the first one went through. Call
+    /// [SecretService::reconnect] directly for those, and check what was
+    /// done before retrying.
+    ///
+    /// `op` has to look up the collections and items it uses from the
+    /// `SecretService` it's handed, as those from before reconnecting can't
+    /// be used anymore.
+    ///
+    /// ```no_run
+    /// # async fn call(mut ss: secret_service::SecretService<'_>) -> Result<(), secret_service::Error> {
+    /// let label = ss
+    ///     .with_reconnect(|ss| {
+    ///         Box::pin(async move { ss.get_default_collection().await?.get_label().await })
+    ///     })
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn with_reconnect<T, F>(&mut self, mut op: F) -> Result<T, Error>
+    where
+        F: for<'s> FnMut(&'s SecretService<'a>) -> BoxFuture<'s, Result<T, Error>>,
+    {
+        match op(self).await {
+            Err(err) if err.is_connection_lost() => {
+                self.reconnect().await?;
+                op(self).await
+            }
+            result => result,
+        }
+    }
+
+    /// Describes the session negotiated with the provider, for diagnostics.
+    ///
+    /// With [SecretServiceBuilder::lazy_session], this describes the session
+    /// to be negotiated until a secret is transferred, with the path `/`.
+    pub fn session(&self) -> SessionInfo {
+        self.session.info()
+    }
+
+    /// Parents the prompts shown from now on to `window`, or to no window,
+    /// replacing the one set with [SecretServiceBuilder::window].
+    ///
+    /// Applies to the collections and items created from this
+    /// `SecretService` too, for instance when another window of the
+    /// application gets the focus.
+    pub fn set_window(&self, window: Option<WindowIdentifier>) {
+        self.config.set_window(window);
+    }
+
+    /// Returns a handle cancelling the prompts this `SecretService`, and
+    /// the collections and items created from it, are waiting for, see
+    /// [PromptCanceller].
+    pub fn prompt_canceller(&self) -> PromptCanceller {
+        self.config.prompts.clone()
+    }
+
+    /// Describes the provider, for bug reports and working around the
+    /// quirks of specific providers.
+    ///
+    /// The bus tells which well-known names the provider's connection owns,
+    /// which identify it better than the standard `org.freedesktop.secrets`
+    /// alone, and its process id. Introspecting the service object lists
+    /// the interfaces it implements beyond the specification's. The
+    /// specification defines no way to query a provider's version, so it's
+    /// only known for providers exposing a `Version` property.
+    pub async fn server_info(&self) -> Result<ServerInfo, Error> {
+        server_info::server_info(self).await
+    }
+
+    /// Opens another session with the provider, for operations that should
+    /// use a different kind of session than the one negotiated when
+    /// connecting.
+    ///
+    /// Collections and items use it through their `with_session` methods,
+    /// for example a plain session for bulk reads of non-sensitive secrets
+    /// next to an encrypted one for the rest. The key protection settings
+    /// of the builder don't apply to it.
+    pub async fn open_session(&self, encryption: EncryptionType) -> Result<Session, Error> {
+        Session::new(&self.service_proxy, encryption).await
+    }
+
+    /// Describes every collection and item of the provider, without secrets
+    /// or attribute values, for debugging and bug reports.
+    ///
+    /// With the `serde` feature, the dump can be serialized with any `serde`
+    /// format, for example as JSON, see [TreeDump].
+    pub async fn dump_tree(&self) -> Result<TreeDump, Error> {
+        dump::dump_tree(self).await
+    }
+
+    /// Deletes the collections and items `matcher` selects, such as those
+    /// left behind by test runs, see [maintenance].
+    ///
+    /// With `dry_run`, every object found is listed and nothing is deleted.
+    /// Otherwise `confirm` is called with each object found, which is
+    /// deleted if it returns `true`; the outcome lists the deleted objects,
+    /// each with its own result. Items of a matching collection are deleted
+    /// with it, without being matched or confirmed.
+    pub async fn cleanup(
+        &self,
+        matcher: &maintenance::Matcher,
+        dry_run: bool,
+        confirm: impl FnMut(&ObjectPath, &maintenance::FoundObject) -> bool,
+    ) -> Result<BatchOutcome<maintenance::FoundObject>, Error> {
+        maintenance::cleanup(self, matcher, dry_run, confirm).await
+    }
+
+    /// Re-encrypts the secrets of all items encrypted with the envelope key
+    /// `old` with `new`, see [SecretServiceBuilder::envelope_key].
+    ///
+    /// Items are found by the id of the key they record. Their secrets are
+    /// fetched, re-encrypted and stored `batch_size` items at a time, so
+    /// only a batch of secrets is held in memory at once. Rotated items
+    /// record the id of `new`, which makes the rotation resumable: if it's
+    /// interrupted, running it again carries on with the items left.
+    ///
+    /// Locked items fail with [Error::Locked] in the outcome, so unlock them
+    /// first, for instance with [SecretService::unlock_all].
+    pub async fn rotate_key(
+        &self,
+        old: &ClientKey,
+        new: &ClientKey,
+        batch_size: NonZeroUsize,
+    ) -> Result<BatchOutcome, Error> {
+        rotation::rotate_key(self, old, new, batch_size, |_| ()).await
+    }
+
+    /// Like [SecretService::rotate_key], calling `progress` after each item.
+    pub async fn rotate_key_with_progress(
+        &self,
+        old: &ClientKey,
+        new: &ClientKey,
+        batch_size: NonZeroUsize,
+        progress: impl FnMut(&Progress),
+    ) -> Result<BatchOutcome, Error> {
+        rotation::rotate_key(self, old, new, batch_size, progress).await
     }
 
     /// Get all collections
@@ -201,6 +644,7 @@ impl<'a> SecretService<'a> {
                 self.conn.clone(),
                 &self.session,
                 &self.service_proxy,
+                &self.config,
                 object_path.into(),
             )
         }))
@@ -209,25 +653,63 @@ impl<'a> SecretService<'a> {
         .collect::<Result<_, _>>()
     }
 
+    /// Get all collections as a stream, yielding each collection as soon as
+    /// it's ready.
+    ///
+    /// Unlike [SecretService::get_all_collections], a collection that is slow
+    /// to set up doesn't hold back the others, so collections may be yielded
+    /// in any order.
+    pub async fn collections_stream(
+        &self,
+    ) -> Result<impl Stream<Item = Result<Collection<'_>, Error>> + '_, Error> {
+        let collections = self.service_proxy.collections().await?;
+
+        Ok(collections
+            .into_iter()
+            .map(|object_path| {
+                Collection::new(
+                    self.conn.clone(),
+                    &self.session,
+                    &self.service_proxy,
+                    &self.config,
+                    object_path.into(),
+                )
+            })
+            .collect::<futures_util::stream::FuturesUnordered<_>>())
+    }
+
     /// Get collection by alias.
     ///
     /// Most common would be the `default` alias, but there
     /// is also a specific method for getting the collection
     /// by default alias.
+    ///
+    /// Resolved aliases are cached until the service signals that a
+    /// collection was created, deleted or changed.
     pub async fn get_collection_by_alias(&self, alias: &str) -> Result<Collection<'_>, Error> {
-        let object_path = self.service_proxy.read_alias(alias).await?;
+        let object_path = self.resolve_alias(alias).await?;
+        Ok(Collection::new(
+            self.conn.clone(),
+            &self.session,
+            &self.service_proxy,
+            &self.config,
+            object_path,
+        )
+        .await?
+        .with_alias(alias))
+    }
 
-        if object_path.as_str() == "/" {
-            Err(Error::NoResult)
-        } else {
-            Collection::new(
-                self.conn.clone(),
-                &self.session,
-                &self.service_proxy,
-                object_path,
-            )
-            .await
-        }
+    /// Get the object path of the collection behind an alias, cached like
+    /// in [SecretService::get_collection_by_alias].
+    async fn resolve_alias(&self, alias: &str) -> Result<OwnedObjectPath, Error> {
+        util::resolve_alias(&self.conn, &self.service_proxy, &self.config, alias).await
+    }
+
+    /// Returns whether a collection exists under `alias`, without looking
+    /// it up, for startup probes.
+    pub async fn collection_exists(&self, alias: &str) -> Result<bool, Error> {
+        let object_path = self.service_proxy.read_alias(alias).await?;
+        Ok(object_path.as_str() != "/")
     }
 
     /// Get default collection.
@@ -236,6 +718,33 @@ impl<'a> SecretService<'a> {
         self.get_collection_by_alias("default").await
     }
 
+    /// Get the object path of the default collection, without looking the
+    /// collection up, for comparing or persisting it.
+    ///
+    /// Like [SecretService::get_default_collection], returns
+    /// [Error::NoResult] if there is no default collection.
+    pub async fn default_collection_path(&self) -> Result<ObjectPath, Error> {
+        Ok(self.resolve_alias("default").await?.into())
+    }
+
+    /// Makes `collection` available under `alias`, replacing the collection
+    /// the alias pointed to, if any.
+    pub async fn set_alias(&self, alias: &str, collection: &Collection<'_>) -> Result<(), Error> {
+        let result = self
+            .service_proxy
+            .set_alias(alias, collection.path.as_ref())
+            .await;
+        // the alias may have moved even if the call failed midway
+        self.config.alias_cache.clear();
+        Ok(result?)
+    }
+
+    /// Makes `collection` the default collection, such as one the
+    /// application created, see [SecretService::set_alias].
+    pub async fn make_default_collection(&self, collection: &Collection<'_>) -> Result<(), Error> {
+        self.set_alias("default", collection).await
+    }
+
     /// Get any collection.
     /// First tries `default` collection, then `session`
     /// collection, then the first collection when it
@@ -262,39 +771,55 @@ impl<'a> SecretService<'a> {
         label: &str,
         alias: &str,
     ) -> Result<Collection<'_>, Error> {
-        let mut properties: HashMap<&str, Value> = HashMap::new();
-        properties.insert(SS_COLLECTION_LABEL, label.into());
-
-        let created_collection = self
-            .service_proxy
-            .create_collection(properties, alias)
-            .await?;
-
-        // This prompt handling is practically identical to create_collection
-        let collection_path: ObjectPath = {
-            // Get path of created object
-            let created_path = created_collection.collection;
-
-            // Check if that path is "/", if so should execute a prompt
-            if created_path.as_str() == "/" {
-                let prompt_path = created_collection.prompt;
-
-                // Exec prompt and parse result
-                let prompt_res = exec_prompt(self.conn.clone(), &prompt_path).await?;
-                prompt_res.try_into()?
-            } else {
-                // if not, just return created path
-                created_path.into()
-            }
-        };
+        let collection_path = util::create_collection(
+            self.conn.clone(),
+            &self.service_proxy,
+            &self.config,
+            label,
+            alias,
+        )
+        .await?;
 
-        Collection::new(
+        let collection = Collection::new(
             self.conn.clone(),
             &self.session,
             &self.service_proxy,
-            collection_path.into(),
+            &self.config,
+            collection_path,
         )
-        .await
+        .await?;
+        Ok(match alias {
+            "" => collection,
+            alias => collection.with_alias(alias),
+        })
+    }
+
+    /// Deletes the items in the trash collection for good, see
+    /// [Item::trash].
+    ///
+    /// Does nothing if there is no trash collection yet.
+    pub async fn empty_trash(&self) -> Result<BatchOutcome, Error> {
+        let trash = match self.get_collection_by_alias(TRASH_ALIAS).await {
+            Ok(trash) => trash,
+            Err(Error::NoResult) => {
+                return Ok(BatchOutcome {
+                    results: Vec::new(),
+                })
+            }
+            Err(err) => return Err(err),
+        };
+
+        let mut results = Vec::new();
+        for item in trash.get_all_items().await? {
+            results.push((item.path(), item.delete().await));
+        }
+        Ok(BatchOutcome { results })
+    }
+
+    /// Starts a search of items by attributes, which can be limited and
+    /// ordered before it runs, see [ItemSearch].
+    pub fn search<'s>(&'s self, attributes: HashMap<&'s str, &'s str>) -> ItemSearch<'s> {
+        ItemSearch::new(self, attributes)
     }
 
     /// Searches all items by attributes
@@ -302,7 +827,8 @@ impl<'a> SecretService<'a> {
         &self,
         attributes: HashMap<&str, &str>,
     ) -> Result<SearchItemsResult<Item<'_>>, Error> {
-        let items = self.service_proxy.search_items(attributes).await?;
+        let attributes = self.config.encrypt_attributes(attributes);
+        let items = self.service_proxy.search_items(attributes.as_map()).await?;
 
         let object_paths_to_items = |items: Vec<_>| {
             futures_util::future::join_all(items.into_iter().map(|item_path| {
@@ -310,6 +836,7 @@ impl<'a> SecretService<'a> {
                     self.conn.clone(),
                     &self.session,
                     &self.service_proxy,
+                    &self.config,
                     item_path,
                 )
             }))
@@ -327,22 +854,307 @@ impl<'a> SecretService<'a> {
         })
     }
 
-    /// Unlock all items in a batch
-    pub async fn unlock_all(&self, items: &[&Item<'_>]) -> Result<(), Error> {
-        let objects = items.iter().map(|i| &*i.item_path).collect();
+    /// Searches all items by attributes, also fetching the label and
+    /// attributes of every result.
+    ///
+    /// The properties are fetched concurrently, saving a round trip per item
+    /// compared to calling [Item::get_label] and [Item::get_attributes] on
+    /// each result of [SecretService::search_items].
+    pub async fn search_items_prefetched(
+        &self,
+        attributes: HashMap<&str, &str>,
+    ) -> Result<SearchItemsResult<PrefetchedItem<Item<'_>>>, Error> {
+        let items = self.search_items(attributes).await?;
+
+        Ok(SearchItemsResult {
+            unlocked: util::prefetch_items(&self.config, items.unlocked).await?,
+            locked: util::prefetch_items(&self.config, items.locked).await?,
+        })
+    }
+
+    /// Searches all items by attributes, grouping the results by the
+    /// collection holding them, such as for presenting matches under the
+    /// heading of their keyring.
+    ///
+    /// Each item's collection is found from its path, without asking the
+    /// provider. The groups are sorted by the path of their collection.
+    /// Within a collection, unlocked items come first; use [Item::is_locked]
+    /// to tell them apart.
+    pub async fn search_items_grouped(
+        &self,
+        attributes: HashMap<&str, &str>,
+    ) -> Result<Vec<(Collection<'_>, Vec<Item<'_>>)>, Error> {
+        let items = self.search_items(attributes).await?;
+        let groups =
+            util::group_by_collection(items.unlocked.into_iter().chain(items.locked), |item| {
+                item.path.as_str()
+            })?;
+
+        let mut grouped = Vec::with_capacity(groups.len());
+        for (path, items) in groups {
+            let collection = Collection::new(
+                self.conn.clone(),
+                &self.session,
+                &self.service_proxy,
+                &self.config,
+                path,
+            )
+            .await?;
+            grouped.push((collection, items));
+        }
+        Ok(grouped)
+    }
+
+    /// Searches all items by attributes, keeping only those whose attribute
+    /// `key` holds a list containing `tag`.
+    ///
+    /// The list must have been encoded with [attributes::encode_list].
+    /// `attributes` narrows the search on the provider; any value it has for
+    /// `key` is ignored.
+    pub async fn search_items_by_tag(
+        &self,
+        mut attributes: HashMap<&str, &str>,
+        key: &str,
+        tag: &str,
+    ) -> Result<SearchItemsResult<Item<'_>>, Error> {
+        attributes.remove(key);
+        let items = self.search_items(attributes).await?;
+
+        Ok(SearchItemsResult {
+            unlocked: util::retain_tagged(&self.config, items.unlocked, key, tag).await?,
+            locked: util::retain_tagged(&self.config, items.locked, key, tag).await?,
+        })
+    }
+
+    /// Searches all items with a query string, see [query].
+    ///
+    /// ```no_run
+    /// # async fn call(ss: &secret_service::SecretService<'_>) -> Result<(), Box<dyn std::error::Error>> {
+    /// let query = "service=smtp AND (user=alice OR user=bob)".parse()?;
+    /// let items = ss.search_query(&query).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn search_query(&self, query: &Query) -> Result<SearchItemsResult<Item<'_>>, Error> {
+        let items = self.search_items(query.required_attributes()).await?;
+
+        Ok(SearchItemsResult {
+            unlocked: query::retain_matching(&self.config, items.unlocked, query).await?,
+            locked: query::retain_matching(&self.config, items.locked, query).await?,
+        })
+    }
+
+    /// Searches all items by label, returning matches ranked best first.
+    ///
+    /// Matching is case-insensitive: exact matches rank above prefix matches,
+    /// which rank above substring matches, followed by fuzzy matches where the
+    /// characters of `query` appear in order. The spec can't search labels, so
+    /// they are fetched concurrently and compared on the client.
+    pub async fn search_labels(&self, query: &str) -> Result<Vec<LabelMatch<Item<'_>>>, Error> {
+        // an empty attribute search returns every item
+        let items = self.search_items(HashMap::new()).await?;
+        let items: Vec<_> = items.unlocked.into_iter().chain(items.locked).collect();
+        let labels = futures_util::future::join_all(
+            items
+                .iter()
+                .map(|item| util::limit_concurrency(&self.config, item.get_label())),
+        )
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(util::rank_label_matches(
+            items.into_iter().zip(labels),
+            query,
+        ))
+    }
+
+    /// Looks up the secrets of environment variables, returning them as a
+    /// map that can be passed to `std::process::Command::envs`.
+    ///
+    /// Each variable is paired with the attributes of the item holding its
+    /// value. The first unlocked matching item is used, or else the first
+    /// locked one, which is unlocked first and may prompt the user. Fails
+    /// with [Error::NoResult] if no item matches the attributes of a
+    /// variable, and with [Error::InvalidUtf8] if a secret isn't text.
+    ///
+    /// ```no_run
+    /// # use secret_service::{EncryptionType, SecretService};
+    /// # use std::collections::HashMap;
+    /// # use std::process::Command;
+    /// # async fn call() {
+    /// let ss = SecretService::connect(EncryptionType::Dh).await.unwrap();
+    /// let env = ss
+    ///     .secrets_as_env([("API_TOKEN", HashMap::from([("service", "example")]))])
+    ///     .await
+    ///     .unwrap();
+    /// Command::new("deploy").envs(&env).status().unwrap();
+    /// # }
+    /// ```
+    ///
+    /// Setting the variables in the current process instead, with
+    /// `std::env::set_var`, is only sound before other threads are started,
+    /// since they may read the environment at the same time.
+    pub async fn secrets_as_env<'v>(
+        &self,
+        vars: impl IntoIterator<Item = (&'v str, HashMap<&'v str, &'v str>)>,
+    ) -> Result<HashMap<String, String>, Error> {
+        let mut env = HashMap::new();
+        for (var, attributes) in vars {
+            let items = self.search_items(attributes).await?;
+            let secret = match (items.unlocked.first(), items.locked.first()) {
+                (Some(item), _) => item.get_secret_string().await?,
+                (None, Some(item)) => {
+                    item.unlock().await?;
+                    item.get_secret_string().await?
+                }
+                (None, None) => return Err(Error::NoResult),
+            };
+            env.insert(var.to_owned(), secret);
+        }
+        Ok(env)
+    }
+
+    /// Subscribes to every change reported by the secret service.
+    ///
+    /// The returned stream multiplexes the signals of the service, of all
+    /// collections (including ones created after subscribing) and lock state
+    /// changes into a single stream of [SecretServiceEvent]s.
+    ///
+    /// The stream ends once this `SecretService` is dropped, which also
+    /// removes its subscription from the connection.
+    pub async fn watch(&self) -> Result<impl Stream<Item = SecretServiceEvent>, Error> {
+        let messages =
+            zbus::MessageStream::for_match_rule(event::match_rule(&self.config)?, &self.conn, None)
+                .await?;
+
+        Ok(self.config.lifetime.bind(messages).filter_map(|msg| {
+            futures_util::future::ready(msg.ok().as_ref().and_then(event::parse_event))
+        }))
+    }
+
+    /// Subscribes to the collections being created, changed or deleted, by
+    /// this or any other application.
+    ///
+    /// Unlike [SecretService::watch], only the signals of the service
+    /// itself are received, not those of every collection and item. The
+    /// stream ends once this `SecretService` is dropped.
+    pub async fn receive_collection_events(
+        &self,
+    ) -> Result<impl Stream<Item = CollectionEvent>, Error> {
+        let events = event::collection_events(&self.service_proxy).await?;
+        Ok(self.config.lifetime.bind(events))
+    }
+
+    /// Unlock all items in a batch, prompting the user once if the provider
+    /// asks to.
+    ///
+    /// The outcome tells which items ended up unlocked. Items the provider
+    /// didn't report as unlocked fail with [Error::Prompt] if the user
+    /// dismissed the prompt, or with [Error::Locked] if they are still
+    /// locked.
+    pub async fn unlock_all(&self, items: &[&Item<'_>]) -> Result<BatchOutcome, Error> {
+        let objects = items.iter().map(|i| &*i.path).collect();
         let lock_action_res = self.service_proxy.unlock(objects).await?;
 
-        if lock_action_res.object_paths.is_empty() {
-            exec_prompt(self.conn.clone(), &lock_action_res.prompt).await?;
+        let mut unlocked: HashSet<_> = lock_action_res.object_paths.into_iter().collect();
+        let mut dismissed = false;
+        if lock_action_res.prompt.as_str() != "/" {
+            match exec_prompt(self.conn.clone(), &self.config, &lock_action_res.prompt).await {
+                Ok(result) => unlocked.extend(util::prompt_paths(&self.config, result)?),
+                Err(Error::Prompt) => dismissed = true,
+                Err(err) => return Err(err),
+            }
+        }
+
+        let mut results = Vec::with_capacity(items.len());
+        for item in items {
+            let result = if unlocked.contains(&item.path) {
+                Ok(())
+            } else if dismissed {
+                Err(Error::Prompt)
+            } else {
+                item.ensure_unlocked().await
+            };
+            results.push((item.path(), result));
         }
+        Ok(BatchOutcome { results })
+    }
 
-        Ok(())
+    /// Locks collections and items in a batch, such as everything an
+    /// application touched once it goes idle, prompting the user once if
+    /// the provider asks to.
+    ///
+    /// The outcome tells which objects ended up locked. Objects the
+    /// provider didn't report as locked fail with [Error::Prompt] if the
+    /// user dismissed the prompt, or with [Error::NotLocked] if they are
+    /// still unlocked.
+    pub async fn lock_all(&self, objects: &[ObjectRef<'_>]) -> Result<BatchOutcome, Error> {
+        let paths = objects.iter().map(|o| &**o.object_path()).collect();
+        let lock_action_res = self.service_proxy.lock(paths).await?;
+
+        let mut locked: HashSet<_> = lock_action_res.object_paths.into_iter().collect();
+        let mut dismissed = false;
+        if lock_action_res.prompt.as_str() != "/" {
+            match exec_prompt(self.conn.clone(), &self.config, &lock_action_res.prompt).await {
+                Ok(result) => locked.extend(util::prompt_paths(&self.config, result)?),
+                Err(Error::Prompt) => dismissed = true,
+                Err(err) => return Err(err),
+            }
+        }
+
+        let locked = &locked;
+        let results = futures_util::future::join_all(objects.iter().map(|object| async move {
+            let result = if locked.contains(object.object_path()) {
+                Ok(())
+            } else if dismissed {
+                Err(Error::Prompt)
+            } else {
+                // such as the items of a collection locked along with them
+                match util::limit_concurrency(&self.config, object.is_locked()).await {
+                    Ok(true) => Ok(()),
+                    Ok(false) => Err(Error::NotLocked),
+                    Err(err) => Err(err),
+                }
+            };
+            (object.path(), result)
+        }))
+        .await;
+        Ok(BatchOutcome { results })
+    }
+
+    /// Returns the secrets of many items with a single call to the
+    /// provider, instead of one [Item::get_secret] call each.
+    ///
+    /// The outcome holds each item's secret in the order given. Providers
+    /// leave locked items out, which fail with [Error::Locked]; unlock them
+    /// first, for instance with [SecretService::unlock_all]. The secrets are
+    /// transferred in the session of this `SecretService`, whichever session
+    /// the items were handed out with.
+    pub async fn get_secrets(&self, items: &[&Item<'_>]) -> Result<BatchOutcome<Vec<u8>>, Error> {
+        if items.is_empty() {
+            return Ok(BatchOutcome {
+                results: Vec::new(),
+            });
+        }
+        let paths = items.iter().map(|i| &*i.path).collect();
+        let secrets = self
+            .service_proxy
+            .get_secrets(paths, self.session.open().await?)
+            .await?;
+        Ok(util::secrets_outcome(
+            &self.session,
+            &self.config,
+            items.iter().map(|i| i.path.clone()),
+            secrets,
+        ))
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::testing::TestHarness;
     use std::convert::TryFrom;
     use zbus::zvariant::ObjectPath;
 
@@ -352,18 +1164,492 @@ mod test {
     }
 
     #[tokio::test]
-    async fn should_get_all_collections() {
-        // Assumes that there will always be a default collection
-        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
-        let collections = ss.get_all_collections().await.unwrap();
-        assert!(!collections.is_empty(), "no collections found");
+    async fn should_create_secret_service_with_builder() {
+        let ss = SecretService::builder()
+            .encryption(EncryptionType::Plain)
+            .window_id("x11:0x2a00003")
+            .connect()
+            .await
+            .unwrap();
+        ss.get_default_collection().await.unwrap();
     }
 
     #[tokio::test]
-    async fn should_get_collection_by_alias() {
-        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
-        ss.get_collection_by_alias("session").await.unwrap();
-    }
+    async fn should_connect_with_existing_connection() {
+        let conn = zbus::Connection::session().await.unwrap();
+        let mut ss = SecretService::connect_with(conn.clone(), EncryptionType::Plain)
+            .await
+            .unwrap();
+        assert_eq!(ss.conn.unique_name(), conn.unique_name());
+        ss.get_default_collection().await.unwrap();
+
+        // the connection is kept
+        ss.reconnect().await.unwrap();
+        assert_eq!(ss.conn.unique_name(), conn.unique_name());
+        ss.get_default_collection().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_set_window() {
+        let ss = SecretService::builder()
+            .encryption(EncryptionType::Plain)
+            .window(WindowIdentifier::X11(0x2a00003))
+            .connect()
+            .await
+            .unwrap();
+        assert_eq!(ss.config.window_id(), "x11:0x2a00003");
+
+        let collection = ss.get_default_collection().await.unwrap();
+        ss.set_window(Some(WindowIdentifier::Wayland("a1b2c3".to_owned())));
+        assert_eq!(collection.config.window_id(), "wayland:a1b2c3");
+        ss.set_window(None);
+        assert_eq!(ss.config.window_id(), "");
+    }
+
+    #[tokio::test]
+    async fn should_reconnect_once_session_lost() {
+        let mut ss = SecretService::connect(EncryptionType::Dh).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let attributes = HashMap::from([("test_attribute_reconnect", "test_value")]);
+        let item = collection
+            .create_item(
+                "TestReconnect",
+                attributes.clone(),
+                b"test",
+                false,
+                "text/plain",
+            )
+            .await
+            .unwrap();
+        let lost = ss.session().path;
+
+        // as if the provider restarted
+        ss.conn
+            .call_method(
+                Some(ss.config.destination.as_str()),
+                lost.as_str(),
+                Some("org.freedesktop.Secret.Session"),
+                "Close",
+                &(),
+            )
+            .await
+            .unwrap();
+        let err = item.get_secret().await.unwrap_err();
+        assert!(err.is_connection_lost());
+
+        let mut attempts = 0;
+        let secret = ss
+            .with_reconnect(|ss| {
+                attempts += 1;
+                let attributes = attributes.clone();
+                Box::pin(async move {
+                    let found = ss.search_items(attributes).await?;
+                    found.unlocked[0].get_secret().await
+                })
+            })
+            .await
+            .unwrap();
+        assert_eq!(secret, b"test");
+        assert_eq!(attempts, 2);
+        assert_ne!(ss.session().path, lost);
+
+        let found = ss.search_items(attributes).await.unwrap();
+        found.unlocked[0].delete().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_search_with_call_limit() {
+        let ss = SecretService::builder()
+            .encryption(EncryptionType::Plain)
+            .max_concurrent_calls(std::num::NonZeroUsize::new(1).unwrap())
+            .connect()
+            .await
+            .unwrap();
+        ss.search_labels("anything").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_work_with_external_executor() {
+        let ss = SecretService::builder()
+            .encryption(EncryptionType::Plain)
+            .internal_executor(false)
+            .connect()
+            .await
+            .unwrap();
+        let collections = util::drive_executor(ss.executor(), ss.get_all_collections())
+            .await
+            .unwrap();
+        assert!(!collections.is_empty());
+    }
+
+    #[tokio::test]
+    async fn should_connect_to_configured_destination() {
+        // address the provider by its unique name instead of the well-known one
+        let conn = zbus::Connection::session().await.unwrap();
+        let owner = zbus::fdo::DBusProxy::new(&conn)
+            .await
+            .unwrap()
+            .get_name_owner(ss::SS_DBUS_NAME.try_into().unwrap())
+            .await
+            .unwrap();
+        let ss = SecretService::builder()
+            .encryption(EncryptionType::Plain)
+            .destination(owner.as_str())
+            .path(ss::SS_DBUS_PATH)
+            .connect()
+            .await
+            .unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        collection.get_all_items().await.unwrap();
+
+        let options = ConnectOptions {
+            destination: owner.to_string(),
+            ..ConnectOptions::default()
+        };
+        let ss = SecretService::connect_with_options(options, EncryptionType::Plain)
+            .await
+            .unwrap();
+        ss.get_default_collection().await.unwrap();
+
+        let missing = SecretService::builder()
+            .encryption(EncryptionType::Plain)
+            .destination("org.example.NoSuchSecrets")
+            .connect()
+            .await;
+        assert!(missing.is_err());
+    }
+
+    #[tokio::test]
+    async fn should_rotate_envelope_key() {
+        let connect = |id: &str| {
+            SecretService::builder()
+                .encryption(EncryptionType::Dh)
+                .envelope_key(ClientKey::new(id, format!("{id}_key").as_bytes()).unwrap())
+                .connect()
+        };
+        let (old_ss, new_ss) = (
+            connect("test_rotate_old").await.unwrap(),
+            connect("test_rotate_new").await.unwrap(),
+        );
+        // the last one is interrupted after storing the secret, before
+        // recording the key
+        let mut paths = Vec::new();
+        for (ss, n) in [
+            (&old_ss, "1"),
+            (&old_ss, "2"),
+            (&old_ss, "3"),
+            (&new_ss, "4"),
+        ] {
+            let collection = ss.get_default_collection().await.unwrap();
+            let item = collection
+                .create_item(
+                    "Rotated",
+                    HashMap::from([("test_rotate", n)]),
+                    n.as_bytes(),
+                    true,
+                    "text/plain",
+                )
+                .await
+                .unwrap();
+            paths.push(item.path());
+        }
+        let found = new_ss
+            .search_items(HashMap::from([("test_rotate", "4")]))
+            .await
+            .unwrap();
+        found.unlocked[0]
+            .set_attributes(HashMap::from([
+                ("test_rotate", "4"),
+                ("envelope_key_id", "test_rotate_old"),
+            ]))
+            .await
+            .unwrap();
+
+        let (old, new) = (
+            ClientKey::new("test_rotate_old", b"test_rotate_old_key").unwrap(),
+            ClientKey::new("test_rotate_new", b"test_rotate_new_key").unwrap(),
+        );
+        let mut last = None;
+        let outcome = new_ss
+            .rotate_key_with_progress(&old, &new, NonZeroUsize::new(2).unwrap(), |progress| {
+                last = Some(progress.clone())
+            })
+            .await
+            .unwrap();
+        let last = last.unwrap();
+        assert_eq!((last.done, last.failed, last.remaining()), (4, 0, Some(0)));
+        let mut rotated: Vec<_> = outcome.into_result().unwrap();
+        rotated.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+        paths.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        assert_eq!(
+            rotated
+                .into_iter()
+                .map(|(path, _)| path)
+                .collect::<Vec<_>>(),
+            paths
+        );
+
+        for n in ["1", "2", "3", "4"] {
+            let found = new_ss
+                .search_items(HashMap::from([("test_rotate", n)]))
+                .await
+                .unwrap();
+            let item = &found.unlocked[0];
+            assert_eq!(item.get_secret().await.unwrap(), n.as_bytes());
+            let attributes = item.get_attributes().await.unwrap();
+            assert_eq!(attributes["envelope_key_id"], "test_rotate_new");
+            item.delete().await.unwrap();
+        }
+
+        let outcome = new_ss
+            .rotate_key(&old, &new, NonZeroUsize::new(2).unwrap())
+            .await
+            .unwrap();
+        assert!(outcome.results.is_empty());
+    }
+
+    #[tokio::test]
+    async fn should_look_up_secrets_as_env() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let attributes = HashMap::from([("test_secrets_as_env", "token")]);
+        let item = collection
+            .create_item("Env", attributes.clone(), b"hunter2", true, "text/plain")
+            .await
+            .unwrap();
+
+        let env = ss
+            .secrets_as_env([("TEST_TOKEN", attributes.clone())])
+            .await
+            .unwrap();
+        assert_eq!(
+            env,
+            HashMap::from([("TEST_TOKEN".to_owned(), "hunter2".to_owned())])
+        );
+
+        let missing = HashMap::from([("test_secrets_as_env", "missing")]);
+        let result = ss
+            .secrets_as_env([("TEST_TOKEN", attributes), ("TEST_MISSING", missing)])
+            .await;
+        assert!(matches!(result, Err(Error::NoResult)));
+        item.delete().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_encrypt_configured_attributes() {
+        let ss = SecretService::builder()
+            .encryption(EncryptionType::Plain)
+            .encrypt_attributes(b"0123456789abcdef", ["test_encrypted_account"])
+            .connect()
+            .await
+            .unwrap();
+        let attributes = HashMap::from([
+            ("test_encrypted_account", "alice"),
+            ("test_encrypted_service", "example"),
+        ]);
+        let collection = ss.get_default_collection().await.unwrap();
+        let item = collection
+            .create_item("test", attributes.clone(), b"test", true, "text/plain")
+            .await
+            .unwrap();
+
+        let found = ss.search_items(attributes.clone()).await.unwrap();
+        assert_eq!(found.unlocked.len(), 1);
+        assert_eq!(found.unlocked[0].path(), item.path());
+        let stored = item.get_attributes().await.unwrap();
+        assert_eq!(stored["test_encrypted_account"], "alice");
+
+        // without the key, only the other attributes are readable
+        let plain = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let found = plain.search_items(attributes).await.unwrap();
+        assert!(found.unlocked.is_empty());
+        let found = plain
+            .search_items(HashMap::from([("test_encrypted_service", "example")]))
+            .await
+            .unwrap();
+        let item = found.unlocked.into_iter().next().unwrap();
+        let stored = item.get_attributes().await.unwrap();
+        assert_ne!(stored["test_encrypted_account"], "alice");
+        assert_eq!(stored["test_encrypted_service"], "example");
+        item.delete().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_get_all_collections() {
+        // Assumes that there will always be a default collection
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collections = ss.get_all_collections().await.unwrap();
+        assert!(!collections.is_empty(), "no collections found");
+    }
+
+    #[tokio::test]
+    async fn should_dump_tree_without_secrets() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let item = collection
+            .create_item(
+                "Dumped",
+                HashMap::from([("test_dump_tree", "hidden value")]),
+                b"hidden secret",
+                false,
+                "text/plain",
+            )
+            .await
+            .unwrap();
+
+        let tree = ss.dump_tree().await.unwrap();
+        let dumped = tree
+            .collections
+            .iter()
+            .flat_map(|collection| &collection.items)
+            .find(|dumped| dumped.path == item.path().as_str())
+            .unwrap();
+        assert_eq!(dumped.label, "Dumped");
+        assert_eq!(dumped.attribute_keys, ["test_dump_tree"]);
+        assert_eq!(dumped.created, item.get_created().await.unwrap());
+        let debug = format!("{tree:?}");
+        assert!(!debug.contains("hidden"));
+
+        item.delete().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_report_unlocked_items() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let item = collection
+            .create_item("Test", HashMap::new(), b"test", false, "text/plain")
+            .await
+            .unwrap();
+
+        let outcome = ss.unlock_all(&[&item]).await.unwrap();
+        assert!(outcome.is_success());
+        assert_eq!(outcome.results[0].0, item.path());
+
+        item.delete().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_stream_collections() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let mut streamed: Vec<_> = ss
+            .collections_stream()
+            .await
+            .unwrap()
+            .map(|collection| collection.unwrap().path.to_string())
+            .collect()
+            .await;
+        let mut collections: Vec<_> = ss
+            .get_all_collections()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|collection| collection.path.to_string())
+            .collect();
+        streamed.sort();
+        collections.sort();
+        assert_eq!(streamed, collections);
+    }
+
+    #[tokio::test]
+    async fn should_get_collection_by_alias() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        ss.get_collection_by_alias("session").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_use_additional_sessions() {
+        let ss = SecretService::connect(EncryptionType::Dh).await.unwrap();
+        let plain = ss.open_session(EncryptionType::Plain).await.unwrap();
+        assert_eq!(plain.info().encryption, EncryptionType::Plain);
+        assert_ne!(plain.info().path, ss.session().path);
+
+        let collection = ss.get_default_collection().await.unwrap();
+        let attributes = HashMap::from([("test_attributes_sessions", "test")]);
+        let plain_collection = collection.with_session(&plain);
+        plain_collection
+            .create_item("Test", attributes.clone(), b"test", true, "text/plain")
+            .await
+            .unwrap();
+        let items = collection.search_items(attributes).await.unwrap();
+        assert_eq!(items[0].get_secret().await.unwrap(), b"test");
+        assert_eq!(
+            items[0].with_session(&plain).get_secret().await.unwrap(),
+            b"test"
+        );
+        items[0].delete().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_describe_session() {
+        let ss = SecretService::connect(EncryptionType::Dh).await.unwrap();
+        let session = ss.session();
+        assert_eq!(session.encryption, EncryptionType::Dh);
+        assert_eq!(session.algorithm(), "dh-ietf1024-sha256-aes128-cbc-pkcs7");
+        assert!(session.created <= std::time::SystemTime::now());
+    }
+
+    #[tokio::test]
+    async fn should_negotiate_lazy_session() {
+        let ss = SecretService::builder()
+            .lazy_session(true)
+            .connect()
+            .await
+            .unwrap();
+        assert_eq!(ss.session().path.as_str(), "/");
+        let collection = ss.get_default_collection().await.unwrap();
+        collection.get_label().await.unwrap();
+        assert_eq!(ss.session().path.as_str(), "/");
+
+        let attributes = HashMap::from([("test_attributes_lazy", "test")]);
+        let item = collection
+            .create_item("Test", attributes, b"test", true, "text/plain")
+            .await
+            .unwrap();
+        let session = ss.session();
+        assert_ne!(session.path.as_str(), "/");
+        assert_eq!(session.encryption, EncryptionType::Dh);
+        assert_eq!(item.get_secret().await.unwrap(), b"test");
+        assert_eq!(ss.session().path, session.path);
+        item.delete().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_accept_valid_responses_when_strict() {
+        let ss = SecretService::builder()
+            .strict(true)
+            .connect()
+            .await
+            .unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let attributes = HashMap::from([("test_attributes_strict", "test")]);
+        let item = collection
+            .create_item("Test", attributes, b"test", true, "text/plain")
+            .await
+            .unwrap();
+        assert_eq!(item.get_secret().await.unwrap(), b"test");
+        item.delete().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_get_default_collection_path() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        assert_eq!(
+            ss.default_collection_path().await.unwrap(),
+            collection.path()
+        );
+    }
+
+    #[tokio::test]
+    async fn should_check_if_collection_exists() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        assert!(ss.collection_exists("session").await.unwrap());
+        assert!(!ss
+            .collection_exists("definitely_defintely_does_not_exist")
+            .await
+            .unwrap());
+    }
 
     #[tokio::test]
     async fn should_return_error_if_collection_doesnt_exist() {
@@ -396,12 +1682,287 @@ mod test {
         let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
         let test_collection = ss.create_collection("Test", "").await.unwrap();
         assert_eq!(
-            ObjectPath::from(test_collection.collection_path.clone()),
+            ObjectPath::from(test_collection.path.clone()),
             ObjectPath::try_from("/org/freedesktop/secrets/collection/Test").unwrap()
         );
         test_collection.delete().await.unwrap();
     }
 
+    #[test_with::no_env(GITHUB_ACTIONS)]
+    #[tokio::test]
+    async fn should_dismiss_prompt_when_cancelled() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let test_collection = ss
+            .create_collection("TestCancel", "test_cancel")
+            .await
+            .unwrap();
+        test_collection.lock().await.unwrap();
+
+        // the prompt for this window is never answered
+        let cancelling = SecretService::builder()
+            .encryption(EncryptionType::Plain)
+            .window_id("never")
+            .connect()
+            .await
+            .unwrap();
+        let collection = cancelling
+            .get_collection_by_alias("test_cancel")
+            .await
+            .unwrap();
+        let rule = zbus::MatchRule::builder()
+            .msg_type(zbus::message::Type::Signal)
+            .interface("org.freedesktop.Secret.Prompt")
+            .unwrap()
+            .member("Completed")
+            .unwrap()
+            .build();
+        let mut completed = zbus::MessageStream::for_match_rule(rule, &ss.conn, None)
+            .await
+            .unwrap();
+
+        let unlock = Box::pin(collection.unlock());
+        let timeout = tokio::task::spawn_blocking(|| {
+            std::thread::sleep(std::time::Duration::from_millis(500))
+        });
+        match futures_util::future::select(unlock, timeout).await {
+            futures_util::future::Either::Left(_) => panic!("the prompt was answered"),
+            futures_util::future::Either::Right((_, unlock)) => drop(unlock),
+        }
+
+        let msg = completed.next().await.unwrap().unwrap();
+        let (dismissed, _): (bool, zbus::zvariant::OwnedValue) = msg.body().deserialize().unwrap();
+        assert!(dismissed);
+
+        test_collection.unlock().await.unwrap();
+        test_collection.delete().await.unwrap();
+    }
+
+    #[test_with::no_env(GITHUB_ACTIONS)]
+    #[tokio::test]
+    async fn should_fail_cancelled_prompt() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let test_collection = ss
+            .create_collection("TestCancelHandle", "test_cancel_handle")
+            .await
+            .unwrap();
+        test_collection.lock().await.unwrap();
+
+        // the prompt for this window is never answered
+        let cancelling = SecretService::builder()
+            .encryption(EncryptionType::Plain)
+            .window_id("never")
+            .connect()
+            .await
+            .unwrap();
+        let collection = cancelling
+            .get_collection_by_alias("test_cancel_handle")
+            .await
+            .unwrap();
+        let canceller = cancelling.prompt_canceller();
+
+        let unlock = collection.unlock();
+        let cancel = async {
+            while canceller.pending() == 0 {
+                tokio::task::spawn_blocking(|| {
+                    std::thread::sleep(std::time::Duration::from_millis(10))
+                })
+                .await
+                .unwrap();
+            }
+            canceller.cancel();
+        };
+        let (unlocked, ()) = futures_util::join!(unlock, cancel);
+        assert!(matches!(unlocked, Err(Error::Prompt)));
+        assert_eq!(canceller.pending(), 0);
+
+        test_collection.unlock().await.unwrap();
+        test_collection.delete().await.unwrap();
+    }
+
+    #[test_with::no_env(GITHUB_ACTIONS)]
+    #[tokio::test]
+    async fn should_cache_aliases_until_collection_deleted() {
+        let harness = TestHarness::start().unwrap();
+        let ss = harness.connect(EncryptionType::Plain).await.unwrap();
+        let test_collection = ss
+            .create_collection("TestAliasCache", "test_alias_cache")
+            .await
+            .unwrap();
+
+        for _ in 0..2 {
+            let collection = ss
+                .get_collection_by_alias("test_alias_cache")
+                .await
+                .unwrap();
+            assert_eq!(collection.path, test_collection.path);
+        }
+        assert!(ss.config.alias_cache.get("test_alias_cache").is_some());
+
+        test_collection.delete().await.unwrap();
+        assert!(matches!(
+            ss.get_collection_by_alias("test_alias_cache").await,
+            Err(Error::NoResult)
+        ));
+    }
+
+    #[test_with::no_env(GITHUB_ACTIONS)]
+    #[tokio::test]
+    async fn should_set_alias() {
+        let harness = TestHarness::start().unwrap();
+        let ss = harness.connect(EncryptionType::Plain).await.unwrap();
+        let first = ss.create_collection("TestSetAlias", "").await.unwrap();
+        let second = ss.create_collection("TestSetAlias", "").await.unwrap();
+
+        ss.set_alias("test_set_alias", &first).await.unwrap();
+        let aliased = ss.get_collection_by_alias("test_set_alias").await.unwrap();
+        assert_eq!(aliased.path, first.path);
+        // moving the alias invalidates the cached resolution
+        ss.set_alias("test_set_alias", &second).await.unwrap();
+        let aliased = ss.get_collection_by_alias("test_set_alias").await.unwrap();
+        assert_eq!(aliased.path, second.path);
+
+        first.delete().await.unwrap();
+        second.delete().await.unwrap();
+    }
+
+    #[test_with::no_env(GITHUB_ACTIONS)]
+    #[tokio::test]
+    async fn should_invalidate_aliases_on_signals() {
+        let harness = TestHarness::start().unwrap();
+        let ss = harness.connect(EncryptionType::Plain).await.unwrap();
+        let other = harness.connect(EncryptionType::Plain).await.unwrap();
+        let test_collection = other
+            .create_collection("TestAliasSignal", "test_alias_signal")
+            .await
+            .unwrap();
+        ss.get_collection_by_alias("test_alias_signal")
+            .await
+            .unwrap();
+
+        // deleted through another connection, so only the signal tells
+        test_collection.delete().await.unwrap();
+        let mut attempts = 0;
+        while ss.config.alias_cache.get("test_alias_signal").is_some() {
+            attempts += 1;
+            assert!(attempts < 1000, "alias cache was not invalidated");
+            tokio::task::yield_now().await;
+        }
+        assert!(ss
+            .get_collection_by_alias("test_alias_signal")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn should_search_labels() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let item = collection
+            .create_item(
+                "Test label in ss search",
+                HashMap::new(),
+                b"test",
+                false,
+                "text/plain",
+            )
+            .await
+            .unwrap();
+
+        let matches = ss.search_labels("label in SS search").await.unwrap();
+        assert!(matches
+            .iter()
+            .any(|m| m.item.path == item.path && m.label == "Test label in ss search"));
+
+        let matches = ss.search_labels("tstlblinsssrch").await.unwrap();
+        assert!(matches.iter().any(|m| m.item.path == item.path));
+
+        let matches = ss.search_labels("no such label anywhere").await.unwrap();
+        assert!(matches.iter().all(|m| m.item.path != item.path));
+        item.delete().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_sort_search_results() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        for label in ["sort b", "Sort a", "sort c"] {
+            collection
+                .create_item(
+                    label,
+                    HashMap::from([("test_sort_in_ss", "sort")]),
+                    b"test",
+                    false,
+                    "text/plain",
+                )
+                .await
+                .unwrap();
+        }
+
+        let mut items = ss
+            .search_items(HashMap::from([("test_sort_in_ss", "sort")]))
+            .await
+            .unwrap();
+        items.sort_by_label().await.unwrap();
+        let mut labels = Vec::new();
+        for item in &items.unlocked {
+            labels.push(item.get_label().await.unwrap());
+        }
+        assert_eq!(labels, ["Sort a", "sort b", "sort c"]);
+
+        sort_by_modified(&mut items.unlocked).await.unwrap();
+        for pair in items.unlocked.windows(2) {
+            assert!(pair[0].get_modified().await.unwrap() >= pair[1].get_modified().await.unwrap());
+        }
+        for item in items.unlocked {
+            item.delete().await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn should_limit_and_order_search() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        for label in ["limit b", "Limit a", "limit c"] {
+            collection
+                .create_item(
+                    label,
+                    HashMap::from([("test_search_limit", "limit")]),
+                    b"test",
+                    false,
+                    "text/plain",
+                )
+                .await
+                .unwrap();
+        }
+        let attributes = HashMap::from([("test_search_limit", "limit")]);
+
+        let found = ss
+            .search(attributes.clone())
+            .order_by(SearchOrder::Label)
+            .limit(2)
+            .execute()
+            .await
+            .unwrap();
+        let mut labels = Vec::new();
+        for item in found.unlocked.iter().chain(&found.locked) {
+            labels.push(item.get_label().await.unwrap());
+        }
+        assert_eq!(labels, ["Limit a", "limit b"]);
+
+        let found = ss
+            .search(attributes.clone())
+            .limit(1)
+            .execute()
+            .await
+            .unwrap();
+        assert_eq!(found.unlocked.len() + found.locked.len(), 1);
+
+        let found = ss.search(attributes).execute().await.unwrap();
+        for item in found.unlocked {
+            item.delete().await.unwrap();
+        }
+    }
+
     #[tokio::test]
     async fn should_search_items() {
         let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
@@ -436,8 +1997,209 @@ mod test {
             .await
             .unwrap();
 
-        assert_eq!(item.item_path, search_item.unlocked[0].item_path);
+        assert_eq!(item.path, search_item.unlocked[0].path);
         assert_eq!(search_item.locked.len(), 0);
         item.delete().await.unwrap();
     }
+
+    #[tokio::test]
+    async fn should_search_items_prefetched() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+
+        let item = collection
+            .create_item(
+                "test prefetched in SS",
+                HashMap::from([("test_attribute_prefetched_in_ss", "test_value")]),
+                b"test_secret",
+                false,
+                "text/plain",
+            )
+            .await
+            .unwrap();
+
+        let search_item = ss
+            .search_items_prefetched(HashMap::from([(
+                "test_attribute_prefetched_in_ss",
+                "test_value",
+            )]))
+            .await
+            .unwrap();
+
+        assert_eq!(search_item.unlocked.len(), 1);
+        let found = &search_item.unlocked[0];
+        assert_eq!(item.path, found.item.path);
+        assert_eq!(found.label, "test prefetched in SS");
+        assert_eq!(
+            found.attributes.get("test_attribute_prefetched_in_ss"),
+            Some(&"test_value".to_owned())
+        );
+        item.delete().await.unwrap();
+    }
+
+    #[test_with::no_env(GITHUB_ACTIONS)]
+    #[tokio::test]
+    async fn should_search_items_grouped() {
+        let harness = TestHarness::start().unwrap();
+        let ss = harness.connect(EncryptionType::Plain).await.unwrap();
+        let default = ss.get_default_collection().await.unwrap();
+        let other = ss.create_collection("TestGrouped", "").await.unwrap();
+        let attributes = HashMap::from([("test_attribute_grouped", "test_value")]);
+        let mut created = Vec::new();
+        for collection in [&default, &default, &other] {
+            let item = collection
+                .create_item("Test", attributes.clone(), b"test", false, "text/plain")
+                .await
+                .unwrap();
+            created.push(item.path());
+        }
+
+        let grouped = ss.search_items_grouped(attributes).await.unwrap();
+        assert_eq!(grouped.len(), 2);
+        let group = |collection: &Collection<'_>| {
+            grouped
+                .iter()
+                .find(|(grouped, _)| grouped.path() == collection.path())
+                .map(|(_, items)| items)
+                .unwrap()
+        };
+        let in_default: Vec<_> = group(&default).iter().map(Item::path).collect();
+        assert_eq!(in_default.len(), 2);
+        assert!(in_default.contains(&created[0]) && in_default.contains(&created[1]));
+        assert_eq!(group(&other).len(), 1);
+        assert_eq!(group(&other)[0].path(), created[2]);
+
+        for item in group(&default) {
+            item.delete().await.unwrap();
+        }
+        other.delete().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore] // locks collections, and will prompt to unlock them again.
+    async fn should_lock_all() {
+        let harness = TestHarness::start().unwrap();
+        let ss = harness.connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.create_collection("TestLockAll", "").await.unwrap();
+        let default = ss.get_default_collection().await.unwrap();
+        let item = default
+            .create_item("Test", HashMap::new(), b"test", false, "text/plain")
+            .await
+            .unwrap();
+
+        let outcome = ss
+            .lock_all(&[(&collection).into(), (&item).into()])
+            .await
+            .unwrap();
+        assert!(outcome.is_success());
+        assert_eq!(outcome.results[0].0, collection.path());
+        assert_eq!(outcome.results[1].0, item.path());
+        assert!(collection.is_locked().await.unwrap());
+        assert!(item.is_locked().await.unwrap());
+
+        item.unlock().await.unwrap();
+        item.delete().await.unwrap();
+        collection.unlock().await.unwrap();
+        collection.delete().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_get_secrets() {
+        let ss = SecretService::connect(EncryptionType::Dh).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let first = collection
+            .create_item("Test", HashMap::new(), b"first", false, "text/plain")
+            .await
+            .unwrap();
+        let second = collection
+            .create_item("Test", HashMap::new(), b"second", false, "text/plain")
+            .await
+            .unwrap();
+
+        let outcome = ss.get_secrets(&[&second, &first, &second]).await.unwrap();
+        let secrets = outcome.into_result().unwrap();
+        assert_eq!(
+            secrets,
+            [
+                (second.path(), b"second".to_vec()),
+                (first.path(), b"first".to_vec()),
+                (second.path(), b"second".to_vec())
+            ]
+        );
+        assert!(ss.get_secrets(&[]).await.unwrap().results.is_empty());
+
+        first.delete().await.unwrap();
+        second.delete().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_watch_item_events() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let mut events = Box::pin(ss.watch().await.unwrap());
+
+        let item = collection
+            .create_item(
+                "test watch in SS",
+                HashMap::from([("test_attribute_watch_in_ss", "test_value")]),
+                b"test_secret",
+                false,
+                "text/plain",
+            )
+            .await
+            .unwrap();
+        let created = SecretServiceEvent::ItemCreated {
+            collection: collection.path(),
+            item: item.path(),
+        };
+        while events.next().await.unwrap() != created {}
+
+        let deleted = SecretServiceEvent::ItemDeleted {
+            collection: collection.path(),
+            item: item.path(),
+        };
+        item.delete().await.unwrap();
+        while events.next().await.unwrap() != deleted {}
+    }
+
+    #[tokio::test]
+    #[ignore] // locks a collection, and will prompt to unlock it again.
+    async fn should_receive_collection_events() {
+        let harness = TestHarness::start().unwrap();
+        let ss = harness.connect(EncryptionType::Plain).await.unwrap();
+        let mut events = Box::pin(ss.receive_collection_events().await.unwrap());
+
+        let collection = ss.create_collection("TestEvents", "").await.unwrap();
+        let path = collection.path();
+        while events.next().await.unwrap() != CollectionEvent::Created(path.clone()) {}
+
+        collection.lock().await.unwrap();
+        while events.next().await.unwrap() != CollectionEvent::Changed(path.clone()) {}
+
+        collection.unlock().await.unwrap();
+        collection.delete().await.unwrap();
+        while events.next().await.unwrap() != CollectionEvent::Deleted(path.clone()) {}
+    }
+
+    #[tokio::test]
+    #[ignore] // locks the default collection, and will prompt to unlock it again.
+    async fn should_watch_lock_state() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        collection.unlock().await.unwrap();
+        let mut events = Box::pin(ss.watch().await.unwrap());
+
+        for locked in [true, false] {
+            if locked {
+                collection.lock().await.unwrap();
+            } else {
+                collection.unlock().await.unwrap();
+            }
+            let changed = SecretServiceEvent::LockStateChanged {
+                path: collection.path(),
+                locked,
+            };
+            while events.next().await.unwrap() != changed {}
+        }
+    }
 }
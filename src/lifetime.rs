@@ -0,0 +1,69 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+// Ends the signal streams handed out by a SecretService once it's dropped,
+// so that their subscriptions don't outlive it on a shared connection.
+
+use async_lock::OnceCell;
+use futures_util::future::{self, Either};
+use futures_util::{Stream, StreamExt};
+use std::sync::Arc;
+
+/// Ends the streams bound to it when dropped.
+#[derive(Debug, Default)]
+pub(crate) struct Lifetime {
+    /// Set once the lifetime is over.
+    ended: Arc<OnceCell<()>>,
+}
+
+impl Lifetime {
+    /// Returns `stream`, ending once this lifetime is over.
+    ///
+    /// The wrapped stream is dropped as soon as the lifetime ends and the
+    /// returned stream is polled, which for `zbus` message streams also
+    /// removes their match rule.
+    pub(crate) fn bind<S>(&self, stream: S) -> impl Stream<Item = S::Item> + Send + 'static
+    where
+        S: Stream + Send + 'static,
+    {
+        let ended = Arc::clone(&self.ended);
+        futures_util::stream::unfold(Box::pin(stream), move |mut stream| {
+            let ended = Arc::clone(&ended);
+            async move {
+                // checked first, so that a busy stream still ends
+                match future::select(Box::pin(ended.wait()), stream.next()).await {
+                    Either::Right((Some(item), _)) => Some((item, stream)),
+                    _ => None,
+                }
+            }
+        })
+        .fuse()
+    }
+}
+
+impl Drop for Lifetime {
+    fn drop(&mut self) {
+        // nothing else sets the cell, so this never blocks
+        let _ = self.ended.set_blocking(());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_end_bound_streams() {
+        let lifetime = Lifetime::default();
+        let mut bound = Box::pin(lifetime.bind(futures_util::stream::repeat(1)));
+        assert_eq!(zbus::block_on(bound.next()), Some(1));
+
+        drop(lifetime);
+        assert_eq!(zbus::block_on(bound.next()), None);
+        assert_eq!(zbus::block_on(bound.next()), None);
+    }
+}
@@ -0,0 +1,246 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Cleaning up collections and items left behind, such as those of test
+//! runs.
+//!
+//! [SecretService::cleanup](crate::SecretService::cleanup) finds the
+//! collections and items a [Matcher] selects, reports each to a callback
+//! deciding whether to delete it, and deletes the confirmed ones.
+//!
+//! ```no_run
+//! # async fn call(ss: &secret_service::SecretService<'_>) -> Result<(), secret_service::Error> {
+//! use secret_service::maintenance::Matcher;
+//!
+//! let matcher = Matcher::LabelPrefix("Test".to_owned());
+//! // lists what would be deleted
+//! let found = ss.cleanup(&matcher, true, |_, _| true).await?;
+//! for (path, object) in found.succeeded() {
+//!     println!("{:?} {path}: {}", object.kind, object.label);
+//! }
+//! // deletes the items, but no collection
+//! let outcome = ss
+//!     .cleanup(&matcher, false, |_, object| object.kind == ObjectKind::Item)
+//!     .await?;
+//! # use secret_service::maintenance::ObjectKind;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! A collection matching is deleted with all of its items, which aren't
+//! reported separately, so a prefix matching the label of the default
+//! collection, such as `Log` for `Login`, deletes the user's passwords.
+//! Dry runs are cheap; make one first.
+
+use crate::util::{prefetch_items, prefetch_items_blocking};
+use crate::{blocking, BatchOutcome, Error, ObjectPath, SecretService};
+
+use std::collections::HashMap;
+
+/// Selects the objects to clean up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Matcher {
+    /// Collections and items whose label starts with the prefix.
+    LabelPrefix(String),
+    /// Items holding the attribute `name` with a value starting with
+    /// `prefix`. Collections have no attributes, so none match.
+    AttributePrefix { name: String, prefix: String },
+}
+
+impl Matcher {
+    fn matches_collection(&self, label: &str) -> bool {
+        match self {
+            Matcher::LabelPrefix(prefix) => label.starts_with(prefix.as_str()),
+            Matcher::AttributePrefix { .. } => false,
+        }
+    }
+
+    fn matches_item(&self, label: &str, attributes: &HashMap<String, String>) -> bool {
+        match self {
+            Matcher::LabelPrefix(prefix) => label.starts_with(prefix.as_str()),
+            Matcher::AttributePrefix { name, prefix } => attributes
+                .get(name)
+                .is_some_and(|value| value.starts_with(prefix.as_str())),
+        }
+    }
+}
+
+/// Whether a cleaned up object is a collection or an item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ObjectKind {
+    Collection,
+    Item,
+}
+
+/// An object found by a [Matcher].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FoundObject {
+    pub kind: ObjectKind,
+    pub label: String,
+}
+
+pub(crate) async fn cleanup(
+    ss: &SecretService<'_>,
+    matcher: &Matcher,
+    dry_run: bool,
+    mut confirm: impl FnMut(&ObjectPath, &FoundObject) -> bool,
+) -> Result<BatchOutcome<FoundObject>, Error> {
+    let mut results = Vec::new();
+    for collection in ss.get_all_collections().await? {
+        let label = collection.get_label().await?;
+        if matcher.matches_collection(&label) {
+            let found = FoundObject {
+                kind: ObjectKind::Collection,
+                label,
+            };
+            let path = collection.path();
+            if dry_run {
+                results.push((path, Ok(found)));
+            } else if confirm(&path, &found) {
+                let deleted = collection.delete().await.map(|()| found);
+                results.push((path, deleted));
+            }
+            continue;
+        }
+
+        let items = prefetch_items(&ss.config, collection.get_all_items().await?).await?;
+        for prefetched in items {
+            if !matcher.matches_item(&prefetched.label, &prefetched.attributes) {
+                continue;
+            }
+            let found = FoundObject {
+                kind: ObjectKind::Item,
+                label: prefetched.label,
+            };
+            let path = prefetched.item.path();
+            if dry_run {
+                results.push((path, Ok(found)));
+            } else if confirm(&path, &found) {
+                let deleted = prefetched.item.delete().await.map(|()| found);
+                results.push((path, deleted));
+            }
+        }
+    }
+    Ok(BatchOutcome { results })
+}
+
+pub(crate) fn cleanup_blocking(
+    ss: &blocking::SecretService<'_>,
+    matcher: &Matcher,
+    dry_run: bool,
+    mut confirm: impl FnMut(&ObjectPath, &FoundObject) -> bool,
+) -> Result<BatchOutcome<FoundObject>, Error> {
+    let mut results = Vec::new();
+    for collection in ss.get_all_collections()? {
+        let label = collection.get_label()?;
+        if matcher.matches_collection(&label) {
+            let found = FoundObject {
+                kind: ObjectKind::Collection,
+                label,
+            };
+            let path = collection.path();
+            if dry_run {
+                results.push((path, Ok(found)));
+            } else if confirm(&path, &found) {
+                let deleted = collection.delete().map(|()| found);
+                results.push((path, deleted));
+            }
+            continue;
+        }
+
+        for prefetched in prefetch_items_blocking(collection.get_all_items()?)? {
+            if !matcher.matches_item(&prefetched.label, &prefetched.attributes) {
+                continue;
+            }
+            let found = FoundObject {
+                kind: ObjectKind::Item,
+                label: prefetched.label,
+            };
+            let path = prefetched.item.path();
+            if dry_run {
+                results.push((path, Ok(found)));
+            } else if confirm(&path, &found) {
+                let deleted = prefetched.item.delete().map(|()| found);
+                results.push((path, deleted));
+            }
+        }
+    }
+    Ok(BatchOutcome { results })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::TestHarness;
+    use crate::EncryptionType;
+
+    #[test]
+    fn should_match_objects() {
+        let attributes = HashMap::from([("test_run".to_owned(), "run-42".to_owned())]);
+        let by_label = Matcher::LabelPrefix("Test".to_owned());
+        assert!(by_label.matches_collection("TestCollection"));
+        assert!(!by_label.matches_collection("Login"));
+        assert!(by_label.matches_item("Test item", &HashMap::new()));
+        assert!(!by_label.matches_item("A test", &attributes));
+
+        let by_attribute = Matcher::AttributePrefix {
+            name: "test_run".to_owned(),
+            prefix: "run-".to_owned(),
+        };
+        assert!(!by_attribute.matches_collection("TestCollection"));
+        assert!(by_attribute.matches_item("Anything", &attributes));
+        assert!(!by_attribute.matches_item("Anything", &HashMap::new()));
+    }
+
+    #[test_with::no_env(GITHUB_ACTIONS)]
+    #[tokio::test]
+    async fn should_clean_up_matching_objects() {
+        let harness = TestHarness::start().unwrap();
+        let ss = harness.connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.create_collection("TestCleanup", "").await.unwrap();
+        let default = ss.get_default_collection().await.unwrap();
+        let attributes = HashMap::from([("test_attribute_cleanup", "leftover-1")]);
+        let kept = default
+            .create_item("Kept", attributes.clone(), b"test", false, "text/plain")
+            .await
+            .unwrap();
+        let leftover = default
+            .create_item("Leftover", attributes, b"test", false, "text/plain")
+            .await
+            .unwrap();
+        let matcher = Matcher::AttributePrefix {
+            name: "test_attribute_cleanup".to_owned(),
+            prefix: "leftover-".to_owned(),
+        };
+
+        let found = ss.cleanup(&matcher, true, |_, _| false).await.unwrap();
+        assert_eq!(found.results.len(), 2);
+        // nothing was deleted
+        assert_eq!(kept.get_label().await.unwrap(), "Kept");
+        assert_eq!(leftover.get_label().await.unwrap(), "Leftover");
+
+        let outcome = ss
+            .cleanup(&matcher, false, |_, found| found.label == "Leftover")
+            .await
+            .unwrap();
+        let deleted = outcome.into_result().unwrap();
+        assert_eq!(deleted.len(), 1);
+        assert_eq!(deleted[0].0, leftover.path());
+        assert_eq!(kept.get_label().await.unwrap(), "Kept");
+
+        let matcher = Matcher::LabelPrefix("TestCleanup".to_owned());
+        let outcome = ss.cleanup(&matcher, false, |_, _| true).await.unwrap();
+        let deleted = outcome.into_result().unwrap();
+        assert_eq!(deleted.len(), 1);
+        assert_eq!(deleted[0].0, collection.path());
+        assert_eq!(deleted[0].1.kind, ObjectKind::Collection);
+
+        kept.delete().await.unwrap();
+    }
+}
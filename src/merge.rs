@@ -0,0 +1,162 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+// Copying the items of one collection into another.
+
+use crate::cipher::ENVELOPE_KEY_ATTRIBUTE;
+use crate::compression::strip_encoding;
+use crate::import::{conflict_policy, imported};
+use crate::transaction::borrowed_attributes;
+use crate::util::{decrypt_secret, limit_concurrency};
+use crate::{blocking, BatchOutcome, Collection, ConflictPolicy, Error, Imported, Item};
+
+use std::collections::HashMap;
+
+/// How many secrets are fetched at once, and so held in memory.
+const MERGE_BATCH_SIZE: usize = 32;
+
+pub(crate) async fn merge_from(
+    target: &Collection<'_>,
+    source: &Collection<'_>,
+    conflict: ConflictPolicy,
+) -> Result<BatchOutcome<Imported>, Error> {
+    let items = source.get_all_items().await?;
+
+    let mut results = Vec::new();
+    for batch in items.chunks(MERGE_BATCH_SIZE) {
+        let mut secrets = source
+            .service_proxy
+            .get_secrets(
+                batch.iter().map(|item| &*item.path).collect(),
+                source.session.open().await?,
+            )
+            .await?;
+        let merged = futures_util::future::join_all(batch.iter().map(|item| {
+            let secret = secrets.remove(&item.path);
+            limit_concurrency(target.config, async move {
+                // not returned for locked items
+                let secret = secret.ok_or(Error::Locked)?;
+                let content_type = strip_encoding(secret.content_type.clone());
+                let secret = decrypt_secret(source.session, source.config, secret)?;
+                let (label, attributes) =
+                    futures_util::try_join!(item.get_label(), item.get_attributes())?;
+                let created = target
+                    .create_item(
+                        &label,
+                        copied_attributes(&attributes),
+                        &secret,
+                        conflict_policy(&attributes, conflict),
+                        &content_type,
+                    )
+                    .await;
+                imported(created.map(|created| created.path()))
+            })
+        }))
+        .await;
+        results.extend(batch.iter().map(Item::path).zip(merged));
+    }
+    Ok(BatchOutcome { results })
+}
+
+pub(crate) fn merge_from_blocking(
+    target: &blocking::Collection<'_>,
+    source: &blocking::Collection<'_>,
+    conflict: ConflictPolicy,
+) -> Result<BatchOutcome<Imported>, Error> {
+    let items = source.get_all_items()?;
+
+    let mut results = Vec::new();
+    for batch in items.chunks(MERGE_BATCH_SIZE) {
+        let mut secrets = source.service_proxy.get_secrets(
+            batch.iter().map(|item| &*item.path).collect(),
+            source.session.open_blocking()?,
+        )?;
+        results.extend(batch.iter().map(|item| {
+            let merged = (|| {
+                let secret = secrets.remove(&item.path).ok_or(Error::Locked)?;
+                let content_type = strip_encoding(secret.content_type.clone());
+                let secret = decrypt_secret(source.session, source.config, secret)?;
+                let attributes = item.get_attributes()?;
+                let created = target.create_item(
+                    &item.get_label()?,
+                    copied_attributes(&attributes),
+                    &secret,
+                    conflict_policy(&attributes, conflict),
+                    &content_type,
+                );
+                imported(created.map(|created| created.path()))
+            })();
+            (item.path(), merged)
+        }));
+    }
+    Ok(BatchOutcome { results })
+}
+
+/// Leaves out the envelope key id, which the target records for itself if
+/// it encrypts the secret.
+fn copied_attributes(attributes: &HashMap<String, String>) -> HashMap<&str, &str> {
+    let mut attributes = borrowed_attributes(attributes);
+    attributes.remove(ENVELOPE_KEY_ATTRIBUTE);
+    attributes
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::TestHarness;
+    use crate::EncryptionType;
+
+    #[test_with::no_env(GITHUB_ACTIONS)]
+    #[tokio::test]
+    async fn should_merge_collections() {
+        let harness = TestHarness::start().unwrap();
+        let ss = harness.connect(EncryptionType::Plain).await.unwrap();
+        let source = ss.create_collection("TestMergeSource", "").await.unwrap();
+        let target = ss.create_collection("TestMergeTarget", "").await.unwrap();
+        let attributes = HashMap::from([("test_attribute_merge", "test_value")]);
+        for (collection, secret) in [(&source, b"new"), (&target, b"old")] {
+            collection
+                .create_item("Test", attributes.clone(), secret, false, "text/plain")
+                .await
+                .unwrap();
+        }
+        source
+            .create_item("TestOther", HashMap::new(), b"other", false, "text/x-other")
+            .await
+            .unwrap();
+
+        let outcome = target
+            .merge_from(&source, ConflictPolicy::Fail)
+            .await
+            .unwrap();
+        let merged: Vec<_> = outcome.into_result().unwrap();
+        assert_eq!(merged.len(), 2);
+        assert_eq!(
+            merged
+                .iter()
+                .filter(|(_, merged)| *merged == Imported::Skipped)
+                .count(),
+            1
+        );
+        let found = target.search_items(attributes.clone()).await.unwrap();
+        assert_eq!(found[0].get_secret().await.unwrap(), b"old");
+
+        let outcome = target
+            .merge_from(&source, ConflictPolicy::Replace)
+            .await
+            .unwrap();
+        assert!(outcome.is_success());
+        let found = target.search_items(attributes).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].get_secret().await.unwrap(), b"new");
+        let items = target.get_all_items().await.unwrap();
+        assert_eq!(items.len(), 3);
+
+        source.delete().await.unwrap();
+        target.delete().await.unwrap();
+    }
+}
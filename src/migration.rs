@@ -0,0 +1,323 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Upgrading the labels and attributes of items as an application's schema
+//! changes.
+//!
+//! Items record the version of the schema they were stored with in the
+//! [SCHEMA_VERSION_ATTRIBUTE] attribute, which items stored before adopting
+//! migrations lack and count as version 0. An application registers a
+//! function for each change to its schema, the first upgrading items from
+//! version 0 to 1, the second from 1 to 2, and so on, and
+//! [Collection::migrate](crate::Collection::migrate) runs the ones each item
+//! is missing.
+//!
+//! ```no_run
+//! # async fn call(collection: &secret_service::Collection<'_>) -> Result<(), secret_service::Error> {
+//! use secret_service::migration::Migrations;
+//! use std::collections::HashMap;
+//!
+//! let migrations = Migrations::new(HashMap::from([("application", "my-app")]))
+//!     // version 1 renamed `user` to `username`
+//!     .register(|item| {
+//!         if let Some(user) = item.attributes.remove("user") {
+//!             item.attributes.insert("username".to_owned(), user);
+//!         }
+//!     })
+//!     // version 2 prefixed labels with the application name
+//!     .register(|item| item.label = format!("My App: {}", item.label));
+//!
+//! let outcome = collection.migrate(&migrations, false, |_| ()).await?;
+//! for (path, err) in outcome.failed() {
+//!     eprintln!("{path} not migrated: {err}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Items are found by the attributes given to [Migrations::new], so those
+//! shouldn't be changed by the migrations themselves. Items of a newer
+//! version than the migrations know, stored by a newer release of the
+//! application, are left alone. Secrets aren't migrated.
+
+use crate::transaction::{borrowed_attributes, owned_attributes};
+use crate::{blocking, BatchOutcome, Collection, Error, Item, ObjectPath, Progress};
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// Attribute recording the version of the schema an item was stored with.
+pub const SCHEMA_VERSION_ATTRIBUTE: &str = "schema-version";
+
+type Migration = Box<dyn Fn(&mut MigratedItem) + Send + Sync>;
+
+/// The migrations of an application's items, see the [module](self)
+/// documentation.
+pub struct Migrations {
+    schema: HashMap<String, String>,
+    migrations: Vec<Migration>,
+}
+
+/// The label and attributes of an item, as changed by a migration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigratedItem {
+    pub label: String,
+    pub attributes: HashMap<String, String>,
+}
+
+impl Migrations {
+    /// Creates migrations for the items holding `schema`, the attributes
+    /// identifying an application's items.
+    pub fn new(schema: HashMap<&str, &str>) -> Self {
+        Migrations {
+            schema: owned_attributes(schema),
+            migrations: Vec::new(),
+        }
+    }
+
+    /// Adds the migration upgrading items to the next version.
+    pub fn register(
+        mut self,
+        migration: impl Fn(&mut MigratedItem) + Send + Sync + 'static,
+    ) -> Self {
+        self.migrations.push(Box::new(migration));
+        self
+    }
+
+    /// The version items are upgraded to, which is the number of
+    /// migrations.
+    pub fn version(&self) -> u32 {
+        self.migrations.len() as u32
+    }
+
+    /// Runs the migrations an item with `label` and `attributes` is missing,
+    /// returning its version and the upgraded item, or `None` if it's up to
+    /// date.
+    fn apply(
+        &self,
+        label: &str,
+        attributes: &HashMap<String, String>,
+    ) -> Result<Option<(u32, MigratedItem)>, Error> {
+        let version = match attributes.get(SCHEMA_VERSION_ATTRIBUTE) {
+            Some(version) => version
+                .parse()
+                .map_err(|_| Error::InvalidSchemaVersion(version.clone()))?,
+            None => 0,
+        };
+        if version >= self.version() {
+            return Ok(None);
+        }
+
+        let mut item = MigratedItem {
+            label: label.to_owned(),
+            attributes: attributes.clone(),
+        };
+        for migration in &self.migrations[version as usize..] {
+            migration(&mut item);
+        }
+        item.attributes.insert(
+            SCHEMA_VERSION_ATTRIBUTE.to_owned(),
+            self.version().to_string(),
+        );
+        Ok(Some((version, item)))
+    }
+}
+
+impl fmt::Debug for Migrations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Migrations")
+            .field("schema", &self.schema)
+            .field("version", &self.version())
+            .finish()
+    }
+}
+
+pub(crate) async fn migrate(
+    collection: &Collection<'_>,
+    migrations: &Migrations,
+    dry_run: bool,
+    mut progress: impl FnMut(&Progress),
+) -> Result<BatchOutcome<u32>, Error> {
+    let items = collection
+        .search_items(borrowed_attributes(&migrations.schema))
+        .await?;
+
+    let mut state = Progress::new(Some(items.len()));
+    let mut results = Vec::new();
+    for item in &items {
+        let migrated = migrate_item(item, migrations, dry_run).await;
+        record(
+            item.path(),
+            migrated,
+            &mut state,
+            &mut progress,
+            &mut results,
+        );
+    }
+    Ok(BatchOutcome { results })
+}
+
+/// Returns the label of the item, and the version it was migrated from.
+async fn migrate_item(
+    item: &Item<'_>,
+    migrations: &Migrations,
+    dry_run: bool,
+) -> Result<(String, Option<u32>), Error> {
+    let (label, attributes) = futures_util::try_join!(item.get_label(), item.get_attributes())?;
+    let Some((version, migrated)) = migrations.apply(&label, &attributes)? else {
+        return Ok((label, None));
+    };
+    if !dry_run {
+        if migrated.label != label {
+            item.set_label(&migrated.label).await?;
+        }
+        item.set_attributes(borrowed_attributes(&migrated.attributes))
+            .await?;
+    }
+    Ok((label, Some(version)))
+}
+
+pub(crate) fn migrate_blocking(
+    collection: &blocking::Collection<'_>,
+    migrations: &Migrations,
+    dry_run: bool,
+    mut progress: impl FnMut(&Progress),
+) -> Result<BatchOutcome<u32>, Error> {
+    let items = collection.search_items(borrowed_attributes(&migrations.schema))?;
+
+    let mut state = Progress::new(Some(items.len()));
+    let mut results = Vec::new();
+    for item in &items {
+        let migrated = (|| {
+            let label = item.get_label()?;
+            let Some((version, migrated)) = migrations.apply(&label, &item.get_attributes()?)?
+            else {
+                return Ok((label, None));
+            };
+            if !dry_run {
+                if migrated.label != label {
+                    item.set_label(&migrated.label)?;
+                }
+                item.set_attributes(borrowed_attributes(&migrated.attributes))?;
+            }
+            Ok((label, Some(version)))
+        })();
+        record(
+            item.path(),
+            migrated,
+            &mut state,
+            &mut progress,
+            &mut results,
+        );
+    }
+    Ok(BatchOutcome { results })
+}
+
+/// Reports an item handled, listing it in the outcome unless it was up to
+/// date.
+fn record(
+    path: ObjectPath,
+    migrated: Result<(String, Option<u32>), Error>,
+    state: &mut Progress,
+    progress: &mut impl FnMut(&Progress),
+    results: &mut Vec<(ObjectPath, Result<u32, Error>)>,
+) {
+    let label = migrated.as_ref().ok().map(|(label, _)| label.as_str());
+    state.advance(label, &migrated, progress);
+    match migrated {
+        Ok((_, Some(version))) => results.push((path, Ok(version))),
+        Ok((_, None)) => (),
+        Err(err) => results.push((path, Err(err))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{EncryptionType, SecretService};
+
+    fn migrations() -> Migrations {
+        Migrations::new(HashMap::from([("test_attribute_migration", "test_value")]))
+            .register(|item| {
+                let user = item.attributes.remove("user").unwrap_or_default();
+                item.attributes.insert("username".to_owned(), user);
+            })
+            .register(|item| item.label = format!("Migrated{}", item.label))
+    }
+
+    #[test]
+    fn should_apply_missing_migrations() {
+        let migrations = migrations();
+        let attributes = HashMap::from([("user".to_owned(), "alice".to_owned())]);
+        let (version, migrated) = migrations.apply("Test", &attributes).unwrap().unwrap();
+        assert_eq!(version, 0);
+        assert_eq!(migrated.label, "MigratedTest");
+        assert_eq!(
+            migrated.attributes,
+            HashMap::from([
+                ("username".to_owned(), "alice".to_owned()),
+                (SCHEMA_VERSION_ATTRIBUTE.to_owned(), "2".to_owned()),
+            ])
+        );
+
+        let attributes = HashMap::from([(SCHEMA_VERSION_ATTRIBUTE.to_owned(), "1".to_owned())]);
+        let (version, migrated) = migrations.apply("Test", &attributes).unwrap().unwrap();
+        assert_eq!(version, 1);
+        assert!(!migrated.attributes.contains_key("username"));
+
+        let attributes = HashMap::from([(SCHEMA_VERSION_ATTRIBUTE.to_owned(), "3".to_owned())]);
+        assert!(migrations.apply("Test", &attributes).unwrap().is_none());
+        let attributes = HashMap::from([(SCHEMA_VERSION_ATTRIBUTE.to_owned(), "x".to_owned())]);
+        assert!(matches!(
+            migrations.apply("Test", &attributes),
+            Err(Error::InvalidSchemaVersion(version)) if version == "x"
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_migrate_collection() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let item = collection
+            .create_item(
+                "Test",
+                HashMap::from([
+                    ("test_attribute_migration", "test_value"),
+                    ("user", "alice"),
+                ]),
+                b"test",
+                false,
+                "text/plain",
+            )
+            .await
+            .unwrap();
+        let migrations = migrations();
+
+        let outcome = collection.migrate(&migrations, true, |_| ()).await.unwrap();
+        assert_eq!(outcome.into_result().unwrap(), [(item.path(), 0)]);
+        assert_eq!(item.get_label().await.unwrap(), "Test");
+
+        let mut reported = 0;
+        let outcome = collection
+            .migrate(&migrations, false, |progress| reported = progress.done)
+            .await
+            .unwrap();
+        assert!(outcome.is_success());
+        assert_eq!(reported, 1);
+        assert_eq!(item.get_label().await.unwrap(), "MigratedTest");
+        let attributes = item.get_attributes().await.unwrap();
+        assert_eq!(attributes["username"], "alice");
+        assert_eq!(attributes[SCHEMA_VERSION_ATTRIBUTE], "2");
+
+        let outcome = collection
+            .migrate(&migrations, false, |_| ())
+            .await
+            .unwrap();
+        assert!(outcome.results.is_empty());
+        item.delete().await.unwrap();
+    }
+}
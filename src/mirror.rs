@@ -0,0 +1,323 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+// A local copy of the metadata of a collection's items, kept up to date
+// through the service's signals.
+
+use crate::builder::Config;
+use crate::event::{match_rule, parse_event};
+use crate::proxy::collection::CollectionProxy;
+use crate::proxy::item::ItemProxy;
+use crate::{util, Error, ObjectPath, SecretServiceEvent};
+
+use event_listener::Event;
+use futures_util::StreamExt;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use zbus::zvariant::OwnedObjectPath;
+use zbus::{CacheProperties, MessageStream, Task};
+
+/// The metadata of an item, as held by a [CollectionMirror].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ItemMetadata {
+    pub label: String,
+    pub attributes: HashMap<String, String>,
+    pub locked: bool,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    items: HashMap<ObjectPath, ItemMetadata>,
+    /// Paths of the items holding each attribute.
+    index: HashMap<(String, String), HashSet<ObjectPath>>,
+}
+
+impl State {
+    fn insert(&mut self, path: ObjectPath, metadata: ItemMetadata) {
+        self.remove(&path);
+        for attribute in metadata.attributes.clone() {
+            self.index
+                .entry(attribute)
+                .or_default()
+                .insert(path.clone());
+        }
+        self.items.insert(path, metadata);
+    }
+
+    fn remove(&mut self, path: &ObjectPath) {
+        let Some(metadata) = self.items.remove(path) else {
+            return;
+        };
+        for attribute in metadata.attributes {
+            if let Some(paths) = self.index.get_mut(&attribute) {
+                paths.remove(path);
+                if paths.is_empty() {
+                    self.index.remove(&attribute);
+                }
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.items.clear();
+        self.index.clear();
+    }
+
+    fn search(&self, attributes: &HashMap<&str, &str>) -> Vec<ObjectPath> {
+        let mut sets = attributes.iter().map(|(key, value)| {
+            self.index
+                .get(&(key.to_string(), value.to_string()))
+                .cloned()
+                .unwrap_or_default()
+        });
+        let Some(first) = sets.next() else {
+            return self.items.keys().cloned().collect();
+        };
+        sets.fold(first, |acc, set| &acc & &set)
+            .into_iter()
+            .collect()
+    }
+}
+
+/// A local copy of the label, attributes and lock state of every item in a
+/// collection, created with [Collection::mirror](crate::Collection::mirror).
+///
+/// The copy is kept up to date in the background from the signals of the
+/// collection and its items, so reading it never makes a call to the
+/// provider. Updates stop when the mirror, or the
+/// [SecretService](crate::SecretService) it was created from, is dropped.
+///
+/// Updates arrive asynchronously, so a change made through the API may take a
+/// moment to show up in the mirror. [CollectionMirror::changed] waits for the
+/// next one.
+///
+/// The mirror also indexes the items by attribute, so that it can answer
+/// exact-match searches locally, see [CollectionMirror::search] and
+/// [Collection::search_items_indexed](crate::Collection::search_items_indexed).
+#[derive(Debug)]
+pub struct CollectionMirror {
+    collection_path: ObjectPath,
+    state: Arc<Mutex<State>>,
+    changed: Arc<Event>,
+    _updater: Task<()>,
+}
+
+impl CollectionMirror {
+    pub(crate) async fn new(
+        conn: &zbus::Connection,
+        config: &Config,
+        collection_proxy: &CollectionProxy<'_>,
+        collection_path: ObjectPath,
+    ) -> Result<Self, Error> {
+        // subscribe before listing the items, so that no change is missed
+        let signals = MessageStream::for_match_rule(match_rule(config)?, conn, None).await?;
+        let mut signals = Box::pin(config.lifetime.bind(signals));
+
+        let item_paths = collection_proxy.items().await?;
+        let items = futures_util::future::try_join_all(item_paths.into_iter().map(|path| {
+            let path = ObjectPath::from(OwnedObjectPath::from(path));
+            util::limit_concurrency(config, async move {
+                let metadata = fetch_metadata(conn, &config.destination, &path).await?;
+                Ok::<_, Error>((path, metadata))
+            })
+        }))
+        .await?;
+        let mut state = State::default();
+        for (path, metadata) in items {
+            state.insert(path, metadata);
+        }
+        let state = Arc::new(Mutex::new(state));
+
+        let changed = Arc::new(Event::new());
+
+        let updater = {
+            let state = Arc::clone(&state);
+            let changed = Arc::clone(&changed);
+            let collection_path = collection_path.clone();
+            let task_conn = conn.clone();
+            let destination = config.destination.clone();
+            conn.executor().spawn(
+                async move {
+                    while let Some(Ok(msg)) = signals.next().await {
+                        if let Some(event) = parse_event(&msg) {
+                            apply_event(&task_conn, &destination, &collection_path, &state, event)
+                                .await;
+                            changed.notify(usize::MAX);
+                        }
+                    }
+                },
+                "secret-service collection mirror",
+            )
+        };
+
+        Ok(CollectionMirror {
+            collection_path,
+            state,
+            changed,
+            _updater: updater,
+        })
+    }
+
+    /// Returns a copy of the metadata of every item, keyed by item path.
+    pub fn items(&self) -> HashMap<ObjectPath, ItemMetadata> {
+        self.state.lock().unwrap().items.clone()
+    }
+
+    /// Returns the metadata of the item at `path`, if it's in the collection.
+    pub fn get(&self, path: &ObjectPath) -> Option<ItemMetadata> {
+        self.state.lock().unwrap().items.get(path).cloned()
+    }
+
+    /// Returns the paths of the items holding all of `attributes`, answered
+    /// from the mirror's index.
+    pub fn search(&self, attributes: &HashMap<&str, &str>) -> Vec<ObjectPath> {
+        self.state.lock().unwrap().search(attributes)
+    }
+
+    /// The path of the mirrored collection.
+    pub(crate) fn collection_path(&self) -> &ObjectPath {
+        &self.collection_path
+    }
+
+    /// Returns a future completing once the mirror has handled the next
+    /// signal of the service.
+    ///
+    /// The wait starts when this is called rather than when the future is
+    /// first polled, so checking the mirror in between doesn't miss an update.
+    pub fn changed(&self) -> impl Future<Output = ()> + Send + 'static {
+        self.changed.listen()
+    }
+
+    /// Returns the paths of the items whose metadata matches `predicate`.
+    pub fn filter<F>(&self, mut predicate: F) -> Vec<ObjectPath>
+    where
+        F: FnMut(&ItemMetadata) -> bool,
+    {
+        self.state
+            .lock()
+            .unwrap()
+            .items
+            .iter()
+            .filter(|(_, metadata)| predicate(metadata))
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+}
+
+async fn fetch_metadata(
+    conn: &zbus::Connection,
+    destination: &str,
+    path: &ObjectPath,
+) -> Result<ItemMetadata, Error> {
+    let proxy = ItemProxy::builder(conn)
+        .destination(destination.to_owned())?
+        .path(OwnedObjectPath::from(path.clone()))?
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?;
+    let (label, attributes, locked) =
+        futures_util::try_join!(proxy.label(), proxy.attributes(), proxy.locked())?;
+    Ok(ItemMetadata {
+        label,
+        attributes,
+        locked,
+    })
+}
+
+async fn apply_event(
+    conn: &zbus::Connection,
+    destination: &str,
+    collection_path: &ObjectPath,
+    state: &Mutex<State>,
+    event: SecretServiceEvent,
+) {
+    match event {
+        SecretServiceEvent::ItemCreated { collection, item }
+        | SecretServiceEvent::ItemChanged { collection, item }
+            if collection == *collection_path =>
+        {
+            // an item that can't be read anymore was deleted in the meantime,
+            // which its own signal reports
+            if let Ok(metadata) = fetch_metadata(conn, destination, &item).await {
+                state.lock().unwrap().insert(item, metadata);
+            }
+        }
+        SecretServiceEvent::ItemDeleted { collection, item } if collection == *collection_path => {
+            state.lock().unwrap().remove(&item);
+        }
+        SecretServiceEvent::LockStateChanged { path, locked } => {
+            let mut state = state.lock().unwrap();
+            if path == *collection_path {
+                // items follow the lock state of their collection
+                for metadata in state.items.values_mut() {
+                    metadata.locked = locked;
+                }
+            } else if let Some(metadata) = state.items.get_mut(&path) {
+                metadata.locked = locked;
+            }
+        }
+        SecretServiceEvent::CollectionDeleted(path) if path == *collection_path => {
+            state.lock().unwrap().clear();
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn metadata(attributes: &[(&str, &str)]) -> ItemMetadata {
+        ItemMetadata {
+            label: String::new(),
+            attributes: attributes
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            locked: false,
+        }
+    }
+
+    fn path(n: u32) -> ObjectPath {
+        format!("/org/freedesktop/secrets/collection/test/{n}")
+            .parse()
+            .unwrap()
+    }
+
+    fn search(state: &State, attributes: &[(&str, &str)]) -> Vec<ObjectPath> {
+        let mut found = state.search(&attributes.iter().copied().collect());
+        found.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        found
+    }
+
+    #[test]
+    fn should_search_index() {
+        let mut state = State::default();
+        state.insert(path(1), metadata(&[("service", "a"), ("user", "x")]));
+        state.insert(path(2), metadata(&[("service", "a"), ("user", "y")]));
+        state.insert(path(3), metadata(&[("service", "b"), ("user", "x")]));
+
+        assert_eq!(search(&state, &[("service", "a")]), vec![path(1), path(2)]);
+        assert_eq!(
+            search(&state, &[("service", "a"), ("user", "x")]),
+            vec![path(1)]
+        );
+        assert!(search(&state, &[("service", "c")]).is_empty());
+        assert!(search(&state, &[("service", "b"), ("user", "y")]).is_empty());
+        assert_eq!(search(&state, &[]).len(), 3);
+
+        // replacing and removing items keeps the index in sync
+        state.insert(path(1), metadata(&[("service", "b")]));
+        assert_eq!(search(&state, &[("service", "a")]), vec![path(2)]);
+        assert_eq!(search(&state, &[("service", "b")]), vec![path(1), path(3)]);
+        state.remove(&path(3));
+        assert_eq!(search(&state, &[("user", "x")]), Vec::<ObjectPath>::new());
+        assert_eq!(state.index.len(), 3);
+    }
+}
@@ -0,0 +1,221 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+// The surface shared by collections and items, for code operating on either.
+
+use crate::{Collection, Error, Item, ObjectPath};
+
+use std::future::Future;
+use zbus::zvariant::OwnedObjectPath;
+
+/// The calls shared by [Collection] and [Item], so that utilities such as
+/// locking a mix of both can be written once.
+///
+/// The methods are the inherent ones of the same name. Their futures are
+/// `Send`, so that generic code can spawn them.
+///
+/// ```no_run
+/// use secret_service::{Error, Object};
+///
+/// async fn relock<O: Object>(objects: &[O]) -> Result<(), Error> {
+///     for object in objects {
+///         if !object.is_locked().await? {
+///             object.lock().await?;
+///         }
+///     }
+///     Ok(())
+/// }
+/// ```
+pub trait Object {
+    /// The object path of the collection or item.
+    fn path(&self) -> ObjectPath;
+
+    fn get_label(&self) -> impl Future<Output = Result<String, Error>> + Send;
+
+    fn set_label(&self, new_label: &str) -> impl Future<Output = Result<(), Error>> + Send;
+
+    fn is_locked(&self) -> impl Future<Output = Result<bool, Error>> + Send;
+
+    /// Locks the object, returning the paths the provider reports as locked.
+    fn lock(&self) -> impl Future<Output = Result<Vec<ObjectPath>, Error>> + Send;
+
+    /// Unlocks the object, returning the paths the provider reports as
+    /// unlocked.
+    fn unlock(&self) -> impl Future<Output = Result<Vec<ObjectPath>, Error>> + Send;
+
+    fn get_created(&self) -> impl Future<Output = Result<u64, Error>> + Send;
+
+    fn get_modified(&self) -> impl Future<Output = Result<u64, Error>> + Send;
+
+    fn delete(&self) -> impl Future<Output = Result<(), Error>> + Send;
+}
+
+impl Object for Collection<'_> {
+    fn path(&self) -> ObjectPath {
+        Collection::path(self)
+    }
+
+    fn get_label(&self) -> impl Future<Output = Result<String, Error>> + Send {
+        Collection::get_label(self)
+    }
+
+    fn set_label(&self, new_label: &str) -> impl Future<Output = Result<(), Error>> + Send {
+        Collection::set_label(self, new_label)
+    }
+
+    fn is_locked(&self) -> impl Future<Output = Result<bool, Error>> + Send {
+        Collection::is_locked(self)
+    }
+
+    fn lock(&self) -> impl Future<Output = Result<Vec<ObjectPath>, Error>> + Send {
+        Collection::lock(self)
+    }
+
+    fn unlock(&self) -> impl Future<Output = Result<Vec<ObjectPath>, Error>> + Send {
+        Collection::unlock(self)
+    }
+
+    fn get_created(&self) -> impl Future<Output = Result<u64, Error>> + Send {
+        Collection::get_created(self)
+    }
+
+    fn get_modified(&self) -> impl Future<Output = Result<u64, Error>> + Send {
+        Collection::get_modified(self)
+    }
+
+    fn delete(&self) -> impl Future<Output = Result<(), Error>> + Send {
+        Collection::delete(self)
+    }
+}
+
+impl Object for Item<'_> {
+    fn path(&self) -> ObjectPath {
+        Item::path(self)
+    }
+
+    fn get_label(&self) -> impl Future<Output = Result<String, Error>> + Send {
+        Item::get_label(self)
+    }
+
+    fn set_label(&self, new_label: &str) -> impl Future<Output = Result<(), Error>> + Send {
+        Item::set_label(self, new_label)
+    }
+
+    fn is_locked(&self) -> impl Future<Output = Result<bool, Error>> + Send {
+        Item::is_locked(self)
+    }
+
+    fn lock(&self) -> impl Future<Output = Result<Vec<ObjectPath>, Error>> + Send {
+        Item::lock(self)
+    }
+
+    fn unlock(&self) -> impl Future<Output = Result<Vec<ObjectPath>, Error>> + Send {
+        Item::unlock(self)
+    }
+
+    fn get_created(&self) -> impl Future<Output = Result<u64, Error>> + Send {
+        Item::get_created(self)
+    }
+
+    fn get_modified(&self) -> impl Future<Output = Result<u64, Error>> + Send {
+        Item::get_modified(self)
+    }
+
+    fn delete(&self) -> impl Future<Output = Result<(), Error>> + Send {
+        Item::delete(self)
+    }
+}
+
+/// A collection or an item, for calls taking a mix of both such as
+/// [SecretService::lock_all](crate::SecretService::lock_all).
+///
+/// Converts from a reference to either:
+///
+/// ```no_run
+/// # async fn call(ss: &secret_service::SecretService<'_>, collection: &secret_service::Collection<'_>, item: &secret_service::Item<'_>) -> Result<(), secret_service::Error> {
+/// ss.lock_all(&[collection.into(), item.into()]).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy)]
+pub enum ObjectRef<'r> {
+    Collection(&'r Collection<'r>),
+    Item(&'r Item<'r>),
+}
+
+impl ObjectRef<'_> {
+    /// The object path of the collection or item.
+    pub fn path(&self) -> ObjectPath {
+        match self {
+            ObjectRef::Collection(collection) => collection.path(),
+            ObjectRef::Item(item) => item.path(),
+        }
+    }
+
+    pub(crate) fn object_path(&self) -> &OwnedObjectPath {
+        match self {
+            ObjectRef::Collection(collection) => &collection.path,
+            ObjectRef::Item(item) => &item.path,
+        }
+    }
+
+    pub(crate) async fn is_locked(&self) -> Result<bool, Error> {
+        match self {
+            ObjectRef::Collection(collection) => collection.is_locked().await,
+            ObjectRef::Item(item) => item.is_locked().await,
+        }
+    }
+}
+
+impl<'r> From<&'r Collection<'r>> for ObjectRef<'r> {
+    fn from(collection: &'r Collection<'r>) -> Self {
+        ObjectRef::Collection(collection)
+    }
+}
+
+impl<'r> From<&'r Item<'r>> for ObjectRef<'r> {
+    fn from(item: &'r Item<'r>) -> Self {
+        ObjectRef::Item(item)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{EncryptionType, SecretService};
+    use std::collections::HashMap;
+
+    fn assert_send<T: Send>(value: T) -> T {
+        value
+    }
+
+    async fn relock(object: &(impl Object + Sync)) -> bool {
+        assert_send(object.lock()).await.unwrap();
+        let locked = object.is_locked().await.unwrap();
+        object.unlock().await.unwrap();
+        locked && !object.is_locked().await.unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore] // locks the default collection, and will prompt to unlock it again.
+    async fn should_share_calls_between_collections_and_items() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let item = collection
+            .create_item("Test", HashMap::new(), b"test", false, "text/plain")
+            .await
+            .unwrap();
+
+        assert!(relock(&collection).await);
+        assert!(relock(&item).await);
+
+        Object::set_label(&item, "TestObject").await.unwrap();
+        assert_eq!(item.get_label().await.unwrap(), "TestObject");
+        Object::delete(&item).await.unwrap();
+        assert!(!item.exists().await.unwrap());
+    }
+}
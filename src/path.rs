@@ -0,0 +1,151 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+// Crate-owned object paths, so that zbus' path types can be kept out of the
+// public API.
+
+use crate::Error;
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use zbus::zvariant::{self, OwnedObjectPath};
+
+/// The object path of a collection, item or other object of the provider.
+///
+/// Unlike the `zbus` path types, this type isn't tied to a `zbus` version, so
+/// it's what the API hands out, for instance from
+/// [Collection::path](crate::Collection::path) and [Item::path](crate::Item::path).
+/// The conversions from and to
+/// [dbus::OwnedObjectPath](crate::dbus::OwnedObjectPath) are for interoperating
+/// with code using `zbus` directly, and change along with the `zbus` version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectPath(OwnedObjectPath);
+
+impl ObjectPath {
+    /// Returns the path as a string, such as
+    /// `/org/freedesktop/secrets/collection/login`.
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl fmt::Display for ObjectPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl AsRef<str> for ObjectPath {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Hash for ObjectPath {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl PartialOrd for ObjectPath {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ObjectPath {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl FromStr for ObjectPath {
+    type Err = Error;
+
+    /// Parses a path, failing with [Error::Zvariant] if it isn't a valid
+    /// D-Bus object path.
+    fn from_str(path: &str) -> Result<Self, Error> {
+        let path = zvariant::ObjectPath::try_from(path)?;
+        Ok(ObjectPath(path.into()))
+    }
+}
+
+impl TryFrom<&str> for ObjectPath {
+    type Error = Error;
+
+    fn try_from(path: &str) -> Result<Self, Error> {
+        path.parse()
+    }
+}
+
+impl From<OwnedObjectPath> for ObjectPath {
+    fn from(path: OwnedObjectPath) -> Self {
+        ObjectPath(path)
+    }
+}
+
+impl From<ObjectPath> for OwnedObjectPath {
+    fn from(path: ObjectPath) -> Self {
+        path.0
+    }
+}
+
+/// Serializes the path as a string.
+#[cfg(feature = "serde")]
+impl serde::Serialize for ObjectPath {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+/// Deserializes a string, failing if it isn't a valid D-Bus object path.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ObjectPath {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let path = String::deserialize(deserializer)?;
+        path.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_parse_object_paths() {
+        let path: ObjectPath = "/org/freedesktop/secrets/collection/login".parse().unwrap();
+        assert_eq!(path.as_str(), "/org/freedesktop/secrets/collection/login");
+        assert_eq!(path.to_string(), path.as_str());
+        assert_eq!(OwnedObjectPath::from(path.clone()).as_str(), path.as_str());
+
+        assert!(matches!(
+            "not a path".parse::<ObjectPath>(),
+            Err(Error::Zvariant(_))
+        ));
+        assert!(ObjectPath::try_from("/trailing/").is_err());
+        assert!(ObjectPath::try_from("/a").unwrap() < ObjectPath::try_from("/b").unwrap());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn should_deserialize_valid_object_paths_only() {
+        use serde::de::{value::Error as ValueError, IntoDeserializer};
+        use serde::Deserialize;
+
+        let path = "/org/freedesktop/secrets/collection/login";
+        let parsed = ObjectPath::deserialize(path.into_deserializer());
+        assert_eq!(
+            parsed.map(|path: ObjectPath| path.to_string()),
+            Ok::<_, ValueError>(path.to_owned())
+        );
+        let invalid: Result<ObjectPath, ValueError> =
+            ObjectPath::deserialize("not a path".into_deserializer());
+        assert!(invalid.is_err());
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+// Progress of operations on many items, reported to a callback.
+
+/// How far a bulk operation has come, as reported to its progress callback.
+///
+/// Bulk operations take an `FnMut(&Progress)` callback, called each time an
+/// item was handled, such as to drive a progress bar:
+/// [Collection::import](crate::Collection::import),
+/// [Collection::purge_older_than_with_progress](crate::Collection::purge_older_than_with_progress)
+/// and
+/// [SecretService::rotate_key_with_progress](crate::SecretService::rotate_key_with_progress),
+/// as well as their blocking counterparts.
+///
+/// ```no_run
+/// # async fn call(collection: &secret_service::Collection<'_>, items: Vec<secret_service::NewItem>) {
+/// collection
+///     .import(items, |progress| {
+///         let remaining = progress.remaining().map_or("?".to_owned(), |n| n.to_string());
+///         eprintln!("{} done, {remaining} to go", progress.done);
+///     })
+///     .await;
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Progress {
+    /// The number of items handled, including those that failed.
+    pub done: usize,
+    /// The number of items that failed.
+    pub failed: usize,
+    /// The number of items to handle in total, if known upfront.
+    pub total: Option<usize>,
+    /// The label of the item last handled, for the operations that know it
+    /// without asking the provider for it.
+    pub label: Option<String>,
+}
+
+impl Progress {
+    pub(crate) fn new(total: Option<usize>) -> Self {
+        Progress {
+            done: 0,
+            failed: 0,
+            total,
+            label: None,
+        }
+    }
+
+    /// Starts counting the items of `items`, whose total is known if the
+    /// iterator tells its exact length.
+    pub(crate) fn of(items: &impl Iterator) -> Self {
+        Self::new(match items.size_hint() {
+            (lower, Some(upper)) if lower == upper => Some(lower),
+            _ => None,
+        })
+    }
+
+    /// Counts an item as handled and reports it to `progress`.
+    pub(crate) fn advance<T, E>(
+        &mut self,
+        label: Option<&str>,
+        result: &Result<T, E>,
+        progress: &mut impl FnMut(&Progress),
+    ) {
+        self.done += 1;
+        if result.is_err() {
+            self.failed += 1;
+        }
+        self.label = label.map(str::to_owned);
+        progress(self);
+    }
+
+    /// The number of items left to handle, if the total is known.
+    pub fn remaining(&self) -> Option<usize> {
+        Some(self.total?.saturating_sub(self.done))
+    }
+
+    /// The share of items handled, from 0 to 1, if the total is known.
+    pub fn fraction(&self) -> Option<f64> {
+        match self.total? {
+            0 => Some(1.0),
+            total => Some(self.done as f64 / total as f64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_estimate_remaining_work() {
+        let mut reported = Vec::new();
+        let mut report = |progress: &Progress| reported.push(progress.clone());
+        let mut progress = Progress::of(&[(); 4].iter());
+        progress.advance(Some("Test"), &Ok::<_, ()>(()), &mut report);
+        progress.advance(None, &Err::<(), _>(()), &mut report);
+
+        assert_eq!(reported[0].label.as_deref(), Some("Test"));
+        assert_eq!(reported[1].label, None);
+        assert_eq!(progress.failed, 1);
+        assert_eq!(progress.remaining(), Some(2));
+        assert_eq!(progress.fraction(), Some(0.5));
+
+        let filtered = [(); 4].iter().filter(|_| true);
+        assert_eq!(Progress::of(&filtered).remaining(), None);
+    }
+}
@@ -0,0 +1,259 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+// Handling and cancelling the prompts a SecretService is waiting for.
+
+use crate::proxy::prompt::PromptProxy;
+use crate::util::{handle_signal, DismissOnDrop};
+use crate::{Error, ObjectPath};
+
+use futures_util::future::BoxFuture;
+use futures_util::StreamExt;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+
+/// Handles the prompts the provider returns, in place of showing them and
+/// waiting for the user.
+///
+/// Set with [SecretServiceBuilder::prompter](crate::SecretServiceBuilder::prompter),
+/// a prompter is handed every prompt of the connection, in both the async
+/// and blocking APIs, and finishes it with [Prompt::show] or
+/// [Prompt::dismiss]. It can do whatever it needs around that, such as a
+/// headless agent dismissing every prompt, a command line tool printing
+/// instructions before showing it, or an application tracking which prompts
+/// are on screen.
+///
+/// ```no_run
+/// use futures_util::future::BoxFuture;
+/// use secret_service::{Error, Prompt, PromptCompletion, Prompter};
+///
+/// struct Instructions;
+///
+/// impl Prompter for Instructions {
+///     fn prompt(&self, prompt: Prompt) -> BoxFuture<'_, Result<PromptCompletion, Error>> {
+///         Box::pin(async move {
+///             eprintln!("Unlock the keyring in the dialog to continue");
+///             prompt.show().await
+///         })
+///     }
+/// }
+/// ```
+pub trait Prompter: Send + Sync {
+    fn prompt(&self, prompt: Prompt) -> BoxFuture<'_, Result<PromptCompletion, Error>>;
+}
+
+/// A prompt returned by the provider, as handed to a [Prompter].
+#[derive(Debug)]
+pub struct Prompt {
+    proxy: PromptProxy<'static>,
+    window_id: String,
+    canceller: PromptCanceller,
+}
+
+/// The result of a prompt the user went through, returned by [Prompt::show].
+#[derive(Debug)]
+pub struct PromptCompletion(pub(crate) OwnedValue);
+
+impl Prompt {
+    pub(crate) fn new(
+        proxy: PromptProxy<'static>,
+        window_id: String,
+        canceller: PromptCanceller,
+    ) -> Self {
+        Prompt {
+            proxy,
+            window_id,
+            canceller,
+        }
+    }
+
+    /// Returns the path of the prompt.
+    pub fn path(&self) -> ObjectPath {
+        OwnedObjectPath::from(self.proxy.inner().path().clone()).into()
+    }
+
+    /// Returns the window identifier the prompt is shown for, as set on the
+    /// connection.
+    pub fn window_id(&self) -> &str {
+        &self.window_id
+    }
+
+    /// Shows the prompt and waits for the user, failing with
+    /// [Error::Prompt] if they dismiss it.
+    ///
+    /// The prompt can be cancelled with the connection's
+    /// [PromptCanceller] meanwhile, and is dismissed if the future is
+    /// dropped.
+    pub async fn show(self) -> Result<PromptCompletion, Error> {
+        let mut receive_completed_iter = self.proxy.receive_completed().await?;
+        let _registration = self.canceller.register(self.proxy.clone());
+        let mut dismiss_guard = DismissOnDrop(Some(self.proxy.clone()));
+        self.proxy.prompt(&self.window_id).await?;
+
+        let completed = receive_completed_iter.next().await.unwrap();
+        dismiss_guard.0 = None;
+        handle_signal(completed).map(PromptCompletion)
+    }
+
+    /// Dismisses the prompt without showing it, failing the operation that
+    /// returned it with [Error::Prompt].
+    pub async fn dismiss(self) -> Result<PromptCompletion, Error> {
+        self.proxy.dismiss().await?;
+        Err(Error::Prompt)
+    }
+}
+
+/// The prompter of a connection, wrapped for
+/// [Config](crate::builder::Config)'s `Debug` implementation.
+pub(crate) struct CustomPrompter(pub(crate) Arc<dyn Prompter>);
+
+impl fmt::Debug for CustomPrompter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CustomPrompter")
+    }
+}
+
+/// Cancels the prompts a [SecretService](crate::SecretService) is waiting
+/// for, as returned by
+/// [SecretService::prompt_canceller](crate::SecretService::prompt_canceller).
+///
+/// Cancelling dismisses the prompts shown at that moment, and the
+/// operations waiting for them fail with [Error::Prompt](crate::Error::Prompt),
+/// for instance to close an unlock dialog when the user navigates away.
+/// Dropping the future of an async operation dismisses its prompt too, but
+/// leaves nothing to report the failure to.
+///
+/// The handle can be cloned and sent to other threads, and is cheap to keep
+/// around for the lifetime of a view.
+///
+/// ```no_run
+/// # async fn call(ss: &secret_service::SecretService<'_>) {
+/// let canceller = ss.prompt_canceller();
+/// // in the handler of the view being closed
+/// canceller.cancel();
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PromptCanceller {
+    pending: Arc<Mutex<Pending>>,
+}
+
+#[derive(Debug, Default)]
+struct Pending {
+    prompts: HashMap<u64, PromptProxy<'static>>,
+    next_id: u64,
+}
+
+impl PromptCanceller {
+    /// Dismisses every prompt shown at this moment.
+    ///
+    /// The provider answers a dismissal as if the user dismissed the
+    /// prompt, which fails the operations waiting for it. Prompts shown
+    /// later aren't affected.
+    pub fn cancel(&self) {
+        let prompts = std::mem::take(&mut self.pending.lock().unwrap().prompts);
+        for proxy in prompts.into_values() {
+            let executor = proxy.inner().connection().executor().clone();
+            executor
+                .spawn(
+                    async move {
+                        let _ = proxy.dismiss().await;
+                    },
+                    "secret-service prompt cancellation",
+                )
+                .detach();
+        }
+    }
+
+    /// Returns how many prompts are being waited for.
+    pub fn pending(&self) -> usize {
+        self.pending.lock().unwrap().prompts.len()
+    }
+
+    /// Records a prompt being waited for, until the returned guard is
+    /// dropped.
+    pub(crate) fn register(&self, proxy: PromptProxy<'static>) -> Registration {
+        let mut pending = self.pending.lock().unwrap();
+        let id = pending.next_id;
+        pending.next_id += 1;
+        pending.prompts.insert(id, proxy);
+        Registration {
+            pending: Arc::clone(&self.pending),
+            id,
+        }
+    }
+}
+
+/// Forgets a prompt once it's no longer waited for.
+pub(crate) struct Registration {
+    pending: Arc<Mutex<Pending>>,
+    id: u64,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        self.pending.lock().unwrap().prompts.remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{EncryptionType, SecretService};
+
+    /// Records the prompts it's handed, dismissing them until told to show
+    /// them.
+    #[derive(Default)]
+    struct Recording {
+        seen: Mutex<Vec<ObjectPath>>,
+        show: std::sync::atomic::AtomicBool,
+    }
+
+    impl Prompter for Recording {
+        fn prompt(&self, prompt: Prompt) -> BoxFuture<'_, Result<PromptCompletion, Error>> {
+            Box::pin(async move {
+                self.seen.lock().unwrap().push(prompt.path());
+                if self.show.load(std::sync::atomic::Ordering::SeqCst) {
+                    prompt.show().await
+                } else {
+                    prompt.dismiss().await
+                }
+            })
+        }
+    }
+
+    #[test_with::no_env(GITHUB_ACTIONS)]
+    #[tokio::test]
+    async fn should_hand_prompts_to_prompter() {
+        let prompter = Arc::new(Recording::default());
+        let ss = SecretService::builder()
+            .encryption(EncryptionType::Plain)
+            .prompter(prompter.clone())
+            .connect()
+            .await
+            .unwrap();
+        let collection = ss
+            .create_collection("TestPrompter", "test_prompter")
+            .await
+            .unwrap();
+        collection.lock().await.unwrap();
+
+        assert!(matches!(collection.unlock().await, Err(Error::Prompt)));
+        assert!(collection.is_locked().await.unwrap());
+
+        prompter
+            .show
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        collection.unlock().await.unwrap();
+        assert!(!collection.is_locked().await.unwrap());
+        assert_eq!(prompter.seen.lock().unwrap().len(), 2);
+
+        collection.delete().await.unwrap();
+    }
+}
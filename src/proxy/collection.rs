@@ -52,6 +52,15 @@ trait Collection {
 
     #[zbus(property)]
     fn modified(&self) -> zbus::fdo::Result<u64>;
+
+    #[zbus(signal)]
+    fn item_created(&self, item: ObjectPath<'_>) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn item_deleted(&self, item: ObjectPath<'_>) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn item_changed(&self, item: ObjectPath<'_>) -> zbus::Result<()>;
 }
 
 #[derive(Debug, Serialize, Deserialize, Type)]
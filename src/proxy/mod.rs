@@ -13,10 +13,15 @@ pub mod service;
 use serde::{Deserialize, Serialize};
 use zbus::zvariant::{OwnedObjectPath, Type};
 
-#[derive(Debug, Serialize, Deserialize, Type)]
+/// A secret as transferred over D-Bus, encrypted for a session.
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
 pub struct SecretStruct {
-    pub(crate) session: OwnedObjectPath,
-    pub(crate) parameters: Vec<u8>,
-    pub(crate) value: Vec<u8>,
-    pub(crate) content_type: String,
+    /// Path of the session the secret is encrypted for.
+    pub session: OwnedObjectPath,
+    /// Algorithm dependent parameters, such as the AES initialization vector.
+    pub parameters: Vec<u8>,
+    /// The possibly encrypted secret value.
+    pub value: Vec<u8>,
+    /// Content type of the secret, such as `text/plain`.
+    pub content_type: String,
 }
@@ -39,7 +39,8 @@ trait Service {
 
     fn get_secrets(
         &self,
-        objects: Vec<ObjectPath<'_>>,
+        items: Vec<&ObjectPath<'_>>,
+        session: &ObjectPath<'_>,
     ) -> zbus::Result<HashMap<OwnedObjectPath, SecretStruct>>;
 
     fn read_alias(&self, name: &str) -> zbus::Result<OwnedObjectPath>;
@@ -48,28 +49,51 @@ trait Service {
 
     #[zbus(property)]
     fn collections(&self) -> zbus::fdo::Result<Vec<ObjectPath<'_>>>;
+
+    #[zbus(signal)]
+    fn collection_created(&self, collection: ObjectPath<'_>) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn collection_deleted(&self, collection: ObjectPath<'_>) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn collection_changed(&self, collection: ObjectPath<'_>) -> zbus::Result<()>;
 }
 
+/// Reply of `OpenSession`.
 #[derive(Debug, Serialize, Deserialize, Type)]
 pub struct OpenSessionResult {
-    pub(crate) output: OwnedValue,
-    pub(crate) result: OwnedObjectPath,
+    /// Output of the session algorithm negotiation, such as the provider's
+    /// public key.
+    pub output: OwnedValue,
+    /// Path of the opened session.
+    pub result: OwnedObjectPath,
 }
 
+/// Reply of `CreateCollection`.
 #[derive(Debug, Serialize, Deserialize, Type)]
 pub struct CreateCollectionResult {
-    pub(crate) collection: OwnedObjectPath,
-    pub(crate) prompt: OwnedObjectPath,
+    /// Path of the new collection, or `/` if a prompt is needed.
+    pub collection: OwnedObjectPath,
+    /// Path of the prompt to complete, or `/` if none is needed.
+    pub prompt: OwnedObjectPath,
 }
 
+/// Reply of `SearchItems`.
 #[derive(Debug, Serialize, Deserialize, Type)]
 pub struct SearchItemsResult {
-    pub(crate) unlocked: Vec<OwnedObjectPath>,
-    pub(crate) locked: Vec<OwnedObjectPath>,
+    /// Paths of matching items that are unlocked.
+    pub unlocked: Vec<OwnedObjectPath>,
+    /// Paths of matching items that are locked.
+    pub locked: Vec<OwnedObjectPath>,
 }
 
+/// Reply of `Lock` and `Unlock`.
 #[derive(Debug, Serialize, Deserialize, Type)]
 pub struct LockActionResult {
-    pub(crate) object_paths: Vec<OwnedObjectPath>,
-    pub(crate) prompt: OwnedObjectPath,
+    /// Paths of the objects that were locked or unlocked without a prompt.
+    pub object_paths: Vec<OwnedObjectPath>,
+    /// Path of the prompt to complete for the remaining objects, or `/` if
+    /// none is needed.
+    pub prompt: OwnedObjectPath,
 }
@@ -0,0 +1,449 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Searches written as compact query strings, for CLI tools and
+//! configuration files.
+//!
+//! A query matches attributes exactly, as `key=value`, and combines matches
+//! with `AND`, `OR`, `NOT` and parentheses. `NOT` binds tightest and `OR`
+//! loosest. Keys and values holding spaces, parentheses, `=` or `"` are
+//! quoted, with `\"` and `\\` escaping within quotes.
+//!
+//! ```
+//! use secret_service::query::Query;
+//! use std::collections::HashMap;
+//!
+//! let query: Query = r#"service=smtp AND (user=alice OR user="bob smith")"#.parse().unwrap();
+//! let attributes = HashMap::from([
+//!     ("service".to_owned(), "smtp".to_owned()),
+//!     ("user".to_owned(), "bob smith".to_owned()),
+//! ]);
+//! assert!(query.matches(&attributes));
+//! ```
+//!
+//! The specification only searches for items holding all of a set of
+//! attributes, so [SecretService::search_query](crate::SecretService::search_query)
+//! and [Collection::search_query](crate::Collection::search_query) search
+//! for the matches every item found must hold, `service=smtp` above, and
+//! check the rest of the query on the client against the attributes of the
+//! items found. Queries without any such match, such as `NOT user=alice`,
+//! check every item.
+
+use crate::builder::Config;
+use crate::util::limit_concurrency;
+use crate::{blocking, Error, Item};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::FromStr;
+use std::vec;
+
+/// A parsed query, see the [module](self) documentation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query(Expr);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Match(String, String),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+}
+
+/// Why a query string couldn't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseQueryError {
+    position: usize,
+    reason: &'static str,
+}
+
+impl ParseQueryError {
+    /// The byte offset in the query string where parsing failed.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl fmt::Display for ParseQueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid query at {}: {}", self.position, self.reason)
+    }
+}
+
+impl std::error::Error for ParseQueryError {}
+
+impl Query {
+    pub fn parse(query: &str) -> Result<Self, ParseQueryError> {
+        let mut parser = Parser {
+            tokens: tokenize(query)?.into_iter().peekable(),
+            end: query.len(),
+        };
+        let expr = parser.or()?;
+        match parser.tokens.peek() {
+            None => Ok(Query(expr)),
+            Some((position, _)) => Err(ParseQueryError {
+                position: *position,
+                reason: "expected AND, OR or the end of the query",
+            }),
+        }
+    }
+
+    /// Returns whether items with `attributes` match the query.
+    pub fn matches(&self, attributes: &HashMap<String, String>) -> bool {
+        self.0.matches(attributes)
+    }
+
+    /// The matches every item matching the query holds, which are searched
+    /// for on the provider.
+    pub(crate) fn required_attributes(&self) -> HashMap<&str, &str> {
+        self.0.required()
+    }
+
+    /// Returns whether searching for [Query::required_attributes] finds
+    /// exactly the items matching, with nothing left to check on the client.
+    pub(crate) fn is_exact(&self) -> bool {
+        let mut values = HashMap::new();
+        self.0.is_conjunction(&mut values)
+    }
+}
+
+impl FromStr for Query {
+    type Err = ParseQueryError;
+
+    fn from_str(query: &str) -> Result<Self, Self::Err> {
+        Query::parse(query)
+    }
+}
+
+impl Expr {
+    fn matches(&self, attributes: &HashMap<String, String>) -> bool {
+        match self {
+            Expr::Match(key, value) => attributes.get(key) == Some(value),
+            Expr::And(left, right) => left.matches(attributes) && right.matches(attributes),
+            Expr::Or(left, right) => left.matches(attributes) || right.matches(attributes),
+            Expr::Not(expr) => !expr.matches(attributes),
+        }
+    }
+
+    fn required(&self) -> HashMap<&str, &str> {
+        match self {
+            Expr::Match(key, value) => HashMap::from([(key.as_str(), value.as_str())]),
+            Expr::And(left, right) => {
+                let mut required = right.required();
+                required.extend(left.required());
+                required
+            }
+            Expr::Or(left, right) => {
+                let right = right.required();
+                let mut required = left.required();
+                required.retain(|key, value| right.get(key) == Some(value));
+                required
+            }
+            Expr::Not(_) => HashMap::new(),
+        }
+    }
+
+    /// Returns whether the expression only joins matches with `AND`, and
+    /// doesn't match the same key with different values.
+    fn is_conjunction<'e>(&'e self, values: &mut HashMap<&'e str, &'e str>) -> bool {
+        match self {
+            Expr::Match(key, value) => {
+                *values.entry(key.as_str()).or_insert(value.as_str()) == value
+            }
+            Expr::And(left, right) => left.is_conjunction(values) && right.is_conjunction(values),
+            Expr::Or(..) | Expr::Not(_) => false,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    Equals,
+    Word { text: String, quoted: bool },
+}
+
+impl Token {
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self, Token::Word { text, quoted: false } if text == keyword)
+    }
+}
+
+fn tokenize(query: &str) -> Result<Vec<(usize, Token)>, ParseQueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = query.char_indices().peekable();
+    while let Some(&(position, c)) = chars.peek() {
+        let token = match c {
+            c if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            '(' | ')' | '=' => {
+                chars.next();
+                match c {
+                    '(' => Token::Open,
+                    ')' => Token::Close,
+                    _ => Token::Equals,
+                }
+            }
+            '"' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, c @ ('"' | '\\'))) => text.push(c),
+                            _ => {
+                                return Err(ParseQueryError {
+                                    position,
+                                    reason: "invalid escape in quoted string",
+                                })
+                            }
+                        },
+                        Some((_, c)) => text.push(c),
+                        None => {
+                            return Err(ParseQueryError {
+                                position,
+                                reason: "unterminated quoted string",
+                            })
+                        }
+                    }
+                }
+                Token::Word { text, quoted: true }
+            }
+            _ => {
+                let mut text = String::new();
+                while let Some(&(_, c)) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '(' | ')' | '=' | '"') {
+                        break;
+                    }
+                    text.push(c);
+                    chars.next();
+                }
+                Token::Word {
+                    text,
+                    quoted: false,
+                }
+            }
+        };
+        tokens.push((position, token));
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Peekable<vec::IntoIter<(usize, Token)>>,
+    /// The length of the query, where errors about its end point.
+    end: usize,
+}
+
+impl Parser {
+    fn or(&mut self) -> Result<Expr, ParseQueryError> {
+        let mut expr = self.and()?;
+        while self.eat_keyword("OR") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, ParseQueryError> {
+        let mut expr = self.unary()?;
+        while self.eat_keyword("AND") {
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseQueryError> {
+        if self.eat_keyword("NOT") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        let (position, token) = self.take("expected a match or (")?;
+        match token {
+            Token::Open => {
+                let expr = self.or()?;
+                match self.take("expected )")? {
+                    (_, Token::Close) => Ok(expr),
+                    (position, _) => Err(ParseQueryError {
+                        position,
+                        reason: "expected )",
+                    }),
+                }
+            }
+            Token::Word { text: key, .. } => {
+                match self.take("expected =")? {
+                    (_, Token::Equals) => (),
+                    (position, _) => {
+                        return Err(ParseQueryError {
+                            position,
+                            reason: "expected =",
+                        })
+                    }
+                }
+                match self.take("expected a value")? {
+                    (_, Token::Word { text: value, .. }) => Ok(Expr::Match(key, value)),
+                    (position, _) => Err(ParseQueryError {
+                        position,
+                        reason: "expected a value",
+                    }),
+                }
+            }
+            _ => Err(ParseQueryError {
+                position,
+                reason: "expected a match or (",
+            }),
+        }
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        self.tokens
+            .next_if(|(_, token)| token.is_keyword(keyword))
+            .is_some()
+    }
+
+    fn take(&mut self, reason: &'static str) -> Result<(usize, Token), ParseQueryError> {
+        self.tokens.next().ok_or(ParseQueryError {
+            position: self.end,
+            reason,
+        })
+    }
+}
+
+/// Keeps the items whose attributes match `query`.
+pub(crate) async fn retain_matching<'a>(
+    config: &Config,
+    items: Vec<Item<'a>>,
+    query: &Query,
+) -> Result<Vec<Item<'a>>, Error> {
+    if query.is_exact() {
+        return Ok(items);
+    }
+    let attributes = futures_util::future::try_join_all(
+        items
+            .iter()
+            .map(|item| limit_concurrency(config, item.get_attributes())),
+    )
+    .await?;
+
+    Ok(items
+        .into_iter()
+        .zip(attributes)
+        .filter(|(_, attributes)| query.matches(attributes))
+        .map(|(item, _)| item)
+        .collect())
+}
+
+/// Blocking variant of [retain_matching].
+pub(crate) fn retain_matching_blocking<'a>(
+    items: Vec<blocking::Item<'a>>,
+    query: &Query,
+) -> Result<Vec<blocking::Item<'a>>, Error> {
+    if query.is_exact() {
+        return Ok(items);
+    }
+    let mut matching = Vec::new();
+    for item in items {
+        if query.matches(&item.get_attributes()?) {
+            matching.push(item);
+        }
+    }
+    Ok(matching)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{EncryptionType, SecretService};
+
+    fn attributes(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn should_parse_queries() {
+        let query = Query::parse("service=smtp AND (user=alice OR user=bob)").unwrap();
+        assert!(query.matches(&attributes(&[("service", "smtp"), ("user", "bob")])));
+        assert!(!query.matches(&attributes(&[("service", "imap"), ("user", "bob")])));
+        assert!(!query.matches(&attributes(&[("service", "smtp")])));
+        assert_eq!(
+            query.required_attributes(),
+            HashMap::from([("service", "smtp")])
+        );
+        assert!(!query.is_exact());
+
+        let query = Query::parse(r#"a=1 OR NOT b="x \"y\"" AND c=AND"#).unwrap();
+        assert!(query.matches(&attributes(&[("a", "1"), ("b", "x \"y\"")])));
+        assert!(query.matches(&attributes(&[("c", "AND")])));
+        assert!(!query.matches(&attributes(&[("b", "x \"y\""), ("c", "AND")])));
+        assert!(query.required_attributes().is_empty());
+
+        let query = Query::parse("(a=1 AND b=2) OR (b=2 AND c=3)").unwrap();
+        assert_eq!(query.required_attributes(), HashMap::from([("b", "2")]));
+        assert!(Query::parse("a=1 AND b=2").unwrap().is_exact());
+        assert!(!Query::parse("a=1 AND a=2").unwrap().is_exact());
+    }
+
+    #[test]
+    fn should_reject_invalid_queries() {
+        for (query, position) in [
+            ("", 0),
+            ("a", 1),
+            ("a=", 2),
+            ("a=1 b=2", 4),
+            ("(a=1", 4),
+            ("a=1 AND", 7),
+            ("a=\"1", 2),
+            ("=1", 0),
+        ] {
+            assert_eq!(
+                Query::parse(query).unwrap_err().position(),
+                position,
+                "{query}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn should_search_query() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let mut items = Vec::new();
+        for user in ["alice", "bob", "carol"] {
+            let item = collection
+                .create_item(
+                    "Test",
+                    HashMap::from([("test_attribute_query", "smtp"), ("user", user)]),
+                    b"test",
+                    false,
+                    "text/plain",
+                )
+                .await;
+            items.push(item.unwrap());
+        }
+
+        let query = Query::parse("test_attribute_query=smtp AND (user=alice OR user=bob)").unwrap();
+        let found = ss.search_query(&query).await.unwrap();
+        let mut paths: Vec<_> = found.unlocked.iter().map(|item| item.path()).collect();
+        paths.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        let mut expected: Vec<_> = items[..2].iter().map(|item| item.path()).collect();
+        expected.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        assert_eq!(paths, expected);
+
+        let query = Query::parse("test_attribute_query=smtp AND NOT user=alice").unwrap();
+        assert_eq!(collection.search_query(&query).await.unwrap().len(), 2);
+
+        for item in items {
+            item.delete().await.unwrap();
+        }
+    }
+}
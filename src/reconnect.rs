@@ -0,0 +1,145 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+// Connecting again once the bus or the provider restarted.
+
+use crate::algorithm::SessionAlgorithm;
+use crate::builder::Config;
+use crate::proxy::service::{ServiceProxy, ServiceProxyBlocking};
+use crate::session::{PendingSession, Session};
+use crate::{util, Error};
+
+use std::fmt;
+use std::sync::Arc;
+
+/// How a [SecretService](crate::SecretService) connected, to connect the
+/// same way again.
+pub(crate) struct Reconnect {
+    pub(crate) algorithm: Arc<dyn SessionAlgorithm>,
+    pub(crate) lazy_session: bool,
+    pub(crate) plain_fallback: bool,
+    #[cfg(feature = "kernel-keyring")]
+    pub(crate) kernel_keyring: bool,
+    pub(crate) internal_executor: bool,
+    /// Whether the connection was handed to the builder, in which case it's
+    /// kept and only the session is negotiated again.
+    pub(crate) given_connection: bool,
+}
+
+impl Reconnect {
+    /// Negotiates the session, or prepares negotiating it on first use.
+    pub(crate) async fn session(
+        &self,
+        service_proxy: &ServiceProxy<'static>,
+    ) -> Result<Session, Error> {
+        let mut session = if self.lazy_session {
+            Session::lazy(
+                PendingSession::new(service_proxy.clone(), self.algorithm.clone())?
+                    .plain_fallback(self.plain_fallback),
+            )
+        } else {
+            Session::negotiate_or_plain(service_proxy, &*self.algorithm, self.plain_fallback)
+                .await?
+        };
+        self.protect_session(&mut session)?;
+        Ok(session)
+    }
+
+    /// Like [Reconnect::session], blocking the current thread.
+    pub(crate) fn session_blocking(
+        &self,
+        service_proxy: &ServiceProxyBlocking<'static>,
+    ) -> Result<Session, Error> {
+        let mut session = if self.lazy_session {
+            let service_proxy = ServiceProxy::from(service_proxy.inner().inner().clone());
+            Session::lazy(
+                PendingSession::new(service_proxy, self.algorithm.clone())?
+                    .plain_fallback(self.plain_fallback),
+            )
+        } else {
+            Session::negotiate_or_plain_blocking(
+                service_proxy,
+                &*self.algorithm,
+                self.plain_fallback,
+            )?
+        };
+        self.protect_session(&mut session)?;
+        Ok(session)
+    }
+
+    /// Applies the key protection settings to a freshly negotiated session.
+    #[cfg_attr(not(feature = "kernel-keyring"), allow(unused_variables))]
+    fn protect_session(&self, session: &mut Session) -> Result<(), Error> {
+        #[cfg(feature = "kernel-keyring")]
+        if self.kernel_keyring {
+            session.move_key_to_kernel()?;
+        }
+        Ok(())
+    }
+
+    /// Opens a connection to the bus connected to before, or keeps `conn` if
+    /// it was handed to the builder.
+    async fn connection(
+        &self,
+        config: &Config,
+        conn: &zbus::Connection,
+    ) -> Result<zbus::Connection, Error> {
+        #[cfg(all(unix, any(test, feature = "test-harness")))]
+        if let Some(address) = &config.bus_address {
+            return Ok(zbus::connection::Builder::address(address.as_str())?
+                .build()
+                .await?);
+        }
+        #[cfg(not(all(unix, any(test, feature = "test-harness"))))]
+        let _ = config;
+        if self.given_connection {
+            return Ok(conn.clone());
+        }
+        util::session_bus(self.internal_executor).await
+    }
+}
+
+impl fmt::Debug for Reconnect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Reconnect")
+            .field("algorithm", &self.algorithm.name())
+            .field("lazy_session", &self.lazy_session)
+            .field("plain_fallback", &self.plain_fallback)
+            .field("given_connection", &self.given_connection)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Connects again the way `config` records, returning the connection, the
+/// proxy of the `Service` object and the session negotiated.
+pub(crate) async fn reconnect<'a>(
+    config: &Config,
+    conn: &zbus::Connection,
+) -> Result<(zbus::Connection, ServiceProxy<'a>, Session), Error> {
+    let reconnect = config
+        .reconnect
+        .as_ref()
+        .expect("connections record how they were made");
+    let conn = reconnect.connection(config, conn).await?;
+
+    // like when connecting, nothing else may tick the executor yet
+    let (service_proxy, session) = util::drive_executor(conn.executor(), async {
+        let service_proxy = ServiceProxy::builder(&conn)
+            .destination(config.destination.clone())?
+            .path(config.path.clone())?
+            .build()
+            .await
+            .map_err(util::handle_conn_error)?;
+        let session = reconnect.session(&service_proxy).await?;
+        Ok::<_, Error>((service_proxy, session))
+    })
+    .await?;
+    // the provider may have come back with other collections, and the cache
+    // has to watch the new connection
+    config.alias_cache.reset();
+    Ok((conn, service_proxy, session))
+}
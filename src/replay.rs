@@ -0,0 +1,534 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Recording and replaying the messages exchanged with a provider, for
+//! regression tests.
+//!
+//! Providers differ in many small ways, and testing against all of them in CI
+//! is rarely possible. With the `replay` feature, a session with a real
+//! provider can be [recorded](record) into a [Fixture], which is later
+//! [replayed](replay) to the client without any bus or provider running.
+//!
+//! In both cases the client is connected to an in-process peer instead of the
+//! session bus. While recording, the peer forwards every call to the provider
+//! and logs the replies and signals; while replaying, it answers calls from
+//! the fixture.
+//!
+//! ```no_run
+//! # use secret_service::replay::{self, Fixture};
+//! # use secret_service::{EncryptionType, SecretService};
+//! # async fn call() {
+//! // against a real provider
+//! let builder = SecretService::builder().encryption(EncryptionType::Plain);
+//! let (builder, recording) = replay::record(builder).await.unwrap();
+//! let ss = builder.connect().await.unwrap();
+//! ss.get_default_collection().await.unwrap();
+//! recording.fixture().save("tests/fixtures/default.bin").unwrap();
+//!
+//! // later, in CI
+//! let fixture = Fixture::load("tests/fixtures/default.bin").unwrap();
+//! let builder = SecretService::builder().encryption(EncryptionType::Plain);
+//! let ss = replay::replay(builder, fixture).await.unwrap().connect().await.unwrap();
+//! ss.get_default_collection().await.unwrap();
+//! # }
+//! ```
+//!
+//! Calls are matched to the recording by object path, interface, method and
+//! arguments, so the replayed client has to make the same calls as the
+//! recorded one, though not necessarily in the same order. Encrypted sessions
+//! negotiate a random key each time, so recordings should use
+//! [EncryptionType::Plain](crate::EncryptionType::Plain).
+
+use crate::{Error, SecretServiceBuilder};
+
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use zbus::connection::socket::Channel;
+use zbus::message::{self, EndianSig};
+use zbus::names::BusName;
+use zbus::zvariant::{self, serialized, Endian, Type, LE};
+use zbus::{fdo, Guid, MatchRule, Message, MessageStream};
+
+/// Unique name the in-process peer sends replies and signals as.
+const PEER_NAME: &str = ":1.0";
+const BUS_NAME: &str = "org.freedesktop.DBus";
+
+/// Version tag at the start of fixture files.
+const FIXTURE_VERSION: &str = "secret-service-replay-1";
+
+const CALL: u8 = 0;
+const REPLY: u8 = 1;
+const ERROR: u8 = 2;
+const SIGNAL: u8 = 3;
+
+/// A recorded message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+struct Entry {
+    kind: u8,
+    /// For calls, a number identifying the call; for replies and errors, the
+    /// number of the call they answer.
+    call: u32,
+    path: String,
+    interface: String,
+    /// Method or signal name, or error name for errors.
+    member: String,
+    big_endian: bool,
+    signature: String,
+    body: Vec<u8>,
+}
+
+impl Entry {
+    fn new(kind: u8, call: u32, msg: &Message) -> Self {
+        let header = msg.header();
+        let body = msg.body();
+        let member = match kind {
+            ERROR => header.error_name().map(|name| name.to_string()),
+            _ => header.member().map(|member| member.to_string()),
+        };
+        Entry {
+            kind,
+            call,
+            path: header
+                .path()
+                .map(|path| path.to_string())
+                .unwrap_or_default(),
+            interface: header
+                .interface()
+                .map(|interface| interface.to_string())
+                .unwrap_or_default(),
+            member: member.unwrap_or_default(),
+            big_endian: msg.primary_header().endian_sig() == EndianSig::Big,
+            signature: body
+                .signature()
+                .map(|signature| signature.to_string())
+                .unwrap_or_default(),
+            body: body.data().to_vec(),
+        }
+    }
+
+    fn endian(&self) -> Endian {
+        if self.big_endian {
+            Endian::Big
+        } else {
+            Endian::Little
+        }
+    }
+
+    /// Whether this is the recording of the call `msg`.
+    fn is_call(&self, msg: &Message, exact: bool) -> bool {
+        let recorded = Entry::new(CALL, self.call, msg);
+        self.kind == CALL
+            && self.path == recorded.path
+            && self.interface == recorded.interface
+            && self.member == recorded.member
+            && (!exact || (self.signature == recorded.signature && self.body == recorded.body))
+    }
+
+    /// Builds the reply to `call` recorded in this entry.
+    fn reply_to(&self, call: &Message) -> zbus::Result<Message> {
+        let builder = match self.kind {
+            ERROR => Message::method_error(call, self.member.as_str())?,
+            _ => Message::method_reply(call)?,
+        };
+        self.build(builder.sender(PEER_NAME)?)
+    }
+
+    /// Builds the signal recorded in this entry.
+    fn signal(&self) -> zbus::Result<Message> {
+        let builder = Message::signal(
+            self.path.as_str(),
+            self.interface.as_str(),
+            self.member.as_str(),
+        )?
+        .sender(PEER_NAME)?;
+        self.build(builder)
+    }
+
+    /// Builds the method call recorded in this entry, addressed to
+    /// `destination`.
+    fn method_call(&self, destination: &str) -> zbus::Result<Message> {
+        let mut builder =
+            Message::method(self.path.as_str(), self.member.as_str())?.destination(destination)?;
+        if !self.interface.is_empty() {
+            builder = builder.interface(self.interface.as_str())?;
+        }
+        self.build(builder)
+    }
+
+    fn build(&self, builder: message::Builder<'_>) -> zbus::Result<Message> {
+        // SAFETY: the body was taken from a message with the same signature
+        // and byte order
+        unsafe {
+            builder.endian(self.endian()).build_raw_body(
+                &self.body,
+                self.signature.as_str(),
+                #[cfg(unix)]
+                vec![],
+            )
+        }
+    }
+}
+
+/// The messages exchanged with a provider, as recorded by [record].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Fixture {
+    entries: Vec<Entry>,
+}
+
+impl Fixture {
+    /// Reads a fixture saved with [Fixture::save].
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    /// Writes the fixture to a file.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        std::fs::write(path, self.to_bytes())
+    }
+
+    /// Decodes a fixture encoded with [Fixture::to_bytes].
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let invalid = |err| io::Error::new(io::ErrorKind::InvalidData, err);
+        let data = serialized::Data::new(bytes, context());
+        let ((version, entries), _): ((String, Vec<Entry>), _) =
+            data.deserialize().map_err(invalid)?;
+        if version != FIXTURE_VERSION {
+            return Err(invalid(zvariant::Error::Message(format!(
+                "unsupported fixture version {version}"
+            ))));
+        }
+        Ok(Fixture { entries })
+    }
+
+    /// Encodes the fixture.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        zvariant::to_bytes(context(), &(FIXTURE_VERSION, &self.entries))
+            .expect("fixtures are always serializable")
+            .to_vec()
+    }
+}
+
+fn context() -> serialized::Context {
+    serialized::Context::new_dbus(LE, 0)
+}
+
+/// A recording in progress, created by [record].
+#[derive(Debug, Clone)]
+pub struct Recording {
+    entries: Arc<Mutex<Vec<Entry>>>,
+}
+
+impl Recording {
+    /// Returns the messages recorded so far.
+    pub fn fixture(&self) -> Fixture {
+        Fixture {
+            entries: self.entries.lock().unwrap().clone(),
+        }
+    }
+}
+
+/// Creates a connected pair of in-process connections, returning the
+/// client's end and the peer's end.
+async fn channel_pair() -> Result<(zbus::Connection, zbus::Connection), Error> {
+    let guid = Guid::generate();
+    let (client, peer) = Channel::pair();
+    let client = zbus::connection::Builder::authenticated_socket(client, guid.clone())?
+        .p2p()
+        .build()
+        .await?;
+    let peer = zbus::connection::Builder::authenticated_socket(peer, guid)?
+        .p2p()
+        .build()
+        .await?;
+    Ok((client, peer))
+}
+
+/// Answers the calls to the bus itself, which the peer stands in for.
+///
+/// Returns `false` if `msg` isn't addressed to the bus.
+async fn answer_bus_call(peer: &zbus::Connection, msg: &Message) -> Result<bool, Error> {
+    let header = msg.header();
+    if header.destination().map(|name| name.as_str()) != Some(BUS_NAME) {
+        return Ok(false);
+    }
+
+    let reply = match header.member().map(|member| member.as_str()) {
+        // the provider owns every name asked about
+        Some("GetNameOwner") => Message::method_reply(msg)?.build(&PEER_NAME)?,
+        _ => Message::method_error(msg, "org.freedesktop.DBus.Error.UnknownMethod")?
+            .build(&"not supported while recording or replaying")?,
+    };
+    peer.send(&reply).await?;
+    Ok(true)
+}
+
+/// Connects `builder` to the provider through a recorder, returning the
+/// builder to connect with and the recording.
+///
+/// Every message exchanged through the returned builder's connection is
+/// recorded, until the client's connection is closed.
+pub async fn record(
+    mut builder: SecretServiceBuilder,
+) -> Result<(SecretServiceBuilder, Recording), Error> {
+    let destination = builder.config.destination.clone();
+    let bus = zbus::Connection::session().await?;
+    let dbus = fdo::DBusProxy::new(&bus).await?;
+    let provider = dbus
+        .get_name_owner(BusName::try_from(destination.as_str()).map_err(zbus::Error::from)?)
+        .await?;
+    dbus.add_match_rule(
+        MatchRule::builder()
+            .msg_type(message::Type::Signal)
+            .sender(provider.clone())?
+            .build(),
+    )
+    .await?;
+
+    let (client, peer) = channel_pair().await?;
+    let recording = Recording {
+        entries: Arc::default(),
+    };
+
+    // streams only receive messages from their creation on, so they are
+    // created before the client can make any call
+    let mut calls = MessageStream::from(&peer);
+    let mut bus_messages = MessageStream::from(&bus);
+    let entries = Arc::clone(&recording.entries);
+    let task_peer = peer.clone();
+    peer.executor()
+        .spawn(
+            async move {
+                let peer = task_peer;
+                // forwarded calls by serial on the bus: their number and the
+                // original call
+                let mut pending: HashMap<u32, (u32, Message)> = HashMap::new();
+                let mut next_call = 0;
+
+                loop {
+                    futures_util::select! {
+                        msg = calls.next() => {
+                            let Some(Ok(msg)) = msg else { break };
+                            if msg.message_type() != message::Type::MethodCall
+                                || answer_bus_call(&peer, &msg).await.unwrap_or(true)
+                            {
+                                continue;
+                            }
+                            let entry = Entry::new(CALL, next_call, &msg);
+                            let Ok(forwarded) = entry.method_call(&destination) else {
+                                continue;
+                            };
+                            pending.insert(forwarded.primary_header().serial_num().get(), (next_call, msg));
+                            entries.lock().unwrap().push(entry);
+                            next_call += 1;
+                            let _ = bus.send(&forwarded).await;
+                        }
+                        msg = bus_messages.next() => {
+                            let Some(Ok(msg)) = msg else { break };
+                            let header = msg.header();
+                            let entry = match msg.message_type() {
+                                message::Type::MethodReturn | message::Type::Error => {
+                                    let Some((call, original)) = header
+                                        .reply_serial()
+                                        .and_then(|serial| pending.remove(&serial.get()))
+                                    else {
+                                        continue;
+                                    };
+                                    let kind = if msg.message_type() == message::Type::Error {
+                                        ERROR
+                                    } else {
+                                        REPLY
+                                    };
+                                    let entry = Entry::new(kind, call, &msg);
+                                    let Ok(reply) = entry.reply_to(&original) else {
+                                        continue;
+                                    };
+                                    let _ = peer.send(&reply).await;
+                                    entry
+                                }
+                                message::Type::Signal if header.sender() == Some(&provider) => {
+                                    let entry = Entry::new(SIGNAL, 0, &msg);
+                                    let Ok(signal) = entry.signal() else {
+                                        continue;
+                                    };
+                                    let _ = peer.send(&signal).await;
+                                    entry
+                                }
+                                _ => continue,
+                            };
+                            entries.lock().unwrap().push(entry);
+                        }
+                    }
+                }
+            },
+            "secret-service recorder",
+        )
+        .detach();
+
+    builder.conn = Some(client);
+    Ok((builder, recording))
+}
+
+/// Connects `builder` to a peer answering calls from `fixture`.
+///
+/// Calls that weren't recorded fail with an `org.freedesktop.DBus.Error.Failed`
+/// error.
+pub async fn replay(
+    mut builder: SecretServiceBuilder,
+    fixture: Fixture,
+) -> Result<SecretServiceBuilder, Error> {
+    let (client, peer) = channel_pair().await?;
+
+    let mut calls = MessageStream::from(&peer);
+    let task_peer = peer.clone();
+    peer.executor()
+        .spawn(
+            async move {
+                let peer = task_peer;
+                let mut entries = fixture.entries.into_iter().map(Some).collect::<Vec<_>>();
+
+                while let Some(Ok(msg)) = calls.next().await {
+                    if msg.message_type() != message::Type::MethodCall
+                        || answer_bus_call(&peer, &msg).await.unwrap_or(true)
+                    {
+                        continue;
+                    }
+                    let _ = replay_call(&peer, &mut entries, &msg).await;
+                }
+            },
+            "secret-service replayer",
+        )
+        .detach();
+
+    builder.conn = Some(client);
+    Ok(builder)
+}
+
+/// Answers `msg` from the unused `entries`, followed by the signals recorded
+/// after the reply.
+async fn replay_call(
+    peer: &zbus::Connection,
+    entries: &mut [Option<Entry>],
+    msg: &Message,
+) -> Result<(), Error> {
+    let header = msg.header();
+
+    // prefer a call with the same arguments, but settle for the same method
+    let found = [true, false].into_iter().find_map(|exact| {
+        entries.iter().position(|entry| {
+            entry
+                .as_ref()
+                .is_some_and(|entry| entry.is_call(msg, exact))
+        })
+    });
+    let Some(call) = found.and_then(|position| entries[position].take()) else {
+        let error =
+            Message::method_error(msg, "org.freedesktop.DBus.Error.Failed")?.build(&format!(
+                "no recorded call to {} on {}",
+                header
+                    .member()
+                    .map(|member| member.as_str())
+                    .unwrap_or_default(),
+                header.path().map(|path| path.as_str()).unwrap_or_default(),
+            ))?;
+        peer.send(&error).await?;
+        return Ok(());
+    };
+
+    let Some(position) = entries.iter().position(|entry| {
+        entry.as_ref().is_some_and(|entry| {
+            entry.kind != CALL && entry.kind != SIGNAL && entry.call == call.call
+        })
+    }) else {
+        // the recording ended before the reply
+        return Ok(());
+    };
+    let reply = entries[position]
+        .take()
+        .expect("position of a present entry");
+    peer.send(&reply.reply_to(msg)?).await?;
+
+    // signals caused by the call were recorded right after its reply
+    for entry in entries[position + 1..].iter_mut() {
+        match entry {
+            Some(signal) if signal.kind == SIGNAL => {
+                peer.send(&signal.signal()?).await?;
+                *entry = None;
+            }
+            Some(_) => break,
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{EncryptionType, SecretService};
+
+    async fn exercise(ss: &SecretService<'_>) -> (String, Vec<u8>) {
+        let collection = ss.get_default_collection().await.unwrap();
+        let item = collection
+            .create_item(
+                "Replayed",
+                HashMap::from([("test_replay", "replay")]),
+                b"test_secret",
+                true,
+                "text/plain",
+            )
+            .await
+            .unwrap();
+        let found = ss
+            .search_items(HashMap::from([("test_replay", "replay")]))
+            .await
+            .unwrap();
+        let label = found.unlocked[0].get_label().await.unwrap();
+        let secret = found.unlocked[0].get_secret().await.unwrap();
+        item.delete().await.unwrap();
+        (label, secret)
+    }
+
+    #[tokio::test]
+    async fn should_replay_recording() {
+        let builder = SecretService::builder().encryption(EncryptionType::Plain);
+        let (builder, recording) = record(builder).await.unwrap();
+        let ss = builder.connect().await.unwrap();
+        let recorded = exercise(&ss).await;
+        drop(ss);
+
+        let fixture = Fixture::from_bytes(&recording.fixture().to_bytes()).unwrap();
+        assert_eq!(fixture, recording.fixture());
+
+        let builder = SecretService::builder().encryption(EncryptionType::Plain);
+        let ss = replay(builder, fixture)
+            .await
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+        assert_eq!(exercise(&ss).await, recorded);
+        assert_eq!(recorded, ("Replayed".to_owned(), b"test_secret".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn should_fail_unrecorded_calls() {
+        let builder = SecretService::builder().encryption(EncryptionType::Plain);
+        let builder = replay(builder, Fixture::default()).await.unwrap();
+        assert!(matches!(builder.connect().await, Err(Error::Zbus(_))));
+    }
+
+    #[test]
+    fn should_reject_unknown_fixtures() {
+        let bytes = zvariant::to_bytes(context(), &("other", Vec::<Entry>::new())).unwrap();
+        assert!(Fixture::from_bytes(&bytes).is_err());
+        assert!(Fixture::from_bytes(b"garbage").is_err());
+    }
+}
@@ -0,0 +1,68 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+// Re-encrypting the secrets of items from one envelope key to another.
+
+use crate::cipher::ENVELOPE_KEY_ATTRIBUTE;
+use crate::util::limit_concurrency;
+use crate::{BatchOutcome, ClientKey, Error, Item, ObjectPath, Progress, SecretService};
+
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+
+pub(crate) async fn rotate_key(
+    ss: &SecretService<'_>,
+    old: &ClientKey,
+    new: &ClientKey,
+    batch_size: NonZeroUsize,
+    mut progress: impl FnMut(&Progress),
+) -> Result<BatchOutcome, Error> {
+    let attributes = ss
+        .config
+        .encrypt_attributes(HashMap::from([(ENVELOPE_KEY_ATTRIBUTE, old.id())]));
+    let found = ss.service_proxy.search_items(attributes.as_map()).await?;
+
+    let mut state = Progress::new(Some(found.unlocked.len() + found.locked.len()));
+    let mut results: Vec<(ObjectPath, Result<(), Error>)> = Vec::new();
+    for batch in found.unlocked.chunks(batch_size.get()) {
+        let mut secrets = ss
+            .service_proxy
+            .get_secrets(
+                batch.iter().map(|path| &**path).collect(),
+                ss.session.open().await?,
+            )
+            .await?;
+        let rotated = futures_util::future::join_all(batch.iter().map(|path| {
+            let secret = secrets.remove(path);
+            limit_concurrency(&ss.config, async move {
+                // not returned for items locked or deleted since the search
+                let secret = secret.ok_or(Error::Locked)?;
+                let item = Item::new(
+                    ss.conn.clone(),
+                    &ss.session,
+                    &ss.service_proxy,
+                    &ss.config,
+                    path.clone(),
+                )
+                .await?;
+                item.rotate_envelope_key(secret, old, new).await
+            })
+        }))
+        .await;
+        for (path, result) in batch.iter().zip(rotated) {
+            state.advance(None, &result, &mut progress);
+            results.push((path.clone().into(), result));
+        }
+    }
+
+    for path in found.locked {
+        let result = Err(Error::Locked);
+        state.advance(None, &result, &mut progress);
+        results.push((path.into(), result));
+    }
+    Ok(BatchOutcome { results })
+}
@@ -0,0 +1,349 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+// Attribute searches with client-side limits and ordering.
+
+use crate::builder::Config;
+use crate::cipher::ENVELOPE_KEY_ATTRIBUTE;
+use crate::util::{limit_concurrency, sort_by_keys, SortOrder};
+use crate::{blocking, Error, Item, SearchItemsResult, SecretService};
+
+use std::collections::HashMap;
+use zbus::zvariant::OwnedObjectPath;
+
+/// What the results of an [ItemSearch] are ordered by.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SearchOrder {
+    /// Most recently modified first.
+    Modified,
+    /// Most recently created first.
+    Created,
+    /// Alphabetically by label, ignoring case.
+    Label,
+}
+
+impl From<SearchOrder> for SortOrder {
+    fn from(order: SearchOrder) -> Self {
+        match order {
+            SearchOrder::Modified => SortOrder::Newest(crate::ItemTimestamp::Modified),
+            SearchOrder::Created => SortOrder::Newest(crate::ItemTimestamp::Created),
+            SearchOrder::Label => SortOrder::Label,
+        }
+    }
+}
+
+/// Keeps the first `limit` paths, unlocked ones first.
+pub(crate) fn truncate_paths(
+    unlocked: &mut Vec<OwnedObjectPath>,
+    locked: &mut Vec<OwnedObjectPath>,
+    limit: Option<usize>,
+) {
+    if let Some(limit) = limit {
+        unlocked.truncate(limit);
+        locked.truncate(limit - unlocked.len());
+    }
+}
+
+/// Splits `items`, tagged with whether they are locked, into a
+/// [SearchItemsResult], keeping the first `limit` of them.
+pub(crate) fn split_items<T>(items: Vec<(bool, T)>, limit: Option<usize>) -> SearchItemsResult<T> {
+    let mut result = SearchItemsResult {
+        unlocked: Vec::new(),
+        locked: Vec::new(),
+    };
+    for (locked, item) in items.into_iter().take(limit.unwrap_or(usize::MAX)) {
+        match locked {
+            true => result.locked.push(item),
+            false => result.unlocked.push(item),
+        }
+    }
+    result
+}
+
+/// Returns whether the attributes of an item are exactly those searched
+/// for, rather than a superset of them. The envelope key id recorded by the
+/// crate is ignored.
+pub(crate) fn is_exact_match(
+    searched: &HashMap<&str, &str>,
+    attributes: &HashMap<String, String>,
+) -> bool {
+    let stored = attributes
+        .keys()
+        .filter(|key| *key != ENVELOPE_KEY_ATTRIBUTE)
+        .count();
+    let searched_len = searched
+        .keys()
+        .filter(|key| **key != ENVELOPE_KEY_ATTRIBUTE)
+        .count();
+    stored == searched_len
+        && searched.iter().all(|(key, value)| {
+            *key == ENVELOPE_KEY_ATTRIBUTE || attributes.get(*key).is_some_and(|v| v == value)
+        })
+}
+
+/// Keeps the items whose attributes are exactly `searched`.
+pub(crate) async fn retain_exact<'a>(
+    config: &Config,
+    items: Vec<Item<'a>>,
+    searched: &HashMap<&str, &str>,
+) -> Result<Vec<Item<'a>>, Error> {
+    let attributes = futures_util::future::try_join_all(
+        items
+            .iter()
+            .map(|item| limit_concurrency(config, item.get_attributes())),
+    )
+    .await?;
+    Ok(items
+        .into_iter()
+        .zip(attributes)
+        .filter(|(_, attributes)| is_exact_match(searched, attributes))
+        .map(|(item, _)| item)
+        .collect())
+}
+
+/// Blocking variant of [retain_exact].
+pub(crate) fn retain_exact_blocking<'a>(
+    items: Vec<blocking::Item<'a>>,
+    searched: &HashMap<&str, &str>,
+) -> Result<Vec<blocking::Item<'a>>, Error> {
+    let mut exact = Vec::new();
+    for item in items {
+        if is_exact_match(searched, &item.get_attributes()?) {
+            exact.push(item);
+        }
+    }
+    Ok(exact)
+}
+
+/// A search of items by attributes, created with [SecretService::search].
+///
+/// Without options, it finds the same items as [SecretService::search_items].
+/// With [ItemSearch::limit], only the first results are turned into [Item]s,
+/// which matters when a broad search matches thousands of items.
+///
+/// ```no_run
+/// # use secret_service::{EncryptionType, SearchOrder, SecretService};
+/// # use std::collections::HashMap;
+/// # async fn call() {
+/// let ss = SecretService::connect(EncryptionType::Dh).await.unwrap();
+/// let newest = ss
+///     .search(HashMap::from([("service", "example")]))
+///     .order_by(SearchOrder::Modified)
+///     .limit(10)
+///     .execute()
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+#[must_use = "a search does nothing until executed"]
+pub struct ItemSearch<'a> {
+    service: &'a SecretService<'a>,
+    attributes: HashMap<&'a str, &'a str>,
+    limit: Option<usize>,
+    order: Option<SearchOrder>,
+    exact: bool,
+}
+
+impl<'a> ItemSearch<'a> {
+    pub(crate) fn new(
+        service: &'a SecretService<'a>,
+        attributes: HashMap<&'a str, &'a str>,
+    ) -> Self {
+        ItemSearch {
+            service,
+            attributes,
+            limit: None,
+            order: None,
+            exact: false,
+        }
+    }
+
+    /// Returns at most `limit` items in total.
+    ///
+    /// Without [ItemSearch::order_by], unlocked items are kept first.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Orders the results, across locked and unlocked items, before the
+    /// [limit](ItemSearch::limit) applies.
+    ///
+    /// The spec can't order results, so the property each item is ordered by
+    /// is fetched on the client, concurrently up to the limit set with
+    /// [SecretServiceBuilder::max_concurrent_calls](crate::SecretServiceBuilder::max_concurrent_calls).
+    /// Items that compare equal keep the order of the provider.
+    pub fn order_by(mut self, order: SearchOrder) -> Self {
+        self.order = Some(order);
+        self
+    }
+
+    /// Only returns items whose attributes are exactly those searched for.
+    ///
+    /// The spec's `SearchItems` finds the items holding the attributes
+    /// searched for among others, so the attributes of each item found are
+    /// fetched on the client and compared, concurrently up to the limit set
+    /// with
+    /// [SecretServiceBuilder::max_concurrent_calls](crate::SecretServiceBuilder::max_concurrent_calls).
+    /// The `envelope_key_id` attribute recorded with
+    /// [SecretServiceBuilder::envelope_key](crate::SecretServiceBuilder::envelope_key)
+    /// is ignored. Locked items may not disclose their attributes, in which
+    /// case the search fails.
+    pub fn exact(mut self) -> Self {
+        self.exact = true;
+        self
+    }
+
+    /// Runs the search.
+    pub async fn execute(self) -> Result<SearchItemsResult<Item<'a>>, Error> {
+        let service = self.service;
+        let attributes = service.config.encrypt_attributes(self.attributes.clone());
+        let found = service
+            .service_proxy
+            .search_items(attributes.as_map())
+            .await?;
+        let (mut unlocked, mut locked) = (found.unlocked, found.locked);
+        if self.order.is_none() && !self.exact {
+            truncate_paths(&mut unlocked, &mut locked, self.limit);
+        }
+
+        let tagged = unlocked
+            .into_iter()
+            .map(|path| (false, path))
+            .chain(locked.into_iter().map(|path| (true, path)));
+        let mut items =
+            futures_util::future::try_join_all(tagged.map(|(locked, path)| async move {
+                let item = Item::new(
+                    service.conn.clone(),
+                    &service.session,
+                    &service.service_proxy,
+                    &service.config,
+                    path,
+                )
+                .await?;
+                Ok::<_, Error>((locked, item))
+            }))
+            .await?;
+
+        if self.exact {
+            let attributes = futures_util::future::try_join_all(
+                items
+                    .iter()
+                    .map(|(_, item)| limit_concurrency(&service.config, item.get_attributes())),
+            )
+            .await?;
+            items = items
+                .into_iter()
+                .zip(attributes)
+                .filter(|(_, attributes)| is_exact_match(&self.attributes, attributes))
+                .map(|(item, _)| item)
+                .collect();
+        }
+
+        if let Some(order) = self.order {
+            let keys =
+                futures_util::future::try_join_all(items.iter().map(|(_, item)| {
+                    limit_concurrency(&service.config, item.sort_key(order.into()))
+                }))
+                .await?;
+            sort_by_keys(&mut items, keys);
+        }
+        Ok(split_items(items, self.limit))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn path(n: u32) -> OwnedObjectPath {
+        OwnedObjectPath::try_from(format!("/org/freedesktop/secrets/collection/test/{n}")).unwrap()
+    }
+
+    #[test]
+    fn should_limit_unlocked_first() {
+        let (mut unlocked, mut locked) = (vec![path(1), path(2)], vec![path(3), path(4)]);
+        truncate_paths(&mut unlocked, &mut locked, Some(3));
+        assert_eq!(unlocked, vec![path(1), path(2)]);
+        assert_eq!(locked, vec![path(3)]);
+
+        truncate_paths(&mut unlocked, &mut locked, Some(1));
+        assert_eq!(unlocked, vec![path(1)]);
+        assert!(locked.is_empty());
+
+        let result = split_items(vec![(true, 1), (false, 2), (true, 3)], Some(2));
+        assert_eq!(result.unlocked, vec![2]);
+        assert_eq!(result.locked, vec![1]);
+    }
+
+    #[test]
+    fn should_match_exact_attributes() {
+        let searched = HashMap::from([("service", "smtp")]);
+        let attributes = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect::<HashMap<_, _>>()
+        };
+        assert!(is_exact_match(
+            &searched,
+            &attributes(&[("service", "smtp")])
+        ));
+        assert!(is_exact_match(
+            &searched,
+            &attributes(&[("service", "smtp"), (ENVELOPE_KEY_ATTRIBUTE, "key")])
+        ));
+        assert!(!is_exact_match(
+            &searched,
+            &attributes(&[("service", "smtp"), ("user", "alice")])
+        ));
+        assert!(!is_exact_match(
+            &searched,
+            &attributes(&[("service", "imap")])
+        ));
+        assert!(!is_exact_match(&searched, &attributes(&[])));
+    }
+
+    #[tokio::test]
+    async fn should_search_exact_attributes() {
+        let ss = SecretService::connect(crate::EncryptionType::Plain)
+            .await
+            .unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let searched = HashMap::from([("test_attribute_exact", "test_value")]);
+        let exact = collection
+            .create_item("Test", searched.clone(), b"test", false, "text/plain")
+            .await
+            .unwrap();
+        let superset = collection
+            .create_item(
+                "Test",
+                HashMap::from([("test_attribute_exact", "test_value"), ("user", "alice")]),
+                b"test",
+                false,
+                "text/plain",
+            )
+            .await
+            .unwrap();
+
+        let found = collection
+            .search_items_exact(searched.clone())
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].path(), exact.path());
+        let found = ss.search(searched.clone()).exact().execute().await.unwrap();
+        assert_eq!(found.unlocked.len(), 1);
+        assert_eq!(found.unlocked[0].path(), exact.path());
+        let found = ss.search(searched).execute().await.unwrap();
+        assert_eq!(found.unlocked.len(), 2);
+
+        exact.delete().await.unwrap();
+        superset.delete().await.unwrap();
+    }
+}
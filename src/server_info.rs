@@ -0,0 +1,229 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+// Identifying the provider through the bus and introspection, for bug
+// reports and working around the quirks of specific providers.
+
+use crate::util::limit_concurrency;
+use crate::{blocking, Error, SecretService};
+
+use zbus::fdo::{DBusProxy, IntrospectableProxy, PropertiesProxy};
+use zbus::names::{BusName, InterfaceName};
+use zbus::zvariant::OwnedValue;
+
+use std::collections::HashMap;
+
+/// Describes the provider, as returned by [SecretService::server_info] and
+/// [blocking::SecretService::server_info].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ServerInfo {
+    /// The unique name of the provider's connection, such as `:1.42`.
+    pub unique_name: String,
+    /// The well-known names the provider owns, sorted, such as
+    /// `org.freedesktop.secrets` and `org.gnome.keyring`, which tell
+    /// providers apart.
+    pub well_known_names: Vec<String>,
+    /// The process id of the provider, if the bus tells.
+    pub process_id: Option<u32>,
+    /// The version of the provider, if it exposes one as a `Version`
+    /// property of its service object. Few do.
+    pub version: Option<String>,
+    /// The interfaces implemented by the provider's service object, sorted.
+    pub interfaces: Vec<String>,
+}
+
+impl ServerInfo {
+    /// Returns whether the service object implements `interface`, such as
+    /// gnome-keyring's `org.gnome.keyring.InternalUnsupportedGuiltRiddenInterface`.
+    pub fn implements(&self, interface: &str) -> bool {
+        self.interfaces.iter().any(|name| name == interface)
+    }
+}
+
+/// Returns the names of the interfaces declared by introspection data,
+/// sorted.
+fn introspected_interfaces(xml: &str) -> Vec<String> {
+    // interfaces of child nodes aren't listed, only the children themselves
+    let mut interfaces: Vec<_> = xml
+        .split("<interface")
+        .skip(1)
+        .filter_map(|element| {
+            let rest = element.split_once("name=")?.1;
+            let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+            let name = rest[1..].split(quote).next()?;
+            Some(name.to_owned())
+        })
+        .collect();
+    interfaces.sort_unstable();
+    interfaces.dedup();
+    interfaces
+}
+
+/// Returns the `Version` property among the properties of an interface, if
+/// it's a string.
+fn version_of(properties: HashMap<String, OwnedValue>) -> Option<String> {
+    properties
+        .get("Version")
+        .and_then(|version| String::try_from(version.try_clone().ok()?).ok())
+}
+
+pub(crate) async fn server_info(ss: &SecretService<'_>) -> Result<ServerInfo, Error> {
+    let destination =
+        BusName::try_from(ss.config.destination.as_str()).map_err(zbus::Error::from)?;
+    let dbus = DBusProxy::new(&ss.conn).await?;
+    let unique_name = match &destination {
+        BusName::Unique(name) => name.to_string(),
+        BusName::WellKnown(_) => dbus.get_name_owner(destination.clone()).await?.to_string(),
+    };
+
+    let names = dbus.list_names().await?;
+    let owners = futures_util::future::join_all(
+        names
+            .iter()
+            .filter(|name| matches!(name.inner(), BusName::WellKnown(_)))
+            .map(|name| {
+                limit_concurrency(&ss.config, async {
+                    let owner = dbus.get_name_owner(name.inner().clone()).await.ok()?;
+                    (owner.as_str() == unique_name).then(|| name.to_string())
+                })
+            }),
+    )
+    .await;
+    let mut well_known_names: Vec<_> = owners.into_iter().flatten().collect();
+    well_known_names.sort_unstable();
+
+    let process_id = dbus
+        .get_connection_unix_process_id(destination.clone())
+        .await
+        .ok();
+
+    let xml = IntrospectableProxy::builder(&ss.conn)
+        .destination(destination.clone())?
+        .path(ss.config.path.as_str())?
+        .build()
+        .await?
+        .introspect()
+        .await?;
+    let interfaces = introspected_interfaces(&xml);
+
+    let properties = PropertiesProxy::builder(&ss.conn)
+        .destination(destination)?
+        .path(ss.config.path.as_str())?
+        .build()
+        .await?;
+    let mut version = None;
+    for interface in &interfaces {
+        let Ok(interface) = InterfaceName::try_from(interface.as_str()) else {
+            continue;
+        };
+        if let Ok(found) = properties.get_all(Some(interface).into()).await {
+            version = version_of(found);
+            if version.is_some() {
+                break;
+            }
+        }
+    }
+
+    Ok(ServerInfo {
+        unique_name,
+        well_known_names,
+        process_id,
+        version,
+        interfaces,
+    })
+}
+
+pub(crate) fn server_info_blocking(ss: &blocking::SecretService<'_>) -> Result<ServerInfo, Error> {
+    let destination =
+        BusName::try_from(ss.config.destination.as_str()).map_err(zbus::Error::from)?;
+    let dbus = zbus::blocking::fdo::DBusProxy::new(&ss.conn)?;
+    let unique_name = match &destination {
+        BusName::Unique(name) => name.to_string(),
+        BusName::WellKnown(_) => dbus.get_name_owner(destination.clone())?.to_string(),
+    };
+
+    let mut well_known_names: Vec<_> = dbus
+        .list_names()?
+        .into_iter()
+        .filter(|name| {
+            matches!(name.inner(), BusName::WellKnown(_))
+                && dbus
+                    .get_name_owner(name.inner().clone())
+                    .is_ok_and(|owner| owner.as_str() == unique_name)
+        })
+        .map(|name| name.to_string())
+        .collect();
+    well_known_names.sort_unstable();
+
+    let process_id = dbus
+        .get_connection_unix_process_id(destination.clone())
+        .ok();
+
+    let xml = zbus::blocking::fdo::IntrospectableProxy::builder(&ss.conn)
+        .destination(destination.clone())?
+        .path(ss.config.path.as_str())?
+        .build()?
+        .introspect()?;
+    let interfaces = introspected_interfaces(&xml);
+
+    let properties = zbus::blocking::fdo::PropertiesProxy::builder(&ss.conn)
+        .destination(destination)?
+        .path(ss.config.path.as_str())?
+        .build()?;
+    let version = interfaces.iter().find_map(|interface| {
+        let interface = InterfaceName::try_from(interface.as_str()).ok()?;
+        version_of(properties.get_all(Some(interface).into()).ok()?)
+    });
+
+    Ok(ServerInfo {
+        unique_name,
+        well_known_names,
+        process_id,
+        version,
+        interfaces,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ss::{SS_DBUS_NAME, SS_SERVICE_INTERFACE};
+    use crate::EncryptionType;
+
+    #[test]
+    fn should_parse_introspected_interfaces() {
+        let xml = r#"<!DOCTYPE node PUBLIC "-//freedesktop//DTD D-BUS Object Introspection 1.0//EN"
+            "http://www.freedesktop.org/standards/dbus/1.0/introspect.dtd">
+            <node>
+              <interface name="org.freedesktop.Secret.Service">
+                <method name="OpenSession"/>
+              </interface>
+              <interface name='org.freedesktop.DBus.Properties'></interface>
+              <node name="collection"/>
+            </node>"#;
+        assert_eq!(
+            introspected_interfaces(xml),
+            [
+                "org.freedesktop.DBus.Properties",
+                "org.freedesktop.Secret.Service"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_describe_server() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let info = ss.server_info().await.unwrap();
+        assert!(info.unique_name.starts_with(':'));
+        assert!(info
+            .well_known_names
+            .iter()
+            .any(|name| name == SS_DBUS_NAME));
+        assert!(info.implements(SS_SERVICE_INTERFACE));
+    }
+}
@@ -17,10 +17,14 @@
 // 7. Format Secret: encode the secret value for the value field in secret struct.
 //      This encoding uses the aes_key from the associated Session.
 
+use crate::algorithm::{Aes128Key, KeyExchange, SessionAlgorithm, SessionCipher, Transport};
+#[cfg(feature = "kernel-keyring")]
+use crate::keyring::KernelKey;
 use crate::proxy::service::{OpenSessionResult, ServiceProxy, ServiceProxyBlocking};
 use crate::ss::{ALGORITHM_DH, ALGORITHM_PLAIN};
-use crate::Error;
+use crate::{Error, ObjectPath};
 
+use async_lock::OnceCell;
 use generic_array::{typenum::U16, GenericArray};
 use num::{
     bigint::BigUint,
@@ -30,9 +34,11 @@ use num::{
 };
 use once_cell::sync::Lazy;
 use rand::{rngs::OsRng, Rng};
-use zbus::zvariant::OwnedObjectPath;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue, Value};
 
-use std::ops::{Mul, Rem, Shr};
+use std::ops::{Deref, Mul, Rem, Shr};
+use std::sync::Arc;
+use std::time::SystemTime;
 
 // for key exchange
 static DH_GENERATOR: Lazy<BigUint> = Lazy::new(|| BigUint::from_u64(0x2).unwrap());
@@ -57,14 +63,87 @@ macro_rules! feature_needed {
     }
 }
 
-type AesKey = GenericArray<u8, U16>;
+pub(crate) type AesKey = GenericArray<u8, U16>;
 
-#[derive(Debug, Eq, PartialEq)]
+/// Length of an AES block, and so of the IV of encrypted secrets.
+pub(crate) const AES_BLOCK_LEN: usize = 16;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum EncryptionType {
+    /// Secrets are sent over the bus unencrypted.
+    ///
+    /// Opening such a session fails with [Error::PlainDenied] when the
+    /// `deny-plain` feature is enabled. The variant itself is kept, so that
+    /// enabling the feature anywhere in a dependency tree doesn't break the
+    /// build of crates naming it.
     Plain,
+    /// Secrets are encrypted with a key negotiated through Diffie-Hellman.
     Dh,
 }
 
+impl EncryptionType {
+    /// Returns the algorithm of the specification negotiating this kind of
+    /// session.
+    pub(crate) fn algorithm(self) -> &'static dyn SessionAlgorithm {
+        match self {
+            EncryptionType::Plain => &PlainAlgorithm,
+            EncryptionType::Dh => &DhAlgorithm,
+        }
+    }
+}
+
+/// The specification's `plain` algorithm.
+pub(crate) struct PlainAlgorithm;
+
+impl SessionAlgorithm for PlainAlgorithm {
+    fn name(&self) -> &str {
+        ALGORITHM_PLAIN
+    }
+
+    fn start(&self) -> Result<Box<dyn KeyExchange>, Error> {
+        deny_plain()?;
+        Ok(Box::new(PlainAlgorithm))
+    }
+}
+
+impl KeyExchange for PlainAlgorithm {
+    fn input(&self) -> Value<'static> {
+        "".into()
+    }
+
+    fn finish(self: Box<Self>, _output: OwnedValue) -> Result<Transport, Error> {
+        Ok(Transport::Plain)
+    }
+}
+
+/// The specification's `dh-ietf1024-sha256-aes128-cbc-pkcs7` algorithm.
+pub(crate) struct DhAlgorithm;
+
+impl SessionAlgorithm for DhAlgorithm {
+    fn name(&self) -> &str {
+        ALGORITHM_DH
+    }
+
+    fn start(&self) -> Result<Box<dyn KeyExchange>, Error> {
+        Ok(Box::new(Keypair::generate()))
+    }
+}
+
+impl KeyExchange for Keypair {
+    fn input(&self) -> Value<'static> {
+        self.public.to_bytes_be().into()
+    }
+
+    fn finish(self: Box<Self>, output: OwnedValue) -> Result<Transport, Error> {
+        let server_public_key = output
+            .try_into()
+            .map(|key: Vec<u8>| BigUint::from_bytes_be(&key))?;
+        let key = Aes128Key(self.derive_shared(&server_public_key));
+        Ok(Transport::Aes128Cbc(key))
+    }
+}
+
 struct Keypair {
     private: BigUint,
     public: BigUint,
@@ -108,7 +187,7 @@ impl Keypair {
 }
 
 #[cfg(feature = "crypto-openssl")]
-fn hkdf(ikm: Vec<u8>, salt: Option<&[u8]>, okm: &mut [u8]) {
+pub(crate) fn hkdf(ikm: Vec<u8>, salt: Option<&[u8]>, okm: &mut [u8]) {
     let mut ctx = openssl::pkey_ctx::PkeyCtx::new_id(openssl::pkey::Id::HKDF)
         .expect("hkdf context should not fail");
     ctx.derive_init().expect("hkdf derive init should not fail");
@@ -128,7 +207,7 @@ fn hkdf(ikm: Vec<u8>, salt: Option<&[u8]>, okm: &mut [u8]) {
 }
 
 #[cfg(feature = "crypto-rust")]
-fn hkdf(ikm: Vec<u8>, salt: Option<&[u8]>, okm: &mut [u8]) {
+pub(crate) fn hkdf(ikm: Vec<u8>, salt: Option<&[u8]>, okm: &mut [u8]) {
     use hkdf::Hkdf;
     use sha2::Sha256;
 
@@ -139,85 +218,457 @@ fn hkdf(ikm: Vec<u8>, salt: Option<&[u8]>, okm: &mut [u8]) {
 }
 
 #[cfg(all(not(feature = "crypto-rust"), not(feature = "crypto-openssl")))]
-fn hkdf(ikm: Vec<u8>, salt: Option<&[u8]>, okm: &mut [u8]) {
+pub(crate) fn hkdf(ikm: Vec<u8>, salt: Option<&[u8]>, okm: &mut [u8]) {
     feature_needed!()
 }
 
+/// An AES key loaded for a single operation, wiped when dropped.
+pub struct TransientKey(AesKey);
+
+impl Deref for TransientKey {
+    type Target = AesKey;
+
+    fn deref(&self) -> &AesKey {
+        &self.0
+    }
+}
+
+impl Drop for TransientKey {
+    fn drop(&mut self) {
+        wipe(&mut self.0);
+    }
+}
+
+pub(crate) fn wipe(key: &mut AesKey) {
+    for byte in key.iter_mut() {
+        // SAFETY: `byte` is a valid, aligned reference; the volatile write
+        // keeps the compiler from eliding the store
+        unsafe { std::ptr::write_volatile(byte, 0) };
+    }
+}
+
+enum SessionKey {
+    Memory(Aes128Key),
+    #[cfg(feature = "kernel-keyring")]
+    Kernel(KernelKey),
+    Cipher(Box<dyn SessionCipher>),
+}
+
+/// A session negotiated with the provider, which secrets are transferred in.
+///
+/// Every [SecretService](crate::SecretService) negotiates one when connecting,
+/// or on the first operation transferring a secret with
+/// [SecretServiceBuilder::lazy_session](crate::SecretServiceBuilder::lazy_session).
+/// More can be opened with
+/// [SecretService::open_session](crate::SecretService::open_session), and
+/// used through the `with_session` methods of collections and items.
 pub struct Session {
-    pub object_path: OwnedObjectPath,
-    aes_key: Option<AesKey>,
+    negotiated: OnceCell<Negotiated>,
+    /// How the session is negotiated on first use, if it's lazy.
+    pending: Option<PendingSession>,
+    created: SystemTime,
 }
 
-impl Session {
-    fn encrypted_session(keypair: &Keypair, session: OpenSessionResult) -> Result<Self, Error> {
-        let server_public_key = session
-            .output
-            .try_into()
-            .map(|key: Vec<u8>| BigUint::from_bytes_be(&key))?;
+/// The state of a session once `OpenSession` returned.
+struct Negotiated {
+    object_path: OwnedObjectPath,
+    key: Option<SessionKey>,
+    algorithm: String,
+}
 
-        let aes_key = keypair.derive_shared(&server_public_key);
+/// A session to negotiate once a secret is transferred.
+pub(crate) struct PendingSession {
+    service_proxy: ServiceProxy<'static>,
+    algorithm: Arc<dyn SessionAlgorithm>,
+    #[cfg(feature = "kernel-keyring")]
+    kernel_keyring: bool,
+    plain_fallback: bool,
+}
 
-        Ok(Session {
-            object_path: session.result,
-            aes_key: Some(aes_key),
+impl PendingSession {
+    /// Prepares negotiating a session with `algorithm`, failing right away
+    /// if it's `plain` and plain sessions are denied.
+    pub(crate) fn new(
+        service_proxy: ServiceProxy<'static>,
+        algorithm: Arc<dyn SessionAlgorithm>,
+    ) -> Result<Self, Error> {
+        if algorithm.name() == ALGORITHM_PLAIN {
+            deny_plain()?;
+        }
+        Ok(PendingSession {
+            service_proxy,
+            algorithm,
+            #[cfg(feature = "kernel-keyring")]
+            kernel_keyring: false,
+            plain_fallback: false,
         })
     }
 
-    pub fn new_blocking(
+    /// Sets whether a plain session is negotiated if the provider doesn't
+    /// support the algorithm, see [Session::negotiate_or_plain].
+    pub(crate) fn plain_fallback(mut self, enabled: bool) -> Self {
+        self.plain_fallback = enabled;
+        self
+    }
+}
+
+/// Describes the session negotiated with the provider, as returned by
+/// [SecretService::session](crate::SecretService::session) and
+/// [blocking::SecretService::session](crate::blocking::SecretService::session).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SessionInfo {
+    /// The object path of the session, or `/` for a lazy session not
+    /// negotiated yet.
+    pub path: ObjectPath,
+    /// The kind of session negotiated. Sessions negotiated with algorithms
+    /// from outside the specification count as [EncryptionType::Dh] if they
+    /// encrypt secrets, as do lazy sessions not negotiated yet, unless they
+    /// are to be `plain`.
+    pub encryption: EncryptionType,
+    /// When the session was opened, or set up to be opened lazily.
+    pub created: SystemTime,
+    algorithm: String,
+}
+
+impl SessionInfo {
+    /// Returns the name of the algorithm negotiated, as passed to the
+    /// provider's `OpenSession`.
+    pub fn algorithm(&self) -> &str {
+        &self.algorithm
+    }
+}
+
+impl std::fmt::Debug for Session {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        // leaves out the key
+        let info = self.info();
+        f.debug_struct("Session")
+            .field("object_path", &info.path)
+            .field("algorithm", &info.algorithm)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Session {
+    pub(crate) fn new_blocking(
         service_proxy: &ServiceProxyBlocking,
         encryption: EncryptionType,
     ) -> Result<Self, Error> {
-        match encryption {
-            EncryptionType::Plain => {
-                let session = service_proxy.open_session(ALGORITHM_PLAIN, "".into())?;
-                let session_path = session.result;
-
-                Ok(Session {
-                    object_path: session_path,
-                    aes_key: None,
-                })
+        Self::negotiate_blocking(service_proxy, encryption.algorithm())
+    }
+
+    pub(crate) async fn new(
+        service_proxy: &ServiceProxy<'_>,
+        encryption: EncryptionType,
+    ) -> Result<Self, Error> {
+        Self::negotiate(service_proxy, encryption.algorithm()).await
+    }
+
+    /// Creates a session negotiated by the first call to [Session::open].
+    pub(crate) fn lazy(pending: PendingSession) -> Self {
+        Session {
+            negotiated: OnceCell::new(),
+            pending: Some(pending),
+            created: SystemTime::now(),
+        }
+    }
+
+    pub(crate) fn negotiate_blocking(
+        service_proxy: &ServiceProxyBlocking,
+        algorithm: &dyn SessionAlgorithm,
+    ) -> Result<Self, Error> {
+        let exchange = algorithm.start()?;
+        let session = service_proxy.open_session(algorithm.name(), exchange.input())?;
+        Ok(Self::negotiated(Negotiated::established(
+            algorithm.name(),
+            exchange,
+            session,
+        )?))
+    }
+
+    pub(crate) async fn negotiate(
+        service_proxy: &ServiceProxy<'_>,
+        algorithm: &dyn SessionAlgorithm,
+    ) -> Result<Self, Error> {
+        Ok(Self::negotiated(
+            Negotiated::negotiate(service_proxy, algorithm).await?,
+        ))
+    }
+
+    /// Negotiates a session with `algorithm`, or with the `plain` algorithm
+    /// if `plain_fallback` is set and the provider doesn't support it.
+    ///
+    /// Some minimal providers only support plain sessions, and reject
+    /// others with `NotSupported`. Under the `deny-plain` feature, nothing
+    /// is retried and the rejection is returned.
+    pub(crate) async fn negotiate_or_plain(
+        service_proxy: &ServiceProxy<'_>,
+        algorithm: &dyn SessionAlgorithm,
+        plain_fallback: bool,
+    ) -> Result<Self, Error> {
+        Ok(Self::negotiated(
+            Negotiated::negotiate_or_plain(service_proxy, algorithm, plain_fallback).await?,
+        ))
+    }
+
+    /// Like [Session::negotiate_or_plain], blocking the current thread.
+    pub(crate) fn negotiate_or_plain_blocking(
+        service_proxy: &ServiceProxyBlocking,
+        algorithm: &dyn SessionAlgorithm,
+        plain_fallback: bool,
+    ) -> Result<Self, Error> {
+        match Self::negotiate_blocking(service_proxy, algorithm) {
+            Err(err) if plain_fallback && falls_back_to_plain(algorithm, &err) => {
+                Self::negotiate_blocking(service_proxy, &PlainAlgorithm)
             }
-            EncryptionType::Dh => {
-                let keypair = Keypair::generate();
+            result => result,
+        }
+    }
 
-                let session = service_proxy
-                    .open_session(ALGORITHM_DH, keypair.public.to_bytes_be().into())?;
+    fn negotiated(negotiated: Negotiated) -> Self {
+        Session {
+            negotiated: negotiated.into(),
+            pending: None,
+            created: SystemTime::now(),
+        }
+    }
+
+    /// Negotiates a lazy session if it isn't yet, returning its object path.
+    ///
+    /// Called before any secret is transferred in the session.
+    pub(crate) async fn open(&self) -> Result<&OwnedObjectPath, Error> {
+        let negotiated = self
+            .negotiated
+            .get_or_try_init(|| async {
+                let pending = self
+                    .pending
+                    .as_ref()
+                    .expect("eager sessions are negotiated when created");
+                #[cfg_attr(not(feature = "kernel-keyring"), allow(unused_mut))]
+                let mut negotiated = Negotiated::negotiate_or_plain(
+                    &pending.service_proxy,
+                    &*pending.algorithm,
+                    pending.plain_fallback,
+                )
+                .await?;
+                #[cfg(feature = "kernel-keyring")]
+                if pending.kernel_keyring {
+                    negotiated.move_key_to_kernel()?;
+                }
+                Ok::<_, Error>(negotiated)
+            })
+            .await?;
+        Ok(&negotiated.object_path)
+    }
+
+    /// Like [Session::open], blocking the current thread.
+    pub(crate) fn open_blocking(&self) -> Result<&OwnedObjectPath, Error> {
+        match self.negotiated.get() {
+            Some(negotiated) => Ok(&negotiated.object_path),
+            None => zbus::block_on(self.open()),
+        }
+    }
+
+    /// Returns the state of the session, which has to be opened first.
+    fn state(&self) -> &Negotiated {
+        self.negotiated
+            .get()
+            .expect("sessions are opened before transferring secrets")
+    }
+
+    /// Returns the object path of an opened session.
+    pub(crate) fn object_path(&self) -> &OwnedObjectPath {
+        &self.state().object_path
+    }
+
+    /// Describes the session.
+    pub fn info(&self) -> SessionInfo {
+        match (self.negotiated.get(), &self.pending) {
+            (Some(negotiated), _) => SessionInfo {
+                path: negotiated.object_path.clone().into(),
+                encryption: match negotiated.key {
+                    None => EncryptionType::Plain,
+                    Some(_) => EncryptionType::Dh,
+                },
+                created: self.created,
+                algorithm: negotiated.algorithm.clone(),
+            },
+            (None, pending) => {
+                let algorithm = pending
+                    .as_ref()
+                    .expect("eager sessions are negotiated when created")
+                    .algorithm
+                    .name();
+                SessionInfo {
+                    path: ObjectPath::try_from("/").expect("/ is an object path"),
+                    encryption: if algorithm == ALGORITHM_PLAIN {
+                        EncryptionType::Plain
+                    } else {
+                        EncryptionType::Dh
+                    },
+                    created: self.created,
+                    algorithm: algorithm.to_owned(),
+                }
+            }
+        }
+    }
+
+    /// Returns the length of the parameters of the session's secrets, the
+    /// IV of encrypted ones, unless the algorithm has a cipher of its own.
+    pub(crate) fn parameters_len(&self) -> Option<usize> {
+        match self.state().key {
+            None => Some(0),
+            Some(SessionKey::Cipher(_)) => None,
+            Some(_) => Some(AES_BLOCK_LEN),
+        }
+    }
+
+    /// Encrypts a secret, returning the parameters and the value of the
+    /// `Secret` struct.
+    pub(crate) fn encrypt(&self, secret: &[u8]) -> Result<(Vec<u8>, Vec<u8>), Error> {
+        let negotiated = self.state();
+        if let Some(SessionKey::Cipher(cipher)) = &negotiated.key {
+            return cipher.encrypt(secret);
+        }
+        match negotiated.get_aes_key()? {
+            Some(key) => {
+                let mut aes_iv = [0; AES_BLOCK_LEN];
+                OsRng.fill(&mut aes_iv);
+                Ok((aes_iv.to_vec(), encrypt(secret, &key, &aes_iv)))
+            }
+            None => Ok((Vec::new(), secret.to_vec())),
+        }
+    }
+
+    /// Decrypts the value of a `Secret` struct.
+    pub(crate) fn decrypt(&self, parameters: &[u8], value: Vec<u8>) -> Result<Vec<u8>, Error> {
+        let negotiated = self.state();
+        if let Some(SessionKey::Cipher(cipher)) = &negotiated.key {
+            return cipher.decrypt(parameters, &value);
+        }
+        match negotiated.get_aes_key()? {
+            // the parameters hold the IV
+            Some(key) => decrypt(&value, &key, parameters),
+            None => Ok(value),
+        }
+    }
 
-                Self::encrypted_session(&keypair, session)
+    /// Moves the AES key of an encrypted session into the kernel's process
+    /// keyring, so that it's only held in memory while in use.
+    #[cfg(feature = "kernel-keyring")]
+    pub(crate) fn move_key_to_kernel(&mut self) -> Result<(), Error> {
+        match (self.negotiated.get_mut(), &mut self.pending) {
+            (Some(negotiated), _) => negotiated.move_key_to_kernel(),
+            (None, pending) => {
+                // done once negotiated
+                if let Some(pending) = pending {
+                    pending.kernel_keyring = true;
+                }
+                Ok(())
             }
         }
     }
+}
 
-    pub async fn new(
+impl Negotiated {
+    async fn negotiate(
         service_proxy: &ServiceProxy<'_>,
-        encryption: EncryptionType,
+        algorithm: &dyn SessionAlgorithm,
+    ) -> Result<Self, Error> {
+        let exchange = algorithm.start()?;
+        let session = service_proxy
+            .open_session(algorithm.name(), exchange.input())
+            .await?;
+        Self::established(algorithm.name(), exchange, session)
+    }
+
+    async fn negotiate_or_plain(
+        service_proxy: &ServiceProxy<'_>,
+        algorithm: &dyn SessionAlgorithm,
+        plain_fallback: bool,
     ) -> Result<Self, Error> {
-        match encryption {
-            EncryptionType::Plain => {
-                let session = service_proxy
-                    .open_session(ALGORITHM_PLAIN, "".into())
-                    .await?;
-                let session_path = session.result;
-
-                Ok(Session {
-                    object_path: session_path,
-                    aes_key: None,
-                })
+        match Self::negotiate(service_proxy, algorithm).await {
+            Err(err) if plain_fallback && falls_back_to_plain(algorithm, &err) => {
+                Self::negotiate(service_proxy, &PlainAlgorithm).await
             }
-            EncryptionType::Dh => {
-                let keypair = Keypair::generate();
+            result => result,
+        }
+    }
 
-                let session = service_proxy
-                    .open_session(ALGORITHM_DH, keypair.public.to_bytes_be().into())
-                    .await?;
+    /// Finishes the key exchange with the provider's reply to `OpenSession`.
+    fn established(
+        algorithm: &str,
+        exchange: Box<dyn KeyExchange>,
+        session: OpenSessionResult,
+    ) -> Result<Self, Error> {
+        // the provider sends secrets in the clear whatever the client does
+        // with them
+        if algorithm == ALGORITHM_PLAIN {
+            deny_plain()?;
+        }
+        let key = match exchange.finish(session.output)? {
+            Transport::Plain => {
+                deny_plain()?;
+                None
+            }
+            Transport::Aes128Cbc(key) => Some(SessionKey::Memory(key)),
+            Transport::Cipher(cipher) => Some(SessionKey::Cipher(cipher)),
+        };
 
-                Self::encrypted_session(&keypair, session)
+        Ok(Negotiated {
+            object_path: session.result,
+            key,
+            algorithm: algorithm.to_owned(),
+        })
+    }
+
+    /// Loads the AES key of an encrypted session.
+    fn get_aes_key(&self) -> Result<Option<TransientKey>, Error> {
+        match &self.key {
+            None | Some(SessionKey::Cipher(_)) => Ok(None),
+            Some(SessionKey::Memory(key)) => Ok(Some(TransientKey(key.0))),
+            #[cfg(feature = "kernel-keyring")]
+            Some(SessionKey::Kernel(kernel_key)) => {
+                let mut key = TransientKey(AesKey::default());
+                let len = kernel_key.read(&mut key.0).map_err(Error::KernelKeyring)?;
+                if len != key.0.len() {
+                    return Err(Error::Crypto("kernel keyring returned a malformed key"));
+                }
+                Ok(Some(key))
             }
         }
     }
 
-    pub fn get_aes_key(&self) -> Option<&AesKey> {
-        self.aes_key.as_ref()
+    #[cfg(feature = "kernel-keyring")]
+    fn move_key_to_kernel(&mut self) -> Result<(), Error> {
+        if let Some(SessionKey::Memory(key)) = &self.key {
+            let description =
+                std::ffi::CString::new(format!("secret-service:{}", self.object_path.as_str()))
+                    .expect("object paths don't contain NUL");
+            let kernel_key = KernelKey::add(&description, &key.0).map_err(Error::KernelKeyring)?;
+            // the key in memory is wiped when dropped
+            self.key = Some(SessionKey::Kernel(kernel_key));
+        }
+        Ok(())
+    }
+}
+
+/// Returns whether negotiating a session with `algorithm` failed because
+/// the provider doesn't support it, and a plain session may be negotiated
+/// instead.
+fn falls_back_to_plain(algorithm: &dyn SessionAlgorithm, err: &Error) -> bool {
+    algorithm.name() != ALGORITHM_PLAIN
+        && deny_plain().is_ok()
+        && err.dbus_error_name().as_deref() == Some("org.freedesktop.DBus.Error.NotSupported")
+}
+
+/// Fails if plain sessions are ruled out by the `deny-plain` feature.
+fn deny_plain() -> Result<(), Error> {
+    if cfg!(feature = "deny-plain") {
+        Err(Error::PlainDenied)
+    } else {
+        Ok(())
     }
 }
 
@@ -313,12 +764,13 @@ mod test {
 
     // There is no async test because this tests that an encryption session can be made, nothing more.
 
+    #[cfg(not(feature = "deny-plain"))]
     #[test]
     fn should_create_plain_session() {
         let conn = zbus::blocking::Connection::session().unwrap();
         let service_proxy = ServiceProxyBlocking::new(&conn).unwrap();
         let session = Session::new_blocking(&service_proxy, EncryptionType::Plain).unwrap();
-        assert!(session.get_aes_key().is_none());
+        assert!(session.state().get_aes_key().unwrap().is_none());
     }
 
     #[test]
@@ -326,6 +778,132 @@ mod test {
         let conn = zbus::blocking::Connection::session().unwrap();
         let service_proxy = ServiceProxyBlocking::new(&conn).unwrap();
         let session = Session::new_blocking(&service_proxy, EncryptionType::Dh).unwrap();
-        assert!(session.get_aes_key().is_some());
+        assert!(session.state().get_aes_key().unwrap().is_some());
+    }
+
+    #[cfg(not(feature = "deny-plain"))]
+    #[test]
+    fn should_negotiate_registered_algorithm() {
+        /// The `plain` algorithm, with a cipher leaving secrets as they are.
+        struct IdentityAlgorithm;
+
+        impl SessionAlgorithm for IdentityAlgorithm {
+            fn name(&self) -> &str {
+                ALGORITHM_PLAIN
+            }
+
+            fn start(&self) -> Result<Box<dyn KeyExchange>, Error> {
+                Ok(Box::new(IdentityAlgorithm))
+            }
+        }
+
+        impl KeyExchange for IdentityAlgorithm {
+            fn input(&self) -> Value<'static> {
+                "".into()
+            }
+
+            fn finish(self: Box<Self>, _output: OwnedValue) -> Result<Transport, Error> {
+                Ok(Transport::Cipher(Box::new(IdentityAlgorithm)))
+            }
+        }
+
+        impl SessionCipher for IdentityAlgorithm {
+            fn encrypt(&self, secret: &[u8]) -> Result<(Vec<u8>, Vec<u8>), Error> {
+                Ok((Vec::new(), secret.to_vec()))
+            }
+
+            fn decrypt(&self, _parameters: &[u8], value: &[u8]) -> Result<Vec<u8>, Error> {
+                Ok(value.to_vec())
+            }
+        }
+
+        let ss = crate::SecretService::builder()
+            .register_algorithm(std::sync::Arc::new(IdentityAlgorithm))
+            .algorithm(ALGORITHM_PLAIN)
+            .connect_blocking()
+            .unwrap();
+        assert_eq!(ss.session().algorithm(), ALGORITHM_PLAIN);
+        let collection = ss.get_default_collection().unwrap();
+        let item = collection
+            .create_item("Test", Default::default(), b"test", false, "text/plain")
+            .unwrap();
+        assert_eq!(item.get_secret().unwrap(), b"test");
+        item.delete().unwrap();
+
+        let unknown = crate::SecretService::builder()
+            .algorithm("unknown")
+            .connect_blocking();
+        assert!(matches!(unknown, Err(Error::UnknownAlgorithm(_))));
+    }
+
+    #[cfg(not(feature = "deny-plain"))]
+    #[test]
+    fn should_fall_back_to_plain_session() {
+        /// An algorithm no provider supports.
+        struct UnsupportedAlgorithm;
+
+        impl SessionAlgorithm for UnsupportedAlgorithm {
+            fn name(&self) -> &str {
+                "test-unsupported"
+            }
+
+            fn start(&self) -> Result<Box<dyn KeyExchange>, Error> {
+                Ok(Box::new(PlainAlgorithm))
+            }
+        }
+
+        let builder = || {
+            crate::SecretService::builder()
+                .register_algorithm(std::sync::Arc::new(UnsupportedAlgorithm))
+                .algorithm("test-unsupported")
+        };
+        let Err(rejected) = builder().connect_blocking() else {
+            panic!("the provider accepted an unknown algorithm");
+        };
+        assert_eq!(
+            rejected.dbus_error_name().as_deref(),
+            Some("org.freedesktop.DBus.Error.NotSupported")
+        );
+
+        let ss = builder().plain_fallback(true).connect_blocking().unwrap();
+        assert_eq!(ss.session().encryption, EncryptionType::Plain);
+        assert_eq!(ss.session().algorithm(), ALGORITHM_PLAIN);
+        ss.get_default_collection().unwrap();
+
+        // negotiated once a secret is transferred
+        let ss = builder()
+            .plain_fallback(true)
+            .lazy_session(true)
+            .connect_blocking()
+            .unwrap();
+        let collection = ss.get_default_collection().unwrap();
+        let item = collection
+            .create_item("Test", Default::default(), b"test", false, "text/plain")
+            .unwrap();
+        assert_eq!(ss.session().algorithm(), ALGORITHM_PLAIN);
+        assert_eq!(item.get_secret().unwrap(), b"test");
+        item.delete().unwrap();
+    }
+
+    #[cfg(feature = "deny-plain")]
+    #[test]
+    fn should_deny_plain_session() {
+        let conn = zbus::blocking::Connection::session().unwrap();
+        let service_proxy = ServiceProxyBlocking::new(&conn).unwrap();
+        let result = Session::new_blocking(&service_proxy, EncryptionType::Plain);
+        assert!(matches!(result, Err(Error::PlainDenied)));
+    }
+
+    #[cfg(feature = "kernel-keyring")]
+    #[test]
+    fn should_move_key_to_kernel() {
+        let conn = zbus::blocking::Connection::session().unwrap();
+        let service_proxy = ServiceProxyBlocking::new(&conn).unwrap();
+        let mut session = Session::new_blocking(&service_proxy, EncryptionType::Dh).unwrap();
+        let key = *session.state().get_aes_key().unwrap().unwrap();
+
+        session.move_key_to_kernel().unwrap();
+        assert!(matches!(session.state().key, Some(SessionKey::Kernel(_))));
+        assert_eq!(*session.state().get_aes_key().unwrap().unwrap(), key);
     }
 }
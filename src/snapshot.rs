@@ -0,0 +1,299 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+// The metadata of a collection's items at a point in time, and what changed
+// between two of them.
+
+use crate::builder::Config;
+use crate::ss::SS_ITEM_INTERFACE;
+use crate::util::limit_concurrency;
+use crate::{Error, ObjectPath};
+
+use std::collections::HashMap;
+use std::time::SystemTime;
+use zbus::fdo::PropertiesProxy;
+use zbus::names::InterfaceName;
+use zbus::zvariant::{self, OwnedObjectPath, OwnedValue};
+
+/// The metadata of an item, as held by a [CollectionSnapshot].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ItemSnapshot {
+    pub label: String,
+    pub attributes: HashMap<String, String>,
+    pub locked: bool,
+    /// Seconds since the Unix epoch.
+    pub created: u64,
+    /// Seconds since the Unix epoch.
+    pub modified: u64,
+}
+
+/// The metadata of every item of a collection at a point in time, as
+/// returned by [Collection::snapshot](crate::Collection::snapshot) and
+/// [blocking::Collection::snapshot](crate::blocking::Collection::snapshot).
+///
+/// Each item's properties are read with a single `GetAll` call. Comparing
+/// two snapshots with [CollectionSnapshot::diff] tells what changed in
+/// between, for sync tools that run now and then instead of following the
+/// service's signals like [CollectionMirror](crate::CollectionMirror) does.
+///
+/// ```no_run
+/// # async fn call(collection: &secret_service::Collection<'_>) -> Result<(), secret_service::Error> {
+/// let before = collection.snapshot().await?;
+/// // ... later
+/// let diff = collection.snapshot().await?.diff(&before);
+/// for path in diff.changed {
+///     println!("{path} changed");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CollectionSnapshot {
+    path: ObjectPath,
+    taken: SystemTime,
+    items: HashMap<ObjectPath, ItemSnapshot>,
+}
+
+/// The items that differ between two snapshots, as returned by
+/// [CollectionSnapshot::diff]. Each list is sorted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SnapshotDiff {
+    /// Items only in the newer snapshot.
+    pub added: Vec<ObjectPath>,
+    /// Items only in the older snapshot.
+    pub removed: Vec<ObjectPath>,
+    /// Items in both snapshots whose metadata differs, including items
+    /// whose secret was set since, as that updates their modification time.
+    pub changed: Vec<ObjectPath>,
+}
+
+impl SnapshotDiff {
+    /// Returns whether nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl CollectionSnapshot {
+    /// Returns the path of the collection.
+    pub fn path(&self) -> &ObjectPath {
+        &self.path
+    }
+
+    /// Returns when the snapshot was taken.
+    pub fn taken(&self) -> SystemTime {
+        self.taken
+    }
+
+    /// Returns the items of the collection, by path.
+    pub fn items(&self) -> &HashMap<ObjectPath, ItemSnapshot> {
+        &self.items
+    }
+
+    /// Returns the items that were added, removed or changed since `older`
+    /// was taken.
+    pub fn diff(&self, older: &CollectionSnapshot) -> SnapshotDiff {
+        let mut diff = SnapshotDiff::default();
+        for (path, item) in &self.items {
+            match older.items.get(path) {
+                None => diff.added.push(path.clone()),
+                Some(old) if old != item => diff.changed.push(path.clone()),
+                Some(_) => {}
+            }
+        }
+        diff.removed = older
+            .items
+            .keys()
+            .filter(|path| !self.items.contains_key(*path))
+            .cloned()
+            .collect();
+        diff.added.sort_unstable();
+        diff.removed.sort_unstable();
+        diff.changed.sort_unstable();
+        diff
+    }
+}
+
+/// Reads the metadata of an item from the properties returned by `GetAll`.
+fn item_snapshot(
+    config: &Config,
+    mut properties: HashMap<String, OwnedValue>,
+) -> Result<ItemSnapshot, Error> {
+    let mut take = |name: &str| properties.remove(name).ok_or(Error::NoResult);
+    Ok(ItemSnapshot {
+        label: take("Label")?.try_into()?,
+        attributes: config.decrypt_attributes(take("Attributes")?.try_into()?)?,
+        locked: take("Locked")?.try_into()?,
+        created: take("Created")?.try_into()?,
+        modified: take("Modified")?.try_into()?,
+    })
+}
+
+fn item_interface() -> InterfaceName<'static> {
+    InterfaceName::from_static_str(SS_ITEM_INTERFACE).expect("valid interface name")
+}
+
+pub(crate) async fn snapshot(
+    conn: &zbus::Connection,
+    config: &Config,
+    path: &OwnedObjectPath,
+    items: Vec<zvariant::ObjectPath<'_>>,
+) -> Result<CollectionSnapshot, Error> {
+    let taken = SystemTime::now();
+    let items: Vec<OwnedObjectPath> = items.into_iter().map(Into::into).collect();
+    let read = futures_util::future::join_all(items.iter().map(|item| {
+        limit_concurrency(config, async move {
+            let properties = PropertiesProxy::builder(conn)
+                .destination(config.destination.as_str())?
+                .path(item)?
+                .build()
+                .await?
+                .get_all(Some(item_interface()).into())
+                .await
+                .map_err(Error::from);
+            match properties {
+                Ok(properties) => item_snapshot(config, properties).map(Some),
+                // deleted since the collection was listed
+                Err(err) if err.is_unknown_object() => Ok(None),
+                Err(err) => Err(err.with_context("snapshot", item)),
+            }
+        })
+    }))
+    .await;
+
+    let mut snapshot_items = HashMap::new();
+    for (item, read) in items.into_iter().zip(read) {
+        if let Some(metadata) = read? {
+            snapshot_items.insert(item.into(), metadata);
+        }
+    }
+    Ok(CollectionSnapshot {
+        path: path.clone().into(),
+        taken,
+        items: snapshot_items,
+    })
+}
+
+pub(crate) fn snapshot_blocking(
+    conn: &zbus::blocking::Connection,
+    config: &Config,
+    path: &OwnedObjectPath,
+    items: Vec<zvariant::ObjectPath<'_>>,
+) -> Result<CollectionSnapshot, Error> {
+    let taken = SystemTime::now();
+    let items: Vec<OwnedObjectPath> = items.into_iter().map(Into::into).collect();
+    let mut snapshot_items = HashMap::new();
+    for item in items {
+        let properties = zbus::blocking::fdo::PropertiesProxy::builder(conn)
+            .destination(config.destination.as_str())?
+            .path(&item)?
+            .build()?
+            .get_all(Some(item_interface()).into())
+            .map_err(Error::from);
+        match properties {
+            Ok(properties) => {
+                snapshot_items.insert(item.into(), item_snapshot(config, properties)?);
+            }
+            // deleted since the collection was listed
+            Err(err) if err.is_unknown_object() => {}
+            Err(err) => return Err(err.with_context("snapshot", &item)),
+        }
+    }
+    Ok(CollectionSnapshot {
+        path: path.clone().into(),
+        taken,
+        items: snapshot_items,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::TestHarness;
+    use crate::EncryptionType;
+
+    fn item(label: &str, modified: u64) -> ItemSnapshot {
+        ItemSnapshot {
+            label: label.to_owned(),
+            attributes: HashMap::new(),
+            locked: false,
+            created: 1,
+            modified,
+        }
+    }
+
+    fn path(name: &str) -> ObjectPath {
+        format!("/org/freedesktop/secrets/collection/test/{name}")
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn should_diff_snapshots() {
+        let older = CollectionSnapshot {
+            path: "/org/freedesktop/secrets/collection/test".parse().unwrap(),
+            taken: SystemTime::UNIX_EPOCH,
+            items: HashMap::from([
+                (path("kept"), item("kept", 1)),
+                (path("removed"), item("removed", 1)),
+                (path("relabeled"), item("old", 1)),
+                (path("rewritten"), item("rewritten", 1)),
+            ]),
+        };
+        let newer = CollectionSnapshot {
+            taken: SystemTime::now(),
+            items: HashMap::from([
+                (path("kept"), item("kept", 1)),
+                (path("added"), item("added", 2)),
+                (path("relabeled"), item("new", 1)),
+                (path("rewritten"), item("rewritten", 2)),
+            ]),
+            ..older.clone()
+        };
+
+        let diff = newer.diff(&older);
+        assert_eq!(diff.added, [path("added")]);
+        assert_eq!(diff.removed, [path("removed")]);
+        assert_eq!(diff.changed, [path("relabeled"), path("rewritten")]);
+        assert!(newer.diff(&newer).is_empty());
+    }
+
+    #[test_with::no_env(GITHUB_ACTIONS)]
+    #[tokio::test]
+    async fn should_snapshot_collection() {
+        let harness = TestHarness::start().unwrap();
+        let ss = harness.connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.create_collection("TestSnapshot", "").await.unwrap();
+        let kept = collection
+            .create_item("Kept", HashMap::new(), b"test", false, "text/plain")
+            .await
+            .unwrap();
+        let removed = collection
+            .create_item("Removed", HashMap::new(), b"test", false, "text/plain")
+            .await
+            .unwrap();
+        let before = collection.snapshot().await.unwrap();
+        assert_eq!(before.items().len(), 2);
+        assert_eq!(before.items()[&kept.path()].label, "Kept");
+
+        removed.delete().await.unwrap();
+        kept.set_label("Relabeled").await.unwrap();
+        let added = collection
+            .create_item("Added", HashMap::new(), b"test", false, "text/plain")
+            .await
+            .unwrap();
+        let diff = collection.snapshot().await.unwrap().diff(&before);
+        assert_eq!(diff.added, [added.path()]);
+        assert_eq!(diff.removed, [removed.path()]);
+        assert_eq!(diff.changed, [kept.path()]);
+
+        collection.delete().await.unwrap();
+    }
+}
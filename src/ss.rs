@@ -9,6 +9,11 @@
 
 // DBus Name
 pub const SS_DBUS_NAME: &str = "org.freedesktop.secrets";
+pub const SS_DBUS_PATH: &str = "/org/freedesktop/secrets";
+
+pub const SS_SERVICE_INTERFACE: &str = "org.freedesktop.Secret.Service";
+pub const SS_COLLECTION_INTERFACE: &str = "org.freedesktop.Secret.Collection";
+pub const SS_ITEM_INTERFACE: &str = "org.freedesktop.Secret.Item";
 
 // Item Properties
 pub const SS_ITEM_LABEL: &str = "org.freedesktop.Secret.Item.Label";
@@ -20,3 +25,7 @@ pub const ALGORITHM_DH: &str = "dh-ietf1024-sha256-aes128-cbc-pkcs7";
 
 // Collection properties
 pub const SS_COLLECTION_LABEL: &str = "org.freedesktop.Secret.Collection.Label";
+
+// Property names of providers predating the namespaced ones
+pub const SS_LEGACY_LABEL: &str = "Label";
+pub const SS_LEGACY_ATTRIBUTES: &str = "Attributes";
@@ -0,0 +1,422 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Helpers for integration tests against an isolated provider.
+//!
+//! Tests run against the session bus change the developer's real login
+//! keyring. With the `test-harness` feature, [TestHarness] instead starts a
+//! private `dbus-daemon` and a `gnome-keyring-daemon` on it, whose login
+//! keyring lives in a temporary directory and is unlocked with
+//! [TestHarness::PASSWORD]. Both programs must be installed.
+//!
+//! ```no_run
+//! # use secret_service::testing::TestHarness;
+//! # use secret_service::EncryptionType;
+//! # use std::collections::HashMap;
+//! # async fn call() {
+//! let harness = TestHarness::start().unwrap();
+//! let ss = harness.connect(EncryptionType::Dh).await.unwrap();
+//! let collection = ss.get_default_collection().await.unwrap();
+//! collection
+//!     .create_item("Test", HashMap::new(), b"test", false, "text/plain")
+//!     .await
+//!     .unwrap();
+//! // both daemons are stopped, and the keyring removed, with the harness
+//! drop(harness);
+//! # }
+//! ```
+//!
+//! Tests that have to run against another provider can still keep it clean
+//! with [ephemeral_collection], which hands out a collection that is deleted
+//! once the test is done, even if it panics.
+
+use crate::proxy::collection::CollectionProxyBlocking;
+use crate::proxy::item::ItemProxyBlocking;
+use crate::{blocking, Collection, EncryptionType, Error, SecretService};
+
+use rand::{rngs::OsRng, Rng};
+use std::io::{self, BufRead, BufReader, Write};
+use std::ops::Deref;
+use std::os::unix::fs::DirBuilderExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+use zbus::names::BusName;
+use zbus::zvariant::OwnedObjectPath;
+use zbus::CacheProperties;
+
+/// How long to wait for the keyring daemon to show up on the bus.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A private bus and keyring daemon, stopped when dropped.
+#[derive(Debug)]
+pub struct TestHarness {
+    dir: PathBuf,
+    address: String,
+    bus: Child,
+    keyring: Child,
+}
+
+impl TestHarness {
+    /// The password the login keyring is created and unlocked with.
+    pub const PASSWORD: &'static str = "test";
+
+    /// Starts the daemons, waiting until the keyring daemon is ready.
+    pub fn start() -> io::Result<Self> {
+        let id: u64 = OsRng.gen();
+        let dir = std::env::temp_dir().join(format!("secret-service-test-{id:016x}"));
+        // the keyring daemon refuses runtime directories others can access
+        std::fs::DirBuilder::new().mode(0o700).create(&dir)?;
+
+        let mut bus = match Self::start_bus(&dir) {
+            Ok(bus) => bus,
+            Err(err) => {
+                let _ = std::fs::remove_dir_all(&dir);
+                return Err(err);
+            }
+        };
+        let address = match read_address(&mut bus) {
+            Ok(address) => address,
+            Err(err) => {
+                stop(&mut bus);
+                let _ = std::fs::remove_dir_all(&dir);
+                return Err(err);
+            }
+        };
+        let keyring = match Self::start_keyring(&dir, &address) {
+            Ok(keyring) => keyring,
+            Err(err) => {
+                stop(&mut bus);
+                let _ = std::fs::remove_dir_all(&dir);
+                return Err(err);
+            }
+        };
+
+        // from here on, dropping the harness cleans up
+        let mut harness = TestHarness {
+            dir,
+            address,
+            bus,
+            keyring,
+        };
+        harness.wait_for_keyring()?;
+        Ok(harness)
+    }
+
+    fn start_bus(dir: &Path) -> io::Result<Child> {
+        Command::new("dbus-daemon")
+            .arg("--session")
+            .arg("--nofork")
+            .arg("--print-address=1")
+            .arg(format!("--address=unix:path={}", dir.join("bus").display()))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+    }
+
+    fn start_keyring(dir: &Path, address: &str) -> io::Result<Child> {
+        let mut keyring = Command::new("gnome-keyring-daemon")
+            .args(["--foreground", "--components=secrets", "--unlock"])
+            .env("DBUS_SESSION_BUS_ADDRESS", address)
+            .env("HOME", dir)
+            .env("XDG_DATA_HOME", dir.join("data"))
+            .env("XDG_CONFIG_HOME", dir.join("config"))
+            .env("XDG_RUNTIME_DIR", dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        // the password is read until the end of its input
+        let mut stdin = keyring.stdin.take().expect("stdin is piped");
+        if let Err(err) = stdin.write_all(Self::PASSWORD.as_bytes()) {
+            stop(&mut keyring);
+            return Err(err);
+        }
+        Ok(keyring)
+    }
+
+    fn wait_for_keyring(&mut self) -> io::Result<()> {
+        // blocking calls can't be made from within an async runtime, which
+        // the harness may be started from
+        std::thread::scope(|scope| {
+            scope
+                .spawn(|| self.poll_keyring())
+                .join()
+                .expect("polling the bus doesn't panic")
+        })
+    }
+
+    fn poll_keyring(&mut self) -> io::Result<()> {
+        let conn = zbus::blocking::connection::Builder::address(self.address.as_str())
+            .and_then(|builder| builder.build())
+            .map_err(io::Error::other)?;
+        let dbus = zbus::blocking::fdo::DBusProxy::new(&conn).map_err(io::Error::other)?;
+        let name = BusName::try_from(crate::ss::SS_DBUS_NAME).expect("valid bus name");
+
+        let started = Instant::now();
+        while !dbus
+            .name_has_owner(name.clone())
+            .map_err(io::Error::other)?
+        {
+            if let Some(status) = self.keyring.try_wait()? {
+                return Err(io::Error::other(format!(
+                    "gnome-keyring-daemon exited with {status}"
+                )));
+            }
+            if started.elapsed() > STARTUP_TIMEOUT {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "gnome-keyring-daemon didn't register on the bus",
+                ));
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        Ok(())
+    }
+
+    /// The address of the private bus.
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Connects to the keyring daemon, like [SecretService::connect].
+    pub async fn connect<'a>(
+        &self,
+        encryption: EncryptionType,
+    ) -> Result<SecretService<'a>, Error> {
+        let mut builder = SecretService::builder().encryption(encryption);
+        builder.conn = Some(self.bus_connection().await?);
+        builder.config.bus_address = Some(self.address.clone());
+        builder.connect().await
+    }
+
+    /// Connects to the keyring daemon, like [blocking::SecretService::connect].
+    pub fn connect_blocking<'a>(
+        &self,
+        encryption: EncryptionType,
+    ) -> Result<blocking::SecretService<'a>, Error> {
+        let mut builder = SecretService::builder().encryption(encryption);
+        builder.conn = Some(zbus::block_on(self.bus_connection())?);
+        builder.config.bus_address = Some(self.address.clone());
+        builder.connect_blocking()
+    }
+
+    async fn bus_connection(&self) -> Result<zbus::Connection, Error> {
+        Ok(zbus::connection::Builder::address(self.address.as_str())?
+            .build()
+            .await?)
+    }
+}
+
+impl Drop for TestHarness {
+    fn drop(&mut self) {
+        stop(&mut self.keyring);
+        stop(&mut self.bus);
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Reads the address the bus daemon prints once it listens.
+fn read_address(bus: &mut Child) -> io::Result<String> {
+    let stdout = bus.stdout.take().expect("stdout is piped");
+    let mut address = String::new();
+    BufReader::new(stdout).read_line(&mut address)?;
+    match address.trim() {
+        "" => Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "dbus-daemon exited without an address",
+        )),
+        address => Ok(address.to_owned()),
+    }
+}
+
+fn stop(child: &mut Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Creates a collection with a unique label, deleted along with its items
+/// when the returned guard is dropped.
+///
+/// ```no_run
+/// # use secret_service::testing::ephemeral_collection;
+/// # use secret_service::{EncryptionType, SecretService};
+/// # use std::collections::HashMap;
+/// # async fn call() {
+/// let ss = SecretService::connect(EncryptionType::Dh).await.unwrap();
+/// let collection = ephemeral_collection(&ss).await.unwrap();
+/// collection
+///     .create_item("Test", HashMap::new(), b"test", false, "text/plain")
+///     .await
+///     .unwrap();
+/// // the collection is gone once `collection` goes out of scope
+/// # }
+/// ```
+pub async fn ephemeral_collection<'a>(
+    ss: &'a SecretService<'_>,
+) -> Result<EphemeralCollection<'a>, Error> {
+    let id: u64 = OsRng.gen();
+    let collection = ss
+        .create_collection(&format!("secret-service-test-{id:016x}"), "")
+        .await?;
+    Ok(EphemeralCollection {
+        bus_address: ss.config.bus_address.clone(),
+        destination: ss.config.destination.clone(),
+        collection,
+    })
+}
+
+/// A collection created by [ephemeral_collection], deleted when dropped.
+///
+/// Dereferences to the [Collection].
+pub struct EphemeralCollection<'a> {
+    bus_address: Option<String>,
+    destination: String,
+    collection: Collection<'a>,
+}
+
+impl<'a> Deref for EphemeralCollection<'a> {
+    type Target = Collection<'a>;
+
+    fn deref(&self) -> &Collection<'a> {
+        &self.collection
+    }
+}
+
+impl Drop for EphemeralCollection<'_> {
+    fn drop(&mut self) {
+        // the collection's own connection may be driven by the thread being
+        // blocked here, so the cleanup uses a connection of its own
+        let path = self.collection.path.clone();
+        std::thread::scope(|scope| {
+            let _ = scope
+                .spawn(|| delete_collection(self.bus_address.as_deref(), &self.destination, path))
+                .join();
+        });
+    }
+}
+
+fn delete_collection(
+    bus_address: Option<&str>,
+    destination: &str,
+    path: OwnedObjectPath,
+) -> Result<(), Error> {
+    let conn = match bus_address {
+        Some(address) => zbus::blocking::connection::Builder::address(address)?.build()?,
+        None => zbus::blocking::Connection::session()?,
+    };
+    let collection = CollectionProxyBlocking::builder(&conn)
+        .destination(destination.to_owned())?
+        .path(path)?
+        .cache_properties(CacheProperties::No)
+        .build()?;
+
+    // items are removed one by one, in case the provider refuses to delete
+    // a collection that isn't empty; prompts are never answered
+    for item in collection.items()? {
+        let item = ItemProxyBlocking::builder(&conn)
+            .destination(destination.to_owned())?
+            .path(item)?
+            .cache_properties(CacheProperties::No)
+            .build()?;
+        let _ = item.delete();
+    }
+    collection.delete()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn should_isolate_keyring() {
+        let harness = TestHarness::start().unwrap();
+        let dir = harness.dir.clone();
+        assert!(harness.address().contains(dir.to_str().unwrap()));
+
+        let ss = harness.connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        collection
+            .create_item(
+                "Isolated",
+                HashMap::from([("test_harness", "isolated")]),
+                b"test",
+                false,
+                "text/plain",
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            ss.search_items(HashMap::from([("test_harness", "isolated")]))
+                .await
+                .unwrap()
+                .unlocked
+                .len(),
+            1
+        );
+
+        drop(ss);
+        drop(harness);
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn should_isolate_keyring_blocking() {
+        let harness = TestHarness::start().unwrap();
+        let ss = harness.connect_blocking(EncryptionType::Plain).unwrap();
+        assert!(ss.get_default_collection().is_ok());
+    }
+
+    #[test_with::no_env(GITHUB_ACTIONS)]
+    #[tokio::test]
+    async fn should_delete_ephemeral_collection() {
+        let harness = TestHarness::start().unwrap();
+        let ss = harness.connect(EncryptionType::Plain).await.unwrap();
+        let collection = ephemeral_collection(&ss).await.unwrap();
+        let path = collection.path.clone();
+        collection
+            .create_item(
+                "Ephemeral",
+                HashMap::from([("test_ephemeral", "ephemeral")]),
+                b"test",
+                false,
+                "text/plain",
+            )
+            .await
+            .unwrap();
+
+        drop(collection);
+        let remaining = ss.get_all_collections().await.unwrap();
+        assert!(remaining.iter().all(|c| c.path != path));
+        assert!(ss
+            .search_items(HashMap::from([("test_ephemeral", "ephemeral")]))
+            .await
+            .unwrap()
+            .unlocked
+            .is_empty());
+    }
+
+    #[test_with::no_env(GITHUB_ACTIONS)]
+    #[tokio::test]
+    async fn should_delete_ephemeral_collection_on_panic() {
+        let harness = TestHarness::start().unwrap();
+        let ss = harness.connect(EncryptionType::Plain).await.unwrap();
+        let count = ss.get_all_collections().await.unwrap().len();
+
+        let panicked = futures_util::FutureExt::catch_unwind(std::panic::AssertUnwindSafe(async {
+            let _collection = ephemeral_collection(&ss).await.unwrap();
+            panic!("test failure");
+        }))
+        .await;
+        assert!(panicked.is_err());
+        assert_eq!(ss.get_all_collections().await.unwrap().len(), count);
+    }
+}
@@ -0,0 +1,329 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+// Changes to several items applied in order, and undone if one fails.
+
+use crate::{Collection, Error, Item, ObjectPath};
+
+use std::collections::HashMap;
+
+/// Changes to several items, applied in order by [Transaction::execute].
+///
+/// The specification has no transactions, so if a change fails, the ones
+/// applied before it are undone by further changes: created items are
+/// deleted, replaced secrets are set back, and deleted items are created
+/// again from what was read before deleting them, under a new path. Undoing
+/// can fail as well, for instance if the provider went away, which the
+/// [TransactionReport] tells. Other applications may see the intermediate
+/// states.
+///
+/// ```no_run
+/// # async fn call(collection: &secret_service::Collection<'_>, old: &secret_service::Item<'_>) {
+/// use secret_service::Transaction;
+/// use std::collections::HashMap;
+///
+/// let mut transaction = Transaction::new();
+/// transaction
+///     .create_item(collection, "Token", HashMap::from([("service", "api")]), b"new", "text/plain")
+///     .delete(old);
+/// let report = transaction.execute().await;
+/// if let Some((index, err)) = &report.failed {
+///     eprintln!("change {index} failed: {err}");
+/// }
+/// # }
+/// ```
+#[derive(Default)]
+pub struct Transaction<'t> {
+    operations: Vec<Operation<'t>>,
+}
+
+enum Operation<'t> {
+    Create {
+        collection: &'t Collection<'t>,
+        label: String,
+        attributes: HashMap<String, String>,
+        secret: Vec<u8>,
+        content_type: String,
+    },
+    SetSecret {
+        item: &'t Item<'t>,
+        secret: Vec<u8>,
+        content_type: String,
+    },
+    Delete {
+        item: &'t Item<'t>,
+    },
+}
+
+/// How to undo an operation that was applied.
+enum Undo<'t> {
+    Delete(Item<'t>),
+    SetSecret {
+        item: &'t Item<'t>,
+        secret: Vec<u8>,
+        content_type: String,
+    },
+    Create {
+        collection: Box<Collection<'t>>,
+        label: String,
+        attributes: HashMap<String, String>,
+        secret: Vec<u8>,
+        content_type: String,
+    },
+}
+
+/// The outcome of [Transaction::execute], and of
+/// [blocking::Transaction::execute](crate::blocking::Transaction::execute).
+///
+/// Operations are numbered in the order they were queued, from 0.
+#[derive(Debug)]
+pub struct TransactionReport {
+    /// The paths of the items created, in the order queued, unless the
+    /// transaction was rolled back.
+    pub created: Vec<ObjectPath>,
+    /// The operation that failed with its error, if any.
+    pub failed: Option<(usize, Error)>,
+    /// The operations undone after the failure, latest first, with the
+    /// result of undoing them.
+    pub rolled_back: Vec<(usize, Result<(), Error>)>,
+}
+
+impl TransactionReport {
+    /// Returns whether every operation was applied.
+    pub fn is_committed(&self) -> bool {
+        self.failed.is_none()
+    }
+
+    /// Returns whether an operation failed, and every operation applied
+    /// before it was undone.
+    pub fn is_rolled_back(&self) -> bool {
+        self.failed.is_some() && self.rolled_back.iter().all(|(_, result)| result.is_ok())
+    }
+}
+
+impl<'t> Transaction<'t> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues creating an item, like [Collection::create_item] without
+    /// replacing.
+    pub fn create_item(
+        &mut self,
+        collection: &'t Collection<'t>,
+        label: &str,
+        attributes: HashMap<&str, &str>,
+        secret: &[u8],
+        content_type: &str,
+    ) -> &mut Self {
+        self.operations.push(Operation::Create {
+            collection,
+            label: label.to_owned(),
+            attributes: owned_attributes(attributes),
+            secret: secret.to_vec(),
+            content_type: content_type.to_owned(),
+        });
+        self
+    }
+
+    /// Queues setting the secret of an item, like [Item::set_secret].
+    pub fn set_secret(
+        &mut self,
+        item: &'t Item<'t>,
+        secret: &[u8],
+        content_type: &str,
+    ) -> &mut Self {
+        self.operations.push(Operation::SetSecret {
+            item,
+            secret: secret.to_vec(),
+            content_type: content_type.to_owned(),
+        });
+        self
+    }
+
+    /// Queues deleting an item, like [Item::delete].
+    pub fn delete(&mut self, item: &'t Item<'t>) -> &mut Self {
+        self.operations.push(Operation::Delete { item });
+        self
+    }
+
+    /// Applies the operations in order, undoing the applied ones if one
+    /// fails.
+    pub async fn execute(self) -> TransactionReport {
+        let mut applied = Vec::new();
+        let mut failed = None;
+        for (index, operation) in self.operations.into_iter().enumerate() {
+            match apply(operation).await {
+                Ok(undo) => applied.push((index, undo)),
+                Err(err) => {
+                    failed = Some((index, err));
+                    break;
+                }
+            }
+        }
+
+        let mut created = Vec::new();
+        let mut rolled_back = Vec::new();
+        if failed.is_none() {
+            for (_, undo) in &applied {
+                if let Undo::Delete(item) = undo {
+                    created.push(item.path());
+                }
+            }
+        } else {
+            for (index, undo) in applied.into_iter().rev() {
+                rolled_back.push((index, undo_operation(undo).await));
+            }
+        }
+
+        TransactionReport {
+            created,
+            failed,
+            rolled_back,
+        }
+    }
+}
+
+async fn apply(operation: Operation<'_>) -> Result<Undo<'_>, Error> {
+    Ok(match operation {
+        Operation::Create {
+            collection,
+            label,
+            attributes,
+            secret,
+            content_type,
+        } => {
+            let item = collection
+                .create_item(
+                    &label,
+                    borrowed_attributes(&attributes),
+                    &secret,
+                    false,
+                    &content_type,
+                )
+                .await?;
+            Undo::Delete(item)
+        }
+        Operation::SetSecret {
+            item,
+            secret,
+            content_type,
+        } => {
+            let (previous, previous_content_type) = item.read_secret("set_secret").await?;
+            item.set_secret(&secret, &content_type).await?;
+            Undo::SetSecret {
+                item,
+                secret: previous,
+                content_type: previous_content_type,
+            }
+        }
+        Operation::Delete { item } => {
+            let (secret, content_type) = item.read_secret("delete").await?;
+            let undo = Undo::Create {
+                collection: Box::new(item.collection().await?),
+                label: item.get_label().await?,
+                attributes: item.get_attributes().await?,
+                secret,
+                content_type,
+            };
+            item.delete().await?;
+            undo
+        }
+    })
+}
+
+async fn undo_operation(undo: Undo<'_>) -> Result<(), Error> {
+    match undo {
+        Undo::Delete(item) => item.delete().await,
+        Undo::SetSecret {
+            item,
+            secret,
+            content_type,
+        } => item.set_secret(&secret, &content_type).await,
+        Undo::Create {
+            collection,
+            label,
+            attributes,
+            secret,
+            content_type,
+        } => collection
+            .create_item(
+                &label,
+                borrowed_attributes(&attributes),
+                &secret,
+                false,
+                &content_type,
+            )
+            .await
+            .map(drop),
+    }
+}
+
+pub(crate) fn owned_attributes(attributes: HashMap<&str, &str>) -> HashMap<String, String> {
+    attributes
+        .into_iter()
+        .map(|(key, value)| (key.to_owned(), value.to_owned()))
+        .collect()
+}
+
+pub(crate) fn borrowed_attributes(attributes: &HashMap<String, String>) -> HashMap<&str, &str> {
+    attributes
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{EncryptionType, SecretService};
+
+    #[tokio::test]
+    async fn should_roll_back_failed_transaction() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let attributes = HashMap::from([("test_attribute_transaction", "test_value")]);
+        let kept = collection
+            .create_item("Test", attributes.clone(), b"test", false, "text/plain")
+            .await
+            .unwrap();
+        let deleted = collection
+            .create_item("Test", HashMap::new(), b"test", false, "text/plain")
+            .await
+            .unwrap();
+        deleted.delete().await.unwrap();
+
+        let mut transaction = Transaction::new();
+        transaction
+            .create_item(
+                &collection,
+                "TestCreated",
+                attributes.clone(),
+                b"created",
+                "text/plain",
+            )
+            .set_secret(&kept, b"other", "text/plain")
+            .delete(&deleted);
+        let report = transaction.execute().await;
+
+        assert!(matches!(report.failed, Some((2, _))));
+        assert!(report.is_rolled_back());
+        assert_eq!(
+            report
+                .rolled_back
+                .iter()
+                .map(|(index, _)| *index)
+                .collect::<Vec<_>>(),
+            [1, 0]
+        );
+        assert!(report.created.is_empty());
+        assert_eq!(kept.get_secret().await.unwrap(), b"test");
+        let found = collection.search_items(attributes).await.unwrap();
+        assert_eq!(found.len(), 1);
+        kept.delete().await.unwrap();
+    }
+}
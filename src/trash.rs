@@ -0,0 +1,64 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+// The trash collection items are moved to by Item::trash. The specification
+// has no trash, so this is a convention of this crate.
+
+use crate::builder::Config;
+use crate::proxy::service::{ServiceProxy, ServiceProxyBlocking};
+use crate::util;
+use crate::Error;
+
+use zbus::zvariant::OwnedObjectPath;
+
+/// Alias of the trash collection.
+pub(crate) const TRASH_ALIAS: &str = "trash";
+
+/// Label the trash collection is created with.
+const TRASH_LABEL: &str = "Trash";
+
+/// Attribute of trashed items, holding the path of the collection they were
+/// trashed from.
+pub(crate) const TRASHED_FROM_ATTRIBUTE: &str = "trashed_from";
+
+/// Returns the path of the trash collection, creating it if there is none.
+pub(crate) async fn trash_collection(
+    conn: &zbus::Connection,
+    service_proxy: &ServiceProxy<'_>,
+    config: &Config,
+) -> Result<OwnedObjectPath, Error> {
+    match util::resolve_alias(conn, service_proxy, config, TRASH_ALIAS).await {
+        Err(Error::NoResult) => {
+            util::create_collection(
+                conn.clone(),
+                service_proxy,
+                config,
+                TRASH_LABEL,
+                TRASH_ALIAS,
+            )
+            .await
+        }
+        path => path,
+    }
+}
+
+pub(crate) fn trash_collection_blocking(
+    conn: &zbus::blocking::Connection,
+    service_proxy: &ServiceProxyBlocking,
+    config: &Config,
+) -> Result<OwnedObjectPath, Error> {
+    match util::resolve_alias_blocking(conn, service_proxy, config, TRASH_ALIAS) {
+        Err(Error::NoResult) => util::create_collection_blocking(
+            conn.clone(),
+            service_proxy,
+            config,
+            TRASH_LABEL,
+            TRASH_ALIAS,
+        ),
+        path => path,
+    }
+}
@@ -0,0 +1,158 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Items that track their lock state in their type.
+//!
+//! [Item] only offers reading and writing the secret in the [Unlocked]
+//! state, which is reached by checking or unlocking the item, so that a
+//! forgotten unlock is caught at compile time rather than by an
+//! [Error::Locked] at runtime.
+//!
+//! ```no_run
+//! # async fn call(item: secret_service::Item<'_>) -> Result<(), secret_service::Error> {
+//! use secret_service::typestate::{Item, State};
+//!
+//! let item = match Item::check(item).await? {
+//!     State::Unlocked(item) => item,
+//!     State::Locked(item) => item.unlock().await?,
+//! };
+//! let secret = item.get_secret().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Reading the secret of an item not known to be unlocked doesn't compile:
+//!
+//! ```compile_fail
+//! # use secret_service::typestate::{Item, Locked};
+//! # async fn call(item: Item<'_, Locked>) {
+//! let secret = item.get_secret().await;
+//! # }
+//! ```
+//!
+//! The state is the one last seen by this process. Another application
+//! can still lock the item in between, in which case calls fail with
+//! [Error::Locked] as they do on [crate::Item].
+
+use crate::{Error, ObjectPath};
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// The state of an item that was locked when last checked.
+#[derive(Debug)]
+pub enum Locked {}
+
+/// The state of an item that was unlocked when last checked.
+#[derive(Debug)]
+pub enum Unlocked {}
+
+/// An item whose lock state `S` is tracked in its type.
+pub struct Item<'a, S> {
+    item: crate::Item<'a>,
+    state: PhantomData<S>,
+}
+
+/// An item in either state, as returned by [Item::check].
+pub enum State<'a> {
+    Locked(Item<'a, Locked>),
+    Unlocked(Item<'a, Unlocked>),
+}
+
+impl<'a> Item<'a, Locked> {
+    /// Checks whether `item` is locked, returning it in the matching state.
+    pub async fn check(item: crate::Item<'a>) -> Result<State<'a>, Error> {
+        Ok(if item.is_locked().await? {
+            State::Locked(Item::new(item))
+        } else {
+            State::Unlocked(Item::new(item))
+        })
+    }
+
+    /// Unlocks the item, prompting the user if the provider asks to.
+    ///
+    /// Fails with [Error::Locked] if the item is still locked afterwards,
+    /// as providers may unlock something else than asked for.
+    pub async fn unlock(self) -> Result<Item<'a, Unlocked>, Error> {
+        self.item.unlock().await?;
+        self.item.ensure_unlocked().await?;
+        Ok(Item::new(self.item))
+    }
+}
+
+impl<'a> Item<'a, Unlocked> {
+    pub async fn get_secret(&self) -> Result<Vec<u8>, Error> {
+        self.item.get_secret().await
+    }
+
+    pub async fn get_secret_string(&self) -> Result<String, Error> {
+        self.item.get_secret_string().await
+    }
+
+    pub async fn set_secret(&self, secret: &[u8], content_type: &str) -> Result<(), Error> {
+        self.item.set_secret(secret, content_type).await
+    }
+
+    /// Locks the item.
+    ///
+    /// Providers may lock the item's collection rather than the item, or
+    /// refuse to, so the item is checked again afterwards.
+    pub async fn lock(self) -> Result<State<'a>, Error> {
+        self.item.lock().await?;
+        Item::check(self.item).await
+    }
+}
+
+impl<'a, S> Item<'a, S> {
+    fn new(item: crate::Item<'a>) -> Self {
+        Item {
+            item,
+            state: PhantomData,
+        }
+    }
+
+    /// The object path of the item.
+    pub fn path(&self) -> ObjectPath {
+        self.item.path()
+    }
+
+    pub async fn get_label(&self) -> Result<String, Error> {
+        self.item.get_label().await
+    }
+
+    pub async fn get_attributes(&self) -> Result<HashMap<String, String>, Error> {
+        self.item.get_attributes().await
+    }
+
+    /// Returns the item without its state, for the calls not offered here.
+    pub fn into_inner(self) -> crate::Item<'a> {
+        self.item
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{EncryptionType, SecretService};
+
+    #[tokio::test]
+    async fn should_track_lock_state() {
+        let ss = SecretService::connect(EncryptionType::Plain).await.unwrap();
+        let collection = ss.get_default_collection().await.unwrap();
+        let item = collection
+            .create_item("Test", HashMap::new(), b"test", false, "text/plain")
+            .await
+            .unwrap();
+
+        let item = match Item::check(item).await.unwrap() {
+            State::Unlocked(item) => item,
+            State::Locked(item) => item.unlock().await.unwrap(),
+        };
+        assert_eq!(item.get_secret().await.unwrap(), b"test");
+        item.into_inner().delete().await.unwrap();
+    }
+}
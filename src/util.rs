@@ -10,18 +10,29 @@
 //!   exec_prompt
 //!   formatting secrets
 
+use crate::attributes::list_contains;
+use crate::builder::Config;
+use crate::compression;
 use crate::error::Error;
+use crate::journal::Change;
+use crate::prompt::Prompt;
 use crate::proxy::prompt::{Completed, PromptProxy, PromptProxyBlocking};
 use crate::proxy::service::{ServiceProxy, ServiceProxyBlocking};
 use crate::proxy::SecretStruct;
-use crate::session::encrypt;
-use crate::session::Session;
-use crate::ss::SS_DBUS_NAME;
+use crate::session::{Session, AES_BLOCK_LEN};
+use crate::ss::{
+    SS_COLLECTION_LABEL, SS_ITEM_ATTRIBUTES, SS_ITEM_LABEL, SS_LEGACY_ATTRIBUTES, SS_LEGACY_LABEL,
+};
+use crate::{
+    blocking, AttributeSummary, BatchOutcome, Item, ItemTimestamp, LabelMatch, PrefetchedItem,
+};
 
-use rand::{rngs::OsRng, Rng};
-use zbus::export::ordered_stream::OrderedStreamExt;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
 use zbus::{
-    zvariant::{self, ObjectPath},
+    zvariant::{self, Dict, ObjectPath, OwnedObjectPath, Value},
     CacheProperties,
 };
 
@@ -31,119 +42,820 @@ pub(crate) enum LockAction {
     Unlock,
 }
 
+/// Locks or unlocks `objects`, returning the paths of the objects the
+/// provider reports as affected: those handled right away and those the user
+/// approved in a prompt.
 pub(crate) async fn lock_or_unlock(
     conn: zbus::Connection,
     service_proxy: &ServiceProxy<'_>,
-    object_path: &ObjectPath<'_>,
+    config: &Config,
+    objects: Vec<&ObjectPath<'_>>,
     lock_action: LockAction,
-) -> Result<(), Error> {
-    let objects = vec![object_path];
-
+) -> Result<Vec<OwnedObjectPath>, Error> {
     let lock_action_res = match lock_action {
         LockAction::Lock => service_proxy.lock(objects).await?,
         LockAction::Unlock => service_proxy.unlock(objects).await?,
     };
 
-    if lock_action_res.object_paths.is_empty() {
-        exec_prompt(conn, &lock_action_res.prompt).await?;
+    let mut affected = lock_action_res.object_paths;
+    if lock_action_res.prompt.as_str() != "/" {
+        let result = exec_prompt(conn, config, &lock_action_res.prompt).await?;
+        affected.extend(prompt_paths(config, result)?);
     }
-    Ok(())
+    Ok(affected)
 }
 
 pub(crate) fn lock_or_unlock_blocking(
     conn: zbus::blocking::Connection,
     service_proxy: &ServiceProxyBlocking,
-    object_path: &ObjectPath,
+    config: &Config,
+    objects: Vec<&ObjectPath<'_>>,
     lock_action: LockAction,
-) -> Result<(), Error> {
-    let objects = vec![object_path];
-
+) -> Result<Vec<OwnedObjectPath>, Error> {
     let lock_action_res = match lock_action {
         LockAction::Lock => service_proxy.lock(objects)?,
         LockAction::Unlock => service_proxy.unlock(objects)?,
     };
 
-    if lock_action_res.object_paths.is_empty() {
-        exec_prompt_blocking(conn, &lock_action_res.prompt)?;
+    let mut affected = lock_action_res.object_paths;
+    if lock_action_res.prompt.as_str() != "/" {
+        let result = exec_prompt_blocking(conn, config, &lock_action_res.prompt)?;
+        affected.extend(prompt_paths(config, result)?);
+    }
+    Ok(affected)
+}
+
+/// Parses the result of a lock or unlock prompt, the paths of the objects
+/// the user approved.
+pub(crate) fn prompt_paths(
+    config: &Config,
+    result: zvariant::OwnedValue,
+) -> Result<Vec<OwnedObjectPath>, Error> {
+    check_prompt_result(config, &result, "ao")?;
+    Ok(Vec::<OwnedObjectPath>::try_from(result)?)
+}
+
+/// Parses the result of a prompt creating a collection or item, its path.
+pub(crate) fn prompt_path(
+    config: &Config,
+    result: zvariant::OwnedValue,
+) -> Result<OwnedObjectPath, Error> {
+    check_prompt_result(config, &result, "o")?;
+    Ok(result.try_into()?)
+}
+
+/// Checks the type of a prompt's result in strict mode, which the
+/// specification leaves to the method prompting.
+fn check_prompt_result(
+    config: &Config,
+    result: &zvariant::OwnedValue,
+    expected: &str,
+) -> Result<(), Error> {
+    let signature = result.value_signature();
+    if config.strict && signature.as_str() != expected {
+        return Err(Error::InvalidResponse(format!(
+            "prompt result of type {signature}, expected {expected}"
+        )));
     }
     Ok(())
 }
 
+/// Builds the properties of a `CreateCollection` call, under the legacy
+/// property names if `legacy` is set.
+pub(crate) fn collection_properties(label: &str, legacy: bool) -> HashMap<&'static str, Value<'_>> {
+    let name = if legacy {
+        SS_LEGACY_LABEL
+    } else {
+        SS_COLLECTION_LABEL
+    };
+    HashMap::from([(name, label.into())])
+}
+
+/// Builds the properties of a `CreateItem` call, under the legacy property
+/// names if `legacy` is set.
+pub(crate) fn item_properties<'a>(
+    label: &'a str,
+    attributes: HashMap<&'a str, &'a str>,
+    legacy: bool,
+) -> HashMap<&'static str, Value<'a>> {
+    let (label_name, attributes_name) = if legacy {
+        (SS_LEGACY_LABEL, SS_LEGACY_ATTRIBUTES)
+    } else {
+        (SS_ITEM_LABEL, SS_ITEM_ATTRIBUTES)
+    };
+    let attributes: Dict = attributes.into();
+    HashMap::from([
+        (label_name, label.into()),
+        (attributes_name, attributes.into()),
+    ])
+}
+
 pub(crate) fn format_secret(
     session: &Session,
+    config: &Config,
     secret: &[u8],
     content_type: &str,
 ) -> Result<SecretStruct, Error> {
-    let content_type = content_type.to_owned();
+    let (secret, content_type) = config.compress_secret(secret, content_type);
+    format_session_secret(session, &config.seal_secret(&secret), &content_type)
+}
 
-    if let Some(session_key) = session.get_aes_key() {
-        let mut rng = OsRng {};
-        let mut aes_iv = [0; 16];
-        rng.fill(&mut aes_iv);
+/// Formats `secret` for the session, without the client-side encryption
+/// configured on the service.
+pub(crate) fn format_session_secret(
+    session: &Session,
+    secret: &[u8],
+    content_type: &str,
+) -> Result<SecretStruct, Error> {
+    validate_content_type(content_type)?;
+    let (parameters, value) = session.encrypt(secret)?;
 
-        let encrypted_secret = encrypt(secret, session_key, &aes_iv);
+    Ok(SecretStruct {
+        session: session.object_path().clone(),
+        parameters,
+        value,
+        content_type: content_type.to_owned(),
+    })
+}
+
+pub(crate) fn decrypt_secret(
+    session: &Session,
+    config: &Config,
+    secret_struct: SecretStruct,
+) -> Result<Vec<u8>, Error> {
+    let content_type = secret_struct.content_type.clone();
+    let secret = config.open_secret(decrypt_session_secret(session, config, secret_struct)?)?;
+    compression::decompress(config.compression.as_ref(), secret, &content_type)
+}
+
+/// Decrypts a secret of the session, leaving the client-side encryption
+/// configured on the service in place.
+pub(crate) fn decrypt_session_secret(
+    session: &Session,
+    config: &Config,
+    secret_struct: SecretStruct,
+) -> Result<Vec<u8>, Error> {
+    if config.strict {
+        validate_secret(session, &secret_struct)?;
+    }
+    session.decrypt(&secret_struct.parameters, secret_struct.value)
+}
 
-        // Construct secret struct
-        let parameters = aes_iv.to_vec();
-        let value = encrypted_secret;
+/// Checks a secret returned by the provider against the specification: it
+/// belongs to the session, its IV fits the algorithm and its content type
+/// is a valid MIME type.
+fn validate_secret(session: &Session, secret_struct: &SecretStruct) -> Result<(), Error> {
+    if secret_struct.session != *session.object_path() {
+        return Err(Error::InvalidResponse(format!(
+            "secret for session {}",
+            secret_struct.session.as_str()
+        )));
+    }
+    if let Some(len) = session.parameters_len() {
+        if secret_struct.parameters.len() != len {
+            return Err(Error::InvalidResponse(format!(
+                "secret parameters of {} bytes, expected {len}",
+                secret_struct.parameters.len()
+            )));
+        }
+        // encrypted secrets are padded to whole blocks
+        if len == AES_BLOCK_LEN
+            && (secret_struct.value.is_empty() || secret_struct.value.len() % AES_BLOCK_LEN != 0)
+        {
+            return Err(Error::InvalidResponse(format!(
+                "encrypted secret of {} bytes",
+                secret_struct.value.len()
+            )));
+        }
+    }
+    if secret_struct.content_type.is_empty() {
+        return Err(Error::InvalidResponse("empty content type".to_owned()));
+    }
+    validate_content_type(&secret_struct.content_type).map_err(|_| {
+        Error::InvalidResponse(format!("content type {:?}", secret_struct.content_type))
+    })
+}
 
-        Ok(SecretStruct {
-            session: session.object_path.clone(),
-            parameters,
-            value,
-            content_type,
+pub(crate) fn decrypt_secrets(
+    session: &Session,
+    config: &Config,
+    secrets: HashMap<OwnedObjectPath, SecretStruct>,
+) -> Result<HashMap<OwnedObjectPath, Vec<u8>>, Error> {
+    secrets
+        .into_iter()
+        .map(|(path, secret_struct)| Ok((path, decrypt_secret(session, config, secret_struct)?)))
+        .collect()
+}
+
+/// Pairs each of `items` with its secret among those `GetSecrets` returned,
+/// decrypted. Items left out of the reply fail with [Error::Locked], as the
+/// specification has providers leave out locked items.
+pub(crate) fn secrets_outcome(
+    session: &Session,
+    config: &Config,
+    items: impl IntoIterator<Item = OwnedObjectPath>,
+    secrets: HashMap<OwnedObjectPath, SecretStruct>,
+) -> BatchOutcome<Vec<u8>> {
+    let results = items
+        .into_iter()
+        .map(|path| {
+            // the same item may be asked for more than once
+            let secret = match secrets.get(&path).cloned() {
+                Some(secret_struct) => decrypt_secret(session, config, secret_struct)
+                    .map_err(|err| err.with_context("get_secrets", &path)),
+                None => Err(Error::Locked),
+            };
+            (path.into(), secret)
         })
+        .collect();
+    BatchOutcome { results }
+}
+
+/// Characters RFC 2045 excludes from tokens, besides spaces and controls.
+const TSPECIALS: &str = "()<>@,;:\\\"/[]?=";
+
+fn is_token(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_graphic() && !TSPECIALS.contains(c))
+}
+
+fn trim_lws(s: &str) -> &str {
+    s.trim_matches(|c| c == ' ' || c == '\t')
+}
+
+/// Returns the length of the quoted string `s` starts with, including the
+/// quotes.
+fn quoted_string_len(s: &str) -> Option<usize> {
+    let mut chars = s.char_indices();
+    if chars.next()?.1 != '"' {
+        return None;
+    }
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some(i + 1),
+            '\\' if chars.next()?.1.is_ascii() => {}
+            c if c.is_ascii() && c != '\r' && c != '\\' => {}
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// Checks that `content_type` is a valid media type as defined by RFC 2045:
+/// `type "/" subtype *(";" attribute "=" value)`, where values are tokens or
+/// quoted strings.
+pub(crate) fn validate_content_type(content_type: &str) -> Result<(), Error> {
+    let (media_type, mut params) = match content_type.split_once(';') {
+        Some((media_type, params)) => (media_type, Some(params)),
+        None => (content_type, None),
+    };
+
+    let (type_, subtype) = trim_lws(media_type)
+        .split_once('/')
+        .ok_or(Error::InvalidContentType)?;
+    if !is_token(type_) || !is_token(subtype) {
+        return Err(Error::InvalidContentType);
+    }
+
+    while let Some(param) = params {
+        let (attribute, value) = param.split_once('=').ok_or(Error::InvalidContentType)?;
+        if !is_token(trim_lws(attribute)) {
+            return Err(Error::InvalidContentType);
+        }
+
+        let value = trim_lws(value);
+        let (value, rest) = match quoted_string_len(value) {
+            Some(len) => value.split_at(len),
+            None if value.starts_with('"') => return Err(Error::InvalidContentType),
+            None => value.split_at(value.find(';').unwrap_or(value.len())),
+        };
+        if !value.starts_with('"') && !is_token(trim_lws(value)) {
+            return Err(Error::InvalidContentType);
+        }
+
+        params = match trim_lws(rest) {
+            "" => None,
+            rest => Some(rest.strip_prefix(';').ok_or(Error::InvalidContentType)?),
+        };
+    }
+    Ok(())
+}
+
+// Content type used when storing string secrets.
+pub(crate) const TEXT_CONTENT_TYPE: &str = "text/plain";
+
+/// Converts a decrypted secret to a string, rejecting content types that
+/// declare a charset other than UTF-8 (or its ASCII subset).
+pub(crate) fn secret_to_string(secret: Vec<u8>, content_type: &str) -> Result<String, Error> {
+    let charset = content_type.split(';').skip(1).find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches('"'))
+    });
+
+    match charset {
+        Some(charset)
+            if !["utf-8", "utf8", "us-ascii", "ascii"]
+                .iter()
+                .any(|c| charset.eq_ignore_ascii_case(c)) =>
+        {
+            Err(Error::InvalidUtf8)
+        }
+        _ => String::from_utf8(secret).map_err(|_| Error::InvalidUtf8),
+    }
+}
+
+/// Scores how well `label` matches `query`, ignoring case. Higher is better:
+/// exact matches beat prefixes, which beat substrings, which beat fuzzy
+/// matches where the characters of `query` appear in order.
+fn label_match_score(label: &str, query: &str) -> Option<u32> {
+    let label = label.to_lowercase();
+    let query = query.to_lowercase();
+
+    if label == query {
+        Some(4000)
+    } else if label.starts_with(&query) {
+        Some(3000)
+    } else if label.contains(&query) {
+        Some(2000)
     } else {
-        // just Plain for now
-        let parameters = Vec::new();
-        let value = secret.to_vec();
-
-        Ok(SecretStruct {
-            session: session.object_path.clone(),
-            parameters,
-            value,
-            content_type,
+        // Subsequence match, penalized by the number of skipped characters.
+        let mut label_chars = label.chars();
+        let mut skipped = 0;
+        for q in query.chars() {
+            loop {
+                let c = label_chars.next()?;
+                if c == q {
+                    break;
+                }
+                skipped += 1;
+            }
+        }
+        Some(1000 - skipped.min(999))
+    }
+}
+
+/// Ranks `(value, label)` pairs against `query`, best match first, dropping
+/// those that don't match at all.
+pub(crate) fn rank_label_matches<T>(
+    entries: impl IntoIterator<Item = (T, String)>,
+    query: &str,
+) -> Vec<LabelMatch<T>> {
+    let mut matches: Vec<_> = entries
+        .into_iter()
+        .filter_map(|(item, label)| {
+            let score = label_match_score(&label, query)?;
+            Some(LabelMatch { item, label, score })
         })
+        .collect();
+
+    matches.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| a.label.len().cmp(&b.label.len()))
+            .then_with(|| a.label.cmp(&b.label))
+    });
+    matches
+}
+
+/// Groups values sharing the same key, keeping only groups with more than one
+/// member. Groups are returned in order of their first member.
+pub(crate) fn group_duplicates<T, K: Eq + Hash>(
+    entries: impl IntoIterator<Item = (T, K)>,
+) -> Vec<Vec<T>> {
+    let mut index: HashMap<K, usize> = HashMap::new();
+    let mut groups: Vec<Vec<T>> = Vec::new();
+
+    for (value, key) in entries {
+        match index.get(&key) {
+            Some(&i) => groups[i].push(value),
+            None => {
+                index.insert(key, groups.len());
+                groups.push(vec![value]);
+            }
+        }
+    }
+
+    groups.retain(|group| group.len() > 1);
+    groups
+}
+
+/// Counts the attribute keys, and values if `per_value` is set, of items
+/// with the given `attributes`.
+pub(crate) fn summarize_attributes(
+    attributes: impl IntoIterator<Item = HashMap<String, String>>,
+    per_value: bool,
+) -> AttributeSummary {
+    let mut summary = AttributeSummary::default();
+    for attributes in attributes {
+        summary.items += 1;
+        for (key, value) in attributes {
+            if per_value {
+                *summary
+                    .values
+                    .entry(key.clone())
+                    .or_default()
+                    .entry(value)
+                    .or_default() += 1;
+            }
+            *summary.keys.entry(key).or_default() += 1;
+        }
     }
+    summary
+}
+
+/// What to sort items by.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum SortOrder {
+    /// Most recent timestamp first.
+    Newest(ItemTimestamp),
+    /// Alphabetically by label, ignoring case.
+    Label,
 }
 
-// TODO: Users could pass their own window ID in.
-const NO_WINDOW_ID: &str = "";
+/// The value an item is sorted by, fetched according to a [SortOrder].
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum SortKey {
+    Newest(Reverse<u64>),
+    /// The lowercased label, then the label itself to order labels that
+    /// only differ in case.
+    Label(String, String),
+}
+
+impl SortKey {
+    pub(crate) fn label(label: String) -> Self {
+        SortKey::Label(label.to_lowercase(), label)
+    }
+}
+
+/// Reorders `values` by their `keys`, keeping the order of equal keys.
+pub(crate) fn sort_by_keys<T>(values: &mut Vec<T>, keys: Vec<SortKey>) {
+    let mut keyed: Vec<_> = keys.into_iter().zip(values.drain(..)).collect();
+    keyed.sort_by(|(a, _), (b, _)| a.cmp(b));
+    values.extend(keyed.into_iter().map(|(_, value)| value));
+}
+
+/// Runs `call` once the concurrent call limit of `config`, if any, allows.
+pub(crate) async fn limit_concurrency<F: Future>(config: &Config, call: F) -> F::Output {
+    let _permit = match &config.call_limit {
+        Some(limit) => Some(limit.acquire().await),
+        None => None,
+    };
+    call.await
+}
+
+/// Runs `fut` while ticking `executor`, for calls made on a connection
+/// whose executor the host app can't tick yet.
+pub(crate) async fn drive_executor<F: Future>(executor: &zbus::Executor<'_>, fut: F) -> F::Output {
+    let ticking = async {
+        loop {
+            executor.tick().await;
+        }
+    };
+    futures_util::pin_mut!(fut, ticking);
+    match futures_util::future::select(fut, ticking).await {
+        futures_util::future::Either::Left((output, _)) => output,
+        futures_util::future::Either::Right(_) => unreachable!("ticking never completes"),
+    }
+}
+
+/// Fetches the label and attributes of each item concurrently.
+pub(crate) async fn prefetch_items<'a>(
+    config: &Config,
+    items: Vec<Item<'a>>,
+) -> Result<Vec<PrefetchedItem<Item<'a>>>, Error> {
+    let details = futures_util::future::join_all(items.iter().map(|item| {
+        limit_concurrency(
+            config,
+            futures_util::future::try_join(item.get_label(), item.get_attributes()),
+        )
+    }))
+    .await;
+
+    items
+        .into_iter()
+        .zip(details)
+        .map(|(item, details)| {
+            let (label, attributes) = details?;
+            Ok(PrefetchedItem {
+                item,
+                label,
+                attributes,
+            })
+        })
+        .collect()
+}
+
+/// Blocking variant of [prefetch_items].
+pub(crate) fn prefetch_items_blocking(
+    items: Vec<blocking::Item<'_>>,
+) -> Result<Vec<PrefetchedItem<blocking::Item<'_>>>, Error> {
+    items
+        .into_iter()
+        .map(|item| {
+            Ok(PrefetchedItem {
+                label: item.get_label()?,
+                attributes: item.get_attributes()?,
+                item,
+            })
+        })
+        .collect()
+}
+
+/// Keeps the items whose attribute `key` holds an encoded list containing
+/// `tag`, fetching the attributes concurrently.
+pub(crate) async fn retain_tagged<'a>(
+    config: &Config,
+    items: Vec<Item<'a>>,
+    key: &str,
+    tag: &str,
+) -> Result<Vec<Item<'a>>, Error> {
+    let attributes = futures_util::future::join_all(
+        items
+            .iter()
+            .map(|item| limit_concurrency(config, item.get_attributes())),
+    )
+    .await
+    .into_iter()
+    .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(items
+        .into_iter()
+        .zip(attributes)
+        .filter(|(_, attributes)| is_tagged(attributes, key, tag))
+        .map(|(item, _)| item)
+        .collect())
+}
+
+/// Blocking variant of [retain_tagged].
+pub(crate) fn retain_tagged_blocking<'a>(
+    items: Vec<blocking::Item<'a>>,
+    key: &str,
+    tag: &str,
+) -> Result<Vec<blocking::Item<'a>>, Error> {
+    let mut tagged = Vec::new();
+    for item in items {
+        if is_tagged(&item.get_attributes()?, key, tag) {
+            tagged.push(item);
+        }
+    }
+    Ok(tagged)
+}
+
+fn is_tagged(attributes: &HashMap<String, String>, key: &str, tag: &str) -> bool {
+    attributes
+        .get(key)
+        .is_some_and(|value| list_contains(value, tag))
+}
+
+/// Returns the path of the collection holding the item at `item_path`.
+///
+/// Items live below their collection, as laid out by the specification.
+pub(crate) fn collection_of(item_path: &str) -> &str {
+    item_path
+        .rsplit_once('/')
+        .map_or(item_path, |(collection, _)| collection)
+}
+
+/// Groups items by the path of the collection holding them, keeping their
+/// order within each group. The groups are sorted by path.
+pub(crate) fn group_by_collection<T>(
+    items: impl IntoIterator<Item = T>,
+    path: impl Fn(&T) -> &str,
+) -> Result<Vec<(OwnedObjectPath, Vec<T>)>, Error> {
+    let mut groups: HashMap<OwnedObjectPath, Vec<T>> = HashMap::new();
+    for item in items {
+        let collection = OwnedObjectPath::try_from(collection_of(path(&item)))?;
+        groups.entry(collection).or_default().push(item);
+    }
+    let mut groups: Vec<_> = groups.into_iter().collect();
+    groups.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+    Ok(groups)
+}
+
+/// Resolves `alias` to the path of a collection, caching the resolution in
+/// the config's alias cache.
+///
+/// Returns [Error::NoResult] if no collection has the alias.
+pub(crate) async fn resolve_alias(
+    conn: &zbus::Connection,
+    service_proxy: &ServiceProxy<'_>,
+    config: &Config,
+    alias: &str,
+) -> Result<OwnedObjectPath, Error> {
+    let object_path = match config.alias_cache.get(alias) {
+        Some(object_path) => object_path,
+        None => {
+            config.alias_cache.watch(conn, config).await?;
+            let generation = config.alias_cache.generation();
+            let object_path = service_proxy.read_alias(alias).await?;
+            if object_path.as_str() != "/" {
+                config
+                    .alias_cache
+                    .insert(alias, object_path.clone(), generation);
+            }
+            object_path
+        }
+    };
+
+    if object_path.as_str() == "/" {
+        Err(Error::NoResult)
+    } else {
+        Ok(object_path)
+    }
+}
+
+pub(crate) fn resolve_alias_blocking(
+    conn: &zbus::blocking::Connection,
+    service_proxy: &ServiceProxyBlocking,
+    config: &Config,
+    alias: &str,
+) -> Result<OwnedObjectPath, Error> {
+    let object_path = match config.alias_cache.get(alias) {
+        Some(object_path) => object_path,
+        None => {
+            config.alias_cache.watch_blocking(conn, config)?;
+            let generation = config.alias_cache.generation();
+            let object_path = service_proxy.read_alias(alias)?;
+            if object_path.as_str() != "/" {
+                config
+                    .alias_cache
+                    .insert(alias, object_path.clone(), generation);
+            }
+            object_path
+        }
+    };
+
+    if object_path.as_str() == "/" {
+        Err(Error::NoResult)
+    } else {
+        Ok(object_path)
+    }
+}
+
+/// Creates a collection, returning its path once the provider, or the user
+/// in a prompt, has accepted it.
+pub(crate) async fn create_collection(
+    conn: zbus::Connection,
+    service_proxy: &ServiceProxy<'_>,
+    config: &Config,
+    label: &str,
+    alias: &str,
+) -> Result<OwnedObjectPath, Error> {
+    let mut created_collection = service_proxy
+        .create_collection(collection_properties(label, false), alias)
+        .await
+        .map_err(Error::from);
+    if config.legacy_properties
+        && created_collection
+            .as_ref()
+            .is_err_and(Error::is_invalid_args)
+    {
+        created_collection = service_proxy
+            .create_collection(collection_properties(label, true), alias)
+            .await
+            .map_err(Error::from);
+    }
+    let created_collection = created_collection?;
+
+    // Check if the path is "/", if so should execute a prompt
+    let collection_path: OwnedObjectPath = if created_collection.collection.as_str() == "/" {
+        let prompt_res = exec_prompt(conn, config, &created_collection.prompt).await?;
+        prompt_path(config, prompt_res)?
+    } else {
+        created_collection.collection
+    };
+    config.alias_cache.clear();
+
+    config.record(collection_path.clone().into(), || {
+        Change::CollectionCreated {
+            label: label.to_owned(),
+        }
+    });
+    Ok(collection_path)
+}
+
+pub(crate) fn create_collection_blocking(
+    conn: zbus::blocking::Connection,
+    service_proxy: &ServiceProxyBlocking,
+    config: &Config,
+    label: &str,
+    alias: &str,
+) -> Result<OwnedObjectPath, Error> {
+    let mut created_collection = service_proxy
+        .create_collection(collection_properties(label, false), alias)
+        .map_err(Error::from);
+    if config.legacy_properties
+        && created_collection
+            .as_ref()
+            .is_err_and(Error::is_invalid_args)
+    {
+        created_collection = service_proxy
+            .create_collection(collection_properties(label, true), alias)
+            .map_err(Error::from);
+    }
+    let created_collection = created_collection?;
+
+    // Check if the path is "/", if so should execute a prompt
+    let collection_path: OwnedObjectPath = if created_collection.collection.as_str() == "/" {
+        let prompt_res = exec_prompt_blocking(conn, config, &created_collection.prompt)?;
+        prompt_path(config, prompt_res)?
+    } else {
+        created_collection.collection
+    };
+    config.alias_cache.clear();
+
+    config.record(collection_path.clone().into(), || {
+        Change::CollectionCreated {
+            label: label.to_owned(),
+        }
+    });
+    Ok(collection_path)
+}
 
 pub(crate) async fn exec_prompt(
     conn: zbus::Connection,
+    config: &Config,
     prompt: &ObjectPath<'_>,
 ) -> Result<zvariant::OwnedValue, Error> {
     let prompt_proxy = PromptProxy::builder(&conn)
-        .destination(SS_DBUS_NAME)?
-        .path(prompt)?
+        .destination(config.destination.clone())?
+        .path(prompt.to_owned())?
         .cache_properties(CacheProperties::No)
         .build()
         .await?;
 
-    let mut receive_completed_iter = prompt_proxy.receive_completed().await?;
-    prompt_proxy.prompt(NO_WINDOW_ID).await?;
+    let prompt = Prompt::new(prompt_proxy, config.window_id(), config.prompts.clone());
+    let completion = match &config.prompter {
+        Some(prompter) => prompter.0.prompt(prompt).await?,
+        None => prompt.show().await?,
+    };
+    Ok(completion.0)
+}
+
+/// Dismisses a prompt that is dropped before completing, so that cancelling
+/// the future waiting for it doesn't leave a dialog on screen.
+pub(crate) struct DismissOnDrop(pub(crate) Option<PromptProxy<'static>>);
 
-    handle_signal(receive_completed_iter.next().await.unwrap())
+impl Drop for DismissOnDrop {
+    fn drop(&mut self) {
+        if let Some(proxy) = self.0.take() {
+            let executor = proxy.inner().connection().executor().clone();
+            executor
+                .spawn(
+                    async move {
+                        let _ = proxy.dismiss().await;
+                    },
+                    "secret-service prompt dismissal",
+                )
+                .detach();
+        }
+    }
 }
 
 pub(crate) fn exec_prompt_blocking(
     conn: zbus::blocking::Connection,
+    config: &Config,
     prompt: &ObjectPath,
 ) -> Result<zvariant::OwnedValue, Error> {
+    if config.prompter.is_some() {
+        // prompters are async
+        return zbus::block_on(exec_prompt(conn.into_inner(), config, prompt));
+    }
     let prompt_proxy = PromptProxyBlocking::builder(&conn)
-        .destination(SS_DBUS_NAME)?
-        .path(prompt)?
+        .destination(config.destination.clone())?
+        .path(prompt.to_owned())?
         .cache_properties(CacheProperties::No)
         .build()?;
 
     let mut receive_completed_iter = prompt_proxy.receive_completed()?;
-    prompt_proxy.prompt(NO_WINDOW_ID)?;
+    let _registration = config
+        .prompts
+        .register(PromptProxy::from(prompt_proxy.inner().inner().clone()));
+    let mut dismiss_guard = DismissOnDropBlocking(Some(&prompt_proxy));
+    prompt_proxy.prompt(&config.window_id())?;
+
+    let completed = receive_completed_iter.next().unwrap();
+    dismiss_guard.0 = None;
+    handle_signal(completed)
+}
 
-    handle_signal(receive_completed_iter.next().unwrap())
+/// Dismisses a prompt that is left without completing, when showing it fails
+/// or the thread waiting for it unwinds, like [DismissOnDrop] does for the
+/// async API.
+struct DismissOnDropBlocking<'a>(Option<&'a PromptProxyBlocking<'a>>);
+
+impl Drop for DismissOnDropBlocking<'_> {
+    fn drop(&mut self) {
+        if let Some(proxy) = self.0.take() {
+            let _ = proxy.dismiss();
+        }
+    }
 }
 
-fn handle_signal(signal: Completed) -> Result<zvariant::OwnedValue, Error> {
+pub(crate) fn handle_signal(signal: Completed) -> Result<zvariant::OwnedValue, Error> {
     let args = signal.args()?;
     if args.dismissed {
         Err(Error::Prompt)
@@ -152,6 +864,16 @@ fn handle_signal(signal: Completed) -> Result<zvariant::OwnedValue, Error> {
     }
 }
 
+/// Opens a connection to the session bus.
+pub(crate) async fn session_bus(internal_executor: bool) -> Result<zbus::Connection, Error> {
+    zbus::connection::Builder::session()
+        .map_err(handle_conn_error)?
+        .internal_executor(internal_executor)
+        .build()
+        .await
+        .map_err(handle_conn_error)
+}
+
 pub(crate) fn handle_conn_error(e: zbus::Error) -> Error {
     match e {
         zbus::Error::InterfaceNotFound | zbus::Error::Address(_) => Error::Unavailable,
@@ -161,3 +883,294 @@ pub(crate) fn handle_conn_error(e: zbus::Error) -> Error {
         e => e.into(),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_find_collection_of_item() {
+        assert_eq!(
+            collection_of("/org/freedesktop/secrets/collection/login/1"),
+            "/org/freedesktop/secrets/collection/login"
+        );
+    }
+
+    #[test]
+    fn should_group_items_by_collection() {
+        let groups = group_by_collection(
+            [
+                "/org/freedesktop/secrets/collection/login/1",
+                "/org/freedesktop/secrets/collection/work/1",
+                "/org/freedesktop/secrets/collection/login/2",
+            ],
+            |path| path,
+        )
+        .unwrap();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(
+            groups[0].0.as_str(),
+            "/org/freedesktop/secrets/collection/login"
+        );
+        assert_eq!(
+            groups[0].1,
+            [
+                "/org/freedesktop/secrets/collection/login/1",
+                "/org/freedesktop/secrets/collection/login/2"
+            ]
+        );
+        assert_eq!(
+            groups[1].0.as_str(),
+            "/org/freedesktop/secrets/collection/work"
+        );
+    }
+
+    #[test]
+    fn should_build_legacy_properties() {
+        let attributes = HashMap::from([("service", "example")]);
+        let properties = item_properties("label", attributes.clone(), false);
+        crate::api::validate_item_properties(&properties, &[]).unwrap();
+
+        let legacy = item_properties("label", attributes, true);
+        let mut names: Vec<_> = legacy.keys().copied().collect();
+        names.sort_unstable();
+        assert_eq!(names, ["Attributes", "Label"]);
+        assert!(collection_properties("label", true).contains_key("Label"));
+    }
+
+    #[test]
+    fn should_sort_by_keys() {
+        let mut values = vec!["old", "new", "older", "same"];
+        let keys = [2, 5, 1, 2].map(|time| SortKey::Newest(Reverse(time)));
+        sort_by_keys(&mut values, keys.into());
+        assert_eq!(values, ["new", "old", "same", "older"]);
+
+        let mut values = vec![1, 2, 3, 4];
+        let keys = ["beta", "Alpha", "alpha", "Gamma"].map(|label| SortKey::label(label.into()));
+        sort_by_keys(&mut values, keys.into());
+        assert_eq!(values, [2, 3, 1, 4]);
+    }
+
+    #[test]
+    fn should_group_duplicates() {
+        let groups = group_duplicates([(1, "a"), (2, "b"), (3, "a"), (4, "c"), (5, "b"), (6, "a")]);
+        assert_eq!(groups, vec![vec![1, 3, 6], vec![2, 5]]);
+
+        let groups = group_duplicates([(1, "a"), (2, "b")]);
+        assert!(groups.is_empty());
+    }
+
+    #[test]
+    fn should_summarize_attributes() {
+        let items = [
+            vec![("service", "a"), ("user", "x")],
+            vec![("service", "a")],
+            vec![("service", "b"), ("xdg:schema", "s")],
+        ]
+        .map(|attributes| {
+            attributes
+                .into_iter()
+                .map(|(key, value)| (key.to_owned(), value.to_owned()))
+                .collect::<HashMap<_, _>>()
+        });
+
+        let summary = summarize_attributes(items.clone(), false);
+        assert_eq!(summary.items, 3);
+        assert_eq!(
+            summary.keys.into_iter().collect::<Vec<_>>(),
+            [
+                ("service".to_owned(), 3),
+                ("user".to_owned(), 1),
+                ("xdg:schema".to_owned(), 1)
+            ]
+        );
+        assert!(summary.values.is_empty());
+
+        let summary = summarize_attributes(items, true);
+        assert_eq!(summary.values["service"]["a"], 2);
+        assert_eq!(summary.values["service"]["b"], 1);
+        assert_eq!(summary.values["user"].len(), 1);
+    }
+
+    #[test]
+    fn should_rank_label_matches() {
+        let labels = [
+            "GitHub token",
+            "github",
+            "My GitHub",
+            "gmail",
+            "Gist hub",
+            "unrelated",
+        ];
+        let ranked = rank_label_matches(labels.iter().map(|l| (*l, l.to_string())), "GitHub");
+        let ranked: Vec<_> = ranked.iter().map(|m| m.item).collect();
+        assert_eq!(
+            ranked,
+            vec!["github", "GitHub token", "My GitHub", "Gist hub"]
+        );
+
+        // everything matches an empty query
+        let ranked = rank_label_matches(labels.iter().map(|l| (*l, l.to_string())), "");
+        assert_eq!(ranked.len(), labels.len());
+    }
+
+    #[test]
+    fn should_check_prompt_results() {
+        let config = Config {
+            strict: true,
+            ..Config::default()
+        };
+        let path = OwnedObjectPath::try_from("/org/freedesktop/secrets/collection/test").unwrap();
+        let single = zvariant::OwnedValue::try_from(Value::from(path.clone())).unwrap();
+        let paths = zvariant::OwnedValue::try_from(Value::new(vec![path.clone()])).unwrap();
+
+        assert_eq!(
+            prompt_path(&config, single.try_clone().unwrap()).unwrap(),
+            path
+        );
+        assert_eq!(
+            prompt_paths(&config, paths.try_clone().unwrap()).unwrap(),
+            [path]
+        );
+        assert!(matches!(
+            prompt_paths(&config, single),
+            Err(Error::InvalidResponse(_))
+        ));
+        assert!(matches!(
+            prompt_path(&config, paths),
+            Err(Error::InvalidResponse(_))
+        ));
+    }
+
+    #[test]
+    fn should_validate_secrets() {
+        let conn = zbus::blocking::Connection::session().unwrap();
+        let service_proxy = ServiceProxyBlocking::new(&conn).unwrap();
+        let session = Session::new_blocking(&service_proxy, crate::EncryptionType::Dh).unwrap();
+        let valid = || SecretStruct {
+            session: session.object_path().clone(),
+            parameters: vec![0; AES_BLOCK_LEN],
+            value: vec![0; 2 * AES_BLOCK_LEN],
+            content_type: "text/plain".to_owned(),
+        };
+        validate_secret(&session, &valid()).unwrap();
+
+        let other_session = SecretStruct {
+            session: OwnedObjectPath::try_from("/org/freedesktop/secrets/session/other").unwrap(),
+            ..valid()
+        };
+        let short_iv = SecretStruct {
+            parameters: vec![0; 8],
+            ..valid()
+        };
+        let unpadded = SecretStruct {
+            value: vec![0; 20],
+            ..valid()
+        };
+        let untyped = SecretStruct {
+            content_type: String::new(),
+            ..valid()
+        };
+        for invalid in [other_session, short_iv, unpadded, untyped] {
+            assert!(matches!(
+                validate_secret(&session, &invalid),
+                Err(Error::InvalidResponse(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn should_convert_text_secrets() {
+        assert_eq!(
+            secret_to_string(b"test".to_vec(), "text/plain").unwrap(),
+            "test"
+        );
+        assert_eq!(
+            secret_to_string("tëst".into(), "text/plain; charset=UTF-8").unwrap(),
+            "tëst"
+        );
+        assert_eq!(
+            secret_to_string(b"test".to_vec(), "text/plain; charset=\"utf8\"").unwrap(),
+            "test"
+        );
+        assert!(matches!(
+            secret_to_string(vec![0xff, 0xfe], "text/plain"),
+            Err(Error::InvalidUtf8)
+        ));
+        assert!(matches!(
+            secret_to_string(b"test".to_vec(), "text/plain; charset=iso-8859-1"),
+            Err(Error::InvalidUtf8)
+        ));
+    }
+
+    #[test]
+    fn should_validate_content_types() {
+        let valid = [
+            "text/plain",
+            "application/octet-stream",
+            "text/plain; charset=utf8",
+            "text/plain;charset=\"utf-8\"",
+            "text/plain ; charset = utf-8 ; format=flowed",
+            "application/x-custom; note=\"a; b \\\" c\"",
+            "application/vnd.example+json",
+        ];
+        for content_type in valid {
+            assert!(
+                validate_content_type(content_type).is_ok(),
+                "{content_type} should be valid"
+            );
+        }
+
+        let invalid = [
+            "",
+            "text",
+            "text/",
+            "/plain",
+            "text/plain/extra",
+            "text /plain",
+            "text/pla in",
+            "text/plain;",
+            "text/plain; charset",
+            "text/plain; charset=",
+            "text/plain; charset=\"utf-8",
+            "text/plain; charset=\"utf-8\" extra",
+            "text/plain; =utf-8",
+            "text/plain; charset=utf 8",
+            "tëxt/plain",
+            "text/plain\n",
+        ];
+        for content_type in invalid {
+            assert!(
+                matches!(
+                    validate_content_type(content_type),
+                    Err(Error::InvalidContentType)
+                ),
+                "{content_type:?} should be invalid"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn should_limit_concurrency() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let config = Config {
+            call_limit: Some(async_lock::Semaphore::new(2)),
+            ..Config::default()
+        };
+        let in_flight = AtomicUsize::new(0);
+        let max_in_flight = AtomicUsize::new(0);
+
+        futures_util::future::join_all((0..8).map(|_| {
+            limit_concurrency(&config, async {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(now, Ordering::SeqCst);
+                tokio::task::yield_now().await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+            })
+        }))
+        .await;
+
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 2);
+    }
+}
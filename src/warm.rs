@@ -0,0 +1,76 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+// A connection opened together with the default collection.
+
+use crate::proxy::collection::CollectionProxy;
+use crate::{Collection, SecretService};
+
+use zbus::zvariant::OwnedObjectPath;
+
+/// A connection made with
+/// [SecretServiceBuilder::connect_and_open_default](crate::SecretServiceBuilder::connect_and_open_default),
+/// along with the default collection.
+///
+/// The collection borrows from the service, so the two can't be returned
+/// side by side. [WarmConnection::default_collection] hands it out without
+/// calling the provider instead.
+pub struct WarmConnection<'a> {
+    pub(crate) service: SecretService<'a>,
+    pub(crate) path: OwnedObjectPath,
+    pub(crate) collection_proxy: CollectionProxy<'static>,
+    pub(crate) locked: bool,
+}
+
+impl<'a> WarmConnection<'a> {
+    pub fn service(&self) -> &SecretService<'a> {
+        &self.service
+    }
+
+    /// Returns the default collection.
+    pub fn default_collection(&self) -> Collection<'_> {
+        Collection::with_proxy(
+            self.service.conn.clone(),
+            &self.service.session,
+            &self.service.service_proxy,
+            &self.service.config,
+            self.path.clone(),
+            self.collection_proxy.clone(),
+        )
+        .with_alias("default")
+    }
+
+    /// Returns whether the default collection was locked when connecting.
+    pub fn locked(&self) -> bool {
+        self.locked
+    }
+
+    pub fn into_service(self) -> SecretService<'a> {
+        self.service
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{EncryptionType, SecretService};
+
+    #[tokio::test]
+    async fn should_open_default_collection() {
+        let warm = SecretService::builder()
+            .encryption(EncryptionType::Plain)
+            .connect_and_open_default()
+            .await
+            .unwrap();
+        let collection = warm.default_collection();
+        assert_eq!(
+            collection.path(),
+            warm.service().default_collection_path().await.unwrap()
+        );
+        assert_eq!(warm.locked(), collection.is_locked().await.unwrap());
+        collection.get_all_items().await.unwrap();
+    }
+}
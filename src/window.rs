@@ -0,0 +1,63 @@
+// Copyright 2022 secret-service-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+// Identifiers of the windows prompts are parented to.
+
+use std::fmt;
+
+/// The window of the application a prompt is shown as a child of.
+///
+/// Set with [SecretServiceBuilder::window](crate::SecretServiceBuilder::window),
+/// or changed later with [SecretService::set_window](crate::SecretService::set_window),
+/// for instance when another window of the application gets the focus.
+/// Formats as the string passed to the provider's `Prompt`, such as
+/// `x11:0x2a00003`.
+///
+/// GTK 4 applications can get the identifier of a window from
+/// `gtk::WindowHandle`, with the `gtk4` feature.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WindowIdentifier {
+    /// The XID of an X11 window.
+    X11(u64),
+    /// The handle of a Wayland toplevel exported with the `xdg-foreign`
+    /// protocol, which has to stay exported for as long as prompts may be
+    /// shown.
+    Wayland(String),
+}
+
+impl fmt::Display for WindowIdentifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WindowIdentifier::X11(xid) => write!(f, "x11:{xid:#x}"),
+            WindowIdentifier::Wayland(handle) => write!(f, "wayland:{handle}"),
+        }
+    }
+}
+
+impl From<WindowIdentifier> for String {
+    fn from(window: WindowIdentifier) -> Self {
+        window.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_format_window_identifiers() {
+        assert_eq!(
+            WindowIdentifier::X11(0x2a00003).to_string(),
+            "x11:0x2a00003"
+        );
+        assert_eq!(
+            WindowIdentifier::Wayland("a1b2c3".to_owned()).to_string(),
+            "wayland:a1b2c3"
+        );
+    }
+}